@@ -12,5 +12,8 @@ fn main() {
         }
     }
 
+    // 更新签名公钥通过 option_env! 嵌入，变化时需要重新编译
+    println!("cargo:rerun-if-env-changed=ZAUGMENT_UPDATER_PUBKEY");
+
     tauri_build::build()
 }
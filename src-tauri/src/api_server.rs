@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use tauri::{Emitter, Manager};
 use crate::storage::traits::{TokenStorage, TokenData};
+use crate::storage::{BanStatus, ChangeSource};
 
 // ==================== 数据结构定义 ====================
 
@@ -149,7 +150,7 @@ impl ApiServer {
                 shutdown_rx.await.ok();
             });
 
-        println!("🚀 API Server started on http://{}", addr);
+        log::info!("🚀 API Server started on http://{}", addr);
 
         // 在后台运行服务器
         tokio::spawn(server);
@@ -160,7 +161,7 @@ impl ApiServer {
     pub fn shutdown(&mut self) {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
-            println!("🛑 API Server shutdown signal sent");
+            log::info!("🛑 API Server shutdown signal sent");
         }
     }
 }
@@ -209,7 +210,7 @@ async fn import_session_handler(
     request: ImportSessionRequest,
     state: Arc<crate::AppState>,
 ) -> Result<impl Reply, Rejection> {
-    println!("📥 API: Importing single session: {}", mask_session(&request.session));
+    log::info!("📥 API: Importing single session: {}", mask_session(&request.session));
 
     // 聚焦窗口
     if let Some(main_window) = state.app_handle.get_webview_window("main") {
@@ -223,7 +224,7 @@ async fn import_session_handler(
         "status": "pending"
     });
     if let Err(e) = state.app_handle.emit("import-session-started", import_event) {
-        eprintln!("⚠️  Failed to emit import-session-started event: {}", e);
+        log::warn!("⚠️  Failed to emit import-session-started event: {}", e);
     }
 
     // 验证 session
@@ -235,7 +236,7 @@ async fn import_session_handler(
             "error": e.clone()
         });
         if let Err(err) = state.app_handle.emit("import-session-failed", error_event) {
-            eprintln!("⚠️  Failed to emit import-session-failed event: {}", err);
+            log::warn!("⚠️  Failed to emit import-session-failed event: {}", err);
         }
 
         let error_response = ApiErrorResponse {
@@ -262,7 +263,7 @@ async fn import_session_handler(
                 "error": error_msg.clone()
             });
             if let Err(err) = state.app_handle.emit("import-session-failed", error_event) {
-                eprintln!("⚠️  Failed to emit import-session-failed event: {}", err);
+                log::warn!("⚠️  Failed to emit import-session-failed event: {}", err);
             }
 
             let error_response = ApiErrorResponse {
@@ -280,11 +281,8 @@ async fn import_session_handler(
             if let Some(ref email_note) = response.email {
                 let email_to_check = email_note.trim().to_lowercase();
 
-                // 从 storage_manager 加载现有 tokens
-                let storage_manager = {
-                    let guard = state.storage_manager.lock().unwrap();
-                    guard.clone()
-                };
+                // 从当前数据目录的 token 存储加载现有 tokens
+                let storage_manager = state.token_storage().ok();
 
                 if let Some(storage) = storage_manager {
                     match storage.load_tokens().await {
@@ -297,7 +295,7 @@ async fn import_session_handler(
                                     false
                                 }
                             }) {
-                                println!("⚠️  API: Duplicate email detected: {}", email_note);
+                                log::warn!("⚠️  API: Duplicate email detected: {}", email_note);
 
                                 // 发送导入失败事件
                                 let error_msg = format!("邮箱 '{}' 已存在", email_note);
@@ -307,7 +305,7 @@ async fn import_session_handler(
                                     "error": error_msg.clone()
                                 });
                                 if let Err(err) = state.app_handle.emit("import-session-failed", error_event) {
-                                    eprintln!("⚠️  Failed to emit import-session-failed event: {}", err);
+                                    log::warn!("⚠️  Failed to emit import-session-failed event: {}", err);
                                 }
 
                                 let error_response = ApiErrorResponse {
@@ -321,7 +319,7 @@ async fn import_session_handler(
                             }
                         }
                         Err(e) => {
-                            eprintln!("⚠️  API: Failed to load existing tokens for duplicate check: {}", e);
+                            log::warn!("⚠️  API: Failed to load existing tokens for duplicate check: {}", e);
                         }
                     }
                 }
@@ -341,28 +339,27 @@ async fn import_session_handler(
                 email_note: response.email.clone(),
                 tag_name: None,
                 tag_color: None,
-                ban_status: Some(serde_json::Value::String("ACTIVE".to_string())),  // Session 导入默认设置为 ACTIVE
+                ban_status: Some(BanStatus::Active),  // Session 导入默认设置为 ACTIVE
                 portal_info: None,  // Session 导入不再获取 portal_info
                 auth_session: Some(request.session.clone()),
                 suspensions: None,  // Session 导入不再获取 suspensions
                 skip_check: Some(false),
+                extra: serde_json::Map::new(),
             };
 
             // 保存到存储
-            let storage = {
-                let storage_guard = state.storage_manager.lock().unwrap();
-                storage_guard.as_ref().cloned()
-            };
-
-            let storage_result = if let Some(storage) = storage {
-                storage.save_token(&token_data).await.map_err(|e| e.to_string())
-            } else {
-                Err("Storage manager not initialized".to_string())
+            let storage_result = match state.token_storage() {
+                Ok(storage) => storage
+                    .for_source(ChangeSource::ApiImport)
+                    .save_token(&token_data)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
             };
 
             match storage_result {
                 Ok(_) => {
-                    println!("✅ API: Session imported successfully");
+                    log::info!("✅ API: Session imported successfully");
 
                     // 发送导入成功事件
                     let success_event = serde_json::json!({
@@ -371,12 +368,12 @@ async fn import_session_handler(
                         "email": response.email.clone()
                     });
                     if let Err(e) = state.app_handle.emit("import-session-success", success_event) {
-                        eprintln!("⚠️  Failed to emit import-session-success event: {}", e);
+                        log::warn!("⚠️  Failed to emit import-session-success event: {}", e);
                     }
 
                     // 发送前端刷新事件
                     if let Err(e) = state.app_handle.emit("tokens-updated", ()) {
-                        eprintln!("⚠️  Failed to emit tokens-updated event: {}", e);
+                        log::warn!("⚠️  Failed to emit tokens-updated event: {}", e);
                     }
 
                     // 根据 detailed_response 参数返回不同格式
@@ -405,7 +402,7 @@ async fn import_session_handler(
                     }
                 }
                 Err(e) => {
-                    println!("❌ API: Failed to save token: {}", e);
+                    log::warn!("❌ API: Failed to save token: {}", e);
 
                     // 发送导入失败事件
                     let error_event = serde_json::json!({
@@ -414,7 +411,7 @@ async fn import_session_handler(
                         "error": format!("Failed to save token: {}", e)
                     });
                     if let Err(err) = state.app_handle.emit("import-session-failed", error_event) {
-                        eprintln!("⚠️  Failed to emit import-session-failed event: {}", err);
+                        log::warn!("⚠️  Failed to emit import-session-failed event: {}", err);
                     }
 
                     let error_response = ApiErrorResponse {
@@ -429,7 +426,7 @@ async fn import_session_handler(
             }
         }
             Err(e) => {
-                println!("❌ API: Failed to import session: {}", e);
+                log::warn!("❌ API: Failed to import session: {}", e);
 
                 // 发送导入失败事件
                 let error_event = serde_json::json!({
@@ -438,7 +435,7 @@ async fn import_session_handler(
                     "error": format!("Failed to import session: {}", e)
                 });
                 if let Err(err) = state.app_handle.emit("import-session-failed", error_event) {
-                    eprintln!("⚠️  Failed to emit import-session-failed event: {}", err);
+                    log::warn!("⚠️  Failed to emit import-session-failed event: {}", err);
                 }
 
                 let error_response = ApiErrorResponse {
@@ -459,7 +456,7 @@ async fn import_sessions_handler(
     request: ImportSessionsRequest,
     state: Arc<crate::AppState>,
 ) -> Result<impl Reply, Rejection> {
-    println!("📥 API: Importing {} sessions", request.sessions.len());
+    log::info!("📥 API: Importing {} sessions", request.sessions.len());
 
     // 聚焦窗口
     if let Some(main_window) = state.app_handle.get_webview_window("main") {
@@ -474,7 +471,7 @@ async fn import_sessions_handler(
         "status": "pending"
     });
     if let Err(e) = state.app_handle.emit("import-sessions-started", batch_start_event) {
-        eprintln!("⚠️  Failed to emit import-sessions-started event: {}", e);
+        log::warn!("⚠️  Failed to emit import-sessions-started event: {}", e);
     }
 
     // 验证请求
@@ -553,10 +550,7 @@ async fn import_sessions_handler(
                     if let Some(ref email) = response.email {
                         let email_to_check = email.trim().to_lowercase();
 
-                        let storage_manager = {
-                            let guard = state.storage_manager.lock().unwrap();
-                            guard.clone()
-                        };
+                        let storage_manager = state.token_storage().ok();
 
                         if let Some(storage) = storage_manager {
                             match storage.load_tokens().await {
@@ -602,20 +596,16 @@ async fn import_sessions_handler(
                         email_note: response.email.clone(),
                         tag_name: None,
                         tag_color: None,
-                        ban_status: Some(serde_json::Value::String("ACTIVE".to_string())),  // Session 导入默认设置为 ACTIVE
+                        ban_status: Some(BanStatus::Active),  // Session 导入默认设置为 ACTIVE
                         portal_info: None,  // Session 导入不再获取 portal_info
                         auth_session: Some(session.clone()),
                         suspensions: None,  // Session 导入不再获取 suspensions
                         skip_check: Some(false),
+                        extra: serde_json::Map::new(),
                     };
 
-                    let storage = {
-                        let storage_guard = state.storage_manager.lock().unwrap();
-                        storage_guard.as_ref().cloned()
-                    };
-
-                    if let Some(storage) = storage {
-                        match storage.save_token(&token_data).await {
+                    match state.token_storage() {
+                        Ok(storage) => match storage.for_source(ChangeSource::ApiImport).save_token(&token_data).await {
                             Ok(_) => ImportResult {
                                 success: true,
                                 token_data: Some(token_data),
@@ -628,14 +618,13 @@ async fn import_sessions_handler(
                                 error: Some(format!("Failed to save token: {}", e)),
                                 session_preview: Some(mask_session(&session)),
                             },
-                        }
-                    } else {
-                        ImportResult {
+                        },
+                        Err(e) => ImportResult {
                             success: false,
                             token_data: None,
-                            error: Some("Storage manager not initialized".to_string()),
+                            error: Some(e),
                             session_preview: Some(mask_session(&session)),
-                        }
+                        },
                     }
                 }
                 Err(e) => ImportResult {
@@ -671,7 +660,7 @@ async fn import_sessions_handler(
     let successful = results.iter().filter(|r| r.success).count();
     let failed = total - successful;
 
-    println!("✅ API: Batch import completed: {}/{} successful", successful, total);
+    log::info!("✅ API: Batch import completed: {}/{} successful", successful, total);
 
     // 发送批量导入完成事件
     let batch_complete_event = serde_json::json!({
@@ -685,13 +674,13 @@ async fn import_sessions_handler(
         })).collect::<Vec<_>>()
     });
     if let Err(e) = state.app_handle.emit("import-sessions-completed", batch_complete_event) {
-        eprintln!("⚠️  Failed to emit import-sessions-completed event: {}", e);
+        log::warn!("⚠️  Failed to emit import-sessions-completed event: {}", e);
     }
 
     // 发送前端刷新事件
     if successful > 0 {
         if let Err(e) = state.app_handle.emit("tokens-updated", ()) {
-            eprintln!("⚠️  Failed to emit tokens-updated event: {}", e);
+            log::warn!("⚠️  Failed to emit tokens-updated event: {}", e);
         }
     }
 
@@ -0,0 +1,419 @@
+//! 本地 mock 服务器，模拟 Augment 认证 / App / 租户接口和 Orb 门户接口
+//!
+//! 所有服务共用一个地址，配合 `ApiEndpoints::with_base_url` 使用；租户地址（tenant_url）
+//! 也指向同一个服务器。账号行为通过 `MockAccount` 配置。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::sync::oneshot;
+use warp::http::{HeaderMap, Method, Response, StatusCode};
+use warp::Filter;
+
+use super::{ApiEndpoints, AugmentApi};
+use crate::http_client::{HttpClient, HttpClientSettings, ProxyMode, ProxySettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockTokenStatus {
+    Active,
+    /// find-missing 返回 401，触发自动刷新
+    Invalid,
+    /// 账号被封禁，session 也无法再换取 token
+    Suspended,
+    /// find-missing 返回 429
+    RateLimited,
+    /// find-missing 接受请求但从不响应
+    Unresponsive,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+    pub email: String,
+    pub access_token: String,
+    pub status: MockTokenStatus,
+    /// 可用于刷新 token 的 auth session
+    pub auth_session: Option<String>,
+    /// 通过 auth session 刷新后签发的新 token
+    pub refreshed_token: Option<String>,
+    /// 新 token 的状态，默认有效
+    pub refreshed_status: MockTokenStatus,
+
+    /// 门户链接中的 token
+    pub portal_token: Option<String>,
+    pub credits_balance: f64,
+}
+
+impl MockAccount {
+    pub fn new(email: &str, access_token: &str, status: MockTokenStatus) -> Self {
+        Self {
+            email: email.to_string(),
+            access_token: access_token.to_string(),
+            status,
+            auth_session: None,
+            refreshed_token: None,
+            refreshed_status: MockTokenStatus::Active,
+            portal_token: None,
+            credits_balance: 0.0,
+        }
+    }
+
+    pub fn with_session(mut self, auth_session: &str, refreshed_token: &str) -> Self {
+        self.auth_session = Some(auth_session.to_string());
+        self.refreshed_token = Some(refreshed_token.to_string());
+        self
+    }
+
+    pub fn with_refreshed_status(mut self, status: MockTokenStatus) -> Self {
+        self.refreshed_status = status;
+        self
+    }
+
+    pub fn with_portal(mut self, portal_token: &str, credits_balance: f64) -> Self {
+        self.portal_token = Some(portal_token.to_string());
+        self.credits_balance = credits_balance;
+        self
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// 服务器绑定端口后写入，在开始处理请求之前
+    base_url: OnceLock<String>,
+    accounts: Vec<MockAccount>,
+    hits: Mutex<HashMap<String, usize>>,
+}
+
+impl MockState {
+    fn base_url(&self) -> &str {
+        self.base_url.get().map(String::as_str).unwrap_or_default()
+    }
+
+    fn record(&self, route: &str) {
+        *self.hits.lock().unwrap().entry(route.to_string()).or_insert(0) += 1;
+    }
+
+    fn by_token(&self, token: &str) -> Option<(&MockAccount, MockTokenStatus)> {
+        self.accounts.iter().find_map(|account| {
+            if account.access_token == token {
+                Some((account, account.status))
+            } else if account.refreshed_token.as_deref() == Some(token) {
+                Some((account, account.refreshed_status))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn by_session(&self, session: &str) -> Option<&MockAccount> {
+        self.accounts.iter().find(|a| a.auth_session.as_deref() == Some(session))
+    }
+
+    fn by_app_session(&self, app_session: &str) -> Option<&MockAccount> {
+        app_session
+            .strip_prefix("app-")
+            .and_then(|session| self.by_session(session))
+    }
+
+    fn by_portal_token(&self, token: &str) -> Option<&MockAccount> {
+        self.accounts.iter().find(|a| a.portal_token.as_deref() == Some(token))
+    }
+}
+
+pub struct MockAugmentServer {
+    base_url: String,
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockAugmentServer {
+    /// 在随机端口启动服务器
+    pub async fn start(accounts: Vec<MockAccount>) -> Self {
+        let state = Arc::new(MockState {
+            base_url: OnceLock::new(),
+            accounts,
+            hits: Mutex::new(HashMap::new()),
+        });
+
+        let route_state = state.clone();
+        let routes = warp::method()
+            .and(warp::path::full())
+            .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .then(move |method: Method, path: warp::path::FullPath, query: String, headers: HeaderMap, body: warp::hyper::body::Bytes| {
+                let state = route_state.clone();
+                async move {
+                    let unresponsive = path.as_str() == "/find-missing"
+                        && bearer(&headers)
+                            .and_then(|t| state.by_token(&t).map(|(_, status)| status))
+                            == Some(MockTokenStatus::Unresponsive);
+                    if unresponsive {
+                        std::future::pending::<()>().await;
+                    }
+                    handle(&state, &method, path.as_str(), &query, &headers, &body)
+                }
+            });
+
+        let (shutdown, rx) = oneshot::channel::<()>();
+        // 由 warp 直接绑定随机端口，绑定后再把实际地址写入状态（tenant_url 需要指向服务器本身）
+        let (addr, server) = warp::serve(routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async {
+            rx.await.ok();
+        });
+        let base_url = format!("http://{}", addr);
+        state.base_url.set(base_url.clone()).unwrap();
+        tokio::spawn(server);
+
+        Self {
+            base_url,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// 指向本服务器的 API 入口（不使用系统代理）
+    pub fn api(&self) -> AugmentApi {
+        let settings = HttpClientSettings {
+            proxy: ProxySettings {
+                mode: ProxyMode::None,
+                ..ProxySettings::default()
+            },
+            ..HttpClientSettings::default()
+        };
+        let http = HttpClient::new(settings, None).unwrap();
+        AugmentApi::new(Arc::new(http), ApiEndpoints::with_base_url(&self.base_url))
+    }
+
+    /// 指定接口被请求的次数
+    pub fn hits(&self, route: &str) -> usize {
+        self.state.hits.lock().unwrap().get(route).copied().unwrap_or(0)
+    }
+
+    /// 账号在服务器上的门户链接
+    pub fn portal_url(&self, portal_token: &str) -> String {
+        format!("{}/portal?token={}", self.base_url, portal_token)
+    }
+}
+
+impl Drop for MockAugmentServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn json(status: StatusCode, value: serde_json::Value) -> Response<String> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(value.to_string())
+        .unwrap()
+}
+
+fn text(status: StatusCode, body: &str) -> Response<String> {
+    Response::builder()
+        .status(status)
+        .body(body.to_string())
+        .unwrap()
+}
+
+fn bearer(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all("cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| urlencoding::decode(value).map(|v| v.into_owned()).unwrap_or_else(|_| value.to_string()))
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn handle(
+    state: &MockState,
+    method: &Method,
+    path: &str,
+    query: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Response<String> {
+    let route = path.trim_start_matches('/');
+    state.record(route.split('/').next().filter(|s| *s != "api").unwrap_or(route));
+
+    match (method.as_str(), route) {
+        // ---------- 认证服务 ----------
+        ("GET", "terms-accept") => {
+            let account = cookie(headers, "session").and_then(|s| state.by_session(&s));
+            match account {
+                Some(account) if account.status != MockTokenStatus::Suspended => {
+                    let session = account.auth_session.as_deref().unwrap_or_default();
+                    let html = format!(
+                        r#"<script>window.__data = {{ code: "code-{}", state: "{}", tenant_url: "{}/" }};</script>"#,
+                        session,
+                        query_param(query, "state").unwrap_or_default(),
+                        state.base_url()
+                    );
+                    text(StatusCode::OK, &html)
+                }
+                _ => text(StatusCode::OK, "<html>Please sign in</html>"),
+            }
+        }
+
+        // ---------- 租户接口 ----------
+        ("POST", "token") => {
+            let payload: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+            let account = payload["code"]
+                .as_str()
+                .and_then(|code| code.strip_prefix("code-"))
+                .and_then(|session| state.by_session(session));
+            match account.and_then(|a| a.refreshed_token.clone()) {
+                Some(token) => json(StatusCode::OK, serde_json::json!({ "access_token": token })),
+                None => json(StatusCode::BAD_REQUEST, serde_json::json!({ "error": "invalid_grant" })),
+            }
+        }
+        ("POST", "find-missing") => match bearer(headers).and_then(|t| state.by_token(&t).map(|(_, s)| s)) {
+            Some(MockTokenStatus::Active) => json(StatusCode::OK, serde_json::json!({ "unknown_memory_names": [] })),
+            Some(MockTokenStatus::Suspended) => json(StatusCode::OK, serde_json::json!({ "error": "Account suspended" })),
+            Some(MockTokenStatus::RateLimited) => text(StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            Some(MockTokenStatus::Unresponsive) => unreachable!("无响应的请求不会到达这里"),
+            Some(MockTokenStatus::Invalid) | None => text(StatusCode::UNAUTHORIZED, "Invalid token"),
+        },
+        ("POST", "get-models") => match bearer(headers).and_then(|t| state.by_token(&t)) {
+            Some((account, MockTokenStatus::Active)) => json(
+                StatusCode::OK,
+                serde_json::json!({ "user": { "id": format!("user-{}", account.email), "email": account.email } }),
+            ),
+            _ => text(StatusCode::UNAUTHORIZED, "Invalid token"),
+        },
+        ("POST", "get-credit-info") => match bearer(headers).and_then(|t| state.by_token(&t)) {
+            Some((account, MockTokenStatus::Active)) => json(
+                StatusCode::OK,
+                serde_json::json!({
+                    "usage_units_remaining": account.credits_balance,
+                    "usage_units_total_current_billing_cycle": 600.0,
+                    "usage_units_total_additional": 0.0,
+                    "is_credit_balance_low": account.credits_balance < 50.0,
+                    "display_info": null,
+                    "refreshed_at": "2024-01-20T00:00:00Z",
+                    "included_usage_units_per_billing_cycle": 600.0,
+                    "current_billing_cycle_end_date_iso": "2024-02-15T00:00:00Z",
+                    "credit_details": null,
+                    "usage_units_total": 600.0
+                }),
+            ),
+            _ => text(StatusCode::UNAUTHORIZED, "Invalid token"),
+        },
+
+        // ---------- App 服务 ----------
+        ("HEAD", "login") | ("GET", "login") => {
+            let mut response = text(StatusCode::OK, "");
+            if let Some(session) = cookie(headers, "session").filter(|s| state.by_session(s).is_some()) {
+                response.headers_mut().append(
+                    "Set-Cookie",
+                    format!("_session=app-{}; Path=/", session).parse().unwrap(),
+                );
+            }
+            response
+        }
+        ("GET", "api/user") => match cookie(headers, "_session").and_then(|s| state.by_app_session(&s).map(|a| (s, a))) {
+            Some((app_session, account)) => {
+                let suspensions = if account.status == MockTokenStatus::Suspended {
+                    serde_json::json!([{ "suspensionType": "ABUSE" }])
+                } else {
+                    serde_json::json!([])
+                };
+                let mut response = json(
+                    StatusCode::OK,
+                    serde_json::json!({ "email": account.email, "suspensions": suspensions }),
+                );
+                response.headers_mut().append(
+                    "Set-Cookie",
+                    format!("_session={}; Path=/", app_session).parse().unwrap(),
+                );
+                response
+            }
+            None => text(StatusCode::UNAUTHORIZED, "Unauthorized"),
+        },
+        ("GET", "api/subscription") => match cookie(headers, "_session").and_then(|s| state.by_app_session(&s)) {
+            Some(account) => json(
+                StatusCode::OK,
+                serde_json::json!({
+                    "portalUrl": account.portal_token.as_ref().map(|t| format!("{}/portal?token={}", state.base_url(), t)),
+                    "billingPeriodEnd": "2024-02-15T00:00:00Z"
+                }),
+            ),
+            None => text(StatusCode::UNAUTHORIZED, "Unauthorized"),
+        },
+        ("GET", "api/credits") => match cookie(headers, "_session").and_then(|s| state.by_app_session(&s)) {
+            Some(account) => json(StatusCode::OK, serde_json::json!({ "usageUnitsAvailable": account.credits_balance as i32 })),
+            None => text(StatusCode::UNAUTHORIZED, "Unauthorized"),
+        },
+        ("GET", "api/credit-consumption") => match cookie(headers, "_session").and_then(|s| state.by_app_session(&s)) {
+            Some(_) => {
+                let data_points = if query_param(query, "groupBy").as_deref() == Some("MODEL_NAME") {
+                    serde_json::json!([{ "groupKey": "claude-sonnet", "creditsConsumed": "42" }])
+                } else {
+                    serde_json::json!([{
+                        "dateRange": { "startDateIso": "2024-01-15T00:00:00Z", "endDateIso": "2024-01-16T00:00:00Z" },
+                        "creditsConsumed": "42"
+                    }])
+                };
+                json(StatusCode::OK, serde_json::json!({ "dataPoints": data_points }))
+            }
+            None => text(StatusCode::UNAUTHORIZED, "Unauthorized"),
+        },
+
+        // ---------- Orb 门户 ----------
+        ("GET", "api/v1/customer_from_link") => {
+            match query_param(query, "token").and_then(|t| state.by_portal_token(&t)) {
+                Some(account) => json(
+                    StatusCode::OK,
+                    serde_json::json!({
+                        "customer": {
+                            "id": format!("cust-{}", account.portal_token.as_deref().unwrap_or_default()),
+                            "ledger_pricing_units": [{ "id": "pu-credits" }]
+                        }
+                    }),
+                ),
+                None => text(StatusCode::NOT_FOUND, "Not found"),
+            }
+        }
+        ("GET", "api/v1/subscriptions_from_link") => {
+            match query_param(query, "token").and_then(|t| state.by_portal_token(&t)) {
+                Some(_) => json(StatusCode::OK, serde_json::json!({ "data": [] })),
+                None => text(StatusCode::NOT_FOUND, "Not found"),
+            }
+        }
+        ("GET", route) if route.starts_with("api/v1/customers/") && route.ends_with("/ledger_summary") => {
+            match query_param(query, "token").and_then(|t| state.by_portal_token(&t)) {
+                Some(account) => json(
+                    StatusCode::OK,
+                    serde_json::json!({
+                        "credits_balance": format!("{:.2}", account.credits_balance),
+                        "credit_blocks": [{ "effective_date": "2024-01-15T00:00:00+00:00" }]
+                    }),
+                ),
+                None => text(StatusCode::NOT_FOUND, "Not found"),
+            }
+        }
+
+        _ => text(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
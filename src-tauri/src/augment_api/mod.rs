@@ -0,0 +1,122 @@
+//! Augment / Orb 接口的访问入口
+//!
+//! 服务地址和 HTTP 传输层都通过 `AugmentApi` 注入，正式环境使用共享 HTTP 客户端和线上地址，
+//! 测试时指向本地 mock 服务器。
+
+#[cfg(test)]
+pub mod mock_server;
+
+use reqwest::cookie::Jar;
+use std::sync::Arc;
+
+use crate::http_client::HttpClient;
+
+pub const AUTH_BASE_URL: &str = "https://auth.augmentcode.com";
+pub const APP_BASE_URL: &str = "https://app.augmentcode.com";
+pub const PORTAL_BASE_URL: &str = "https://portal.withorb.com";
+
+/// 各服务的基础地址（不带末尾斜杠）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiEndpoints {
+    pub auth_base_url: String,
+    pub app_base_url: String,
+    pub portal_base_url: String,
+}
+
+impl Default for ApiEndpoints {
+    fn default() -> Self {
+        Self {
+            auth_base_url: AUTH_BASE_URL.to_string(),
+            app_base_url: APP_BASE_URL.to_string(),
+            portal_base_url: PORTAL_BASE_URL.to_string(),
+        }
+    }
+}
+
+impl ApiEndpoints {
+    /// 所有服务都使用同一个地址，例如本地 mock 服务器
+    pub fn with_base_url(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        Self {
+            auth_base_url: base_url.clone(),
+            app_base_url: base_url.clone(),
+            portal_base_url: base_url,
+        }
+    }
+
+    /// 认证服务地址，`path` 以 `/` 开头
+    pub fn auth_url(&self, path: &str) -> String {
+        format!("{}{}", self.auth_base_url, path)
+    }
+
+    /// App 服务地址，`path` 以 `/` 开头
+    pub fn app_url(&self, path: &str) -> String {
+        format!("{}{}", self.app_base_url, path)
+    }
+
+    /// Orb 门户地址，`path` 以 `/` 开头
+    pub fn portal_url(&self, path: &str) -> String {
+        format!("{}{}", self.portal_base_url, path)
+    }
+}
+
+/// HTTP 传输层
+pub trait HttpTransport: Send + Sync {
+    /// 发送普通请求使用的客户端
+    fn client(&self) -> &reqwest::Client;
+
+    /// 使用独立 cookie 存储的客户端，session 交换时需要在重定向之间携带 cookie
+    fn client_with_cookies(&self, jar: Arc<Jar>) -> Result<reqwest::Client, String>;
+}
+
+impl HttpTransport for HttpClient {
+    fn client(&self) -> &reqwest::Client {
+        HttpClient::client(self)
+    }
+
+    fn client_with_cookies(&self, jar: Arc<Jar>) -> Result<reqwest::Client, String> {
+        self.builder()?
+            .cookie_provider(jar)
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+}
+
+/// 传输层 + 服务地址
+#[derive(Clone)]
+pub struct AugmentApi {
+    transport: Arc<dyn HttpTransport>,
+    endpoints: ApiEndpoints,
+}
+
+impl AugmentApi {
+    pub fn new(transport: Arc<dyn HttpTransport>, endpoints: ApiEndpoints) -> Self {
+        Self { transport, endpoints }
+    }
+
+    /// 使用共享 HTTP 客户端访问线上服务
+    pub fn from_http_client(http: HttpClient) -> Self {
+        Self::new(Arc::new(http), ApiEndpoints::default())
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        self.transport.client()
+    }
+
+    pub fn client_with_cookies(&self, jar: Arc<Jar>) -> Result<reqwest::Client, String> {
+        self.transport.client_with_cookies(jar)
+    }
+
+    pub fn endpoints(&self) -> &ApiEndpoints {
+        &self.endpoints
+    }
+}
+
+impl std::fmt::Debug for AugmentApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AugmentApi")
+            .field("endpoints", &self.endpoints)
+            .finish()
+    }
+}
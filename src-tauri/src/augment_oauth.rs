@@ -1,15 +1,15 @@
 use base64::{engine::general_purpose, Engine as _};
 use rand::Rng;
-use reqwest;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
+use crate::augment_api::{AugmentApi, AUTH_BASE_URL};
+
 const CLIENT_ID: &str = "v";
-const AUTH_BASE_URL: &str = "https://auth.augmentcode.com";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AugmentOAuthState {
@@ -33,14 +33,146 @@ pub struct AugmentTokenResponse {
     pub email: Option<String>,           // 从 get-models API 获取的邮箱
 }
 
+/// 账号检测状态，序列化为前端使用的状态字符串
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountStatusCode {
+    Active,
+    Suspended,
+    /// 额度用完或订阅过期
+    Expired,
+    /// token 已失效，可用 auth_session 刷新
+    InvalidToken,
+    /// 403 且响应中没有封禁信息
+    Forbidden,
+    RateLimited,
+    ServerError,
+    UnknownError,
+    /// 请求未完成（网络错误、超时）
+    Error,
+}
+
+impl AccountStatusCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatusCode::Active => "ACTIVE",
+            AccountStatusCode::Suspended => "SUSPENDED",
+            AccountStatusCode::Expired => "EXPIRED",
+            AccountStatusCode::InvalidToken => "INVALID_TOKEN",
+            AccountStatusCode::Forbidden => "FORBIDDEN",
+            AccountStatusCode::RateLimited => "RATE_LIMITED",
+            AccountStatusCode::ServerError => "SERVER_ERROR",
+            AccountStatusCode::UnknownError => "UNKNOWN_ERROR",
+            AccountStatusCode::Error => "ERROR",
+        }
+    }
+
+    /// 是否确定了账号状态；请求失败、限流和服务端错误时不应覆盖已保存的状态
+    pub fn is_conclusive(&self) -> bool {
+        !matches!(
+            self,
+            AccountStatusCode::Error | AccountStatusCode::RateLimited | AccountStatusCode::ServerError
+        )
+    }
+}
+
+impl std::fmt::Display for AccountStatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 检测失败的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// 连接失败、请求或响应传输中断
+    Network,
+    Timeout,
+    /// token 失效或没有权限
+    Auth,
+    RateLimited,
+    /// 服务端 5xx 或响应无法解析
+    Server,
+    Unknown,
+}
+
+/// 检测失败的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl CheckError {
+    pub fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        Self {
+            category,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for CheckError {
+    fn from(error: reqwest::Error) -> Self {
+        let category = if error.is_timeout() {
+            ErrorCategory::Timeout
+        } else if error.is_connect() || error.is_request() || error.is_body() {
+            ErrorCategory::Network
+        } else if error.is_decode() || error.is_status() {
+            ErrorCategory::Server
+        } else {
+            ErrorCategory::Unknown
+        };
+        Self::new(category, error.to_string())
+    }
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// 状态的判定依据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StatusReason {
+    /// find-missing 正常返回，响应内容中匹配到的关键字
+    ResponseKeyword { keyword: String },
+    /// 根据 HTTP 状态码判定
+    HttpStatus { code: u16 },
+    /// 刷新 token 时认证服务返回 SESSION_ERROR_OR_ACCOUNT_BANNED
+    RefreshRejected,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AccountStatus {
     pub is_banned: bool,
-    pub status: String,
+    pub status: AccountStatusCode,
+    pub reason: Option<StatusReason>,
+    /// 未能正常确定状态时的失败分类
+    pub error: Option<CheckError>,
     pub error_message: Option<String>,
     pub response_code: Option<u16>,
-    // 调试信息
-    pub debug_info: DebugInfo,
+    // 调试信息，仅在诊断模式下收集
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_info: Option<DebugInfo>,
+}
+
+impl AccountStatus {
+    /// 请求未完成时的状态
+    pub fn failed(error: CheckError) -> Self {
+        Self {
+            is_banned: false,
+            status: AccountStatusCode::Error,
+            reason: None,
+            error_message: Some(error.message.clone()),
+            error: Some(error),
+            response_code: None,
+            debug_info: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,7 +186,7 @@ pub struct DebugInfo {
 }
 
 // 批量检测相关结构体
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenInfo {
     pub access_token: String,
     pub tenant_url: String,
@@ -67,7 +199,7 @@ pub struct TokenInfo {
 // Portal信息结构体
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PortalInfo {
-    pub credits_balance: i32,
+    pub credits_balance: i64,
     pub expiry_date: Option<String>,
 }
 
@@ -233,13 +365,12 @@ pub fn parse_code(code: &str) -> Result<ParsedCode, Box<dyn std::error::Error>>
 
 /// Get access token using authorization code
 pub async fn get_augment_access_token(
+    api: &AugmentApi,
     tenant_url: &str,
     code_verifier: &str,
     code: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = api.client();
     
     let mut data = HashMap::new();
     data.insert("grant_type", "authorization_code");
@@ -261,22 +392,24 @@ pub async fn get_augment_access_token(
 
 /// Complete OAuth flow and return token with tenant URL
 pub async fn complete_augment_oauth_flow(
+    api: &AugmentApi,
     oauth_state: &AugmentOAuthState,
     code_input: &str,
 ) -> Result<AugmentTokenResponse, Box<dyn std::error::Error>> {
     let parsed_code = parse_code(code_input)?;
 
     let token = get_augment_access_token(
+        api,
         &parsed_code.tenant_url,
         &oauth_state.code_verifier,
         &parsed_code.code,
     ).await?;
 
     // 获取用户邮箱（OAuth 流程也不再获取 credits_balance 和 expiry_date）
-    let email = match get_models(&token, &parsed_code.tenant_url).await {
+    let email = match get_models(api, &token, &parsed_code.tenant_url).await {
         Ok(models_response) => Some(models_response.user.email),
         Err(err) => {
-            println!("Failed to get user email: {}", err);
+            log::warn!("Failed to get user email: {}", err);
             None
         }
     };
@@ -289,13 +422,15 @@ pub async fn complete_augment_oauth_flow(
 }
 
 /// Check account ban status by testing find-missing API
+///
+/// `with_debug` 为 true 时在结果中附带请求和响应的调试信息（token 已隐藏）
 pub async fn check_account_ban_status(
+    api: &AugmentApi,
     token: &str,
     tenant_url: &str,
-) -> Result<AccountStatus, Box<dyn std::error::Error + Send + Sync>> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    with_debug: bool,
+) -> Result<AccountStatus, CheckError> {
+    let client = api.client();
 
     // Ensure tenant_url ends with a slash
     let base_url = if tenant_url.ends_with('/') {
@@ -309,18 +444,7 @@ pub async fn check_account_ban_status(
     // Empty request body for find-missing endpoint
     let request_body = serde_json::json!({});
 
-    // Prepare request headers for debugging
-    let mut request_headers = HashMap::new();
-    request_headers.insert("Content-Type".to_string(), "application/json".to_string());
-    request_headers.insert("Authorization".to_string(), format!("Bearer {}", token));
-
-    // Print debug info to console
-    println!("=== API Request Debug Info ===");
-    println!("URL: {}", api_url);
-    println!("Method: POST");
-    println!("Headers: {:#?}", request_headers);
-    println!("Request Body: {}", request_body.to_string());
-    println!("==============================");
+    log::debug!("find-missing request: POST {}", api_url);
 
     // Send request to find-missing API
     let response = client
@@ -335,109 +459,124 @@ pub async fn check_account_ban_status(
     let status_text = response.status().to_string();
 
     // Collect response headers
-    let mut response_headers = HashMap::new();
-    for (name, value) in response.headers() {
-        response_headers.insert(
-            name.to_string(),
-            value.to_str().unwrap_or("<invalid utf8>").to_string(),
-        );
-    }
+    let response_headers: HashMap<String, String> = if with_debug {
+        response
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or("<invalid utf8>").to_string()))
+            .collect()
+    } else {
+        HashMap::new()
+    };
 
-    // Print response debug info
-    println!("=== API Response Debug Info ===");
-    println!("Status Code: {} ({})", status_code, status_text);
-    println!("Response Headers: {:#?}", response_headers);
+    log::debug!("find-missing response: {} ({})", status_code, status_text);
 
     // Read response body
     let response_body = response.text().await?;
-    println!("Response Body: {}", response_body);
-    println!("===============================");
-
-    // Create debug info
-    let debug_info = DebugInfo {
-        request_url: api_url.clone(),
-        request_headers,
-        request_body: request_body.to_string(),
-        response_headers,
-        response_body: response_body.clone(),
-        response_status_text: status_text,
+
+    let mut status = classify_find_missing_response(status_code, &response_body);
+    if with_debug {
+        let mut request_headers = HashMap::new();
+        request_headers.insert("Content-Type".to_string(), "application/json".to_string());
+        // 调试信息中不保留真实的 token
+        request_headers.insert("Authorization".to_string(), "Bearer ***".to_string());
+        status.debug_info = Some(DebugInfo {
+            request_url: api_url,
+            request_headers,
+            request_body: request_body.to_string(),
+            response_headers,
+            response_body,
+            response_status_text: status_text,
+        });
+    }
+    Ok(status)
+}
+
+/// 根据 find-missing 的响应判断账号状态
+fn classify_find_missing_response(status_code: u16, response_body: &str) -> AccountStatus {
+    let status = |is_banned, status, reason, error: Option<CheckError>, message: &str| AccountStatus {
+        is_banned,
+        status,
+        reason: Some(reason),
+        error,
+        error_message: Some(message.to_string()),
+        response_code: Some(status_code),
+        debug_info: None,
     };
 
-    // Analyze response to determine ban status
     if (200..300).contains(&status_code) {
         // Check for various status indicators in response body
+        const EXPIRED_KEYWORDS: [&str; 3] = ["out of user messages", "Please update your account", "continue using Augment"];
+        let keyword = |keyword: &str| StatusReason::ResponseKeyword { keyword: keyword.to_string() };
+
         if response_body.contains("suspended") {
-            Ok(AccountStatus {
-                is_banned: true,
-                status: "SUSPENDED".to_string(),
-                error_message: Some("Account is suspended based on response content".to_string()),
-                response_code: Some(status_code),
-                debug_info,
-            })
-        } else if response_body.to_lowercase().contains("invalid token") {
-            Ok(AccountStatus {
-                is_banned: false,
-                status: "INVALID_TOKEN".to_string(),
-                error_message: Some("Token is invalid".to_string()),
-                response_code: Some(status_code),
-                debug_info,
-            })
-        } else if response_body.contains("You are out of user messages")
-                || response_body.contains("out of user messages")
-                || response_body.contains("Please update your account")
-                || (response_body.contains("continue using Augment") && !response_body.contains("Please upgrade to the latest version")) {
-            Ok(AccountStatus {
-                is_banned: true,
-                status: "EXPIRED".to_string(),
-                error_message: Some("Account has expired or run out of credits".to_string()),
-                response_code: Some(status_code),
-                debug_info,
-            })
-        } else {
-            Ok(AccountStatus {
-                is_banned: false,
-                status: "ACTIVE".to_string(),
-                error_message: None,
-                response_code: Some(status_code),
-                debug_info,
-            })
+            return status(
+                true,
+                AccountStatusCode::Suspended,
+                keyword("suspended"),
+                None,
+                "Account is suspended based on response content",
+            );
         }
-    } else {
-        // Handle different error status codes
-        let (is_banned, status, error_message) = match status_code {
-            401 => {
-                // 401 通常表示 Token 无效，需要进一步判断是封禁还是 Token 失效
-                if response_body.contains("suspended") || response_body.contains("banned") {
-                    (true, "SUSPENDED", "Account is suspended")
-                } else {
-                    (true, "INVALID_TOKEN", "Token is invalid or expired")
-                }
-            },
-            403 => {
-                // 403 可能是封禁或权限问题
-                if response_body.contains("suspended") || response_body.contains("banned") {
-                    (true, "SUSPENDED", "Account is suspended")
-                } else {
-                    (true, "FORBIDDEN", "Access forbidden - account may be banned")
-                }
-            },
-            429 => (false, "RATE_LIMITED", "Rate limited - account is active but throttled"),
-            500..=599 => (false, "SERVER_ERROR", "Server error - cannot determine ban status"),
-            _ => (true, "UNKNOWN_ERROR", "Unknown error - possible ban"),
-        };
-
-        Ok(AccountStatus {
-            is_banned,
-            status: status.to_string(),
-            error_message: Some(format!("{}: {}", error_message, response_body)),
+        if response_body.to_lowercase().contains("invalid token") {
+            return status(
+                false,
+                AccountStatusCode::InvalidToken,
+                keyword("invalid token"),
+                Some(CheckError::new(ErrorCategory::Auth, "Token is invalid")),
+                "Token is invalid",
+            );
+        }
+        let expired = EXPIRED_KEYWORDS.iter().find(|k| {
+            response_body.contains(*k)
+                // 提示升级客户端版本的响应不代表账号过期
+                && (**k != "continue using Augment" || !response_body.contains("Please upgrade to the latest version"))
+        });
+        if let Some(expired) = expired {
+            return status(
+                true,
+                AccountStatusCode::Expired,
+                keyword(expired),
+                None,
+                "Account has expired or run out of credits",
+            );
+        }
+        return AccountStatus {
+            is_banned: false,
+            status: AccountStatusCode::Active,
+            reason: None,
+            error: None,
+            error_message: None,
             response_code: Some(status_code),
-            debug_info,
-        })
+            debug_info: None,
+        };
     }
+
+    // Handle different error status codes
+    let banned_in_body = response_body.contains("suspended") || response_body.contains("banned");
+    let (is_banned, code, category, error_message) = match status_code {
+        // 401 通常表示 Token 无效，需要进一步判断是封禁还是 Token 失效
+        401 if banned_in_body => (true, AccountStatusCode::Suspended, None, "Account is suspended"),
+        401 => (true, AccountStatusCode::InvalidToken, Some(ErrorCategory::Auth), "Token is invalid or expired"),
+        // 403 可能是封禁或权限问题
+        403 if banned_in_body => (true, AccountStatusCode::Suspended, None, "Account is suspended"),
+        403 => (true, AccountStatusCode::Forbidden, Some(ErrorCategory::Auth), "Access forbidden - account may be banned"),
+        429 => (false, AccountStatusCode::RateLimited, Some(ErrorCategory::RateLimited), "Rate limited - account is active but throttled"),
+        500..=599 => (false, AccountStatusCode::ServerError, Some(ErrorCategory::Server), "Server error - cannot determine ban status"),
+        _ => (true, AccountStatusCode::UnknownError, Some(ErrorCategory::Unknown), "Unknown error - possible ban"),
+    };
+    let message = format!("{}: {}", error_message, response_body);
+    status(
+        is_banned,
+        code,
+        StatusReason::HttpStatus { code: status_code },
+        category.map(|category| CheckError::new(category, message.clone())),
+        &message,
+    )
 }
 
 /// 从 auth session 中提取 access token
-pub async fn extract_token_from_session(session: &str) -> Result<AugmentTokenResponse, String> {
+pub async fn extract_token_from_session(api: &AugmentApi, session: &str) -> Result<AugmentTokenResponse, String> {
     use regex::Regex;
 
     // 生成 PKCE 参数
@@ -447,16 +586,15 @@ pub async fn extract_token_from_session(session: &str) -> Result<AugmentTokenRes
     let client_id = CLIENT_ID;
 
     // 使用 session 访问 terms-accept 获取 HTML
-    let terms_url = format!(
-        "{}/terms-accept?response_type=code&code_challenge={}&client_id={}&state={}&prompt=login",
-        AUTH_BASE_URL, code_challenge, client_id, state
-    );
+    let terms_url = api.endpoints().auth_url(&format!(
+        "/terms-accept?response_type=code&code_challenge={}&client_id={}&state={}&prompt=login",
+        code_challenge, client_id, state
+    ));
 
-    let client = reqwest::Client::new();
+    let client = api.client();
     let html_response = client
         .get(&terms_url)
         .header("Cookie", format!("session={}", session))
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .send()
         .await
         .map_err(|e| format!("Failed to fetch terms page: {}", e))?;
@@ -489,7 +627,7 @@ pub async fn extract_token_from_session(session: &str) -> Result<AugmentTokenRes
         .map(|m| m.as_str())
         .ok_or("SESSION_ERROR_OR_ACCOUNT_BANNED")?;
 
-    println!("Extracted - code: {}, state: {}, tenant_url: {}", code, parsed_state, tenant_url);
+    log::debug!("Extracted authorization code (state: {}) for tenant_url: {}", parsed_state, tenant_url);
 
     // 用授权码换 Token
     let token_url = format!("{}token", tenant_url);
@@ -518,18 +656,15 @@ pub async fn extract_token_from_session(session: &str) -> Result<AugmentTokenRes
     let token = token_data.access_token.clone();
     let tenant_url_clone = tenant_url.to_string();
 
-    let email = match get_models(&token, &tenant_url_clone).await {
+    let email = match get_models(api, &token, &tenant_url_clone).await {
         Ok(models_response) => Some(models_response.user.email),
         Err(err) => {
-            println!("Failed to get user email from session: {}", err);
+            log::warn!("Failed to get user email from session: {}", err);
             None
         }
     };
 
-    println!("📦 Final AugmentTokenResponse:");
-    println!("   access_token: {}", token_data.access_token);
-    println!("   tenant_url: {}", tenant_url);
-    println!("   email: {:?}", email);
+    log::debug!("Session exchanged for access token (tenant_url: {}, email: {:?})", tenant_url, email);
 
     Ok(AugmentTokenResponse {
         access_token: token_data.access_token,
@@ -559,21 +694,17 @@ fn extract_token_from_portal_url(portal_url: &str) -> Option<String> {
 }
 
 // 获取Portal信息
-async fn get_portal_info(portal_url: &str) -> Result<PortalInfo, String> {
+async fn get_portal_info(api: &AugmentApi, portal_url: &str) -> Result<PortalInfo, String> {
     let token = extract_token_from_portal_url(portal_url)
         .ok_or("Failed to extract token from portal URL")?;
 
     // 获取customer信息
-    let customer_url = format!("https://portal.withorb.com/api/v1/customer_from_link?token={}", token);
+    let customer_url = api.endpoints().portal_url(&format!("/api/v1/customer_from_link?token={}", token));
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
 
     let customer_response = client
         .get(&customer_url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .send()
@@ -600,14 +731,13 @@ async fn get_portal_info(portal_url: &str) -> Result<PortalInfo, String> {
         .ok_or("Pricing unit ID not found")?;
 
     // 获取ledger summary
-    let ledger_url = format!(
-        "https://portal.withorb.com/api/v1/customers/{}/ledger_summary?pricing_unit_id={}&token={}",
+    let ledger_url = api.endpoints().portal_url(&format!(
+        "/api/v1/customers/{}/ledger_summary?pricing_unit_id={}&token={}",
         customer_id, pricing_unit_id, token
-    );
+    ));
 
     let ledger_response = client
         .get(&ledger_url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .send()
@@ -625,9 +755,9 @@ async fn get_portal_info(portal_url: &str) -> Result<PortalInfo, String> {
         .map_err(|e| format!("Failed to parse ledger response: {}", e))?;
 
     // 解析Portal信息
-    let credits_balance: i32 = ledger_data["credits_balance"].as_str()
+    let credits_balance = ledger_data["credits_balance"].as_str()
         .and_then(|s| s.parse::<f64>().ok())
-        .map(|v| v.floor() as i32)
+        .map(crate::storage::token_fields::round_credits)
         .unwrap_or(0);
 
     let mut expiry_date = None;
@@ -652,329 +782,454 @@ async fn get_portal_info(portal_url: &str) -> Result<PortalInfo, String> {
     })
 }
 
-// 批量检测账号状态
-pub async fn batch_check_account_status(
-    tokens: Vec<TokenInfo>,
-    app_session_cache: Arc<Mutex<HashMap<String, crate::AppSessionCache>>>,
-) -> Result<Vec<TokenStatusResult>, String> {
+/// 批量检测的并发数与超时设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchCheckOptions {
+    /// 同时检测的账号数上限
+    pub max_concurrency: usize,
+    /// 单个账号检测（含 token 刷新、门户查询）的超时秒数
+    pub timeout_secs: u64,
+    /// 诊断模式：结果中附带 find-missing 请求和响应的调试信息
+    pub debug_info: bool,
+}
+
+impl Default for BatchCheckOptions {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 8,
+            timeout_secs: 60,
+            debug_info: false,
+        }
+    }
+}
+
+impl BatchCheckOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrency == 0 {
+            return Err("并发数必须大于 0".to_string());
+        }
+        if self.timeout_secs == 0 {
+            return Err("超时时间必须大于 0 秒".to_string());
+        }
+        Ok(())
+    }
+}
 
-    // 创建并发任务并立即spawn
-    let mut handles = Vec::new();
+/// 批量检测的取消句柄，clone 后共享同一状态
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<tokio::sync::watch::Sender<bool>>);
 
-    for token_info in tokens {
-        let mut token = token_info.access_token.clone();
-        let mut tenant_url = token_info.tenant_url.clone();
-        let token_id = token_info.id.clone();
-        let portal_url = token_info.portal_url.clone();
-        let auth_session = token_info.auth_session.clone();
-        let cache = app_session_cache.clone();
+impl Default for CancelHandle {
+    fn default() -> Self {
+        Self(Arc::new(tokio::sync::watch::channel(false).0))
+    }
+}
 
-        let handle = tokio::spawn(async move {
-            println!("Checking status for token: {:?}", token_id);
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
 
-            // 1. 先检测账号封禁状态
-            let status_result = check_account_ban_status(&token, &tenant_url).await;
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
 
-            // 处理账号状态检测结果
-            let mut status_result = match status_result {
-                Ok(status) => status,
-                Err(err) => {
-                    // 如果出错，创建一个错误状态并直接返回
-                    let error_status = AccountStatus {
-                        is_banned: false,
-                        status: "ERROR".to_string(),
-                        error_message: Some(format!("Failed to check status: {}", err)),
-                        response_code: None,
-                        debug_info: DebugInfo {
-                            request_url: format!("{}find-missing", tenant_url),
-                            request_headers: HashMap::new(),
-                            request_body: "{}".to_string(),
-                            response_headers: HashMap::new(),
-                            response_body: format!("Error: {}", err),
-                            response_status_text: "Error".to_string(),
-                        },
-                    };
-
-                    return TokenStatusResult {
-                        token_id,
-                        access_token: token,
-                        tenant_url,
-                        portal_url,
-                        status_result: error_status,
-                        portal_info: None,
-                        portal_error: Some(format!("Status check failed: {}", err)),
-                        suspensions: None,
-                        email_note: None,
-                    };
-                }
-            };
+    /// 等待取消
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.subscribe();
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
 
-            // 2. 如果检测到 INVALID_TOKEN 且有 auth_session，尝试自动刷新
-            if status_result.status == "INVALID_TOKEN" {
-                if let Some(ref session) = auth_session {
-                    println!("Detected INVALID_TOKEN for {:?}, attempting auto-refresh with auth_session", token_id);
-
-                    match extract_token_from_session(session).await {
-                        Ok(new_token_response) => {
-                            println!("Successfully refreshed token for {:?}", token_id);
-                            // 更新 token 和 tenant_url
-                            token = new_token_response.access_token;
-                            tenant_url = new_token_response.tenant_url;
-
-                            // Session 导入不再获取 portal_url，保持原值
-                            // portal_url 保持不变
-
-                            // 重新检测状态
-                            match check_account_ban_status(&token, &tenant_url).await {
-                                Ok(new_status) => {
-                                    status_result = new_status;
-                                    status_result.error_message = Some(format!(
-                                        "Token was invalid but successfully auto-refreshed. New status: {}",
-                                        status_result.status
-                                    ));
-                                }
-                                Err(err) => {
-                                    println!("Failed to check status after refresh: {}", err);
-                                    status_result.error_message = Some(format!(
-                                        "Token refreshed but status check failed: {}",
-                                        err
-                                    ));
-                                }
-                            }
+/// 所有批量检测共享的并发上限，后台定时检测与手动检测同时进行时合计不超过该上限
+#[derive(Debug, Clone)]
+pub struct CheckLimiter(Arc<tokio::sync::Semaphore>);
+
+impl CheckLimiter {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self(Arc::new(tokio::sync::Semaphore::new(max_concurrency.max(1))))
+    }
+}
+
+impl Default for CheckLimiter {
+    fn default() -> Self {
+        Self::new(BatchCheckOptions::default().max_concurrency)
+    }
+}
+
+/// 单个账号检测完成时的进度
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchCheckProgress<'a> {
+    pub completed: usize,
+    pub total: usize,
+    pub result: &'a TokenStatusResult,
+}
+
+/// 批量检测结果；取消时只包含已完成的账号
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchCheckOutcome {
+    pub results: Vec<TokenStatusResult>,
+    pub cancelled: bool,
+}
+
+/// 检测出错（请求失败、超时）时的结果，保留原 token 信息
+fn error_status_result(token_info: &TokenInfo, error: CheckError) -> TokenStatusResult {
+    TokenStatusResult {
+        token_id: token_info.id.clone(),
+        access_token: token_info.access_token.clone(),
+        tenant_url: token_info.tenant_url.clone(),
+        portal_url: token_info.portal_url.clone(),
+        portal_error: Some(error.message.clone()),
+        status_result: AccountStatus::failed(error),
+        portal_info: None,
+        suspensions: None,
+        email_note: None,
+    }
+}
+
+// 检测单个账号：封禁状态、失效时自动刷新、封禁详情、门户信息和邮箱
+pub(crate) async fn check_single_token(
+    api: &AugmentApi,
+    token_info: &TokenInfo,
+    cache: crate::augment_user_info::SharedAppSessions,
+    with_debug: bool,
+    refreshed: &mut Option<(String, String)>,
+) -> TokenStatusResult {
+    let mut token = token_info.access_token.clone();
+    let mut tenant_url = token_info.tenant_url.clone();
+    let token_id = token_info.id.clone();
+    let portal_url = token_info.portal_url.clone();
+    let auth_session = token_info.auth_session.clone();
+
+    log::info!("Checking status for token: {:?}", token_id);
+
+    // 1. 先检测账号封禁状态
+    let status_result = check_account_ban_status(api, &token, &tenant_url, with_debug).await;
+
+    // 处理账号状态检测结果
+    let mut status_result = match status_result {
+        Ok(status) => status,
+        Err(err) => {
+            // 如果出错，返回错误状态
+            return error_status_result(
+                token_info,
+                CheckError::new(err.category, format!("Failed to check status: {}", err)),
+            );
+        }
+    };
+
+    // 2. 如果检测到 INVALID_TOKEN 且有 auth_session，尝试自动刷新
+    if status_result.status == AccountStatusCode::InvalidToken {
+        if let Some(ref session) = auth_session {
+            log::info!("Detected INVALID_TOKEN for {:?}, attempting auto-refresh with auth_session", token_id);
+
+            match extract_token_from_session(api, session).await {
+                Ok(new_token_response) => {
+                    log::info!("Successfully refreshed token for {:?}", token_id);
+                    // 更新 token 和 tenant_url
+                    token = new_token_response.access_token;
+                    tenant_url = new_token_response.tenant_url;
+                    // 记录新 token，后续步骤超时时也能带回（旧 token 已经失效）
+                    *refreshed = Some((token.clone(), tenant_url.clone()));
+
+                    // Session 导入不再获取 portal_url，保持原值
+                    // portal_url 保持不变
+
+                    // 重新检测状态
+                    match check_account_ban_status(api, &token, &tenant_url, with_debug).await {
+                        Ok(new_status) => {
+                            status_result = new_status;
+                            status_result.error_message = Some(format!(
+                                "Token was invalid but successfully auto-refreshed. New status: {}",
+                                status_result.status
+                            ));
                         }
                         Err(err) => {
-                            println!("Failed to refresh token for {:?}: {}", token_id, err);
-
-                            // 如果刷新失败原因是 SESSION_ERROR_OR_ACCOUNT_BANNED，视为账号封禁
-                            if err.contains("SESSION_ERROR_OR_ACCOUNT_BANNED") {
-                                status_result.status = "SUSPENDED".to_string();
-                                status_result.is_banned = true;
-                                status_result.error_message = Some(
-                                    "Account is suspended (detected during token refresh)".to_string()
-                                );
-                            } else {
-                                status_result.error_message = Some(format!(
-                                    "Token is invalid. Auto-refresh failed: {}",
-                                    err
-                                ));
-                            }
+                            log::warn!("Failed to check status after refresh: {}", err);
+                            // 旧 token 的 INVALID_TOKEN 已不适用于新 token，状态未知
+                            status_result = AccountStatus::failed(CheckError::new(
+                                err.category,
+                                format!("Token refreshed but status check failed: {}", err),
+                            ));
                         }
                     }
-                } else {
-                    println!("Token {:?} is invalid but no auth_session available for refresh", token_id);
-                    status_result.error_message = Some(
-                        "Token is invalid. No auth_session available for auto-refresh".to_string()
-                    );
+                }
+                Err(err) => {
+                    log::warn!("Failed to refresh token for {:?}: {}", token_id, err);
+
+                    // 如果刷新失败原因是 SESSION_ERROR_OR_ACCOUNT_BANNED，视为账号封禁
+                    if err.contains("SESSION_ERROR_OR_ACCOUNT_BANNED") {
+                        status_result.status = AccountStatusCode::Suspended;
+                        status_result.is_banned = true;
+                        status_result.reason = Some(StatusReason::RefreshRejected);
+                        status_result.error = None;
+                        status_result.error_message = Some(
+                            "Account is suspended (detected during token refresh)".to_string()
+                        );
+                    } else {
+                        status_result.error_message = Some(format!(
+                            "Token is invalid. Auto-refresh failed: {}",
+                            err
+                        ));
+                    }
                 }
             }
+        } else {
+            log::info!("Token {:?} is invalid but no auth_session available for refresh", token_id);
+            status_result.error_message = Some(
+                "Token is invalid. No auth_session available for auto-refresh".to_string()
+            );
+        }
+    }
 
-            // 3. 如果账号被封禁，尝试获取详细的用户信息
-            let mut suspensions_info = None;
-            if status_result.is_banned {
-                // 如果有 auth_session,获取详细的封禁信息
-                if let Some(ref session) = auth_session {
-                    println!("Account banned for {:?}, fetching detailed user info", token_id);
-                    match crate::augment_user_info::get_user_info(session, &cache).await {
-                        Ok(user_info) => {
-                            println!("Successfully fetched user info for banned account {:?}", token_id);
-                            // 保存 suspensions 信息
-                            if let Some(suspensions) = user_info.suspensions {
-                                suspensions_info = Some(suspensions.clone());
-                                status_result.error_message = Some(format!(
-                                    "Account banned. Suspensions: {}",
-                                    serde_json::to_string(&suspensions).unwrap_or_else(|_| "N/A".to_string())
-                                ));
-                            }
-                        }
-                        Err(err) => {
-                            println!("Failed to fetch user info for banned account {:?}: {}", token_id, err);
-                            // 不影响主流程,只记录错误
-                        }
+    // 3. 如果账号被封禁，尝试获取详细的用户信息
+    let mut suspensions_info = None;
+    if status_result.is_banned {
+        // 如果有 auth_session,获取详细的封禁信息
+        if let Some(ref session) = auth_session {
+            log::info!("Account banned for {:?}, fetching detailed user info", token_id);
+            match crate::augment_user_info::get_user_info(api, session, &cache).await {
+                Ok(user_info) => {
+                    log::info!("Successfully fetched user info for banned account {:?}", token_id);
+                    // 保存 suspensions 信息
+                    if let Some(suspensions) = user_info.suspensions {
+                        suspensions_info = Some(suspensions.clone());
+                        status_result.error_message = Some(format!(
+                            "Account banned. Suspensions: {}",
+                            serde_json::to_string(&suspensions).unwrap_or_else(|_| "N/A".to_string())
+                        ));
                     }
                 }
-
-                return TokenStatusResult {
-                    token_id,
-                    access_token: token,
-                    tenant_url,
-                    portal_url,
-                    status_result,
-                    portal_info: None,
-                    portal_error: None,
-                    suspensions: suspensions_info,
-                    email_note: None,  // 封禁账号不获取邮箱
-                };
+                Err(err) => {
+                    log::warn!("Failed to fetch user info for banned account {:?}: {}", token_id, err);
+                    // 不影响主流程,只记录错误
+                }
             }
+        }
 
-            // 4. 如果没有 portal_url 但有 auth_session，尝试获取 portal_url
-            let mut fetched_portal_url = portal_url.clone();
-            if fetched_portal_url.is_none() && auth_session.is_some() {
-                println!("No portal_url for token {:?}, attempting to fetch from auth_session", token_id);
-
-                // 尝试从 auth_session 获取 portal_url
-                if let Some(ref session) = auth_session {
-                    // 检查缓存
-                    let cached_app_session = {
-                        let cache_guard = cache.lock().unwrap();
-                        cache_guard.get(session).map(|c| c.app_session.clone())
-                    };
-
-                    // 尝试使用缓存的 app_session
-                    let app_session = if let Some(app_session) = cached_app_session {
-                        println!("Using cached app_session for portal_url fetch");
-                        match crate::augment_user_info::fetch_app_subscription(&app_session).await {
-                            Ok(subscription) => {
-                                fetched_portal_url = subscription.portal_url.clone();
-                                if let Some(ref url) = fetched_portal_url {
-                                    println!("Successfully fetched portal_url from cached app_session: {}", url);
-                                } else {
-                                    println!("Subscription response has no portal_url");
-                                }
-                                Some(app_session)
-                            }
-                            Err(e) => {
-                                println!("Cached app_session failed: {}, will refresh", e);
-                                None
-                            }
+        return TokenStatusResult {
+            token_id,
+            access_token: token,
+            tenant_url,
+            portal_url,
+            status_result,
+            portal_info: None,
+            portal_error: None,
+            suspensions: suspensions_info,
+            email_note: None,  // 封禁账号不获取邮箱
+        };
+    }
+
+    // 4. 如果没有 portal_url 但有 auth_session，尝试获取 portal_url
+    let mut fetched_portal_url = portal_url.clone();
+    if fetched_portal_url.is_none() && auth_session.is_some() {
+        log::info!("No portal_url for token {:?}, attempting to fetch from auth_session", token_id);
+
+        // 尝试从 auth_session 获取 portal_url
+        if let Some(ref session) = auth_session {
+            // 检查缓存
+            let cached_app_session = cache.lock().unwrap().get(session);
+
+            // 尝试使用缓存的 app_session
+            let app_session = if let Some(app_session) = cached_app_session {
+                log::info!("Using cached app_session for portal_url fetch");
+                match crate::augment_user_info::fetch_app_subscription(api, &app_session).await {
+                    Ok(subscription) => {
+                        fetched_portal_url = subscription.portal_url.clone();
+                        if let Some(ref url) = fetched_portal_url {
+                            log::info!("Successfully fetched portal_url from cached app_session: {}", url);
+                        } else {
+                            log::info!("Subscription response has no portal_url");
                         }
-                    } else {
-                        println!("No cached app_session found, will exchange auth_session");
+                        Some(app_session)
+                    }
+                    Err(e) => {
+                        log::warn!("Cached app_session failed: {}, will refresh", e);
+                        cache.lock().unwrap().remove(session);
                         None
-                    };
-
-                    // 如果缓存失败或不存在，交换新的 app_session
-                    if app_session.is_none() && fetched_portal_url.is_none() {
-                        match crate::augment_user_info::exchange_auth_session_for_app_session(session).await {
-                            Ok(new_app_session) => {
-                                // 更新缓存
-                                {
-                                    let mut cache_guard = cache.lock().unwrap();
-                                    cache_guard.insert(session.clone(), crate::AppSessionCache {
-                                        app_session: new_app_session.clone(),
-                                        created_at: std::time::SystemTime::now(),
-                                    });
-                                }
-
-                                // 获取订阅信息
-                                match crate::augment_user_info::fetch_app_subscription(&new_app_session).await {
-                                    Ok(subscription) => {
-                                        fetched_portal_url = subscription.portal_url;
-                                    }
-                                    Err(e) => {
-                                        println!("Failed to fetch subscription with new app_session: {}", e);
-                                    }
-                                }
+                    }
+                }
+            } else {
+                log::info!("No cached app_session found, will exchange auth_session");
+                None
+            };
+
+            // 如果缓存失败或不存在，交换新的 app_session
+            if app_session.is_none() && fetched_portal_url.is_none() {
+                match crate::augment_user_info::exchange_auth_session_for_app_session(api, session).await {
+                    Ok(new_app_session) => {
+                        // 更新缓存
+                        cache.lock().unwrap().insert(session, new_app_session.clone());
+
+                        // 获取订阅信息
+                        match crate::augment_user_info::fetch_app_subscription(api, &new_app_session).await {
+                            Ok(subscription) => {
+                                fetched_portal_url = subscription.portal_url;
                             }
                             Err(e) => {
-                                println!("Failed to exchange auth_session for app_session: {}", e);
+                                log::warn!("Failed to fetch subscription with new app_session: {}", e);
                             }
                         }
                     }
-
-                    if let Some(ref url) = fetched_portal_url {
-                        println!("Successfully fetched portal_url for token {:?}: {}", token_id, url);
+                    Err(e) => {
+                        log::warn!("Failed to exchange auth_session for app_session: {}", e);
                     }
                 }
             }
 
-            // 5. 获取余额和过期时间信息
-            // 使用 get_portal_info (需要 portal_url)
-            let (portal_info, portal_error) = if let Some(ref url) = fetched_portal_url {
-                match get_portal_info(url).await {
-                    Ok(info) => {
-                        println!("Successfully fetched portal_info for token {:?}: balance={}, expiry={:?}",
-                                 token_id, info.credits_balance, info.expiry_date);
-                        (Some(info), None)
-                    }
-                    Err(err) => {
-                        println!("Failed to fetch portal_info for token {:?}: {}", token_id, err);
-                        (None, Some(err))
-                    }
-                }
-            } else {
-                println!("No portal_url available for token {:?}, skipping portal_info fetch", token_id);
-                (None, Some("No portal_url available".to_string()))
-            };
+            if let Some(ref url) = fetched_portal_url {
+                log::info!("Successfully fetched portal_url for token {:?}: {}", token_id, url);
+            }
+        }
+    }
 
-            // 6. 如果没有邮箱备注,尝试获取邮箱
-            let email_note = if token_info.email_note.is_none() {
-                match get_models(&token, &tenant_url).await {
-                    Ok(models_response) => {
-                        println!("Successfully got email for token {:?}: {}", token_id, models_response.user.email);
-                        Some(models_response.user.email)
-                    }
-                    Err(err) => {
-                        println!("Failed to get email for token {:?}: {}", token_id, err);
-                        None
-                    }
-                }
-            } else {
-                token_info.email_note.clone()
-            };
+    // 5. 获取余额和过期时间信息
+    // 使用 get_portal_info (需要 portal_url)
+    let (portal_info, portal_error) = if let Some(ref url) = fetched_portal_url {
+        match get_portal_info(api, url).await {
+            Ok(info) => {
+                log::info!("Successfully fetched portal_info for token {:?}: balance={}, expiry={:?}",
+                         token_id, info.credits_balance, info.expiry_date);
+                (Some(info), None)
+            }
+            Err(err) => {
+                log::warn!("Failed to fetch portal_info for token {:?}: {}", token_id, err);
+                (None, Some(err))
+            }
+        }
+    } else {
+        log::info!("No portal_url available for token {:?}, skipping portal_info fetch", token_id);
+        (None, Some("No portal_url available".to_string()))
+    };
 
-            TokenStatusResult {
-                token_id,
-                access_token: token,
-                tenant_url,
-                portal_url: fetched_portal_url,
-                status_result,
-                portal_info,
-                portal_error,
-                suspensions: None,  // 正常情况下不需要 suspensions
-                email_note,
+    // 6. 如果没有邮箱备注,尝试获取邮箱
+    let email_note = if token_info.email_note.is_none() {
+        match get_models(api, &token, &tenant_url).await {
+            Ok(models_response) => {
+                log::debug!("Successfully got email for token {:?}: {}", token_id, models_response.user.email);
+                Some(models_response.user.email)
             }
-        });
+            Err(err) => {
+                log::warn!("Failed to get email for token {:?}: {}", token_id, err);
+                None
+            }
+        }
+    } else {
+        token_info.email_note.clone()
+    };
 
-        handles.push(handle);
+    TokenStatusResult {
+        token_id,
+        access_token: token,
+        tenant_url,
+        portal_url: fetched_portal_url,
+        status_result,
+        portal_info,
+        portal_error,
+        suspensions: None,  // 正常情况下不需要 suspensions
+        email_note,
     }
+}
 
-    let mut results = Vec::new();
-    for (index, handle) in handles.into_iter().enumerate() {
-        match handle.await {
-            Ok(result) => results.push(result),
-            Err(err) => {
-                eprintln!("Task {} failed: {}", index, err);
-                // 创建一个错误状态的结果
-                results.push(TokenStatusResult {
-                    token_id: Some(format!("task_{}", index)),
-                    access_token: "".to_string(),
-                    tenant_url: "".to_string(),
-                    portal_url: None,
-                    status_result: AccountStatus {
-                        is_banned: false,
-                        status: "ERROR".to_string(),
-                        error_message: Some(format!("Task execution failed: {}", err)),
-                        response_code: None,
-                        debug_info: DebugInfo {
-                            request_url: "".to_string(),
-                            request_headers: HashMap::new(),
-                            request_body: "{}".to_string(),
-                            response_headers: HashMap::new(),
-                            response_body: format!("Task Error: {}", err),
-                            response_status_text: "Error".to_string(),
-                        },
-                    },
-                    portal_info: None,
-                    portal_error: Some(format!("Task failed: {}", err)),
-                    suspensions: None,
-                    email_note: None,
-                });
+// 批量检测账号状态
+pub async fn batch_check_account_status(
+    api: &AugmentApi,
+    tokens: Vec<TokenInfo>,
+    app_session_cache: crate::augment_user_info::SharedAppSessions,
+) -> Result<Vec<TokenStatusResult>, String> {
+    let options = BatchCheckOptions::default();
+    let outcome = batch_check_account_status_with(
+        api,
+        tokens,
+        app_session_cache,
+        &options,
+        &CheckLimiter::new(options.max_concurrency),
+        &CancelHandle::default(),
+        |_| {},
+    )
+    .await?;
+    Ok(outcome.results)
+}
+
+/// 批量检测账号状态，同时检测的账号数和单个账号的耗时受 `options` 限制
+///
+/// 每个账号开始检测前还要从 `limiter` 取得名额，多个批次共享同一个 `limiter` 时合计并发不超过其上限；
+/// 等待名额的时间不计入超时。
+///
+/// 每完成一个账号调用一次 `on_progress`；`cancel` 取消后不再开始新的检测，进行中的请求被丢弃，
+/// 返回已完成的部分。结果按输入顺序排列。
+pub async fn batch_check_account_status_with(
+    api: &AugmentApi,
+    tokens: Vec<TokenInfo>,
+    app_session_cache: crate::augment_user_info::SharedAppSessions,
+    options: &BatchCheckOptions,
+    limiter: &CheckLimiter,
+    cancel: &CancelHandle,
+    mut on_progress: impl FnMut(BatchCheckProgress<'_>),
+) -> Result<BatchCheckOutcome, String> {
+    use futures::stream::StreamExt;
+
+    let total = tokens.len();
+    let timeout = std::time::Duration::from_secs(options.timeout_secs.max(1));
+    let with_debug = options.debug_info;
+    let mut pending = futures::stream::iter(tokens.into_iter().enumerate())
+        .map(|(index, token_info)| {
+            let cache = app_session_cache.clone();
+            async move {
+                let _permit = limiter.0.acquire().await.expect("检测并发信号量不会被关闭");
+                let mut refreshed = None;
+                let checked = tokio::time::timeout(timeout, check_single_token(api, &token_info, cache, with_debug, &mut refreshed)).await;
+                let result = match checked {
+                    Ok(result) => result,
+                    Err(_) => {
+                        log::warn!("Status check timed out for token: {:?}", token_info.id);
+                        let message = format!("Status check timed out after {}s", timeout.as_secs());
+                        let mut result = error_status_result(&token_info, CheckError::new(ErrorCategory::Timeout, message));
+                        if let Some((access_token, tenant_url)) = refreshed {
+                            result.access_token = access_token;
+                            result.tenant_url = tenant_url;
+                        }
+                        result
+                    }
+                };
+                (index, result)
             }
+        })
+        .buffer_unordered(options.max_concurrency.max(1));
+
+    let mut results = Vec::with_capacity(total);
+    while !cancel.is_cancelled() {
+        tokio::select! {
+            next = pending.next() => match next {
+                Some((index, result)) => {
+                    on_progress(BatchCheckProgress { completed: results.len() + 1, total, result: &result });
+                    results.push((index, result));
+                }
+                None => break,
+            },
+            _ = cancel.cancelled() => {}
         }
     }
+    let cancelled = results.len() < total;
+    if cancelled {
+        log::info!("Batch status check cancelled after {}/{} tokens", results.len(), total);
+    }
 
-    Ok(results)
+    results.sort_by_key(|(index, _)| *index);
+    Ok(BatchCheckOutcome {
+        results: results.into_iter().map(|(_, result)| result).collect(),
+        cancelled,
+    })
 }
 
 /// 获取 Credit 信息 (get-credit-info API)
 pub async fn get_credit_info(
+    api: &AugmentApi,
     token: &str,
     tenant_url: &str,
 ) -> Result<CreditInfoResponse, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
 
     // Ensure tenant_url ends with a slash
     let base_url = if tenant_url.ends_with('/') {
@@ -985,8 +1240,7 @@ pub async fn get_credit_info(
 
     let api_url = format!("{}get-credit-info", base_url);
 
-    println!("=== get-credit-info API Request ===");
-    println!("URL: {}", api_url);
+    log::debug!("get-credit-info request: POST {}", api_url);
 
     // Send request to get-credit-info API
     let response = client
@@ -999,7 +1253,7 @@ pub async fn get_credit_info(
         .map_err(|e| format!("HTTP request failed: {}", e))?;
 
     let status_code = response.status().as_u16();
-    println!("Response Status: {}", status_code);
+    log::debug!("Response Status: {}", status_code);
 
     if status_code != 200 {
         return Err(format!("get-credit-info API returned status {}", status_code));
@@ -1010,7 +1264,7 @@ pub async fn get_credit_info(
         .await
         .map_err(|e| format!("Failed to parse credit info response: {}", e))?;
 
-    println!("Credit Info: balance={}, expiry={}",
+    log::debug!("Credit Info: balance={}, expiry={}",
         credit_info.usage_units_remaining,
         credit_info.current_billing_cycle_end_date_iso);
 
@@ -1019,13 +1273,11 @@ pub async fn get_credit_info(
 
 /// 获取用户模型信息 (get-models API)
 pub async fn get_models(
+    api: &AugmentApi,
     token: &str,
     tenant_url: &str,
 ) -> Result<ModelsResponse, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
 
     // Ensure tenant_url ends with a slash
     let base_url = if tenant_url.ends_with('/') {
@@ -1036,8 +1288,7 @@ pub async fn get_models(
 
     let api_url = format!("{}get-models", base_url);
 
-    println!("=== get-models API Request ===");
-    println!("URL: {}", api_url);
+    log::debug!("get-models request: POST {}", api_url);
 
     // Send request to get-models API
     let response = client
@@ -1050,7 +1301,7 @@ pub async fn get_models(
         .map_err(|e| format!("HTTP request failed: {}", e))?;
 
     let status_code = response.status().as_u16();
-    println!("Response Status: {}", status_code);
+    log::debug!("Response Status: {}", status_code);
 
     if status_code != 200 {
         return Err(format!("get-models API returned status {}", status_code));
@@ -1061,33 +1312,29 @@ pub async fn get_models(
         .await
         .map_err(|e| format!("Failed to parse models response: {}", e))?;
 
-    println!("User Email: {}", models_response.user.email);
+    log::debug!("User Email: {}", models_response.user.email);
 
     Ok(models_response)
 }
 
 /// 使用已有的 app_session 获取 Credit 消费数据
 pub async fn get_batch_credit_consumption_with_app_session(
+    api: &AugmentApi,
     app_session: &str,
 ) -> Result<BatchCreditConsumptionResponse, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
 
     // 并行获取两个数据
-    let stats_url = "https://app.augmentcode.com/api/credit-consumption?groupBy=NONE&granularity=DAY&billingCycle=CURRENT_BILLING_CYCLE";
-    let chart_url = "https://app.augmentcode.com/api/credit-consumption?groupBy=MODEL_NAME&granularity=TOTAL&billingCycle=CURRENT_BILLING_CYCLE";
+    let stats_url = api.endpoints().app_url("/api/credit-consumption?groupBy=NONE&granularity=DAY&billingCycle=CURRENT_BILLING_CYCLE");
+    let chart_url = api.endpoints().app_url("/api/credit-consumption?groupBy=MODEL_NAME&granularity=TOTAL&billingCycle=CURRENT_BILLING_CYCLE");
 
-    println!("Fetching stats from: {}", stats_url);
-    println!("Fetching chart from: {}", chart_url);
+    log::debug!("Fetching credit consumption stats and chart data");
 
     let (stats_result, chart_result) = tokio::join!(
         async {
             let response = client
                 .get(stats_url)
                 .header("Cookie", format!("_session={}", urlencoding::encode(app_session)))
-                .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
                 .header("Accept", "application/json")
                 .send()
                 .await
@@ -1095,23 +1342,22 @@ pub async fn get_batch_credit_consumption_with_app_session(
 
             let status = response.status();
             if !status.is_success() {
-                let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                return Err(format!("Stats API returned status {}: {}", status, error_body));
+                return Err(format!("Stats API returned status {}", status));
             }
 
             let response_text = response.text().await
                 .map_err(|e| format!("Failed to read stats response body: {}", e))?;
 
-            println!("Stats response: {}", response_text);
-
             serde_json::from_str::<CreditConsumptionResponse>(&response_text)
-                .map_err(|e| format!("Failed to parse stats response: {}. Response body: {}", e, response_text))
+                .map_err(|e| {
+                    log::debug!("Unparseable stats response ({} bytes)", response_text.len());
+                    format!("Failed to parse stats response: {}", e)
+                })
         },
         async {
             let response = client
                 .get(chart_url)
                 .header("Cookie", format!("_session={}", urlencoding::encode(app_session)))
-                .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
                 .header("Accept", "application/json")
                 .send()
                 .await
@@ -1119,17 +1365,17 @@ pub async fn get_batch_credit_consumption_with_app_session(
 
             let status = response.status();
             if !status.is_success() {
-                let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                return Err(format!("Chart API returned status {}: {}", status, error_body));
+                return Err(format!("Chart API returned status {}", status));
             }
 
             let response_text = response.text().await
                 .map_err(|e| format!("Failed to read chart response body: {}", e))?;
 
-            println!("Chart response: {}", response_text);
-
             serde_json::from_str::<CreditConsumptionResponse>(&response_text)
-                .map_err(|e| format!("Failed to parse chart response: {}. Response body: {}", e, response_text))
+                .map_err(|e| {
+                    log::debug!("Unparseable chart response ({} bytes)", response_text.len());
+                    format!("Failed to parse chart response: {}", e)
+                })
         }
     );
 
@@ -1141,4 +1387,380 @@ pub async fn get_batch_credit_consumption_with_app_session(
         chart_data,
         portal_url: None,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment_api::mock_server::{MockAccount, MockAugmentServer, MockTokenStatus};
+    use std::sync::Mutex;
+
+    fn token_info(server: &MockAugmentServer, id: &str, account: &MockAccount) -> TokenInfo {
+        TokenInfo {
+            access_token: account.access_token.clone(),
+            tenant_url: format!("{}/", server.base_url()),
+            id: Some(id.to_string()),
+            portal_url: account.portal_token.as_deref().map(|t| server.portal_url(t)),
+            auth_session: account.auth_session.clone(),
+            email_note: None,
+        }
+    }
+
+    fn empty_cache() -> crate::augment_user_info::SharedAppSessions {
+        Arc::new(Mutex::new(crate::augment_user_info::AppSessionStore::default()))
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_active_token() {
+        let account = MockAccount::new("active@example.com", "token-active", MockTokenStatus::Active)
+            .with_portal("portal-active", 123.0);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+
+        let results = batch_check_account_status(
+            &server.api(),
+            vec![token_info(&server, "1", &account)],
+            empty_cache(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert_eq!(result.token_id.as_deref(), Some("1"));
+        assert_eq!(result.status_result.status, AccountStatusCode::Active);
+        assert!(!result.status_result.is_banned);
+        assert_eq!(result.access_token, "token-active");
+        assert_eq!(result.email_note.as_deref(), Some("active@example.com"));
+
+        let portal_info = result.portal_info.as_ref().unwrap();
+        assert_eq!(portal_info.credits_balance, 123);
+        assert!(portal_info.expiry_date.is_some());
+        assert_eq!(server.hits("token"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_refreshes_invalid_token() {
+        let account = MockAccount::new("expired@example.com", "token-expired", MockTokenStatus::Invalid)
+            .with_session("session-expired", "token-refreshed")
+            .with_portal("portal-expired", 50.0);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+
+        let mut info = token_info(&server, "2", &account);
+        // 没有 portal_url 时需要通过 app session 获取
+        info.portal_url = None;
+        let cache = empty_cache();
+
+        let results = batch_check_account_status(&server.api(), vec![info], cache.clone())
+            .await
+            .unwrap();
+
+        let result = &results[0];
+        assert_eq!(result.status_result.status, AccountStatusCode::Active);
+        assert_eq!(result.access_token, "token-refreshed");
+        assert_eq!(result.tenant_url, format!("{}/", server.base_url()));
+        assert!(result
+            .status_result
+            .error_message
+            .as_deref()
+            .unwrap()
+            .contains("successfully auto-refreshed"));
+        assert_eq!(server.hits("token"), 1);
+
+        assert_eq!(result.portal_url.as_deref(), Some(server.portal_url("portal-expired").as_str()));
+        assert_eq!(result.portal_info.as_ref().unwrap().credits_balance, 50);
+        assert_eq!(result.email_note.as_deref(), Some("expired@example.com"));
+        assert_eq!(cache.lock().unwrap().get("session-expired").as_deref(), Some("app-session-expired"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_invalid_token_without_session() {
+        let account = MockAccount::new("nosession@example.com", "token-invalid", MockTokenStatus::Invalid);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+
+        let results = batch_check_account_status(
+            &server.api(),
+            vec![token_info(&server, "3", &account)],
+            empty_cache(),
+        )
+        .await
+        .unwrap();
+
+        let result = &results[0];
+        assert_eq!(result.status_result.status, AccountStatusCode::InvalidToken);
+        assert_eq!(result.access_token, "token-invalid");
+        assert!(result
+            .status_result
+            .error_message
+            .as_deref()
+            .unwrap()
+            .contains("No auth_session"));
+        assert_eq!(server.hits("terms-accept"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_suspended_account() {
+        let suspended = MockAccount::new("banned@example.com", "token-banned", MockTokenStatus::Suspended)
+            .with_session("session-banned", "token-never-issued");
+        let server = MockAugmentServer::start(vec![suspended.clone()]).await;
+
+        let results = batch_check_account_status(
+            &server.api(),
+            vec![token_info(&server, "4", &suspended)],
+            empty_cache(),
+        )
+        .await
+        .unwrap();
+
+        let result = &results[0];
+        assert_eq!(result.status_result.status, AccountStatusCode::Suspended);
+        assert!(result.status_result.is_banned);
+        assert!(result.suspensions.as_ref().unwrap().is_array());
+        assert!(result.email_note.is_none());
+        assert!(result.portal_info.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_refresh_failure_marks_suspended() {
+        // token 已失效，session 对应的账号被封禁，刷新页面拿不到授权码
+        let account = MockAccount::new("gone@example.com", "token-gone", MockTokenStatus::Invalid);
+        let banned = MockAccount::new("gone@example.com", "token-other", MockTokenStatus::Suspended)
+            .with_session("session-gone", "token-never-issued");
+        let server = MockAugmentServer::start(vec![account.clone(), banned]).await;
+
+        let mut info = token_info(&server, "5", &account);
+        info.auth_session = Some("session-gone".to_string());
+
+        let results = batch_check_account_status(&server.api(), vec![info], empty_cache())
+            .await
+            .unwrap();
+
+        let result = &results[0];
+        assert_eq!(result.status_result.status, AccountStatusCode::Suspended);
+        assert!(result.status_result.is_banned);
+        assert_eq!(result.access_token, "token-gone");
+        assert_eq!(server.hits("token"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_reports_progress_and_keeps_input_order() {
+        let accounts: Vec<MockAccount> = (0..5)
+            .map(|i| MockAccount::new(&format!("user{}@example.com", i), &format!("token-{}", i), MockTokenStatus::Active))
+            .collect();
+        let server = MockAugmentServer::start(accounts.clone()).await;
+        let infos: Vec<TokenInfo> = accounts.iter().enumerate().map(|(i, a)| token_info(&server, &i.to_string(), a)).collect();
+
+        let mut progress = Vec::new();
+        let options = BatchCheckOptions { max_concurrency: 2, ..BatchCheckOptions::default() };
+        let outcome = batch_check_account_status_with(&server.api(), infos, empty_cache(), &options, &CheckLimiter::default(), &CancelHandle::default(), |p| {
+            progress.push((p.completed, p.total, p.result.token_id.clone().unwrap()));
+        })
+        .await
+        .unwrap();
+
+        assert!(!outcome.cancelled);
+        let ids: Vec<_> = outcome.results.iter().map(|r| r.token_id.clone().unwrap()).collect();
+        assert_eq!(ids, ["0", "1", "2", "3", "4"]);
+        assert_eq!(progress.iter().map(|p| p.0).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+        assert!(progress.iter().all(|p| p.1 == 5));
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_cancel_returns_partial_results() {
+        let accounts: Vec<MockAccount> = (0..4)
+            .map(|i| MockAccount::new(&format!("user{}@example.com", i), &format!("token-{}", i), MockTokenStatus::Active))
+            .collect();
+        let server = MockAugmentServer::start(accounts.clone()).await;
+        let infos: Vec<TokenInfo> = accounts.iter().enumerate().map(|(i, a)| token_info(&server, &i.to_string(), a)).collect();
+
+        let cancel = CancelHandle::default();
+        let options = BatchCheckOptions { max_concurrency: 1, ..BatchCheckOptions::default() };
+        let outcome = batch_check_account_status_with(&server.api(), infos, empty_cache(), &options, &CheckLimiter::default(), &cancel, |_| cancel.cancel())
+            .await
+            .unwrap();
+
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.results.len(), 1);
+        assert_eq!(outcome.results[0].status_result.status, AccountStatusCode::Active);
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_times_out_slow_tenant() {
+        // 接受连接但从不响应
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+        let server = MockAugmentServer::start(Vec::new()).await;
+        let info = TokenInfo {
+            access_token: "token-slow".to_string(),
+            tenant_url: format!("http://{}/", address),
+            id: Some("slow".to_string()),
+            portal_url: None,
+            auth_session: None,
+            email_note: None,
+        };
+
+        let options = BatchCheckOptions { max_concurrency: 1, timeout_secs: 1, ..BatchCheckOptions::default() };
+        let outcome = batch_check_account_status_with(&server.api(), vec![info], empty_cache(), &options, &CheckLimiter::default(), &CancelHandle::default(), |_| {})
+            .await
+            .unwrap();
+
+        let result = &outcome.results[0];
+        assert_eq!(result.status_result.status, AccountStatusCode::Error);
+        assert_eq!(result.access_token, "token-slow");
+        assert!(result.status_result.error_message.as_deref().unwrap().contains("timed out"));
+        assert_eq!(result.status_result.error.as_ref().unwrap().category, ErrorCategory::Timeout);
+        assert!(result.status_result.debug_info.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_timeout_keeps_refreshed_token() {
+        // 刷新成功后重新检测时租户不再响应
+        let account = MockAccount::new("slow@example.com", "token-old", MockTokenStatus::Invalid)
+            .with_session("session-slow", "token-new")
+            .with_refreshed_status(MockTokenStatus::Unresponsive);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+
+        let options = BatchCheckOptions { max_concurrency: 1, timeout_secs: 2, ..BatchCheckOptions::default() };
+        let outcome = batch_check_account_status_with(
+            &server.api(),
+            vec![token_info(&server, "slow", &account)],
+            empty_cache(),
+            &options,
+            &CheckLimiter::default(),
+            &CancelHandle::default(),
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        let result = &outcome.results[0];
+        assert_eq!(result.status_result.error.as_ref().unwrap().category, ErrorCategory::Timeout);
+        assert_eq!(result.access_token, "token-new");
+        assert_eq!(server.hits("token"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shared_limiter_caps_concurrent_batches() {
+        let account = MockAccount::new("slow@example.com", "token-slow", MockTokenStatus::Unresponsive);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+        let api = server.api();
+        let options = BatchCheckOptions { max_concurrency: 4, timeout_secs: 1, ..BatchCheckOptions::default() };
+        let limiter = CheckLimiter::new(1);
+        let cancel = CancelHandle::default();
+        let run = || {
+            batch_check_account_status_with(
+                &api,
+                vec![token_info(&server, "slow", &account)],
+                empty_cache(),
+                &options,
+                &limiter,
+                &cancel,
+                |_| {},
+            )
+        };
+
+        // 两个批次共享一个名额，只能依次超时
+        let started = std::time::Instant::now();
+        let (first, second) = tokio::join!(run(), run());
+        assert!(started.elapsed() >= std::time::Duration::from_secs(2));
+        for outcome in [first.unwrap(), second.unwrap()] {
+            assert_eq!(outcome.results[0].status_result.error.as_ref().unwrap().category, ErrorCategory::Timeout);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_classifies_connection_failure_as_network() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let server = MockAugmentServer::start(Vec::new()).await;
+        let info = TokenInfo {
+            access_token: "token-offline".to_string(),
+            tenant_url: format!("http://{}/", address),
+            id: Some("offline".to_string()),
+            portal_url: None,
+            auth_session: None,
+            email_note: None,
+        };
+
+        let results = batch_check_account_status(&server.api(), vec![info], empty_cache()).await.unwrap();
+        let status = &results[0].status_result;
+        assert_eq!(status.status, AccountStatusCode::Error);
+        assert!(!status.status.is_conclusive());
+        assert_eq!(status.error.as_ref().unwrap().category, ErrorCategory::Network);
+    }
+
+    #[tokio::test]
+    async fn test_debug_info_is_opt_in() {
+        let account = MockAccount::new("debug@example.com", "token-debug", MockTokenStatus::Active);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+        let tenant_url = format!("{}/", server.base_url());
+
+        let status = check_account_ban_status(&server.api(), "token-debug", &tenant_url, false).await.unwrap();
+        assert!(status.debug_info.is_none());
+        assert!(!serde_json::to_value(&status).unwrap().as_object().unwrap().contains_key("debug_info"));
+
+        let status = check_account_ban_status(&server.api(), "token-debug", &tenant_url, true).await.unwrap();
+        let debug_info = status.debug_info.unwrap();
+        assert!(debug_info.request_url.ends_with("/find-missing"));
+        assert_eq!(debug_info.request_headers.get("Authorization").map(String::as_str), Some("Bearer ***"));
+    }
+
+    #[test]
+    fn test_classify_find_missing_response() {
+        let status = classify_find_missing_response(200, "{}");
+        assert_eq!(status.status, AccountStatusCode::Active);
+        assert!(status.reason.is_none() && status.error.is_none());
+
+        let status = classify_find_missing_response(200, "You are out of user messages");
+        assert_eq!(status.status, AccountStatusCode::Expired);
+        assert!(status.is_banned);
+        assert_eq!(status.reason, Some(StatusReason::ResponseKeyword { keyword: "out of user messages".to_string() }));
+
+        // 只提示升级客户端时仍为正常
+        let status = classify_find_missing_response(200, "Please upgrade to the latest version to continue using Augment");
+        assert_eq!(status.status, AccountStatusCode::Active);
+
+        let status = classify_find_missing_response(401, "unauthorized");
+        assert_eq!(status.status, AccountStatusCode::InvalidToken);
+        assert_eq!(status.error.unwrap().category, ErrorCategory::Auth);
+
+        let status = classify_find_missing_response(403, "account banned");
+        assert_eq!(status.status, AccountStatusCode::Suspended);
+        assert_eq!(status.reason, Some(StatusReason::HttpStatus { code: 403 }));
+        assert!(status.error.is_none());
+
+        let status = classify_find_missing_response(503, "unavailable");
+        assert_eq!(status.status, AccountStatusCode::ServerError);
+        assert_eq!(status.error.unwrap().category, ErrorCategory::Server);
+        assert!(!AccountStatusCode::RateLimited.is_conclusive());
+
+        // 序列化为前端使用的状态字符串
+        assert_eq!(serde_json::to_value(AccountStatusCode::InvalidToken).unwrap(), "INVALID_TOKEN");
+        assert_eq!(serde_json::to_value(ErrorCategory::RateLimited).unwrap(), "rate_limited");
+    }
+
+    #[tokio::test]
+    async fn test_credit_endpoints() {
+        let account = MockAccount::new("credit@example.com", "token-credit", MockTokenStatus::Active)
+            .with_session("session-credit", "token-credit-refreshed")
+            .with_portal("portal-credit", 320.0);
+        let server = MockAugmentServer::start(vec![account]).await;
+        let api = server.api();
+        let tenant_url = format!("{}/", server.base_url());
+
+        let credit_info = get_credit_info(&api, "token-credit", &tenant_url).await.unwrap();
+        assert_eq!(credit_info.usage_units_remaining, 320.0);
+        assert!(get_credit_info(&api, "token-unknown", &tenant_url).await.is_err());
+
+        let consumption = get_batch_credit_consumption_with_app_session(&api, "app-session-credit")
+            .await
+            .unwrap();
+        assert!(!consumption.stats_data.data_points.is_empty());
+        assert!(!consumption.chart_data.data_points.is_empty());
+    }
+}
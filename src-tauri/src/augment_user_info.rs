@@ -1,8 +1,285 @@
 use reqwest;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use urlencoding;
 
+use crate::augment_api::AugmentApi;
+use crate::storage::write_file_atomic;
+use crate::webdav::PasswordManager;
+
+/// App session 缓存文件（加密），位于数据目录
+pub const APP_SESSION_CACHE_FILE: &str = "app_sessions.enc.json";
+
+/// App session 缓存设置，保存在 UnifiedAppConfig 中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSessionCacheSettings {
+    /// 缓存的有效小时数，超过后重新交换
+    pub ttl_hours: u32,
+    /// 最多缓存的 session 数，超出时淘汰最久未使用的
+    pub max_entries: usize,
+    /// 加密保存到数据目录，重启后继续使用
+    pub persist: bool,
+}
+
+impl Default for AppSessionCacheSettings {
+    fn default() -> Self {
+        Self {
+            ttl_hours: 24,
+            max_entries: 200,
+            persist: false,
+        }
+    }
+}
+
+impl AppSessionCacheSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ttl_hours == 0 {
+            return Err("缓存有效期必须大于 0 小时".to_string());
+        }
+        if self.max_entries == 0 {
+            return Err("缓存数量上限必须大于 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+// App Session 缓存结构（命令行工具也会用到，因此放在这里而不是 main.rs）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSessionCache {
+    pub app_session: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    /// 最近一次请求确认 session 仍然有效的时间
+    pub last_validated_at: Option<DateTime<Utc>>,
+}
+
+/// 缓存条目的状态，不包含 session 本身
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppSessionInfo {
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub last_validated_at: Option<DateTime<Utc>>,
+    pub persisted: bool,
+}
+
+/// 加密缓存文件的内容
+#[derive(Serialize, Deserialize)]
+struct EncryptedSessions {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 缓存的 key：auth_session 的哈希，内存和文件中都不保存 auth_session 原文
+fn cache_key(auth_session: &str) -> String {
+    format!("{:x}", Sha256::digest(auth_session.as_bytes()))
+}
+
+/// auth_session -> app_session 缓存，按有效期和数量上限淘汰，可加密保存到数据目录
+#[derive(Default)]
+pub struct AppSessionStore {
+    entries: HashMap<String, AppSessionCache>,
+    settings: AppSessionCacheSettings,
+    data_dir: Option<PathBuf>,
+    /// 启用持久化时的加密密钥
+    key: Option<[u8; 32]>,
+}
+
+impl AppSessionStore {
+    pub fn new(settings: AppSessionCacheSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    /// 绑定到数据目录并应用设置：目录变化时清空内存中的缓存；
+    /// 启用持久化（传入密钥）时从文件加载，关闭持久化时删除缓存文件。
+    /// 启用持久化但密钥不可用（传入 None）时只缓存在内存中，不改动缓存文件
+    pub fn attach(&mut self, data_dir: &Path, settings: AppSessionCacheSettings, key: Option<[u8; 32]>) {
+        if self.data_dir.as_deref() != Some(data_dir) {
+            self.entries.clear();
+        }
+        self.data_dir = Some(data_dir.to_path_buf());
+        self.settings = settings;
+        self.key = key.filter(|_| self.settings.persist);
+
+        let path = data_dir.join(APP_SESSION_CACHE_FILE);
+        match self.key {
+            Some(key) => match load_sessions(&path, &key) {
+                Ok(loaded) => {
+                    for (cache_key, entry) in loaded {
+                        self.entries.entry(cache_key).or_insert(entry);
+                    }
+                }
+                Err(e) => log::warn!("读取 app session 缓存失败: {}", e),
+            },
+            None if !self.settings.persist => {
+                if path.exists() {
+                    if let Err(e) = fs::remove_file(&path) {
+                        log::warn!("删除 app session 缓存文件失败: {}", e);
+                    }
+                }
+            }
+            None => log::warn!("app session 缓存密钥不可用，本次只缓存在内存中"),
+        }
+        self.evict(Utc::now());
+        self.save();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn expires_at(&self, entry: &AppSessionCache) -> DateTime<Utc> {
+        entry.created_at + Duration::hours(i64::from(self.settings.ttl_hours.max(1)))
+    }
+
+    /// 未过期的 app_session
+    pub fn get(&mut self, auth_session: &str) -> Option<String> {
+        self.get_at(auth_session, Utc::now())
+    }
+
+    fn get_at(&mut self, auth_session: &str, now: DateTime<Utc>) -> Option<String> {
+        let key = cache_key(auth_session);
+        let expires_at = self.expires_at(self.entries.get(&key)?);
+        if expires_at <= now {
+            self.entries.remove(&key);
+            self.save();
+            return None;
+        }
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used_at = now;
+        Some(entry.app_session.clone())
+    }
+
+    pub fn insert(&mut self, auth_session: &str, app_session: String) {
+        self.insert_at(auth_session, app_session, Utc::now());
+    }
+
+    fn insert_at(&mut self, auth_session: &str, app_session: String, now: DateTime<Utc>) {
+        self.entries.insert(
+            cache_key(auth_session),
+            AppSessionCache {
+                app_session,
+                created_at: now,
+                last_used_at: now,
+                last_validated_at: None,
+            },
+        );
+        self.evict(now);
+        self.save();
+    }
+
+    /// 记录 session 刚被确认有效
+    pub fn mark_validated(&mut self, auth_session: &str) {
+        if let Some(entry) = self.entries.get_mut(&cache_key(auth_session)) {
+            entry.last_validated_at = Some(Utc::now());
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, auth_session: &str) -> bool {
+        let removed = self.entries.remove(&cache_key(auth_session)).is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+
+    pub fn info(&self, auth_session: &str) -> Option<AppSessionInfo> {
+        let entry = self.entries.get(&cache_key(auth_session))?;
+        Some(AppSessionInfo {
+            created_at: entry.created_at,
+            expires_at: self.expires_at(entry),
+            last_used_at: entry.last_used_at,
+            last_validated_at: entry.last_validated_at,
+            persisted: self.key.is_some(),
+        })
+    }
+
+    /// 移除过期条目，超出数量上限时淘汰最久未使用的
+    fn evict(&mut self, now: DateTime<Utc>) {
+        let ttl = Duration::hours(i64::from(self.settings.ttl_hours.max(1)));
+        self.entries.retain(|_, entry| entry.created_at + ttl > now);
+
+        let max_entries = self.settings.max_entries.max(1);
+        if self.entries.len() > max_entries {
+            let mut by_use: Vec<(DateTime<Utc>, String)> = self
+                .entries
+                .iter()
+                .map(|(key, entry)| (entry.last_used_at, key.clone()))
+                .collect();
+            by_use.sort();
+            let excess = self.entries.len() - max_entries;
+            for (_, key) in by_use.into_iter().take(excess) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn save(&self) {
+        let (Some(data_dir), Some(key)) = (&self.data_dir, &self.key) else {
+            return;
+        };
+        if let Err(e) = save_sessions(&data_dir.join(APP_SESSION_CACHE_FILE), key, &self.entries) {
+            log::warn!("保存 app session 缓存失败: {}", e);
+        }
+    }
+}
+
+fn save_sessions(path: &Path, key: &[u8; 32], entries: &HashMap<String, AppSessionCache>) -> Result<(), String> {
+    let plaintext = serde_json::to_string(entries).map_err(|e| format!("序列化缓存失败: {}", e))?;
+    let (ciphertext, nonce) = PasswordManager::new().encrypt_data(&plaintext, key)?;
+    let content = serde_json::to_vec(&EncryptedSessions {
+        version: 1,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+    .map_err(|e| format!("序列化缓存失败: {}", e))?;
+    write_file_atomic(path, &content)
+}
+
+fn load_sessions(path: &Path, key: &[u8; 32]) -> Result<HashMap<String, AppSessionCache>, String> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("读取缓存文件失败: {}", e)),
+    };
+    let file: EncryptedSessions = serde_json::from_slice(&content).map_err(|e| format!("缓存文件格式错误: {}", e))?;
+    let decode = |value: &str| BASE64.decode(value).map_err(|e| format!("缓存文件格式错误: {}", e));
+    let nonce = decode(&file.nonce)?
+        .try_into()
+        .map_err(|_| "缓存文件格式错误: 随机数长度不正确".to_string())?;
+    // 密钥变化（例如换了电脑）时无法解密，视为没有缓存
+    let plaintext = PasswordManager::new()
+        .decrypt_data(&decode(&file.ciphertext)?, key, &nonce)
+        .map_err(|_| "无法解密缓存文件，密钥已变化".to_string())?;
+    serde_json::from_str(&plaintext).map_err(|e| format!("缓存文件格式错误: {}", e))
+}
+
+
+/// 共享的 app session 缓存
+pub type SharedAppSessions = Arc<Mutex<AppSessionStore>>;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserInfo {
     pub email: Option<String>,
@@ -30,7 +307,7 @@ pub struct CompleteUserInfo {
 }
 
 /// 通过 auth session 交换 app session
-pub async fn exchange_auth_session_for_app_session(auth_session: &str) -> Result<String, String> {
+pub async fn exchange_auth_session_for_app_session(api: &AugmentApi, auth_session: &str) -> Result<String, String> {
     use reqwest::cookie::Jar;
     use std::sync::Arc;
 
@@ -38,39 +315,32 @@ pub async fn exchange_auth_session_for_app_session(auth_session: &str) -> Result
     let jar = Arc::new(Jar::default());
 
     // 设置 auth session cookie 到 auth.augmentcode.com 域
-    let auth_url = "https://auth.augmentcode.com/".parse::<reqwest::Url>()
+    let auth_url = api.endpoints().auth_url("/").parse::<reqwest::Url>()
         .map_err(|e| format!("Failed to parse auth URL: {}", e))?;
     jar.add_cookie_str(
         &format!("session={}", auth_session),
         &auth_url
     );
 
-    // 创建带 cookie store 的客户端
-    let client = reqwest::Client::builder()
-        .cookie_provider(jar.clone())
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    // 创建带 cookie store 的客户端（沿用共享客户端的代理和证书设置）
+    let client = api.client_with_cookies(jar.clone())?;
 
     // HEAD 敲门
     let _ = client
-        .head("https://app.augmentcode.com/login")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .head(api.endpoints().app_url("/login"))
         .send()
         .await;
 
     // GET 触发授权流
     let _ = client
-        .get("https://app.augmentcode.com/login")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .get(api.endpoints().app_url("/login"))
         .send()
         .await
         .map_err(|e| format!("Failed to exchange session: {}", e))?;
 
     // 发送一个简单的请求来获取 app session
     let final_response = client
-        .get("https://app.augmentcode.com/api/user")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .get(api.endpoints().app_url("/api/user"))
         .send()
         .await;
 
@@ -89,15 +359,11 @@ pub async fn exchange_auth_session_for_app_session(auth_session: &str) -> Result
 }
 
 /// 获取用户信息
-pub async fn fetch_app_user(app_session: &str) -> Result<UserInfo, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+pub async fn fetch_app_user(api: &AugmentApi, app_session: &str) -> Result<UserInfo, String> {
+    let client = api.client();
 
     let response = client
-        .get("https://app.augmentcode.com/api/user")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .get(api.endpoints().app_url("/api/user"))
         .header("Cookie", format!("_session={}", urlencoding::encode(app_session)))
         .send()
         .await
@@ -110,15 +376,11 @@ pub async fn fetch_app_user(app_session: &str) -> Result<UserInfo, String> {
 }
 
 /// 获取订阅信息
-pub async fn fetch_app_subscription(app_session: &str) -> Result<SubscriptionInfo, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+pub async fn fetch_app_subscription(api: &AugmentApi, app_session: &str) -> Result<SubscriptionInfo, String> {
+    let client = api.client();
 
     let response = client
-        .get("https://app.augmentcode.com/api/subscription")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .get(api.endpoints().app_url("/api/subscription"))
         .header("Cookie", format!("_session={}", urlencoding::encode(app_session)))
         .send()
         .await
@@ -131,11 +393,10 @@ pub async fn fetch_app_subscription(app_session: &str) -> Result<SubscriptionInf
 }
 
 /// 获取积分信息
-pub async fn fetch_app_credits(app_session: &str) -> Result<CreditsInfo, String> {
-    let client = reqwest::Client::new();
+pub async fn fetch_app_credits(api: &AugmentApi, app_session: &str) -> Result<CreditsInfo, String> {
+    let client = api.client();
     let response = client
-        .get("https://app.augmentcode.com/api/credits")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .get(api.endpoints().app_url("/api/credits"))
         .header("Cookie", format!("_session={}", urlencoding::encode(app_session)))
         .send()
         .await
@@ -148,9 +409,9 @@ pub async fn fetch_app_credits(app_session: &str) -> Result<CreditsInfo, String>
 }
 
 /// 使用已有的 app_session 获取完整的用户信息
-pub async fn get_user_info_with_app_session(app_session: &str) -> Result<CompleteUserInfo, String> {
+pub async fn get_user_info_with_app_session(api: &AugmentApi, app_session: &str) -> Result<CompleteUserInfo, String> {
     // 只获取用户信息
-    let user_info = fetch_app_user(app_session).await.ok();
+    let user_info = fetch_app_user(api, app_session).await.ok();
 
     // 计算 ban_status
     let ban_status = if let Some(ref user) = user_info {
@@ -187,40 +448,170 @@ pub async fn get_user_info_with_app_session(app_session: &str) -> Result<Complet
 
 /// 获取完整的用户信息 (使用缓存的 app_session)
 pub async fn get_user_info(
+    api: &AugmentApi,
     auth_session: &str,
-    app_session_cache: &std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, crate::AppSessionCache>>>,
+    app_session_cache: &SharedAppSessions,
 ) -> Result<CompleteUserInfo, String> {
-    use std::time::SystemTime;
+    // 1. 检查缓存中是否有未过期的 app_session
+    let cached_app_session = app_session_cache.lock().unwrap().get(auth_session);
 
-    // 1. 检查缓存中是否有有效的 app_session
-    let cached_app_session = {
-        let cache = app_session_cache.lock().unwrap();
-        cache.get(auth_session).map(|c| c.app_session.clone())
-    };
-
-    // 2. 尝试使用缓存的 app_session
+    // 2. 尝试使用缓存的 app_session，失败时丢弃
     if let Some(app_session) = cached_app_session {
-        match get_user_info_with_app_session(&app_session).await {
-            Ok(user_info) => return Ok(user_info),
-            Err(e) => println!("Cached app_session failed: {}, will refresh", e),
+        match get_user_info_with_app_session(api, &app_session).await {
+            Ok(user_info) => {
+                app_session_cache.lock().unwrap().mark_validated(auth_session);
+                return Ok(user_info);
+            }
+            Err(e) => {
+                log::warn!("Cached app_session failed: {}, will refresh", e);
+                app_session_cache.lock().unwrap().remove(auth_session);
+            }
         }
     }
 
     // 3. 交换新的 app_session
-    let app_session = exchange_auth_session_for_app_session(auth_session).await?;
+    let app_session = exchange_auth_session_for_app_session(api, auth_session).await?;
 
-    println!("App session obtained: {}", &app_session[..20.min(app_session.len())]);
+    log::debug!("App session obtained");
 
     // 4. 更新缓存
-    {
-        let mut cache = app_session_cache.lock().unwrap();
-        cache.insert(auth_session.to_string(), crate::AppSessionCache {
-            app_session: app_session.clone(),
-            created_at: SystemTime::now(),
-        });
-    }
+    app_session_cache.lock().unwrap().insert(auth_session, app_session.clone());
 
     // 5. 获取用户信息
-    get_user_info_with_app_session(&app_session).await
+    get_user_info_with_app_session(api, &app_session).await
+}
+
+/// 检查缓存的 app_session 是否仍然有效，失效或 `force_refresh` 时重新交换
+pub async fn validate_or_refresh_app_session(
+    api: &AugmentApi,
+    auth_session: &str,
+    cache: &SharedAppSessions,
+    force_refresh: bool,
+) -> Result<AppSessionInfo, String> {
+    let cached = if force_refresh { None } else { cache.lock().unwrap().get(auth_session) };
+    if let Some(app_session) = cached {
+        match fetch_app_user(api, &app_session).await {
+            Ok(_) => {
+                let mut cache = cache.lock().unwrap();
+                cache.mark_validated(auth_session);
+                return cache.info(auth_session).ok_or_else(|| "缓存已被清除".to_string());
+            }
+            Err(e) => log::warn!("Cached app_session failed validation: {}, will refresh", e),
+        }
+    }
+
+    cache.lock().unwrap().remove(auth_session);
+    let app_session = exchange_auth_session_for_app_session(api, auth_session).await?;
+    fetch_app_user(api, &app_session).await?;
+    let mut cache = cache.lock().unwrap();
+    cache.insert(auth_session, app_session);
+    cache.mark_validated(auth_session);
+    cache.info(auth_session).ok_or_else(|| "缓存已被清除".to_string())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment_api::mock_server::{MockAccount, MockAugmentServer, MockTokenStatus};
+    use tempfile::tempdir;
+
+    fn settings(ttl_hours: u32, max_entries: usize) -> AppSessionCacheSettings {
+        AppSessionCacheSettings { ttl_hours, max_entries, persist: false }
+    }
+
+    #[test]
+    fn test_expired_sessions_are_dropped() {
+        let mut store = AppSessionStore::new(settings(1, 10));
+        let start = Utc::now();
+        store.insert_at("session-a", "app-a".to_string(), start);
+
+        assert_eq!(store.get_at("session-a", start + Duration::minutes(59)).as_deref(), Some("app-a"));
+        assert_eq!(store.get_at("session-a", start + Duration::minutes(61)), None);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_session_is_evicted() {
+        let mut store = AppSessionStore::new(settings(24, 2));
+        let start = Utc::now();
+        store.insert_at("session-a", "app-a".to_string(), start);
+        store.insert_at("session-b", "app-b".to_string(), start + Duration::seconds(1));
+        // a 最近被使用过，超出上限时淘汰 b
+        store.get_at("session-a", start + Duration::seconds(2));
+        store.insert_at("session-c", "app-c".to_string(), start + Duration::seconds(3));
+
+        assert_eq!(store.len(), 2);
+        assert!(store.info("session-a").is_some());
+        assert!(store.info("session-b").is_none());
+        assert!(store.info("session-c").is_some());
+    }
+
+    #[test]
+    fn test_persisted_sessions_are_encrypted_and_reloaded() {
+        let dir = tempdir().unwrap();
+        let key = [7u8; 32];
+        let persist = AppSessionCacheSettings { persist: true, ..AppSessionCacheSettings::default() };
+
+        let mut store = AppSessionStore::default();
+        store.attach(dir.path(), persist.clone(), Some(key));
+        store.insert("session-a", "app-secret".to_string());
+
+        let content = fs::read_to_string(dir.path().join(APP_SESSION_CACHE_FILE)).unwrap();
+        assert!(!content.contains("app-secret"));
+        assert!(!content.contains("session-a"));
+
+        let mut reloaded = AppSessionStore::default();
+        reloaded.attach(dir.path(), persist.clone(), Some(key));
+        assert_eq!(reloaded.get("session-a").as_deref(), Some("app-secret"));
+
+        // 密钥暂时不可用时只缓存在内存中，保留缓存文件
+        let mut memory_only = AppSessionStore::default();
+        memory_only.attach(dir.path(), persist.clone(), Some(key));
+        memory_only.attach(dir.path(), persist.clone(), None);
+        memory_only.insert("session-b", "app-secret-b".to_string());
+        assert_eq!(memory_only.len(), 2);
+        assert!(dir.path().join(APP_SESSION_CACHE_FILE).exists());
+
+        // 密钥不同时无法解密，视为没有缓存
+        let mut other = AppSessionStore::default();
+        other.attach(dir.path(), persist.clone(), Some([8u8; 32]));
+        assert!(other.is_empty());
+
+        // 关闭持久化时删除缓存文件
+        reloaded.attach(dir.path(), AppSessionCacheSettings::default(), None);
+        assert!(!dir.path().join(APP_SESSION_CACHE_FILE).exists());
+        assert_eq!(reloaded.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate_or_refresh_app_session() {
+        let server = MockAugmentServer::start(vec![
+            MockAccount::new("a@example.com", "token-a", MockTokenStatus::Active).with_session("session-a", "token-a2"),
+        ])
+        .await;
+        let api = server.api();
+        let cache: SharedAppSessions = Arc::new(Mutex::new(AppSessionStore::default()));
+
+        // 没有缓存时交换新的 session
+        let info = validate_or_refresh_app_session(&api, "session-a", &cache, false).await.unwrap();
+        assert!(info.last_validated_at.is_some());
+        let exchange_hits = server.hits("login");
+        assert!(exchange_hits > 0);
+
+        // 缓存有效时只做校验
+        validate_or_refresh_app_session(&api, "session-a", &cache, false).await.unwrap();
+        assert_eq!(server.hits("login"), exchange_hits);
+
+        // 缓存失效时丢弃并重新交换
+        cache.lock().unwrap().insert("session-a", "app-stale".to_string());
+        validate_or_refresh_app_session(&api, "session-a", &cache, false).await.unwrap();
+        assert_eq!(server.hits("login"), exchange_hits * 2);
+        assert_eq!(cache.lock().unwrap().get("session-a").as_deref(), Some("app-session-a"));
+
+        validate_or_refresh_app_session(&api, "session-a", &cache, true).await.unwrap();
+        assert_eq!(server.hits("login"), exchange_hits * 3);
+
+        assert!(validate_or_refresh_app_session(&api, "session-unknown", &cache, false).await.is_err());
+        assert!(cache.lock().unwrap().info("session-unknown").is_none());
+    }
+}
@@ -0,0 +1,485 @@
+//! 数据文件的本地滚动备份
+//!
+//! tokens.json、bookmarks.json 和 config.json 在写入前按写入次数或时间间隔打包为 zip，
+//! 保存在数据目录的 `backups/` 下，只保留最近若干份。启动时检查数据文件能否解析，
+//! 损坏时给出最近一份可用的备份供恢复。
+
+use crate::storage::write_file_atomic;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+pub const BACKUP_DIR: &str = "backups";
+/// 参与备份的数据文件
+pub const BACKUP_FILES: &[&str] = &["tokens.json", "bookmarks.json", "config.json"];
+const MANIFEST_NAME: &str = "manifest.json";
+const FILE_PREFIX: &str = "backup-";
+const FILE_EXTENSION: &str = ".zip";
+
+/// 备份策略，保存在 UnifiedAppConfig 中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// 每写入多少次备份一次，0 表示不按次数备份
+    pub every_writes: u32,
+    /// 距上次备份超过多少小时后，下一次写入前备份，0 表示不按时间备份
+    pub interval_hours: u32,
+    /// 保留的备份份数
+    pub max_generations: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            every_writes: 20,
+            interval_hours: 24,
+            max_generations: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupReason {
+    /// 达到写入次数
+    WriteCount,
+    /// 距上次备份超过时间间隔
+    Interval,
+    /// 手动创建
+    Manual,
+    /// 恢复备份前保存当前数据
+    BeforeRestore,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: DateTime<Utc>,
+    pub reason: BackupReason,
+    pub app_version: String,
+    pub files: Vec<BackupFileInfo>,
+}
+
+/// 备份列表中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    /// 压缩后的大小
+    pub size: u64,
+    pub manifest: BackupManifest,
+}
+
+/// 备份中单个文件的检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFileReport {
+    pub name: String,
+    pub size: u64,
+    pub checksum_ok: bool,
+    /// 内容能否解析为 JSON
+    pub valid: bool,
+    /// tokens.json 的账号数或 bookmarks.json 的书签数
+    pub items: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInspection {
+    pub info: BackupInfo,
+    pub files: Vec<BackupFileReport>,
+}
+
+/// 启动检查发现的损坏文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub file: String,
+    pub error: String,
+    /// 包含该文件可用副本的最新备份
+    pub backup: Option<String>,
+}
+
+fn backup_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(BACKUP_DIR)
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// 只接受本模块生成的文件名，防止通过命令参数访问备份目录以外的文件
+fn backup_path(data_dir: &Path, file_name: &str) -> Result<PathBuf, String> {
+    let valid = file_name.starts_with(FILE_PREFIX)
+        && file_name.ends_with(FILE_EXTENSION)
+        && !file_name.contains(['/', '\\'])
+        && !file_name.contains("..");
+    if !valid {
+        return Err(format!("无效的备份文件名: {}", file_name));
+    }
+    Ok(backup_dir(data_dir).join(file_name))
+}
+
+/// 打包当前数据文件，并按 `max_generations` 清理旧备份
+pub fn create_backup(data_dir: &Path, reason: BackupReason, max_generations: u32) -> Result<BackupInfo, String> {
+    use zip::write::FileOptions;
+
+    let mut files = Vec::new();
+    for name in BACKUP_FILES {
+        match fs::read(data_dir.join(name)) {
+            Ok(content) => files.push((name.to_string(), content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("读取 {} 失败: {}", name, e)),
+        }
+    }
+    if files.is_empty() {
+        return Err("没有可备份的数据文件".to_string());
+    }
+
+    let created_at = Utc::now();
+    let manifest = BackupManifest {
+        created_at,
+        reason,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: files
+            .iter()
+            .map(|(name, content)| BackupFileInfo {
+                name: name.clone(),
+                size: content.len() as u64,
+                sha256: sha256_hex(content),
+            })
+            .collect(),
+    };
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| format!("序列化备份清单失败: {}", e))?;
+    for (name, content) in std::iter::once((MANIFEST_NAME.to_string(), manifest_json)).chain(files) {
+        zip.start_file(name, options).map_err(|e| format!("写入备份失败: {}", e))?;
+        zip.write_all(&content).map_err(|e| format!("写入备份失败: {}", e))?;
+    }
+    let bytes = zip.finish().map_err(|e| format!("写入备份失败: {}", e))?.into_inner();
+
+    let file_name = format!("{}{}{}", FILE_PREFIX, created_at.format("%Y%m%d-%H%M%S-%3f"), FILE_EXTENSION);
+    write_file_atomic(&backup_dir(data_dir).join(&file_name), &bytes)?;
+    log::info!("已创建数据备份 {}（{:?}）", file_name, reason);
+
+    prune_backups(data_dir, max_generations)?;
+    Ok(BackupInfo {
+        file_name,
+        size: bytes.len() as u64,
+        manifest,
+    })
+}
+
+/// 只保留最新的 `max_generations` 份备份（至少保留一份）
+fn prune_backups(data_dir: &Path, max_generations: u32) -> Result<(), String> {
+    let keep = max_generations.max(1) as usize;
+    for backup in list_backups(data_dir)?.into_iter().skip(keep) {
+        let path = backup_dir(data_dir).join(&backup.file_name);
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("删除旧备份 {} 失败: {}", path.display(), e);
+        }
+    }
+    Ok(())
+}
+
+fn open_archive(path: &Path) -> Result<zip::ZipArchive<fs::File>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("打开备份失败: {}", e))?;
+    zip::ZipArchive::new(file).map_err(|e| format!("备份文件损坏: {}", e))
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive.by_name(name).map_err(|e| format!("备份中缺少 {}: {}", name, e))?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).map_err(|e| format!("读取备份内容失败: {}", e))?;
+    Ok(content)
+}
+
+fn read_info(path: &Path) -> Result<BackupInfo, String> {
+    let mut archive = open_archive(path)?;
+    let manifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST_NAME)?)
+        .map_err(|e| format!("备份清单无效: {}", e))?;
+    Ok(BackupInfo {
+        file_name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        manifest,
+    })
+}
+
+/// 所有备份，最新的在前；无法读取的备份文件跳过
+pub fn list_backups(data_dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match fs::read_dir(backup_dir(data_dir)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("读取备份目录失败: {}", e)),
+    };
+
+    let mut backups: Vec<BackupInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION))
+        })
+        .filter_map(|path| match read_info(&path) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                log::warn!("跳过无法读取的备份 {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.manifest.created_at));
+    Ok(backups)
+}
+
+/// 读取备份中的数据文件并校验哈希；`files` 为空时读取全部文件
+pub fn read_backup(data_dir: &Path, file_name: &str, files: &[String]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let path = backup_path(data_dir, file_name)?;
+    let info = read_info(&path)?;
+    let mut archive = open_archive(&path)?;
+
+    let mut contents = Vec::new();
+    for file in &info.manifest.files {
+        if !files.is_empty() && !files.contains(&file.name) {
+            continue;
+        }
+        let content = read_entry(&mut archive, &file.name)?;
+        if sha256_hex(&content) != file.sha256 {
+            return Err(format!("备份中的 {} 校验失败", file.name));
+        }
+        contents.push((file.name.clone(), content));
+    }
+
+    if let Some(missing) = files.iter().find(|name| !contents.iter().any(|(n, _)| n == *name)) {
+        return Err(format!("备份中没有 {}", missing));
+    }
+    Ok(contents)
+}
+
+fn count_items(name: &str, value: &Value) -> Option<usize> {
+    match name {
+        // 当前版本为 {"version", "tokens"}，早期版本直接是数组
+        "tokens.json" => value.get("tokens").unwrap_or(value).as_array().map(Vec::len),
+        "bookmarks.json" => value.get("bookmarks").and_then(Value::as_array).map(Vec::len),
+        _ => None,
+    }
+}
+
+/// 检查备份中每个文件的哈希和内容
+pub fn inspect_backup(data_dir: &Path, file_name: &str) -> Result<BackupInspection, String> {
+    let path = backup_path(data_dir, file_name)?;
+    let info = read_info(&path)?;
+    let mut archive = open_archive(&path)?;
+
+    let files = info
+        .manifest
+        .files
+        .iter()
+        .map(|file| {
+            let content = read_entry(&mut archive, &file.name).unwrap_or_default();
+            let parsed = serde_json::from_slice::<Value>(&content).ok();
+            BackupFileReport {
+                name: file.name.clone(),
+                size: content.len() as u64,
+                checksum_ok: sha256_hex(&content) == file.sha256,
+                valid: parsed.is_some(),
+                items: parsed.as_ref().and_then(|value| count_items(&file.name, value)),
+            }
+        })
+        .collect();
+
+    Ok(BackupInspection { info, files })
+}
+
+/// 检查数据文件能否解析；损坏的文件附上包含其可用副本的最新备份
+pub fn check_integrity(data_dir: &Path) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    for name in BACKUP_FILES {
+        let error = match fs::read(data_dir.join(name)) {
+            Ok(content) => match serde_json::from_slice::<Value>(&content) {
+                Ok(_) => continue,
+                Err(e) => format!("无法解析: {}", e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => format!("无法读取: {}", e),
+        };
+
+        let backup = list_backups(data_dir).unwrap_or_default().into_iter().find(|backup| {
+            read_backup(data_dir, &backup.file_name, &[name.to_string()])
+                .is_ok_and(|files| files.iter().all(|(_, content)| serde_json::from_slice::<Value>(content).is_ok()))
+        });
+        issues.push(IntegrityIssue {
+            file: name.to_string(),
+            error,
+            backup: backup.map(|b| b.file_name),
+        });
+    }
+    issues
+}
+
+/// 自动备份的调度状态，绑定到当前数据目录
+struct Scheduler {
+    data_dir: PathBuf,
+    settings: BackupSettings,
+    writes: u32,
+    last_backup: Option<DateTime<Utc>>,
+}
+
+fn scheduler() -> &'static Mutex<Option<Scheduler>> {
+    static SCHEDULER: OnceLock<Mutex<Option<Scheduler>>> = OnceLock::new();
+    SCHEDULER.get_or_init(|| Mutex::new(None))
+}
+
+/// 为数据目录启用自动备份（数据目录或设置变更后重新调用）
+pub fn configure(data_dir: &Path, settings: BackupSettings) {
+    let last_backup = list_backups(data_dir)
+        .ok()
+        .and_then(|backups| backups.first().map(|b| b.manifest.created_at));
+    *scheduler().lock().unwrap() = Some(Scheduler {
+        data_dir: data_dir.to_path_buf(),
+        settings,
+        writes: 0,
+        last_backup,
+    });
+}
+
+/// 写入数据文件前调用，达到写入次数或时间间隔时先备份当前内容；备份失败不影响写入
+pub fn before_write(path: &Path) {
+    let mut guard = scheduler().lock().unwrap();
+    let Some(scheduler) = guard.as_mut() else { return };
+
+    let tracked = path.parent() == Some(scheduler.data_dir.as_path())
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| BACKUP_FILES.contains(&name));
+    if !tracked || !scheduler.settings.enabled {
+        return;
+    }
+
+    scheduler.writes += 1;
+    let settings = &scheduler.settings;
+    let reason = if settings.interval_hours > 0
+        && scheduler
+            .last_backup
+            .is_none_or(|last| Utc::now() - last >= Duration::hours(i64::from(settings.interval_hours)))
+    {
+        BackupReason::Interval
+    } else if settings.every_writes > 0 && scheduler.writes >= settings.every_writes {
+        BackupReason::WriteCount
+    } else {
+        return;
+    };
+
+    match create_backup(&scheduler.data_dir, reason, settings.max_generations) {
+        Ok(info) => {
+            scheduler.writes = 0;
+            scheduler.last_backup = Some(info.manifest.created_at);
+        }
+        Err(e) => log::warn!("自动备份失败: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_data(dir: &Path, tokens: &str) {
+        fs::write(dir.join("tokens.json"), tokens).unwrap();
+        fs::write(dir.join("bookmarks.json"), r#"{"bookmarks": [{"id": "b"}]}"#).unwrap();
+    }
+
+    #[test]
+    fn test_create_inspect_and_read_backup() {
+        let temp_dir = tempdir().unwrap();
+        write_data(temp_dir.path(), r#"[{"id": "a"}, {"id": "b"}]"#);
+
+        let info = create_backup(temp_dir.path(), BackupReason::Manual, 5).unwrap();
+        let names: Vec<&str> = info.manifest.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["tokens.json", "bookmarks.json"]);
+
+        let inspection = inspect_backup(temp_dir.path(), &info.file_name).unwrap();
+        assert!(inspection.files.iter().all(|f| f.checksum_ok && f.valid));
+        assert_eq!(inspection.files[0].items, Some(2));
+        assert_eq!(inspection.files[1].items, Some(1));
+
+        let files = read_backup(temp_dir.path(), &info.file_name, &["tokens.json".to_string()]).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1, br#"[{"id": "a"}, {"id": "b"}]"#);
+        assert!(read_backup(temp_dir.path(), &info.file_name, &["config.json".to_string()]).is_err());
+        assert!(read_backup(temp_dir.path(), "../tokens.json", &[]).is_err());
+    }
+
+    #[test]
+    fn test_prune_keeps_latest_generations() {
+        let temp_dir = tempdir().unwrap();
+        write_data(temp_dir.path(), "[]");
+
+        let mut created = Vec::new();
+        for _ in 0..4 {
+            created.push(create_backup(temp_dir.path(), BackupReason::Manual, 2).unwrap().file_name);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let remaining: Vec<String> = list_backups(temp_dir.path()).unwrap().into_iter().map(|b| b.file_name).collect();
+        assert_eq!(remaining, [created[3].clone(), created[2].clone()]);
+    }
+
+    #[test]
+    fn test_integrity_check_points_to_valid_backup() {
+        let temp_dir = tempdir().unwrap();
+        write_data(temp_dir.path(), r#"[{"id": "a"}]"#);
+        let good = create_backup(temp_dir.path(), BackupReason::Manual, 5).unwrap();
+        assert!(check_integrity(temp_dir.path()).is_empty());
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        write_data(temp_dir.path(), r#"[{"id": "a"}"#);
+        create_backup(temp_dir.path(), BackupReason::Manual, 5).unwrap();
+
+        // 最新的备份中同样是损坏的内容，应跳过它
+        let issues = check_integrity(temp_dir.path());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].file, "tokens.json");
+        assert_eq!(issues[0].backup.as_deref(), Some(good.file_name.as_str()));
+    }
+
+    #[test]
+    fn test_before_write_follows_schedule() {
+        let temp_dir = tempdir().unwrap();
+        write_data(temp_dir.path(), "[]");
+        let tokens_path = temp_dir.path().join("tokens.json");
+        let settings = BackupSettings {
+            enabled: true,
+            every_writes: 3,
+            interval_hours: 24,
+            max_generations: 10,
+        };
+        configure(temp_dir.path(), settings);
+
+        // 没有备份时第一次写入前先备份，之后每 3 次写入备份一次
+        for _ in 0..7 {
+            before_write(&tokens_path);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        before_write(&temp_dir.path().join("other.json"));
+        let reasons: Vec<BackupReason> = list_backups(temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|b| b.manifest.reason)
+            .collect();
+        assert_eq!(reasons, [BackupReason::WriteCount, BackupReason::WriteCount, BackupReason::Interval]);
+        *scheduler().lock().unwrap() = None;
+    }
+}
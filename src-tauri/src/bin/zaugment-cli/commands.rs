@@ -0,0 +1,762 @@
+//! 命令行子命令的实现
+//!
+//! 只读命令（list、credits、export、import --dry-run）不获取数据目录锁，也不改写数据文件；
+//! 其余命令在写入前通过 `CliContext::lock_for_write` 获取锁。
+
+use crate::augment_oauth::{self, TokenInfo};
+use crate::augment_user_info::AppSessionStore;
+use crate::context::CliContext;
+use crate::migrations::{self, DataKind, MigrationContext};
+use crate::status_scheduler::apply_status_result;
+use crate::storage::{self, BanStatus, ChangeSource, TokenData, TokenStorage};
+use crate::thresholds::StatusThresholds;
+use crate::token_export::{self, ExportFilter, ExportFormat, ExportRequest};
+use crate::token_import::{self, ImportFormat, ImportRequest, RowStatus};
+use crate::webdav::{CloudSync, StoredCredential};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 列表过滤条件中的账号状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatusFilter {
+    Active,
+    Banned,
+    Expired,
+    Invalid,
+    /// 尚未检测过
+    Unknown,
+}
+
+impl From<StatusFilter> for token_export::StatusFilter {
+    fn from(status: StatusFilter) -> Self {
+        match status {
+            StatusFilter::Active => token_export::StatusFilter::Active,
+            StatusFilter::Banned => token_export::StatusFilter::Banned,
+            StatusFilter::Expired => token_export::StatusFilter::Expired,
+            StatusFilter::Invalid => token_export::StatusFilter::Invalid,
+            StatusFilter::Unknown => token_export::StatusFilter::Unknown,
+        }
+    }
+}
+
+/// 余额和到期时间相对阈值的等级，与前端卡片的颜色一致
+fn level(value: i64, warning: i32, safe: i32) -> &'static str {
+    if value <= warning as i64 {
+        "low"
+    } else if value <= safe as i64 {
+        "warning"
+    } else {
+        "ok"
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TokenRow {
+    id: String,
+    email: Option<String>,
+    tag: Option<String>,
+    status: Option<String>,
+    credits: Option<i64>,
+    credits_level: Option<&'static str>,
+    days_left: Option<i64>,
+    days_level: Option<&'static str>,
+    skip_check: bool,
+}
+
+impl TokenRow {
+    fn new(token: &TokenData, thresholds: &StatusThresholds) -> Self {
+        let credits = token.credits_balance();
+        let days_left = token.expiry().map(|expiry| (expiry - Utc::now()).num_days());
+        Self {
+            id: token.id.clone(),
+            email: token.email_note.clone(),
+            tag: token.tag_name.clone(),
+            status: token.ban_status.as_ref().map(|s| s.to_string()),
+            credits,
+            credits_level: credits.map(|c| level(c, thresholds.balance.warning, thresholds.balance.safe)),
+            days_left,
+            days_level: days_left.map(|d| level(d, thresholds.time.warning, thresholds.time.safe)),
+            skip_check: token.skip_check.unwrap_or(false),
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let output = serde_json::to_string_pretty(value).map_err(|e| format!("序列化输出失败: {}", e))?;
+    println!("{}", output);
+    Ok(())
+}
+
+fn print_rows(rows: &[TokenRow]) {
+    println!(
+        "{:<38} {:<32} {:<12} {:<14} {:>10} {:>6}",
+        "ID", "EMAIL", "TAG", "STATUS", "CREDITS", "DAYS"
+    );
+    for row in rows {
+        println!(
+            "{:<38} {:<32} {:<12} {:<14} {:>10} {:>6}",
+            row.id,
+            row.email.as_deref().unwrap_or("-"),
+            row.tag.as_deref().unwrap_or("-"),
+            row.status.as_deref().unwrap_or("-"),
+            row.credits.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+            row.days_left.map(|d| d.to_string()).unwrap_or_else(|| "-".to_string()),
+        );
+    }
+    println!("共 {} 个账号", rows.len());
+}
+
+/// 只读加载 tokens.json：在内存中迁移，不改写文件
+pub fn load_tokens_readonly(context: &CliContext) -> Result<Vec<TokenData>, String> {
+    let path = context.data_dir.join("tokens.json");
+    let document = match crate::context::read_document(DataKind::Tokens, &path, &MigrationContext::default())? {
+        Some(document) => document,
+        None => return Ok(Vec::new()),
+    };
+    Ok(migrations::tokens_from_document(document)
+        .iter()
+        .filter_map(|item| match storage::convert_legacy_token(item) {
+            Ok(token) => Some(token),
+            Err(e) => {
+                log::warn!("跳过无法解析的 token: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// 按 id 选择账号，ids 为空时返回全部；存在未知 id 时报错
+fn select<'a>(tokens: &'a [TokenData], ids: &[String]) -> Result<Vec<&'a TokenData>, String> {
+    if ids.is_empty() {
+        return Ok(tokens.iter().collect());
+    }
+    ids.iter()
+        .map(|id| tokens.iter().find(|t| &t.id == id).ok_or_else(|| format!("未找到账号: {}", id)))
+        .collect()
+}
+
+pub fn list(context: &CliContext, tag: Option<&str>, status: Option<StatusFilter>, json: bool) -> Result<(), String> {
+    let thresholds = context.thresholds();
+    let rows: Vec<TokenRow> = load_tokens_readonly(context)?
+        .iter()
+        .filter(|t| tag.is_none_or(|tag| t.tag_name.as_deref() == Some(tag)))
+        .filter(|t| status.is_none_or(|status| token_export::StatusFilter::from(status).matches(t)))
+        .map(|t| TokenRow::new(t, &thresholds))
+        .collect();
+
+    if json {
+        print_json(&rows)
+    } else {
+        print_rows(&rows);
+        Ok(())
+    }
+}
+
+pub struct AddArgs {
+    pub access_token: Option<String>,
+    pub tenant_url: Option<String>,
+    pub session: Option<String>,
+    pub portal_url: Option<String>,
+    pub email: Option<String>,
+    pub tag: Option<String>,
+    pub tag_color: Option<String>,
+}
+
+pub async fn add(context: &mut CliContext, args: AddArgs, json: bool) -> Result<(), String> {
+    context.lock_for_write()?;
+    let storage = context.storage();
+
+    let mut token = match (args.session, args.access_token, args.tenant_url) {
+        (Some(session), None, None) => {
+            let response = augment_oauth::extract_token_from_session(&context.augment_api()?, &session).await?;
+            let mut token = TokenData::new(
+                uuid::Uuid::new_v4().to_string(),
+                response.tenant_url,
+                response.access_token,
+                args.portal_url,
+                response.email.or(args.email),
+            );
+            token.ban_status = Some(BanStatus::Active);
+            token.auth_session = Some(session);
+            token.skip_check = Some(false);
+            token
+        }
+        (None, Some(access_token), Some(tenant_url)) => TokenData::new(
+            uuid::Uuid::new_v4().to_string(),
+            tenant_url,
+            access_token,
+            args.portal_url,
+            args.email,
+        ),
+        _ => return Err("请提供 --session，或同时提供 --access-token 和 --tenant-url".to_string()),
+    };
+    token.tag_name = args.tag;
+    token.tag_color = args.tag_color;
+
+    let existing = storage.load_tokens().await.map_err(|e| format!("读取账号失败: {}", e))?;
+    if let Some(duplicate) = existing.iter().find(|t| t.access_token == token.access_token) {
+        return Err(format!("账号已存在: {}", duplicate.id));
+    }
+    if let Some(email) = token.email_note.as_deref() {
+        if let Some(duplicate) = existing.iter().find(|t| t.email_note.as_deref() == Some(email)) {
+            return Err(format!("邮箱 '{}' 已存在: {}", email, duplicate.id));
+        }
+    }
+
+    storage.save_token(&token).await.map_err(|e| format!("保存账号失败: {}", e))?;
+
+    if json {
+        print_json(&TokenRow::new(&token, &context.thresholds()))
+    } else {
+        println!("已添加账号 {}", token.id);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormatArg {
+    Csv,
+    Jsonl,
+    Tokens,
+    Sessions,
+    Archive,
+}
+
+impl From<ImportFormatArg> for ImportFormat {
+    fn from(format: ImportFormatArg) -> Self {
+        match format {
+            ImportFormatArg::Csv => ImportFormat::Csv,
+            ImportFormatArg::Jsonl => ImportFormat::JsonLines,
+            ImportFormatArg::Tokens => ImportFormat::TokensJson,
+            ImportFormatArg::Sessions => ImportFormat::Sessions,
+            ImportFormatArg::Archive => ImportFormat::EncryptedArchive,
+        }
+    }
+}
+
+pub async fn import(
+    context: &mut CliContext,
+    file: &Path,
+    format: Option<ImportFormatArg>,
+    mappings: &[String],
+    dry_run: bool,
+    json: bool,
+) -> Result<(), String> {
+    let content = fs::read_to_string(file).map_err(|e| format!("读取 {} 失败: {}", file.display(), e))?;
+    let csv_mapping = if mappings.is_empty() {
+        None
+    } else {
+        let mapping = mappings
+            .iter()
+            .map(|m| {
+                m.split_once('=')
+                    .map(|(column, field)| (column.trim().to_string(), field.trim().to_string()))
+                    .ok_or_else(|| format!("列映射格式错误: {}（应为 列名=字段名）", m))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Some(mapping)
+    };
+    let request = ImportRequest {
+        content,
+        format: format.map(Into::into),
+        csv_mapping,
+        passphrase: crate::context::archive_passphrase(),
+    };
+
+    // 预览时按只读方式读取，正式导入前获取数据目录锁
+    if !dry_run {
+        context.lock_for_write()?;
+    }
+    let existing = load_tokens_readonly(context)?;
+    let (format, rows) = token_import::parse(&request)?;
+    let resolved = token_import::resolve(&context.augment_api()?, rows, &existing).await;
+    let plan = token_import::plan(format, resolved, &existing, dry_run);
+    let mut report = plan.report;
+
+    if !dry_run && !plan.tokens.is_empty() {
+        context
+            .storage()
+            .insert_tokens(&plan.tokens)
+            .await
+            .map_err(|e| format!("导入失败，未写入任何账号: {}", e))?;
+        report.imported = plan.tokens.len();
+    }
+
+    if json {
+        return print_json(&report);
+    }
+    for row in report.rows.iter().filter(|r| r.status != RowStatus::Ready) {
+        let status = if row.status == RowStatus::Duplicate { "重复" } else { "无效" };
+        println!("第 {} 行{}: {}", row.line, status, row.message.as_deref().unwrap_or(""));
+    }
+    println!(
+        "共 {} 行：可导入 {}，重复 {}，无效 {}{}",
+        report.total,
+        report.ready,
+        report.duplicates,
+        report.invalid,
+        if dry_run { "（预览，未写入）".to_string() } else { format!("，已导入 {}", report.imported) }
+    );
+    Ok(())
+}
+
+pub async fn remove(context: &mut CliContext, ids: &[String]) -> Result<(), String> {
+    context.lock_for_write()?;
+    let removed = context
+        .storage()
+        .delete_tokens(ids)
+        .await
+        .map_err(|e| format!("删除账号失败: {}", e))?;
+    if removed < ids.len() {
+        eprintln!("有 {} 个 id 未找到", ids.len() - removed);
+    }
+    println!("已删除 {} 个账号（已移入回收站）", removed);
+    Ok(())
+}
+
+pub async fn tag(
+    context: &mut CliContext,
+    id: &str,
+    name: Option<String>,
+    color: Option<String>,
+    clear: bool,
+) -> Result<(), String> {
+    context.lock_for_write()?;
+    let storage = context.storage();
+    let mut token = storage
+        .get_token(id)
+        .await
+        .map_err(|e| format!("读取账号失败: {}", e))?
+        .ok_or_else(|| format!("未找到账号: {}", id))?;
+
+    if clear {
+        token.tag_name = None;
+        token.tag_color = None;
+    } else {
+        if name.is_none() && color.is_none() {
+            return Err("请提供 --name、--color 或 --clear".to_string());
+        }
+        if name.is_some() {
+            token.tag_name = name;
+        }
+        if color.is_some() {
+            token.tag_color = color;
+        }
+    }
+    token.updated_at = Utc::now();
+
+    storage.update_token(&token).await.map_err(|e| format!("保存账号失败: {}", e))?;
+    println!("已更新账号 {} 的标签", token.id);
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct StatusRow {
+    id: String,
+    email: Option<String>,
+    status: String,
+    credits: Option<i64>,
+    error: Option<String>,
+}
+
+pub async fn status(context: &mut CliContext, ids: &[String], include_skipped: bool, json: bool) -> Result<(), String> {
+    context.lock_for_write()?;
+    let storage = context.storage();
+    let tokens = storage.load_tokens().await.map_err(|e| format!("读取账号失败: {}", e))?;
+
+    let targets: Vec<TokenInfo> = select(&tokens, ids)?
+        .into_iter()
+        .filter(|t| include_skipped || !ids.is_empty() || !t.skip_check.unwrap_or(false))
+        .map(|t| TokenInfo {
+            access_token: t.access_token.clone(),
+            tenant_url: t.tenant_url.clone(),
+            id: Some(t.id.clone()),
+            portal_url: t.portal_url.clone(),
+            auth_session: t.auth_session.clone(),
+            email_note: t.email_note.clone(),
+        })
+        .collect();
+    if targets.is_empty() {
+        println!("没有需要检测的账号");
+        return Ok(());
+    }
+
+    let results = augment_oauth::batch_check_account_status(
+        &context.augment_api()?,
+        targets,
+        Arc::new(Mutex::new(AppSessionStore::default())),
+    )
+    .await?;
+
+    let mut updated = Vec::new();
+    let mut rows = Vec::new();
+    for result in &results {
+        let Some(token) = result.token_id.as_ref().and_then(|id| tokens.iter().find(|t| &t.id == id)) else {
+            continue;
+        };
+        let mut token = token.clone();
+        // 请求失败、限流或服务端错误时不覆盖已有状态
+        if result.status_result.status.is_conclusive() && apply_status_result(&mut token, result) {
+            updated.push(token.clone());
+        }
+        rows.push(StatusRow {
+            id: token.id.clone(),
+            email: token.email_note.clone(),
+            status: result.status_result.status.to_string(),
+            credits: result.portal_info.as_ref().map(|p| p.credits_balance),
+            error: result.status_result.error_message.clone().or_else(|| result.portal_error.clone()),
+        });
+    }
+
+    storage.save_tokens(&updated).await.map_err(|e| format!("保存检测结果失败: {}", e))?;
+
+    if json {
+        return print_json(&rows);
+    }
+    for row in &rows {
+        println!(
+            "{:<38} {:<32} {:<14} {:>10} {}",
+            row.id,
+            row.email.as_deref().unwrap_or("-"),
+            row.status,
+            row.credits.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+            row.error.as_deref().unwrap_or(""),
+        );
+    }
+    println!("已检测 {} 个账号，更新 {} 个", rows.len(), updated.len());
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct CreditRow {
+    id: String,
+    email: Option<String>,
+    remaining: Option<f64>,
+    total: Option<f64>,
+    cycle_end: Option<String>,
+    low: Option<bool>,
+    error: Option<String>,
+}
+
+pub async fn credits(context: &CliContext, ids: &[String], json: bool) -> Result<(), String> {
+    let tokens = load_tokens_readonly(context)?;
+    let api = context.augment_api()?;
+
+    let mut rows = Vec::new();
+    for token in select(&tokens, ids)? {
+        let row = match augment_oauth::get_credit_info(&api, &token.access_token, &token.tenant_url).await {
+            Ok(info) => CreditRow {
+                id: token.id.clone(),
+                email: token.email_note.clone(),
+                remaining: Some(info.usage_units_remaining),
+                total: Some(info.usage_units_total),
+                cycle_end: Some(info.current_billing_cycle_end_date_iso),
+                low: Some(info.is_credit_balance_low),
+                error: None,
+            },
+            Err(e) => CreditRow {
+                id: token.id.clone(),
+                email: token.email_note.clone(),
+                remaining: None,
+                total: None,
+                cycle_end: None,
+                low: None,
+                error: Some(e),
+            },
+        };
+        rows.push(row);
+    }
+
+    if json {
+        return print_json(&rows);
+    }
+    for row in &rows {
+        match &row.error {
+            Some(error) => println!("{:<38} {:<32} 失败: {}", row.id, row.email.as_deref().unwrap_or("-"), error),
+            None => println!(
+                "{:<38} {:<32} {:>10} / {:<10} {}",
+                row.id,
+                row.email.as_deref().unwrap_or("-"),
+                row.remaining.unwrap_or_default(),
+                row.total.unwrap_or_default(),
+                row.cycle_end.as_deref().unwrap_or("-"),
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormatArg {
+    Json,
+    Csv,
+    /// 加密归档，口令从环境变量读取
+    Archive,
+}
+
+impl From<ExportFormatArg> for ExportFormat {
+    fn from(format: ExportFormatArg) -> Self {
+        match format {
+            ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Csv => ExportFormat::Csv,
+            ExportFormatArg::Archive => ExportFormat::EncryptedArchive,
+        }
+    }
+}
+
+pub struct ExportArgs {
+    pub output: Option<PathBuf>,
+    pub format: ExportFormatArg,
+    pub tags: Vec<String>,
+    pub statuses: Vec<StatusFilter>,
+    pub domains: Vec<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub fields: Option<Vec<String>>,
+    pub include_sensitive: bool,
+}
+
+/// 解析日期参数：RFC 3339 时间或 YYYY-MM-DD（`end_of_day` 为 true 时取当天结束）
+fn parse_date(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("日期格式错误: {}（应为 YYYY-MM-DD 或 RFC 3339）", value))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.ok_or("日期无效")?.and_utc())
+}
+
+pub fn export(context: &CliContext, args: ExportArgs) -> Result<(), String> {
+    let format = ExportFormat::from(args.format);
+    let fields = match (args.fields, args.include_sensitive) {
+        (Some(fields), _) => Some(fields),
+        (None, true) => Some(token_export::EXPORTABLE_FIELDS.iter().map(|f| f.to_string()).collect()),
+        (None, false) => None,
+    };
+    let passphrase = match format {
+        ExportFormat::EncryptedArchive => Some(crate::context::archive_passphrase().ok_or_else(|| {
+            format!("加密归档需要口令，请通过 {} 环境变量提供", crate::context::ARCHIVE_PASSPHRASE_ENV)
+        })?),
+        _ => None,
+    };
+    let request = ExportRequest {
+        filter: ExportFilter {
+            tags: args.tags,
+            statuses: args.statuses.into_iter().map(Into::into).collect(),
+            email_domains: args.domains,
+            created_after: args.since.as_deref().map(|d| parse_date(d, false)).transpose()?,
+            created_before: args.until.as_deref().map(|d| parse_date(d, true)).transpose()?,
+        },
+        format,
+        fields,
+        passphrase,
+    };
+
+    let (content, summary) = token_export::export(&load_tokens_readonly(context)?, &request)?;
+    match args.output {
+        None if format == ExportFormat::EncryptedArchive => return Err("加密归档需要通过 --output 指定输出文件".to_string()),
+        None => {
+            std::io::stdout()
+                .write_all(&content)
+                .map_err(|e| format!("输出失败: {}", e))?;
+        }
+        Some(path) => {
+            storage::write_file_atomic(&path, &content)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                    .map_err(|e| format!("设置文件权限失败: {}", e))?;
+            }
+            eprintln!("已导出 {} 个账号到 {}", summary.count, path.display());
+        }
+    }
+    if summary.includes_sensitive {
+        eprintln!("注意：导出内容包含 access_token 或 auth_session，请妥善保管");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SyncDirection {
+    /// 上传本地数据到云端（覆盖云端）
+    Upload,
+    /// 从云端下载 tokens 和书签（覆盖本地）
+    Download,
+}
+
+/// 构造与桌面应用相同格式的用户数据包
+fn collect_package(context: &CliContext) -> Result<serde_json::Value, String> {
+    let ctx = MigrationContext::default();
+    let tokens = crate::context::read_document(DataKind::Tokens, &context.data_dir.join("tokens.json"), &ctx)?;
+    let bookmarks = crate::context::read_document(DataKind::Bookmarks, &context.data_dir.join("bookmarks.json"), &ctx)?;
+
+    let mut unified_config = context.raw_config.clone();
+    if context.config.encrypted_credential_sync {
+        if let Some(webdav) = unified_config.as_mut().and_then(|c| c.get_mut("webdav_config")).filter(|w| w.is_object()) {
+            let password = context.webdav_config()?.password;
+            let credential = context
+                .password_manager
+                .encrypt_with_passphrase(&password, &context.sync_passphrase()?)?;
+            webdav["credential"] = serde_json::to_value(credential).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(serde_json::json!({
+        "version": migrations::SYNC_PACKAGE_VERSION,
+        "timestamp": Utc::now(),
+        "tokens": tokens,
+        "unified_config": unified_config,
+        "bookmarks": bookmarks,
+    }))
+}
+
+/// 恢复云端数据包中的 tokens 和书签；配置与本机环境相关，命令行不覆盖
+async fn restore_package(context: &CliContext, package: serde_json::Value) -> Result<(usize, bool), String> {
+    let password_manager = &context.password_manager;
+    let package = migrations::migrate_sync_package(package, &MigrationContext::with_secrets(password_manager))
+        .map_err(|e| e.to_string())?;
+
+    let mut restored_tokens = 0;
+    if let Some(document) = package.get("tokens").filter(|t| !t.is_null()) {
+        let tokens: Vec<TokenData> = migrations::tokens_from_document(document.clone())
+            .iter()
+            .filter_map(|item| storage::convert_legacy_token(item).ok())
+            .collect();
+        restored_tokens = tokens.len();
+        context
+            .storage()
+            .for_source(ChangeSource::Sync)
+            .replace_all_tokens(&tokens)
+            .await
+            .map_err(|e| format!("写入tokens.json失败: {}", e))?;
+    }
+
+    let bookmarks = package.get("bookmarks").filter(|b| !b.is_null());
+    if let Some(bookmarks) = bookmarks {
+        let path = context.data_dir.join("bookmarks.json");
+        migrations::ensure_writable(DataKind::Bookmarks, &path).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(bookmarks).map_err(|e| format!("序列化bookmarks失败: {}", e))?;
+        storage::write_file_atomic(&path, content.as_bytes())
+            .map_err(|e| format!("写入bookmarks.json失败: {}", e))?;
+    }
+
+    if package
+        .pointer("/unified_config/webdav_config/credential")
+        .and_then(|c| serde_json::from_value::<StoredCredential>(c.clone()).ok())
+        .is_some_and(|c| matches!(c, StoredCredential::Encrypted { .. }))
+    {
+        log::info!("云端配置未恢复，请在桌面应用中同步配置");
+    }
+
+    Ok((restored_tokens, bookmarks.is_some()))
+}
+
+pub async fn sync(context: &mut CliContext, direction: SyncDirection) -> Result<(), String> {
+    context.lock_for_write()?;
+    let config = context.webdav_config()?.user_data_config();
+    let temp_path = context.data_dir.join("user_data_temp.json");
+    let http = context.http_client()?;
+
+    match direction {
+        SyncDirection::Upload => {
+            let package = collect_package(context)?;
+            let bytes = serde_json::to_vec_pretty(&package).map_err(|e| format!("序列化用户数据包失败: {}", e))?;
+            fs::write(&temp_path, &bytes).map_err(|e| format!("写入临时文件失败: {}", e))?;
+
+            let mut sync = CloudSync::new(config, temp_path.clone(), &http)
+                .map_err(|e| format!("创建同步实例失败: {}", e))?;
+            let result = sync.force_upload().await;
+            let _ = fs::remove_file(&temp_path);
+            let result = result.map_err(|e| format!("强制上传失败: {}", e))?;
+            println!("上传完成: {} (传输 {} 字节)", result.message, result.bytes_transferred);
+        }
+        SyncDirection::Download => {
+            let mut sync = CloudSync::new(config, temp_path.clone(), &http)
+                .map_err(|e| format!("创建同步实例失败: {}", e))?;
+            let result = sync.force_download().await.map_err(|e| format!("强制下载失败: {}", e))?;
+            if !result.success {
+                let _ = fs::remove_file(&temp_path);
+                return Err(format!("下载未成功: {}", result.message));
+            }
+
+            let bytes = fs::read(&temp_path).map_err(|e| format!("读取下载文件失败: {}", e));
+            let _ = fs::remove_file(&temp_path);
+            let package: serde_json::Value =
+                serde_json::from_slice(&bytes?).map_err(|e| format!("解析用户数据包失败: {}", e))?;
+
+            let (tokens, bookmarks) = restore_package(context, package).await?;
+            println!(
+                "下载完成: 恢复 {} 个账号{} (传输 {} 字节)",
+                tokens,
+                if bookmarks { "和书签" } else { "" },
+                result.bytes_transferred
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn token(id: &str, status: Option<BanStatus>, tag: Option<&str>) -> TokenData {
+        let mut token = TokenData::new(
+            id.to_string(),
+            "https://d1.api.augmentcode.com/".to_string(),
+            format!("access-{}", id),
+            None,
+            Some(format!("{}@example.com", id)),
+        );
+        token.ban_status = status;
+        token.tag_name = tag.map(str::to_string);
+        token
+    }
+
+    #[test]
+    fn test_status_filter() {
+        let matches = |status: StatusFilter, token: &TokenData| token_export::StatusFilter::from(status).matches(token);
+        assert!(matches(StatusFilter::Active, &token("a", Some(BanStatus::Active), None)));
+        assert!(matches(StatusFilter::Banned, &token("b", Some(BanStatus::Suspended), None)));
+        assert!(matches(StatusFilter::Banned, &token("c", Some(BanStatus::parse("BANNED-FRAUD")), None)));
+        assert!(matches(StatusFilter::Invalid, &token("d", Some(BanStatus::InvalidToken), None)));
+        assert!(matches(StatusFilter::Unknown, &token("e", None, None)));
+        assert!(!matches(StatusFilter::Active, &token("f", None, None)));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2025-03-01", false).unwrap().to_rfc3339(), "2025-03-01T00:00:00+00:00");
+        assert_eq!(parse_date("2025-03-01", true).unwrap().to_rfc3339(), "2025-03-01T23:59:59+00:00");
+        assert_eq!(parse_date("2025-03-01T08:00:00+08:00", false).unwrap().to_rfc3339(), "2025-03-01T00:00:00+00:00");
+        assert!(parse_date("03/01/2025", false).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_readonly_load_does_not_rewrite_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        // 旧版本（无版本号的数组）格式
+        let legacy = serde_json::json!([storage::convert_to_legacy_format(&token("a", None, Some("t")))]);
+        fs::write(&path, legacy.to_string()).unwrap();
+
+        let context = CliContext::load(Some(dir.path().to_path_buf())).unwrap();
+        let tokens = load_tokens_readonly(&context).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].tag_name.as_deref(), Some("t"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), legacy.to_string());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+//! 命令行工具的运行环境：数据目录、配置、HTTP 客户端和数据目录锁
+//!
+//! 数据目录与桌面应用一致：默认目录为系统数据目录下的应用标识目录，
+//! 默认目录的 config.json 中设置了自定义目录时使用自定义目录。
+
+use crate::augment_api::AugmentApi;
+use crate::backups::{self, BackupSettings};
+use crate::http_client::{HttpClient, HttpClientSettings};
+use crate::instance_lock::{InstanceLock, LockError};
+use crate::migrations::{self, DataKind, MigrationContext};
+use crate::storage::{ChangeSource, LocalFileStorage};
+use crate::thresholds::StatusThresholds;
+use crate::webdav::{PasswordManager, SecureWebDAVConfig, WebDAVConfig};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 与 tauri.conf.json 中的 identifier 一致
+pub const APP_IDENTIFIER: &str = "com.cubezhao.atm";
+pub const DATA_DIR_ENV: &str = "ZAUGMENT_DATA_DIR";
+/// 无法访问系统密钥链时（CI、SSH 会话）从环境变量读取 WebDAV 密码和同步口令
+pub const WEBDAV_PASSWORD_ENV: &str = "ZAUGMENT_WEBDAV_PASSWORD";
+pub const SYNC_PASSPHRASE_ENV: &str = "ZAUGMENT_SYNC_PASSPHRASE";
+/// 加密归档的导出和导入口令，不通过命令行参数传递以免留在 shell 历史中
+pub const ARCHIVE_PASSPHRASE_ENV: &str = "ZAUGMENT_ARCHIVE_PASSPHRASE";
+
+/// 统一配置中命令行工具用到的部分
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CliConfig {
+    pub custom_data_dir: Option<String>,
+    pub webdav_config: Option<SecureWebDAVConfig>,
+    pub encrypted_credential_sync: bool,
+    pub status_thresholds: Option<StatusThresholds>,
+    pub http_client: HttpClientSettings,
+    pub backup_settings: BackupSettings,
+}
+
+pub struct CliContext {
+    pub data_dir: PathBuf,
+    pub config: CliConfig,
+    /// 迁移到当前版本后的完整配置，上传数据包时原样使用
+    pub raw_config: Option<serde_json::Value>,
+    pub password_manager: PasswordManager,
+    lock: Option<InstanceLock>,
+}
+
+pub fn archive_passphrase() -> Option<String> {
+    std::env::var(ARCHIVE_PASSPHRASE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+/// 桌面应用的默认数据目录（Tauri 的 app_data_dir）
+pub fn default_data_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "无法确定系统数据目录".to_string())
+}
+
+/// 读取数据文件并在内存中迁移到当前版本，不改写文件
+pub fn read_document(kind: DataKind, path: &Path, ctx: &MigrationContext) -> Result<Option<serde_json::Value>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("读取 {} 失败: {}", path.display(), e)),
+    };
+    let value = serde_json::from_str(&content).map_err(|e| format!("解析 {} 失败: {}", path.display(), e))?;
+    migrations::migrate_value(kind, value, ctx)
+        .map(|outcome| Some(outcome.value))
+        .map_err(|e| e.to_string())
+}
+
+fn read_config(data_dir: &Path) -> Result<(CliConfig, Option<serde_json::Value>), String> {
+    // 旧版本配置中的明文密码在迁移时需要写入密钥链
+    let password_manager = PasswordManager::new();
+    let ctx = MigrationContext::with_secrets(&password_manager);
+    match read_document(DataKind::Config, &data_dir.join("config.json"), &ctx)? {
+        Some(value) => {
+            let config = serde_json::from_value(value.clone()).map_err(|e| format!("解析 config.json 失败: {}", e))?;
+            Ok((config, Some(value)))
+        }
+        None => Ok((CliConfig::default(), None)),
+    }
+}
+
+/// 按桌面应用的规则确定有效数据目录：默认目录配置中的自定义目录存在时使用自定义目录
+pub fn resolve_data_dir(default_dir: &Path) -> Result<PathBuf, String> {
+    let (config, _) = read_config(default_dir)?;
+    Ok(config
+        .custom_data_dir
+        .map(PathBuf::from)
+        .filter(|dir| dir.exists())
+        .unwrap_or_else(|| default_dir.to_path_buf()))
+}
+
+impl CliContext {
+    /// `data_dir` 为命令行或环境变量指定的目录，未指定时与桌面应用相同
+    pub fn load(data_dir: Option<PathBuf>) -> Result<Self, String> {
+        let data_dir = match data_dir.or_else(|| std::env::var_os(DATA_DIR_ENV).map(PathBuf::from)) {
+            Some(dir) => dir,
+            None => resolve_data_dir(&default_data_dir()?)?,
+        };
+        let (config, raw_config) = read_config(&data_dir)?;
+
+        Ok(Self {
+            data_dir,
+            config,
+            raw_config,
+            password_manager: PasswordManager::new(),
+            lock: None,
+        })
+    }
+
+    /// 写入数据前获取数据目录锁，桌面应用正在运行时拒绝执行
+    pub fn lock_for_write(&mut self) -> Result<(), String> {
+        if self.lock.is_none() {
+            let lock = InstanceLock::acquire(&self.data_dir, "cli").map_err(|e| match e {
+                LockError::Held(_) => format!("{}，请先退出桌面应用再执行写入操作", e),
+                other => other.to_string(),
+            })?;
+            self.lock = Some(lock);
+            // 与桌面应用一样按设置在写入前自动备份
+            backups::configure(&self.data_dir, self.config.backup_settings.clone());
+        }
+        Ok(())
+    }
+
+    pub fn storage(&self) -> LocalFileStorage {
+        LocalFileStorage::new_with_path(self.data_dir.join("tokens.json")).for_source(ChangeSource::Cli)
+    }
+
+    pub fn thresholds(&self) -> StatusThresholds {
+        self.config.status_thresholds.clone().unwrap_or_default()
+    }
+
+    pub fn http_client(&self) -> Result<HttpClient, String> {
+        let settings = &self.config.http_client;
+        let proxy_password = settings
+            .proxy
+            .username
+            .as_deref()
+            .filter(|username| !username.is_empty())
+            .and_then(|username| self.password_manager.get_proxy_password(username).ok());
+        HttpClient::new(settings.clone(), proxy_password)
+    }
+
+    pub fn augment_api(&self) -> Result<AugmentApi, String> {
+        Ok(AugmentApi::from_http_client(self.http_client()?))
+    }
+
+    /// WebDAV 配置，密码优先从环境变量读取
+    pub fn webdav_config(&self) -> Result<WebDAVConfig, String> {
+        let secure_config = self.config.webdav_config.as_ref().ok_or("WebDAV未配置")?;
+        match std::env::var(WEBDAV_PASSWORD_ENV) {
+            Ok(password) if !password.is_empty() => Ok(WebDAVConfig {
+                server_url: secure_config.server_url.clone(),
+                username: secure_config.username.clone(),
+                password,
+                enabled: secure_config.enabled,
+                auto_sync: secure_config.auto_sync,
+                sync_interval_minutes: secure_config.sync_interval_minutes,
+                remote_path: secure_config.remote_path.clone(),
+            }),
+            _ => secure_config.to_config(&self.password_manager).map_err(|e| {
+                format!("获取WebDAV密码失败: {}（可通过 {} 环境变量提供）", e, WEBDAV_PASSWORD_ENV)
+            }),
+        }
+    }
+
+    pub fn sync_passphrase(&self) -> Result<String, String> {
+        match std::env::var(SYNC_PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(passphrase),
+            _ => self.password_manager.get_sync_passphrase().map_err(|e| {
+                format!("已启用加密凭据同步，但无法读取同步口令: {}（可通过 {} 环境变量提供）", e, SYNC_PASSPHRASE_ENV)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resolve_data_dir_follows_custom_dir() {
+        let default_dir = tempdir().unwrap();
+        assert_eq!(resolve_data_dir(default_dir.path()).unwrap(), default_dir.path());
+
+        let custom_dir = tempdir().unwrap();
+        let config = serde_json::json!({
+            "version": migrations::CONFIG_VERSION,
+            "custom_data_dir": custom_dir.path(),
+            "status_thresholds": {
+                "time": {"warning": 5, "safe": 15},
+                "balance": {"warning": 100, "safe": 200},
+                "timeMax": 365,
+                "balanceMax": 1000
+            }
+        });
+        fs::write(default_dir.path().join("config.json"), config.to_string()).unwrap();
+        assert_eq!(resolve_data_dir(default_dir.path()).unwrap(), custom_dir.path());
+
+        let (config, raw) = read_config(default_dir.path()).unwrap();
+        assert_eq!(config.status_thresholds.unwrap().balance.warning, 100);
+        assert!(raw.is_some());
+
+        // 自定义目录不存在时回退到默认目录
+        let missing = custom_dir.path().join("missing");
+        let config = serde_json::json!({ "version": migrations::CONFIG_VERSION, "custom_data_dir": missing });
+        fs::write(default_dir.path().join("config.json"), config.to_string()).unwrap();
+        assert_eq!(resolve_data_dir(default_dir.path()).unwrap(), default_dir.path());
+    }
+
+    #[test]
+    fn test_write_lock_conflicts_with_app() {
+        let dir = tempdir().unwrap();
+        let app_lock = InstanceLock::acquire(dir.path(), "app").unwrap();
+
+        let mut context = CliContext::load(Some(dir.path().to_path_buf())).unwrap();
+        let error = context.lock_for_write().unwrap_err();
+        assert!(error.contains("桌面应用"), "{}", error);
+
+        drop(app_lock);
+        context.lock_for_write().unwrap();
+    }
+}
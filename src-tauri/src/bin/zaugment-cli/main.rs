@@ -0,0 +1,246 @@
+//! ZAugment 命令行工具
+//!
+//! 与桌面应用共用数据目录、配置和存储代码，用于在无界面环境下管理账号、
+//! 检测状态、导出和同步。写入数据的命令在桌面应用运行时会被拒绝。
+
+// 与桌面应用共用的模块，命令行只用到其中一部分
+#[allow(dead_code, unused_imports)]
+#[path = "../../augment_api/mod.rs"]
+mod augment_api;
+#[allow(dead_code, unused_imports)]
+#[path = "../../augment_oauth.rs"]
+mod augment_oauth;
+#[allow(dead_code, unused_imports)]
+#[path = "../../augment_user_info.rs"]
+mod augment_user_info;
+#[allow(dead_code, unused_imports)]
+#[path = "../../backups.rs"]
+mod backups;
+#[allow(dead_code, unused_imports)]
+#[path = "../../data_watcher.rs"]
+mod data_watcher;
+#[allow(dead_code, unused_imports)]
+#[path = "../../http_client.rs"]
+mod http_client;
+#[allow(dead_code, unused_imports)]
+#[path = "../../instance_lock.rs"]
+mod instance_lock;
+#[allow(dead_code, unused_imports)]
+#[path = "../../migrations.rs"]
+mod migrations;
+#[allow(dead_code, unused_imports)]
+#[path = "../../storage/mod.rs"]
+mod storage;
+#[allow(dead_code, unused_imports)]
+#[path = "../../status_scheduler.rs"]
+mod status_scheduler;
+#[allow(dead_code, unused_imports)]
+#[path = "../../thresholds.rs"]
+mod thresholds;
+#[allow(dead_code, unused_imports)]
+#[path = "../../token_export.rs"]
+mod token_export;
+#[allow(dead_code, unused_imports)]
+#[path = "../../token_import.rs"]
+mod token_import;
+#[allow(dead_code, unused_imports)]
+#[path = "../../webdav/mod.rs"]
+mod webdav;
+
+mod commands;
+mod context;
+
+use clap::{Parser, Subcommand};
+use commands::{AddArgs, ExportArgs, ExportFormatArg, ImportFormatArg, StatusFilter, SyncDirection};
+use context::CliContext;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "zaugment-cli", version, about = "ZAugment 命令行工具")]
+struct Cli {
+    /// 数据目录（默认与桌面应用相同，也可通过 ZAUGMENT_DATA_DIR 指定）
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// 以 JSON 格式输出
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 列出账号
+    List {
+        /// 只显示指定标签的账号
+        #[arg(long)]
+        tag: Option<String>,
+        /// 只显示指定状态的账号
+        #[arg(long, value_enum)]
+        status: Option<StatusFilter>,
+    },
+    /// 添加账号（通过 Session 或 access token）
+    Add {
+        #[arg(long, conflicts_with = "session", requires = "tenant_url")]
+        access_token: Option<String>,
+        #[arg(long, conflicts_with = "session", requires = "access_token")]
+        tenant_url: Option<String>,
+        /// Auth Session，自动换取 access token
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long)]
+        portal_url: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+        #[arg(long)]
+        tag: Option<String>,
+        #[arg(long, requires = "tag")]
+        tag_color: Option<String>,
+    },
+    /// 批量导入账号（CSV、JSON Lines、tokens.json 导出、加密归档或每行一个 Session）
+    Import {
+        file: PathBuf,
+        /// 输入格式，缺省时自动识别
+        #[arg(long, value_enum)]
+        format: Option<ImportFormatArg>,
+        /// CSV 列映射，格式为 列名=字段名，可重复
+        #[arg(long = "map", value_name = "COLUMN=FIELD")]
+        mappings: Vec<String>,
+        /// 只输出预览报告，不写入
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 删除账号
+    Remove {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// 设置或清除账号标签
+    Tag {
+        id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        color: Option<String>,
+        #[arg(long, conflicts_with_all = ["name", "color"])]
+        clear: bool,
+    },
+    /// 检测账号状态并保存结果（默认检测所有未跳过检测的账号）
+    Status {
+        ids: Vec<String>,
+        /// 同时检测设置了跳过检测的账号
+        #[arg(long)]
+        include_skipped: bool,
+    },
+    /// 查询账号 Credit 信息
+    Credits { ids: Vec<String> },
+    /// 按条件导出账号（默认不含 access_token 和 auth_session）
+    Export {
+        /// 输出文件，缺省时输出到标准输出
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        #[arg(long, value_enum, default_value = "json")]
+        format: ExportFormatArg,
+        /// 只导出指定标签，可重复
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// 只导出指定状态，可重复
+        #[arg(long = "status", value_enum)]
+        statuses: Vec<StatusFilter>,
+        /// 只导出指定邮箱域名，可重复
+        #[arg(long = "domain")]
+        domains: Vec<String>,
+        /// 创建时间不早于（YYYY-MM-DD 或 RFC 3339）
+        #[arg(long)]
+        since: Option<String>,
+        /// 创建时间不晚于（YYYY-MM-DD 或 RFC 3339）
+        #[arg(long)]
+        until: Option<String>,
+        /// 导出的字段，逗号分隔
+        #[arg(long, value_delimiter = ',', conflicts_with = "include_sensitive")]
+        fields: Option<Vec<String>>,
+        /// 在默认字段之外导出 access_token 和 auth_session
+        #[arg(long)]
+        include_sensitive: bool,
+    },
+    /// 与 WebDAV 同步用户数据
+    Sync {
+        #[arg(value_enum)]
+        direction: SyncDirection,
+    },
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let mut context = CliContext::load(cli.data_dir)?;
+    let json = cli.json;
+
+    match cli.command {
+        Command::List { tag, status } => commands::list(&context, tag.as_deref(), status, json),
+        Command::Add {
+            access_token,
+            tenant_url,
+            session,
+            portal_url,
+            email,
+            tag,
+            tag_color,
+        } => {
+            let args = AddArgs {
+                access_token,
+                tenant_url,
+                session,
+                portal_url,
+                email,
+                tag,
+                tag_color,
+            };
+            commands::add(&mut context, args, json).await
+        }
+        Command::Import {
+            file,
+            format,
+            mappings,
+            dry_run,
+        } => commands::import(&mut context, &file, format, &mappings, dry_run, json).await,
+        Command::Remove { ids } => commands::remove(&mut context, &ids).await,
+        Command::Tag { id, name, color, clear } => commands::tag(&mut context, &id, name, color, clear).await,
+        Command::Status { ids, include_skipped } => commands::status(&mut context, &ids, include_skipped, json).await,
+        Command::Credits { ids } => commands::credits(&context, &ids, json).await,
+        Command::Export {
+            output,
+            format,
+            tags,
+            statuses,
+            domains,
+            since,
+            until,
+            fields,
+            include_sensitive,
+        } => {
+            let args = ExportArgs {
+                output,
+                format,
+                tags,
+                statuses,
+                domains,
+                since,
+                until,
+                fields,
+                include_sensitive,
+            };
+            commands::export(&context, args)
+        }
+        Command::Sync { direction } => commands::sync(&mut context, direction).await,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("错误: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
-use tauri::Manager;
+
+use crate::migrations::{self, DataKind, MigrationContext};
+use crate::instance_lock::WriteLock;
+use crate::storage::write_file_atomic;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bookmark {
@@ -40,12 +43,19 @@ impl Bookmark {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BookmarkStorage {
+    #[serde(default = "default_bookmarks_version")]
+    pub version: String,
     pub bookmarks: Vec<Bookmark>,
 }
 
+fn default_bookmarks_version() -> String {
+    migrations::BOOKMARKS_VERSION.to_string()
+}
+
 impl BookmarkStorage {
     pub fn new() -> Self {
         Self {
+            version: default_bookmarks_version(),
             bookmarks: Vec::new(),
         }
     }
@@ -89,86 +99,74 @@ pub struct BookmarkManager {
 }
 
 impl BookmarkManager {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
-        let app_data_dir = app_handle
-            .path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-        
-        // Create app data directory if it doesn't exist
-        fs::create_dir_all(&app_data_dir)
-            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    /// 书签保存在数据目录（有效数据目录）下的 bookmarks.json
+    pub fn new(data_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        // Create data directory if it doesn't exist
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
         
-        let storage_path = app_data_dir.join("bookmarks.json");
+        let storage_path = data_dir.join("bookmarks.json");
         
         Ok(Self { storage_path })
     }
 
     pub fn load_bookmarks(&self) -> Result<BookmarkStorage, Box<dyn std::error::Error>> {
-        if !self.storage_path.exists() {
-            return Ok(BookmarkStorage::new());
-        }
+        self.load_with(migrations::migrate_file)
+    }
 
-        let content = fs::read_to_string(&self.storage_path)
-            .map_err(|e| format!("Failed to read bookmarks file: {}", e))?;
-        
-        let storage: BookmarkStorage = serde_json::from_str(&content)
+    /// 同 `load_bookmarks`，调用方已持有写入锁
+    fn load_bookmarks_locked(&self) -> Result<BookmarkStorage, Box<dyn std::error::Error>> {
+        self.load_with(migrations::migrate_file_locked)
+    }
+
+    fn load_with(
+        &self,
+        migrate: fn(DataKind, &Path, &MigrationContext) -> Result<Option<serde_json::Value>, migrations::MigrationError>,
+    ) -> Result<BookmarkStorage, Box<dyn std::error::Error>> {
+        // 读取时按需升级文件版本（升级前会备份原文件）
+        let document = match migrate(DataKind::Bookmarks, &self.storage_path, &MigrationContext::default())
+            .map_err(|e| format!("Failed to load bookmarks file: {}", e))?
+        {
+            Some(document) => document,
+            None => return Ok(BookmarkStorage::new()),
+        };
+
+        let storage: BookmarkStorage = serde_json::from_value(document)
             .map_err(|e| format!("Failed to parse bookmarks file: {}", e))?;
         
         Ok(storage)
     }
 
+    /// 跨进程的写入锁，数据目录被多个应用实例共用时串行化"读取-修改-写入"
+    fn write_lock(&self) -> Result<WriteLock, Box<dyn std::error::Error>> {
+        Ok(WriteLock::acquire(self.storage_path.parent().unwrap_or(Path::new(".")))?)
+    }
+
     pub fn save_bookmarks(&self, storage: &BookmarkStorage) -> Result<(), Box<dyn std::error::Error>> {
         let content = serde_json::to_string_pretty(storage)
             .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
 
-        // 确保父目录存在
-        if let Some(parent) = self.storage_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
+        // 不覆盖由更新版本应用写入的文件
+        migrations::ensure_writable(DataKind::Bookmarks, &self.storage_path)
+            .map_err(|e| e.to_string())?;
 
-        // 使用同一目录下的唯一临时文件名，确保在同一文件系统上
-        let parent_dir = self.storage_path.parent()
-            .ok_or("Failed to get parent directory")?;
-        let temp_path = parent_dir.join(format!("bookmarks.{}.tmp", Uuid::new_v4()));
-
-        // 写入临时文件
-        fs::write(&temp_path, &content)
-            .map_err(|e| format!("Failed to write temp file: {}", e))?;
-
-        // 尝试原子性重命名，如果失败则使用复制+删除的方式
-        match fs::rename(&temp_path, &self.storage_path) {
-            Ok(_) => Ok(()),
-            Err(rename_err) => {
-                // 如果 rename 失败（可能是跨文件系统），尝试复制+删除
-                eprintln!("Rename failed, trying copy+delete: {}", rename_err);
-
-                match fs::copy(&temp_path, &self.storage_path) {
-                    Ok(_) => {
-                        // 复制成功，删除临时文件
-                        let _ = fs::remove_file(&temp_path);
-                        Ok(())
-                    }
-                    Err(copy_err) => {
-                        // 复制也失败，清理临时文件并返回错误
-                        let _ = fs::remove_file(&temp_path);
-                        Err(format!("Failed to save file (rename: {}, copy: {})", rename_err, copy_err).into())
-                    }
-                }
-            }
-        }
+        crate::backups::before_write(&self.storage_path);
+        crate::data_watcher::record_write(&self.storage_path, content.as_bytes());
+        write_file_atomic(&self.storage_path, content.as_bytes())?;
+        Ok(())
     }
 
     pub fn add_bookmark(&self, name: String, url: String, description: Option<String>, category: String) -> Result<String, Box<dyn std::error::Error>> {
-        let mut storage = self.load_bookmarks()?;
+        let _write_lock = self.write_lock()?;
+        let mut storage = self.load_bookmarks_locked()?;
         let id = storage.add_bookmark(name, url, description, category);
         self.save_bookmarks(&storage)?;
         Ok(id)
     }
 
     pub fn update_bookmark(&self, id: &str, name: String, url: String, description: Option<String>) -> Result<bool, Box<dyn std::error::Error>> {
-        let mut storage = self.load_bookmarks()?;
+        let _write_lock = self.write_lock()?;
+        let mut storage = self.load_bookmarks_locked()?;
         let updated = storage.update_bookmark(id, name, url, description);
         if updated {
             self.save_bookmarks(&storage)?;
@@ -177,7 +175,8 @@ impl BookmarkManager {
     }
 
     pub fn remove_bookmark(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
-        let mut storage = self.load_bookmarks()?;
+        let _write_lock = self.write_lock()?;
+        let mut storage = self.load_bookmarks_locked()?;
         let removed = storage.remove_bookmark(id);
         if removed {
             self.save_bookmarks(&storage)?;
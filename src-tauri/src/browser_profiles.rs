@@ -0,0 +1,215 @@
+//! 内置浏览器的独立配置档案
+//!
+//! 每个账号（按 `TokenData.id`）可以使用单独的 WebView 数据目录，cookie、缓存和本地存储
+//! 互不影响。档案保存在默认应用数据目录的 `browser_profiles/` 下，不随数据目录迁移。
+//! 打开中的浏览器窗口记录在 `BrowserWindowTracker` 中，关闭窗口、清理档案和退出应用时使用。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const PROFILES_DIR: &str = "browser_profiles";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserProfileInfo {
+    /// 档案目录名（由 token id 得到）
+    pub profile_id: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+    /// 正在使用该档案的窗口
+    pub open_windows: Vec<String>,
+}
+
+/// token id 对应的档案目录名：安全字符原样使用，其他情况使用哈希，避免路径穿越
+pub fn profile_id(token_id: &str) -> String {
+    let is_safe = !token_id.is_empty()
+        && token_id.len() <= 64
+        && token_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_safe {
+        token_id.to_string()
+    } else {
+        let digest = Sha256::digest(token_id.as_bytes());
+        format!("h{}", &format!("{:x}", digest)[..32])
+    }
+}
+
+pub fn profile_dir(root: &Path, token_id: &str) -> PathBuf {
+    root.join(PROFILES_DIR).join(profile_id(token_id))
+}
+
+/// macOS 的 WKWebView 通过 16 字节标识区分数据存储
+pub fn data_store_identifier(token_id: &str) -> [u8; 16] {
+    let digest = Sha256::digest(profile_id(token_id).as_bytes());
+    let mut identifier = [0u8; 16];
+    identifier.copy_from_slice(&digest[..16]);
+    identifier
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// 列出已有的档案
+pub fn list_profiles(root: &Path, windows: &BrowserWindowTracker) -> Result<Vec<BrowserProfileInfo>, String> {
+    let profiles_dir = root.join(PROFILES_DIR);
+    if !profiles_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&profiles_dir).map_err(|e| format!("读取浏览器档案目录失败: {}", e))?;
+    let mut profiles = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let profile_id = entry.file_name().to_string_lossy().to_string();
+        profiles.push(BrowserProfileInfo {
+            open_windows: windows.windows_for_profile(&profile_id),
+            size_bytes: dir_size(&path),
+            modified_at: entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(DateTime::<Utc>::from),
+            path: path.to_string_lossy().to_string(),
+            profile_id,
+        });
+    }
+
+    profiles.sort_by(|a, b| a.profile_id.cmp(&b.profile_id));
+    Ok(profiles)
+}
+
+/// 清空档案中的数据（cookie、缓存等），保留目录
+pub fn clear_profile(root: &Path, token_id: &str) -> Result<bool, String> {
+    let dir = profile_dir(root, token_id);
+    if !dir.exists() {
+        return Ok(false);
+    }
+    for entry in fs::read_dir(&dir).map_err(|e| format!("读取浏览器档案失败: {}", e))? {
+        let path = entry.map_err(|e| format!("读取浏览器档案失败: {}", e))?.path();
+        let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        result.map_err(|e| format!("清除浏览器档案失败 {}: {}", path.display(), e))?;
+    }
+    Ok(true)
+}
+
+/// 删除整个档案目录
+pub fn delete_profile(root: &Path, token_id: &str) -> Result<bool, String> {
+    let dir = profile_dir(root, token_id);
+    if !dir.exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("删除浏览器档案失败: {}", e))?;
+    Ok(true)
+}
+
+/// 记录打开中的内置浏览器窗口：窗口标签 -> 档案目录名（共享数据存储时为 None）
+#[derive(Debug, Default)]
+pub struct BrowserWindowTracker {
+    windows: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl BrowserWindowTracker {
+    pub fn register(&self, label: &str, token_id: Option<&str>) {
+        self.windows
+            .lock()
+            .unwrap()
+            .insert(label.to_string(), token_id.map(profile_id));
+    }
+
+    pub fn unregister(&self, label: &str) -> bool {
+        self.windows.lock().unwrap().remove(label).is_some()
+    }
+
+    pub fn is_tracked(&self, label: &str) -> bool {
+        self.windows.lock().unwrap().contains_key(label)
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.windows.lock().unwrap().keys().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    pub fn windows_for_profile(&self, profile_id: &str) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .windows
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, profile)| profile.as_deref() == Some(profile_id))
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_profile_id_is_path_safe() {
+        assert_eq!(profile_id("4f0c2b1e-9a6d-4a43-8f3e-0d9d7c1b2a11"), "4f0c2b1e-9a6d-4a43-8f3e-0d9d7c1b2a11");
+
+        let hashed = profile_id("../../etc");
+        assert!(hashed.starts_with('h'));
+        assert_eq!(hashed.len(), 33);
+        assert_eq!(hashed, profile_id("../../etc"));
+        assert_ne!(profile_id("账号"), profile_id("账号2"));
+        assert_ne!(data_store_identifier("a"), data_store_identifier("b"));
+    }
+
+    #[test]
+    fn test_list_clear_and_delete_profiles() {
+        let root = tempdir().unwrap();
+        let tracker = BrowserWindowTracker::default();
+        let dir = profile_dir(root.path(), "token-1");
+        fs::create_dir_all(dir.join("EBWebView")).unwrap();
+        fs::write(dir.join("EBWebView").join("Cookies"), b"cookie").unwrap();
+        tracker.register("browser_1", Some("token-1"));
+        tracker.register("browser_2", None);
+
+        let profiles = list_profiles(root.path(), &tracker).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].profile_id, "token-1");
+        assert_eq!(profiles[0].size_bytes, 6);
+        assert_eq!(profiles[0].open_windows, vec!["browser_1"]);
+
+        assert!(clear_profile(root.path(), "token-1").unwrap());
+        assert!(dir.exists());
+        assert_eq!(list_profiles(root.path(), &tracker).unwrap()[0].size_bytes, 0);
+
+        assert!(delete_profile(root.path(), "token-1").unwrap());
+        assert!(!dir.exists());
+        assert!(!delete_profile(root.path(), "token-1").unwrap());
+        assert!(list_profiles(root.path(), &tracker).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_window_tracker() {
+        let tracker = BrowserWindowTracker::default();
+        tracker.register("browser_2", Some("token-1"));
+        tracker.register("browser_1", None);
+        assert_eq!(tracker.labels(), vec!["browser_1", "browser_2"]);
+        assert!(tracker.is_tracked("browser_2"));
+        assert!(tracker.unregister("browser_2"));
+        assert!(!tracker.unregister("browser_2"));
+        assert!(tracker.windows_for_profile("token-1").is_empty());
+    }
+}
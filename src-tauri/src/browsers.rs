@@ -0,0 +1,579 @@
+//! 外部浏览器发现与启动
+//!
+//! 按平台的常见安装位置查找 Chrome、Chromium、Edge、Firefox 和 Brave，并读取各自的
+//! 用户档案（Chromium 系读取 `Local State`，Firefox 读取 `profiles.ini`）。
+//! 用户可以注册自定义浏览器和参数模板，并为书签或账号的 Portal 链接指定首选浏览器。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserKind {
+    Chrome,
+    Chromium,
+    Edge,
+    Firefox,
+    Brave,
+    Custom,
+}
+
+impl BrowserKind {
+    pub const BUILTIN: [BrowserKind; 5] = [
+        BrowserKind::Chrome,
+        BrowserKind::Chromium,
+        BrowserKind::Edge,
+        BrowserKind::Firefox,
+        BrowserKind::Brave,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            BrowserKind::Chrome => "chrome",
+            BrowserKind::Chromium => "chromium",
+            BrowserKind::Edge => "edge",
+            BrowserKind::Firefox => "firefox",
+            BrowserKind::Brave => "brave",
+            BrowserKind::Custom => "custom",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BrowserKind::Chrome => "Google Chrome",
+            BrowserKind::Chromium => "Chromium",
+            BrowserKind::Edge => "Microsoft Edge",
+            BrowserKind::Firefox => "Firefox",
+            BrowserKind::Brave => "Brave",
+            BrowserKind::Custom => "自定义浏览器",
+        }
+    }
+
+    /// 内置浏览器的参数模板：`{url}` 为打开的链接，`{profile}` 为档案
+    fn templates(&self) -> ArgTemplates {
+        match self {
+            BrowserKind::Firefox => ArgTemplates {
+                args: vec!["{url}".to_string()],
+                profile_args: vec!["-P".to_string(), "{profile}".to_string()],
+                incognito_args: vec!["-private-window".to_string()],
+            },
+            BrowserKind::Edge => ArgTemplates {
+                args: vec!["{url}".to_string()],
+                profile_args: vec!["--profile-directory={profile}".to_string()],
+                incognito_args: vec!["--inprivate".to_string()],
+            },
+            _ => ArgTemplates {
+                args: vec!["{url}".to_string()],
+                profile_args: vec!["--profile-directory={profile}".to_string()],
+                incognito_args: vec!["--incognito".to_string()],
+            },
+        }
+    }
+}
+
+/// 参数模板
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArgTemplates {
+    /// 基本参数，不含 `{url}` 时链接追加在最后
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 指定档案时追加的参数
+    #[serde(default)]
+    pub profile_args: Vec<String>,
+    /// 隐私模式时追加的参数
+    #[serde(default)]
+    pub incognito_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrowserProfile {
+    /// 启动参数中使用的档案标识（Chromium 为目录名，Firefox 为档案名）
+    pub id: String,
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedBrowser {
+    pub id: String,
+    pub name: String,
+    pub kind: BrowserKind,
+    pub path: String,
+    pub profiles: Vec<BrowserProfile>,
+}
+
+/// 用户注册的浏览器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomBrowser {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    #[serde(default, flatten)]
+    pub templates: ArgTemplates,
+}
+
+/// 打开链接时使用的浏览器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrowserChoice {
+    pub browser_id: String,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub incognito: bool,
+}
+
+/// 浏览器设置，保存在 UnifiedAppConfig 中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BrowserSettings {
+    #[serde(default)]
+    pub custom_browsers: Vec<CustomBrowser>,
+    /// 没有单独指定时使用的浏览器，None 表示系统默认浏览器
+    #[serde(default)]
+    pub default_browser: Option<BrowserChoice>,
+    /// 书签 id -> 首选浏览器
+    #[serde(default)]
+    pub bookmark_browsers: HashMap<String, BrowserChoice>,
+    /// token id -> 打开 Portal 链接时的首选浏览器
+    #[serde(default)]
+    pub portal_browsers: HashMap<String, BrowserChoice>,
+}
+
+impl BrowserSettings {
+    /// 按 书签 / 账号 / 默认 的顺序选择浏览器
+    pub fn resolve(&self, bookmark_id: Option<&str>, token_id: Option<&str>) -> Option<&BrowserChoice> {
+        bookmark_id
+            .and_then(|id| self.bookmark_browsers.get(id))
+            .or_else(|| token_id.and_then(|id| self.portal_browsers.get(id)))
+            .or(self.default_browser.as_ref())
+    }
+}
+
+/// 浏览器发现所需的环境信息，便于在测试中替换
+#[derive(Debug, Clone, Default)]
+pub struct DetectionEnv {
+    pub os: String,
+    pub home: Option<PathBuf>,
+    pub program_files: Vec<PathBuf>,
+    pub local_app_data: Option<PathBuf>,
+    pub app_data: Option<PathBuf>,
+    pub path_dirs: Vec<PathBuf>,
+}
+
+impl DetectionEnv {
+    pub fn current() -> Self {
+        let env_path = |name: &str| std::env::var_os(name).map(PathBuf::from);
+        DetectionEnv {
+            os: std::env::consts::OS.to_string(),
+            home: dirs::home_dir(),
+            program_files: ["ProgramFiles", "ProgramFiles(x86)", "ProgramW6432"]
+                .iter()
+                .filter_map(|name| env_path(name))
+                .collect(),
+            local_app_data: env_path("LOCALAPPDATA"),
+            app_data: env_path("APPDATA"),
+            path_dirs: std::env::var_os("PATH")
+                .map(|path| std::env::split_paths(&path).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn executable_candidates(&self, kind: BrowserKind) -> Vec<PathBuf> {
+        match self.os.as_str() {
+            "windows" => {
+                let relative: &[&str] = match kind {
+                    BrowserKind::Chrome => &["Google\\Chrome\\Application\\chrome.exe"],
+                    BrowserKind::Chromium => &["Chromium\\Application\\chrome.exe"],
+                    BrowserKind::Edge => &["Microsoft\\Edge\\Application\\msedge.exe"],
+                    BrowserKind::Firefox => &["Mozilla Firefox\\firefox.exe"],
+                    BrowserKind::Brave => &["BraveSoftware\\Brave-Browser\\Application\\brave.exe"],
+                    BrowserKind::Custom => &[],
+                };
+                self.program_files
+                    .iter()
+                    .chain(self.local_app_data.iter())
+                    .flat_map(|base| relative.iter().map(move |rel| join_windows(base, rel)))
+                    .collect()
+            }
+            "macos" => {
+                let app: &[(&str, &str)] = match kind {
+                    BrowserKind::Chrome => &[("Google Chrome.app", "Google Chrome")],
+                    BrowserKind::Chromium => &[("Chromium.app", "Chromium")],
+                    BrowserKind::Edge => &[("Microsoft Edge.app", "Microsoft Edge")],
+                    BrowserKind::Firefox => &[("Firefox.app", "firefox")],
+                    BrowserKind::Brave => &[("Brave Browser.app", "Brave Browser")],
+                    BrowserKind::Custom => &[],
+                };
+                let mut roots = vec![PathBuf::from("/Applications")];
+                if let Some(home) = &self.home {
+                    roots.push(home.join("Applications"));
+                }
+                roots
+                    .iter()
+                    .flat_map(|root| {
+                        app.iter().map(move |(bundle, binary)| {
+                            root.join(bundle).join("Contents").join("MacOS").join(binary)
+                        })
+                    })
+                    .collect()
+            }
+            _ => {
+                let names: &[&str] = match kind {
+                    BrowserKind::Chrome => &["google-chrome", "google-chrome-stable"],
+                    BrowserKind::Chromium => &["chromium", "chromium-browser"],
+                    BrowserKind::Edge => &["microsoft-edge", "microsoft-edge-stable"],
+                    BrowserKind::Firefox => &["firefox"],
+                    BrowserKind::Brave => &["brave-browser", "brave"],
+                    BrowserKind::Custom => &[],
+                };
+                self.path_dirs
+                    .iter()
+                    .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+                    .collect()
+            }
+        }
+    }
+
+    /// 浏览器的用户数据目录
+    pub fn user_data_dir(&self, kind: BrowserKind) -> Option<PathBuf> {
+        match self.os.as_str() {
+            "windows" => {
+                if kind == BrowserKind::Firefox {
+                    return self.app_data.as_ref().map(|dir| join_windows(dir, "Mozilla\\Firefox"));
+                }
+                let relative = match kind {
+                    BrowserKind::Chrome => "Google\\Chrome\\User Data",
+                    BrowserKind::Chromium => "Chromium\\User Data",
+                    BrowserKind::Edge => "Microsoft\\Edge\\User Data",
+                    BrowserKind::Brave => "BraveSoftware\\Brave-Browser\\User Data",
+                    _ => return None,
+                };
+                self.local_app_data.as_ref().map(|dir| join_windows(dir, relative))
+            }
+            "macos" => {
+                let relative = match kind {
+                    BrowserKind::Chrome => "Google/Chrome",
+                    BrowserKind::Chromium => "Chromium",
+                    BrowserKind::Edge => "Microsoft Edge",
+                    BrowserKind::Firefox => "Firefox",
+                    BrowserKind::Brave => "BraveSoftware/Brave-Browser",
+                    BrowserKind::Custom => return None,
+                };
+                self.home
+                    .as_ref()
+                    .map(|home| home.join("Library").join("Application Support").join(relative))
+            }
+            _ => {
+                let relative = match kind {
+                    BrowserKind::Chrome => ".config/google-chrome",
+                    BrowserKind::Chromium => ".config/chromium",
+                    BrowserKind::Edge => ".config/microsoft-edge",
+                    BrowserKind::Firefox => ".mozilla/firefox",
+                    BrowserKind::Brave => ".config/BraveSoftware/Brave-Browser",
+                    BrowserKind::Custom => return None,
+                };
+                self.home.as_ref().map(|home| home.join(relative))
+            }
+        }
+    }
+}
+
+fn join_windows(base: &Path, relative: &str) -> PathBuf {
+    relative.split('\\').fold(base.to_path_buf(), |path, part| path.join(part))
+}
+
+/// 检测已安装的浏览器及其档案
+pub fn detect_browsers(env: &DetectionEnv) -> Vec<DetectedBrowser> {
+    BrowserKind::BUILTIN
+        .iter()
+        .filter_map(|kind| {
+            let path = env.executable_candidates(*kind).into_iter().find(|path| path.is_file())?;
+            let profiles = env
+                .user_data_dir(*kind)
+                .map(|dir| match kind {
+                    BrowserKind::Firefox => firefox_profiles(&dir),
+                    _ => chromium_profiles(&dir),
+                })
+                .unwrap_or_default();
+            Some(DetectedBrowser {
+                id: kind.id().to_string(),
+                name: kind.display_name().to_string(),
+                kind: *kind,
+                path: path.to_string_lossy().to_string(),
+                profiles,
+            })
+        })
+        .collect()
+}
+
+/// Chromium 系浏览器的档案：优先读取 `Local State` 中的显示名称
+pub fn chromium_profiles(user_data_dir: &Path) -> Vec<BrowserProfile> {
+    let names: HashMap<String, String> = fs::read_to_string(user_data_dir.join("Local State"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|state| state.pointer("/profile/info_cache").cloned())
+        .and_then(|cache| cache.as_object().cloned())
+        .map(|cache| {
+            cache
+                .iter()
+                .map(|(dir, info)| {
+                    let name = info.get("name").and_then(|n| n.as_str()).unwrap_or(dir);
+                    (dir.clone(), name.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut profiles: Vec<BrowserProfile> = fs::read_dir(user_data_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|dir| names.contains_key(dir) || dir == "Default" || dir.starts_with("Profile "))
+                .map(|dir| BrowserProfile {
+                    name: names.get(&dir).cloned().unwrap_or_else(|| dir.clone()),
+                    path: user_data_dir.join(&dir).to_string_lossy().to_string(),
+                    id: dir,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    profiles.sort_by(|a, b| a.id.cmp(&b.id));
+    profiles
+}
+
+/// Firefox 档案：解析 `profiles.ini` 中的 `[ProfileN]` 段
+pub fn firefox_profiles(firefox_dir: &Path) -> Vec<BrowserProfile> {
+    let content = match fs::read_to_string(firefox_dir.join("profiles.ini")) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut profiles = Vec::new();
+    let mut current: Option<HashMap<String, String>> = None;
+    let flush = |section: Option<HashMap<String, String>>, profiles: &mut Vec<BrowserProfile>| {
+        let Some(section) = section else { return };
+        let (Some(name), Some(path)) = (section.get("Name"), section.get("Path")) else { return };
+        let path = if section.get("IsRelative").map(|v| v == "1").unwrap_or(true) {
+            firefox_dir.join(path)
+        } else {
+            PathBuf::from(path)
+        };
+        profiles.push(BrowserProfile {
+            id: name.clone(),
+            name: name.clone(),
+            path: path.to_string_lossy().to_string(),
+        });
+    };
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            flush(current.take(), &mut profiles);
+            if line[1..line.len() - 1].starts_with("Profile") {
+                current = Some(HashMap::new());
+            }
+        } else if let (Some(section), Some((key, value))) = (current.as_mut(), line.split_once('=')) {
+            section.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    flush(current.take(), &mut profiles);
+
+    profiles
+}
+
+/// 按模板生成启动参数
+pub fn build_args(templates: &ArgTemplates, url: &str, profile: Option<&str>, incognito: bool) -> Vec<String> {
+    let expand = |arg: &String| arg.replace("{url}", url).replace("{profile}", profile.unwrap_or_default());
+
+    let mut args: Vec<String> = Vec::new();
+    if profile.is_some_and(|p| !p.is_empty()) {
+        args.extend(templates.profile_args.iter().map(expand));
+    }
+    if incognito {
+        args.extend(templates.incognito_args.iter().map(expand));
+    }
+    args.extend(templates.args.iter().map(expand));
+
+    let has_url_placeholder = templates
+        .args
+        .iter()
+        .chain(&templates.profile_args)
+        .chain(&templates.incognito_args)
+        .any(|arg| arg.contains("{url}"));
+    if !has_url_placeholder {
+        args.push(url.to_string());
+    }
+    args
+}
+
+/// 解析浏览器选择，返回可执行文件和启动参数
+pub fn resolve_launch(
+    choice: &BrowserChoice,
+    detected: &[DetectedBrowser],
+    custom: &[CustomBrowser],
+    url: &str,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let profile = choice.profile.as_deref();
+    if let Some(browser) = custom.iter().find(|b| b.id == choice.browser_id) {
+        if !Path::new(&browser.path).exists() {
+            return Err(format!("浏览器不存在: {}", browser.path));
+        }
+        return Ok((
+            PathBuf::from(&browser.path),
+            build_args(&browser.templates, url, profile, choice.incognito),
+        ));
+    }
+    if let Some(browser) = detected.iter().find(|b| b.id == choice.browser_id) {
+        return Ok((
+            PathBuf::from(&browser.path),
+            build_args(&browser.kind.templates(), url, profile, choice.incognito),
+        ));
+    }
+    Err(format!("未找到浏览器: {}", choice.browser_id))
+}
+
+/// 校验并规范化自定义浏览器
+pub fn validate_custom_browser(mut browser: CustomBrowser) -> Result<CustomBrowser, String> {
+    browser.name = browser.name.trim().to_string();
+    browser.path = browser.path.trim().to_string();
+    if browser.name.is_empty() {
+        return Err("浏览器名称不能为空".to_string());
+    }
+    if !Path::new(&browser.path).is_file() {
+        return Err(format!("浏览器路径不存在: {}", browser.path));
+    }
+    if BrowserKind::BUILTIN.iter().any(|kind| kind.id() == browser.id) {
+        return Err("浏览器 id 与内置浏览器冲突".to_string());
+    }
+    if browser.id.trim().is_empty() {
+        browser.id = format!("custom-{}", uuid::Uuid::new_v4().simple());
+    }
+    Ok(browser)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn test_detect_linux_browsers_and_profiles() {
+        let root = tempdir().unwrap();
+        let bin = root.path().join("bin");
+        let home = root.path().join("home");
+        touch(&bin.join("google-chrome-stable"));
+        touch(&bin.join("firefox"));
+
+        let chrome_data = home.join(".config/google-chrome");
+        fs::create_dir_all(chrome_data.join("Default")).unwrap();
+        fs::create_dir_all(chrome_data.join("Profile 1")).unwrap();
+        fs::create_dir_all(chrome_data.join("Crashpad")).unwrap();
+        fs::write(
+            chrome_data.join("Local State"),
+            r#"{"profile": {"info_cache": {"Default": {"name": "工作"}, "Profile 1": {"name": "Personal"}}}}"#,
+        )
+        .unwrap();
+
+        let firefox_dir = home.join(".mozilla/firefox");
+        fs::create_dir_all(&firefox_dir).unwrap();
+        fs::write(
+            firefox_dir.join("profiles.ini"),
+            "[General]\nStartWithLastProfile=1\n\n[Profile0]\nName=default-release\nIsRelative=1\nPath=abcd.default-release\n\n[Install123]\nDefault=abcd.default-release\n",
+        )
+        .unwrap();
+
+        let env = DetectionEnv {
+            os: "linux".to_string(),
+            home: Some(home),
+            path_dirs: vec![bin],
+            ..Default::default()
+        };
+        let browsers = detect_browsers(&env);
+        let ids: Vec<&str> = browsers.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["chrome", "firefox"]);
+
+        let chrome = &browsers[0];
+        assert!(chrome.path.ends_with("google-chrome-stable"));
+        assert_eq!(chrome.profiles.len(), 2);
+        assert_eq!(chrome.profiles[0].id, "Default");
+        assert_eq!(chrome.profiles[0].name, "工作");
+
+        let firefox = &browsers[1];
+        assert_eq!(firefox.profiles.len(), 1);
+        assert_eq!(firefox.profiles[0].id, "default-release");
+        assert!(firefox.profiles[0].path.ends_with("abcd.default-release"));
+    }
+
+    #[test]
+    fn test_detect_windows_layout() {
+        let root = tempdir().unwrap();
+        let program_files = root.path().join("Program Files");
+        let local = root.path().join("Local");
+        touch(&join_windows(&program_files, "Microsoft\\Edge\\Application\\msedge.exe"));
+        touch(&join_windows(&local, "BraveSoftware\\Brave-Browser\\Application\\brave.exe"));
+
+        let env = DetectionEnv {
+            os: "windows".to_string(),
+            program_files: vec![program_files],
+            local_app_data: Some(local),
+            ..Default::default()
+        };
+        let ids: Vec<String> = detect_browsers(&env).into_iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec!["edge", "brave"]);
+    }
+
+    #[test]
+    fn test_build_args_from_templates() {
+        let url = "https://app.augmentcode.com";
+        assert_eq!(
+            build_args(&BrowserKind::Chrome.templates(), url, Some("Profile 1"), true),
+            vec!["--profile-directory=Profile 1", "--incognito", url]
+        );
+        assert_eq!(
+            build_args(&BrowserKind::Firefox.templates(), url, Some("work"), false),
+            vec!["-P", "work", url]
+        );
+
+        // 自定义模板不含 {url} 时追加在最后
+        let templates = ArgTemplates {
+            args: vec!["--new-window".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(build_args(&templates, url, None, true), vec!["--new-window", url]);
+    }
+
+    #[test]
+    fn test_resolve_preferred_browser() {
+        let choice = |id: &str| BrowserChoice { browser_id: id.to_string(), profile: None, incognito: false };
+        let mut settings = BrowserSettings {
+            default_browser: Some(choice("chrome")),
+            ..Default::default()
+        };
+        settings.bookmark_browsers.insert("b1".to_string(), choice("firefox"));
+        settings.portal_browsers.insert("t1".to_string(), choice("edge"));
+
+        assert_eq!(settings.resolve(Some("b1"), Some("t1")).unwrap().browser_id, "firefox");
+        assert_eq!(settings.resolve(Some("b2"), Some("t1")).unwrap().browser_id, "edge");
+        assert_eq!(settings.resolve(None, None).unwrap().browser_id, "chrome");
+
+        let detected = vec![DetectedBrowser {
+            id: "chrome".to_string(),
+            name: "Google Chrome".to_string(),
+            kind: BrowserKind::Chrome,
+            path: "/usr/bin/google-chrome".to_string(),
+            profiles: Vec::new(),
+        }];
+        let (path, args) = resolve_launch(&choice("chrome"), &detected, &[], "https://x").unwrap();
+        assert_eq!(path, PathBuf::from("/usr/bin/google-chrome"));
+        assert_eq!(args, vec!["https://x"]);
+        assert!(resolve_launch(&choice("missing"), &detected, &[], "https://x").is_err());
+    }
+}
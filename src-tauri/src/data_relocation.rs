@@ -0,0 +1,572 @@
+//! 数据目录迁移
+//!
+//! 把数据目录中属于本应用的文件（tokens、书签、配置、备份、日志等）迁移到新目录：
+//! 先复制到新目录下的暂存目录并逐个校验 SHA-256，再整体移入新目录；任何一步失败都会
+//! 清理已移入的文件。旧目录中的副本保留到用户确认（`finalize`）之后才删除，
+//! 撤销（`revert`）时把新目录中的改动带回旧目录。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage::write_file_atomic;
+
+/// 待确认的迁移记录，保存在默认应用数据目录中
+pub const RELOCATION_FILE: &str = "relocation.json";
+
+/// 随数据目录迁移的文件；以 `<文件名>.` 开头的版本迁移备份也一并迁移
+pub const DATA_FILES: &[&str] = &[
+    "tokens.json",
+    "bookmarks.json",
+    "config.json",
+    crate::status_scheduler::SUMMARY_FILE,
+    crate::augment_user_info::APP_SESSION_CACHE_FILE,
+];
+
+/// WebDAV 同步冲突时生成的本地备份：`tokens_local_<时间>.json`
+const SYNC_BACKUP_PREFIX: &str = "tokens_";
+
+/// 随数据目录迁移的子目录
+pub const DATA_DIRS: &[&str] = &[
+    crate::logging::LOG_DIR_NAME,
+    "diagnostics",
+    crate::storage::journal::HISTORY_DIR,
+    crate::backups::BACKUP_DIR,
+];
+
+/// 内容会持续变化的子目录（日志），迁移过程中不检查源文件是否被修改
+const VOLATILE_DIRS: &[&str] = &[crate::logging::LOG_DIR_NAME];
+
+const STAGING_DIR: &str = ".zaugment-relocation";
+const REPLACED_SUFFIX: &str = "pre-relocation.bak";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelocatedEntry {
+    /// 相对数据目录的路径，统一使用 `/` 分隔
+    pub relative_path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RelocationOptions {
+    /// 确认后仍保留在旧目录中的文件（例如默认目录中记录自定义目录的 config.json）
+    pub retain_in_source: Vec<String>,
+    /// 允许覆盖新目录中已存在的文件，被覆盖的文件先改名备份，撤销时恢复
+    pub replace_in_target: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRelocation {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub entries: Vec<RelocatedEntry>,
+    #[serde(default)]
+    pub retained_in_source: Vec<String>,
+    /// 新目录中被覆盖的文件（已改名为 `<文件名>.pre-relocation.bak`）
+    #[serde(default)]
+    pub replaced_in_target: Vec<String>,
+    /// 迁移前新目录中已有的数据文件（不含被覆盖的），撤销时保留
+    #[serde(default)]
+    pub existing_in_target: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PendingRelocation {
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn hash_file(path: &Path) -> Result<String, String> {
+    let content = fs::read(path).map_err(|e| format!("读取文件失败 {}: {}", path.display(), e))?;
+    Ok(sha256_hex(&content))
+}
+
+fn to_path(base: &Path, relative: &str) -> PathBuf {
+    relative.split('/').fold(base.to_path_buf(), |path, part| path.join(part))
+}
+
+fn is_data_file(name: &str) -> bool {
+    if name.ends_with(REPLACED_SUFFIX) {
+        return false;
+    }
+    (name.starts_with(SYNC_BACKUP_PREFIX) && name.ends_with(".json"))
+        || DATA_FILES
+            .iter()
+            .any(|file| name == *file || name.starts_with(&format!("{}.", file)))
+}
+
+fn is_volatile(relative: &str) -> bool {
+    VOLATILE_DIRS
+        .iter()
+        .any(|dir| relative.starts_with(&format!("{}/", dir)))
+}
+
+fn collect_dir(dir: &Path, prefix: &str, out: &mut Vec<String>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取目录失败 {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取目录失败 {}: {}", dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = format!("{}/{}", prefix, name);
+        let file_type = entry.file_type().map_err(|e| format!("读取文件类型失败: {}", e))?;
+        if file_type.is_dir() {
+            collect_dir(&entry.path(), &relative, out)?;
+        } else if file_type.is_file() {
+            out.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// 列出数据目录中需要迁移的文件（相对路径，已排序）
+pub fn collect_entries(data_dir: &Path) -> Result<Vec<String>, String> {
+    let mut entries = Vec::new();
+    if !data_dir.exists() {
+        return Ok(entries);
+    }
+
+    let dir_entries = fs::read_dir(data_dir)
+        .map_err(|e| format!("读取目录失败 {}: {}", data_dir.display(), e))?;
+    for entry in dir_entries {
+        let entry = entry.map_err(|e| format!("读取目录失败 {}: {}", data_dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let file_type = entry.file_type().map_err(|e| format!("读取文件类型失败: {}", e))?;
+
+        if file_type.is_file() && is_data_file(&name) {
+            entries.push(name);
+        } else if file_type.is_dir() && DATA_DIRS.contains(&name.as_str()) {
+            collect_dir(&entry.path(), &name, &mut entries)?;
+        }
+    }
+
+    entries.sort();
+    Ok(entries)
+}
+
+/// 两个路径是否指向同一目录
+pub fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 检查新目录可用：存在（不存在则创建）、是目录且可写
+pub fn validate_target(target: &Path) -> Result<(), String> {
+    if target.as_os_str().is_empty() {
+        return Err("目标目录不能为空".to_string());
+    }
+    if target.exists() && !target.is_dir() {
+        return Err(format!("选择的路径不是一个目录: {}", target.display()));
+    }
+    fs::create_dir_all(target).map_err(|e| format!("无法创建目录 {}: {}", target.display(), e))?;
+
+    let probe = target.join(format!("{}.probe", STAGING_DIR));
+    fs::write(&probe, b"ok").map_err(|e| format!("目录不可写 {}: {}", target.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// 复制、校验并切换到新目录；成功后旧目录中的文件保持不变，等待确认
+pub fn relocate(source: &Path, target: &Path, options: &RelocationOptions) -> Result<PendingRelocation, String> {
+    validate_target(target)?;
+    if same_dir(source, target) {
+        return Err("新目录与当前数据目录相同".to_string());
+    }
+
+    let relatives = collect_entries(source)?;
+
+    // 新目录中已有同名文件时拒绝覆盖（内容相同或明确允许替换的除外）
+    let mut replaced = Vec::new();
+    for relative in &relatives {
+        let existing = to_path(target, relative);
+        if !existing.exists() {
+            continue;
+        }
+        if options.replace_in_target.contains(relative) {
+            replaced.push(relative.clone());
+        } else if hash_file(&existing)? != hash_file(&to_path(source, relative))? {
+            return Err(format!("新目录中已存在不同的 {}，请选择空目录", relative));
+        }
+    }
+
+    let existing_in_target: Vec<String> = collect_entries(target)?
+        .into_iter()
+        .filter(|relative| !replaced.contains(relative))
+        .collect();
+
+    // 1. 复制到暂存目录并校验
+    let staging = target.join(STAGING_DIR);
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| format!("清理暂存目录失败: {}", e))?;
+    }
+
+    let staged = stage_entries(source, &staging, &relatives);
+    let entries = match staged {
+        Ok(entries) => entries,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    // 2. 复制期间源文件被修改则放弃，避免丢失刚写入的数据
+    for entry in entries.iter().filter(|e| !is_volatile(&e.relative_path)) {
+        if hash_file(&to_path(source, &entry.relative_path))? != entry.sha256 {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(format!("{} 在迁移过程中被修改，请重试", entry.relative_path));
+        }
+    }
+
+    // 3. 把暂存文件移入新目录，失败时撤回已移入的文件
+    if let Err(e) = commit_staged(&staging, target, &entries, &replaced) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    let _ = fs::remove_dir_all(&staging);
+
+    log::info!(
+        "数据目录已迁移 {} -> {}（{} 个文件）",
+        source.display(),
+        target.display(),
+        entries.len()
+    );
+
+    Ok(PendingRelocation {
+        source: source.to_path_buf(),
+        target: target.to_path_buf(),
+        entries,
+        retained_in_source: options.retain_in_source.clone(),
+        replaced_in_target: replaced,
+        existing_in_target,
+        created_at: Utc::now(),
+    })
+}
+
+fn stage_entries(source: &Path, staging: &Path, relatives: &[String]) -> Result<Vec<RelocatedEntry>, String> {
+    let mut entries = Vec::new();
+    for relative in relatives {
+        let content = fs::read(to_path(source, relative))
+            .map_err(|e| format!("读取 {} 失败: {}", relative, e))?;
+        let sha256 = sha256_hex(&content);
+
+        let staged_path = to_path(staging, relative);
+        write_file_atomic(&staged_path, &content)?;
+
+        if hash_file(&staged_path)? != sha256 {
+            return Err(format!("{} 复制后校验失败", relative));
+        }
+
+        entries.push(RelocatedEntry {
+            relative_path: relative.clone(),
+            size: content.len() as u64,
+            sha256,
+        });
+    }
+    Ok(entries)
+}
+
+fn replaced_backup_path(target: &Path, relative: &str) -> PathBuf {
+    let path = to_path(target, relative);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}", name, REPLACED_SUFFIX))
+}
+
+fn commit_staged(staging: &Path, target: &Path, entries: &[RelocatedEntry], replaced: &[String]) -> Result<(), String> {
+    let mut moved: Vec<&str> = Vec::new();
+    let mut backed_up: Vec<&str> = Vec::new();
+
+    let result = (|| {
+        for relative in replaced {
+            let existing = to_path(target, relative);
+            fs::rename(&existing, replaced_backup_path(target, relative))
+                .map_err(|e| format!("备份 {} 失败: {}", relative, e))?;
+            backed_up.push(relative);
+        }
+
+        for entry in entries {
+            let destination = to_path(target, &entry.relative_path);
+            if destination.exists() {
+                // 内容相同的文件已在新目录中
+                continue;
+            }
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+            }
+            fs::rename(to_path(staging, &entry.relative_path), &destination)
+                .map_err(|e| format!("移动 {} 失败: {}", entry.relative_path, e))?;
+            moved.push(&entry.relative_path);
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        for relative in &moved {
+            let _ = fs::remove_file(to_path(target, relative));
+        }
+        for relative in &backed_up {
+            let _ = fs::rename(replaced_backup_path(target, relative), to_path(target, relative));
+        }
+    }
+    result
+}
+
+/// 确认迁移：新目录中的文件完好时删除旧目录中的副本，返回删除的文件数
+pub fn finalize(pending: &PendingRelocation) -> Result<usize, String> {
+    for entry in &pending.entries {
+        if !to_path(&pending.target, &entry.relative_path).exists() {
+            return Err(format!("新目录中缺少 {}，已保留旧目录中的数据", entry.relative_path));
+        }
+    }
+
+    let retained: HashSet<&str> = pending.retained_in_source.iter().map(String::as_str).collect();
+    let mut removed = 0;
+    for entry in &pending.entries {
+        if retained.contains(entry.relative_path.as_str()) {
+            continue;
+        }
+        let path = to_path(&pending.source, &entry.relative_path);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除旧文件失败 {}: {}", path.display(), e))?;
+            removed += 1;
+        }
+    }
+
+    // 删除已清空的子目录
+    for dir in DATA_DIRS {
+        let path = pending.source.join(dir);
+        if path.is_dir() && is_empty_dir(&path) {
+            let _ = fs::remove_dir_all(&path);
+        }
+    }
+
+    for relative in &pending.replaced_in_target {
+        let _ = fs::remove_file(replaced_backup_path(&pending.target, relative));
+    }
+
+    Ok(removed)
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    let mut entries = Vec::new();
+    collect_dir(dir, "", &mut entries).map(|_| entries.is_empty()).unwrap_or(false)
+}
+
+/// 撤销迁移：把新目录中迁移后产生的改动带回旧目录，再删除新目录中的副本
+pub fn revert(pending: &PendingRelocation) -> Result<(), String> {
+    let recorded: std::collections::HashMap<&str, &str> = pending
+        .entries
+        .iter()
+        .map(|e| (e.relative_path.as_str(), e.sha256.as_str()))
+        .collect();
+
+    for relative in collect_entries(&pending.target)? {
+        if pending.existing_in_target.contains(&relative) {
+            continue;
+        }
+        let target_path = to_path(&pending.target, &relative);
+        let changed = match recorded.get(relative.as_str()) {
+            Some(sha256) => hash_file(&target_path)? != *sha256,
+            None => true,
+        };
+
+        if changed {
+            let content = fs::read(&target_path).map_err(|e| format!("读取 {} 失败: {}", relative, e))?;
+            write_file_atomic(&to_path(&pending.source, &relative), &content)?;
+        }
+        fs::remove_file(&target_path).map_err(|e| format!("删除 {} 失败: {}", relative, e))?;
+    }
+
+    for relative in &pending.replaced_in_target {
+        let backup = replaced_backup_path(&pending.target, relative);
+        if backup.exists() {
+            fs::rename(&backup, to_path(&pending.target, relative))
+                .map_err(|e| format!("恢复 {} 失败: {}", relative, e))?;
+        }
+    }
+
+    for dir in DATA_DIRS {
+        let path = pending.target.join(dir);
+        if path.is_dir() && is_empty_dir(&path) {
+            let _ = fs::remove_dir_all(&path);
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取待确认的迁移记录
+pub fn load_pending(path: &Path) -> Result<Option<PendingRelocation>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("读取迁移记录失败: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("解析迁移记录失败: {}", e))
+}
+
+/// 保存或清除待确认的迁移记录
+pub fn save_pending(path: &Path, pending: Option<&PendingRelocation>) -> Result<(), String> {
+    match pending {
+        Some(pending) => {
+            let content = serde_json::to_string_pretty(pending)
+                .map_err(|e| format!("序列化迁移记录失败: {}", e))?;
+            write_file_atomic(path, content.as_bytes())
+        }
+        None => {
+            if path.exists() {
+                fs::remove_file(path).map_err(|e| format!("删除迁移记录失败: {}", e))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(dir: &Path, relative: &str, content: &str) {
+        let path = to_path(dir, relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(dir: &Path, relative: &str) -> String {
+        fs::read_to_string(to_path(dir, relative)).unwrap()
+    }
+
+    fn populate(dir: &Path) {
+        write(dir, "tokens.json", r#"{"tokens":[]}"#);
+        write(dir, "bookmarks.json", "{}");
+        write(dir, "config.json", "{}");
+        write(dir, "tokens.json.v1.0.0-20250101T000000.bak", "[]");
+        write(dir, "tokens_local_20250101_120000.json", "[]");
+        write(dir, "logs/zaugment.log", "line");
+        write(dir, "EBWebView/cache", "not ours");
+        write(dir, "profiles.json", "{}");
+    }
+
+    #[test]
+    fn test_collect_entries_only_includes_app_data() {
+        let dir = tempdir().unwrap();
+        populate(dir.path());
+
+        let entries = collect_entries(dir.path()).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                "bookmarks.json",
+                "config.json",
+                "logs/zaugment.log",
+                "tokens.json",
+                "tokens.json.v1.0.0-20250101T000000.bak",
+                "tokens_local_20250101_120000.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relocate_to_cjk_path_and_finalize() {
+        let root = tempdir().unwrap();
+        let source = root.path().join("source");
+        let target = root.path().join("数据目录").join("账号");
+        populate(&source);
+
+        let pending = relocate(&source, &target, &RelocationOptions {
+            retain_in_source: vec!["config.json".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(pending.entries.len(), 6);
+        assert_eq!(read(&target, "tokens.json"), r#"{"tokens":[]}"#);
+        assert_eq!(read(&target, "logs/zaugment.log"), "line");
+        assert!(!target.join(STAGING_DIR).exists());
+        // 确认前旧目录保持不变
+        assert!(source.join("tokens.json").exists());
+
+        let removed = finalize(&pending).unwrap();
+        assert_eq!(removed, 5);
+        assert!(!source.join("tokens.json").exists());
+        assert!(!source.join("logs").exists());
+        assert!(source.join("config.json").exists());
+        assert!(source.join("profiles.json").exists());
+    }
+
+    #[test]
+    fn test_relocate_rejects_conflicts_and_same_dir() {
+        let root = tempdir().unwrap();
+        let source = root.path().join("source");
+        let target = root.path().join("target");
+        populate(&source);
+        write(&target, "tokens.json", "different");
+
+        let err = relocate(&source, &target, &RelocationOptions::default()).unwrap_err();
+        assert!(err.contains("tokens.json"));
+        // 失败后新目录保持原样
+        assert_eq!(read(&target, "tokens.json"), "different");
+        assert!(!target.join("bookmarks.json").exists());
+        assert!(!target.join(STAGING_DIR).exists());
+
+        assert!(relocate(&source, &source, &RelocationOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_revert_restores_changes_and_replaced_files() {
+        let root = tempdir().unwrap();
+        let source = root.path().join("source");
+        let target = root.path().join("target");
+        populate(&source);
+        write(&target, "config.json", "target config");
+        write(&target, "unrelated.txt", "keep");
+        write(&target, "bookmarks.json", "{}");
+
+        let pending = relocate(&source, &target, &RelocationOptions {
+            replace_in_target: vec!["config.json".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(read(&target, "config.json"), "{}");
+
+        // 迁移后在新目录中的修改
+        write(&target, "tokens.json", r#"{"tokens":[1]}"#);
+
+        revert(&pending).unwrap();
+        assert_eq!(read(&source, "tokens.json"), r#"{"tokens":[1]}"#);
+        assert!(!target.join("tokens.json").exists());
+        assert!(!target.join("logs").exists());
+        assert_eq!(read(&target, "config.json"), "target config");
+        assert_eq!(read(&target, "unrelated.txt"), "keep");
+        assert_eq!(read(&target, "bookmarks.json"), "{}");
+    }
+
+    #[test]
+    fn test_pending_record_round_trip() {
+        let root = tempdir().unwrap();
+        let source = root.path().join("source");
+        populate(&source);
+        let pending = relocate(&source, &root.path().join("target"), &RelocationOptions::default()).unwrap();
+
+        let record = root.path().join(RELOCATION_FILE);
+        save_pending(&record, Some(&pending)).unwrap();
+        let loaded = load_pending(&record).unwrap().unwrap();
+        assert_eq!(loaded.entries, pending.entries);
+        assert_eq!(loaded.total_size(), pending.total_size());
+
+        save_pending(&record, None).unwrap();
+        assert!(load_pending(&record).unwrap().is_none());
+    }
+}
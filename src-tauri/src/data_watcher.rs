@@ -0,0 +1,256 @@
+//! 数据目录的外部修改检测
+//!
+//! 数据目录可能放在网盘或 NAS 上，也可能被其他工具或另一个应用实例修改。这类目录上的
+//! 文件系统通知并不可靠，因此按固定间隔轮询数据文件：先比较大小和修改时间，变化时再比较
+//! 内容哈希。本进程写入的内容通过 `record_write` 登记，轮询时不会被当成外部修改。
+//!
+//! tokens.json 的外部修改会记录涉及的账号 id，前端重新加载之前保存这些账号时视为冲突。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// 检测的数据文件
+pub const WATCHED_FILES: &[&str] = &["tokens.json", "bookmarks.json", "config.json"];
+/// 轮询间隔
+pub const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 一次检测到的外部修改
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalChange {
+    pub file: String,
+    /// tokens.json 中新增、删除或修改的账号 id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_ids: Vec<String>,
+}
+
+fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+fn own_writes() -> &'static Mutex<HashMap<PathBuf, String>> {
+    static OWN_WRITES: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+    OWN_WRITES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 登记本进程写入的文件内容
+pub fn record_write(path: &Path, content: &[u8]) {
+    own_writes().lock().unwrap().insert(path.to_path_buf(), content_hash(content));
+}
+
+fn is_own_write(path: &Path, hash: &str) -> bool {
+    own_writes().lock().unwrap().get(path).is_some_and(|own| own == hash)
+}
+
+/// tokens.json 中的账号（id -> 内容），兼容早期的数组格式
+fn tokens_by_id(content: &[u8]) -> Option<HashMap<String, Value>> {
+    let document: Value = serde_json::from_slice(content).ok()?;
+    let tokens = document.get("tokens").unwrap_or(&document).as_array()?;
+    Some(
+        tokens
+            .iter()
+            .filter_map(|token| Some((token.get("id")?.as_str()?.to_string(), token.clone())))
+            .collect(),
+    )
+}
+
+/// 两个版本之间新增、删除或修改的账号 id（排序）
+pub fn changed_token_ids(before: &HashMap<String, Value>, after: &HashMap<String, Value>) -> Vec<String> {
+    let mut ids: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|id| before.get(*id) != after.get(*id))
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[derive(Debug, Default, Clone)]
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: Option<String>,
+}
+
+impl FileState {
+    fn same_metadata(&self, other: &FileState) -> bool {
+        self.len == other.len && self.modified == other.modified
+    }
+}
+
+fn metadata_state(path: &Path) -> FileState {
+    match fs::metadata(path) {
+        Ok(metadata) => FileState {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: None,
+        },
+        Err(_) => FileState::default(),
+    }
+}
+
+/// 轮询状态，绑定到一个数据目录
+#[derive(Debug, Default)]
+pub struct DataWatcher {
+    data_dir: PathBuf,
+    files: HashMap<&'static str, FileState>,
+    tokens: HashMap<String, Value>,
+    /// 外部修改过、前端尚未重新加载的账号
+    pending_token_ids: HashSet<String>,
+    /// 是否已完成基准扫描；基准扫描读到的内容不算外部修改
+    initialized: bool,
+}
+
+impl DataWatcher {
+    pub fn new(data_dir: &Path) -> Self {
+        let mut watcher = Self::default();
+        watcher.set_data_dir(data_dir);
+        watcher
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// 切换数据目录，以新目录的当前内容为基准
+    pub fn set_data_dir(&mut self, data_dir: &Path) {
+        self.data_dir = data_dir.to_path_buf();
+        self.files.clear();
+        self.tokens.clear();
+        self.pending_token_ids.clear();
+        self.initialized = false;
+        self.poll();
+        self.initialized = true;
+    }
+
+    /// 检查数据文件，返回自上次检查以来的外部修改
+    pub fn poll(&mut self) -> Vec<ExternalChange> {
+        let mut changes = Vec::new();
+        for name in WATCHED_FILES {
+            let path = self.data_dir.join(name);
+            let mut state = metadata_state(&path);
+            let previous = self.files.get(name).cloned();
+            if previous.as_ref().is_some_and(|previous| previous.same_metadata(&state)) {
+                continue;
+            }
+
+            let content = fs::read(&path).unwrap_or_default();
+            let hash = content_hash(&content);
+            state.hash = Some(hash.clone());
+            self.files.insert(name, state);
+            if previous.and_then(|previous| previous.hash).as_deref() == Some(hash.as_str()) {
+                continue;
+            }
+
+            let external = self.initialized && !is_own_write(&path, &hash);
+            let mut change = ExternalChange {
+                file: name.to_string(),
+                token_ids: Vec::new(),
+            };
+            if *name == "tokens.json" {
+                // 文件暂时无法解析（例如网盘正在写入）时保留旧的基准，下次再比较
+                let Some(tokens) = tokens_by_id(&content).or_else(|| content.is_empty().then(HashMap::new)) else {
+                    self.files.remove(name);
+                    continue;
+                };
+                change.token_ids = changed_token_ids(&self.tokens, &tokens);
+                self.tokens = tokens;
+                if external {
+                    self.pending_token_ids.extend(change.token_ids.iter().cloned());
+                }
+            }
+            if external {
+                changes.push(change);
+            }
+        }
+        changes
+    }
+
+    /// 前端重新加载账号后调用，之前的外部修改不再视为冲突
+    pub fn acknowledge_tokens(&mut self) {
+        self.pending_token_ids.clear();
+    }
+
+    /// 即将保存的账号中，被外部修改且前端尚未重新加载的部分（调用前先 `poll`）
+    pub fn conflicts(&self, token_ids: &[String]) -> Vec<String> {
+        let mut conflicts: Vec<String> = token_ids
+            .iter()
+            .filter(|id| self.pending_token_ids.contains(*id))
+            .cloned()
+            .collect();
+        conflicts.sort();
+        conflicts.dedup();
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_tokens(dir: &Path, tokens: &str, own: bool) {
+        let path = dir.join("tokens.json");
+        let content = format!(r#"{{"version": "1", "tokens": {}}}"#, tokens);
+        fs::write(&path, &content).unwrap();
+        if own {
+            record_write(&path, content.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_changed_token_ids() {
+        let before = tokens_by_id(br#"[{"id": "a", "x": 1}, {"id": "b"}]"#).unwrap();
+        let after = tokens_by_id(br#"{"tokens": [{"id": "a", "x": 2}, {"id": "c"}]}"#).unwrap();
+        assert_eq!(changed_token_ids(&before, &after), ["a", "b", "c"]);
+        assert!(changed_token_ids(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_poll_ignores_own_writes_and_reports_external_changes() {
+        let dir = tempdir().unwrap();
+        write_tokens(dir.path(), r#"[{"id": "a"}]"#, true);
+        let mut watcher = DataWatcher::new(dir.path());
+        assert!(watcher.poll().is_empty());
+
+        write_tokens(dir.path(), r#"[{"id": "a"}, {"id": "b"}]"#, true);
+        assert!(watcher.poll().is_empty());
+        assert!(watcher.conflicts(&["b".to_string()]).is_empty());
+
+        // 外部修改：文件大小变化即可被发现
+        write_tokens(dir.path(), r#"[{"id": "a", "tag_name": "external"}, {"id": "b"}]"#, false);
+        fs::write(dir.path().join("bookmarks.json"), "{}").unwrap();
+        let changes = watcher.poll();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].file, "tokens.json");
+        assert_eq!(changes[0].token_ids, ["a"]);
+        assert_eq!(changes[1].file, "bookmarks.json");
+
+        assert_eq!(watcher.conflicts(&["a".to_string(), "b".to_string()]), ["a"]);
+        watcher.acknowledge_tokens();
+        assert!(watcher.conflicts(&["a".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_unparsable_tokens_are_retried() {
+        let dir = tempdir().unwrap();
+        write_tokens(dir.path(), r#"[{"id": "a"}]"#, true);
+        let mut watcher = DataWatcher::new(dir.path());
+
+        fs::write(dir.path().join("tokens.json"), r#"{"tokens": [{"id": "#).unwrap();
+        assert!(watcher.poll().is_empty());
+
+        write_tokens(dir.path(), r#"[{"id": "a"}, {"id": "c"}]"#, false);
+        let changes = watcher.poll();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].token_ids, ["c"]);
+    }
+}
@@ -0,0 +1,302 @@
+//! Deep-Link 路由
+//!
+//! 解析 `zaugment://<action>?...` 链接并转换为 `DeepLinkAction`。解析结果先放入
+//! `DeepLinkInbox` 等待用户在应用内确认，确认之后才会执行导入、同步、添加书签等操作。
+//! 格式错误或超长的链接直接拒绝，并返回可记录到日志的原因。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use thiserror::Error;
+
+pub const SCHEME: &str = "zaugment";
+/// 整个链接的最大长度
+pub const MAX_URL_LENGTH: usize = 16 * 1024;
+/// 单个参数的最大长度（session 是其中最长的）
+pub const MAX_PARAM_LENGTH: usize = 8 * 1024;
+/// 最多保留的待确认链接数，超出时丢弃最早的
+pub const MAX_PENDING: usize = 10;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DeepLinkError {
+    #[error("链接超长: {0} 字节")]
+    TooLong(usize),
+
+    #[error("链接格式错误: {0}")]
+    Malformed(String),
+
+    #[error("不支持的协议: {0}")]
+    InvalidScheme(String),
+
+    #[error("未知的操作: {0}")]
+    UnknownAction(String),
+
+    #[error("缺少参数: {0}")]
+    MissingParam(&'static str),
+
+    #[error("参数 {0} 无效: {1}")]
+    InvalidParam(&'static str, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    /// 双向同步
+    Both,
+    Upload,
+    Download,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    /// `zaugment://import?session=...`
+    ImportSession { session: String },
+    /// `zaugment://open-token?id=...`
+    OpenToken { token_id: String },
+    /// `zaugment://sync?direction=upload|download`
+    TriggerSync { direction: SyncDirection },
+    /// `zaugment://add-bookmark?name=...&url=...&description=...&category=temp|service`
+    AddBookmark {
+        name: String,
+        url: String,
+        description: Option<String>,
+        category: String,
+    },
+}
+
+impl DeepLinkAction {
+    /// 显示在确认框中的说明，不包含 session 原文
+    pub fn summary(&self) -> String {
+        match self {
+            DeepLinkAction::ImportSession { session } => {
+                let preview: String = session.chars().take(8).collect();
+                format!("导入 Session（{}…，共 {} 个字符）", preview, session.chars().count())
+            }
+            DeepLinkAction::OpenToken { token_id } => format!("打开账号 {}", token_id),
+            DeepLinkAction::TriggerSync { direction } => match direction {
+                SyncDirection::Both => "与云端同步数据".to_string(),
+                SyncDirection::Upload => "上传本地数据到云端（覆盖云端）".to_string(),
+                SyncDirection::Download => "从云端下载数据（覆盖本地）".to_string(),
+            },
+            DeepLinkAction::AddBookmark { name, url, .. } => format!("添加书签 {}（{}）", name, url),
+        }
+    }
+}
+
+/// 解析并校验 deep-link
+pub fn parse(raw: &str) -> Result<DeepLinkAction, DeepLinkError> {
+    let raw = raw.trim();
+    if raw.len() > MAX_URL_LENGTH {
+        return Err(DeepLinkError::TooLong(raw.len()));
+    }
+
+    let url = url::Url::parse(raw).map_err(|e| DeepLinkError::Malformed(e.to_string()))?;
+    if url.scheme() != SCHEME {
+        return Err(DeepLinkError::InvalidScheme(url.scheme().to_string()));
+    }
+
+    let mut params: HashMap<String, String> = HashMap::new();
+    for (key, value) in url.query_pairs() {
+        if value.len() > MAX_PARAM_LENGTH {
+            return Err(DeepLinkError::TooLong(value.len()));
+        }
+        if params.insert(key.to_string(), value.to_string()).is_some() {
+            return Err(DeepLinkError::Malformed(format!("重复的参数 {}", key)));
+        }
+    }
+
+    // 操作名可以写在 host 或路径中：zaugment://import?... 或 zaugment:import?...
+    let action = url
+        .host_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| url.path().trim_matches('/').to_string())
+        .to_ascii_lowercase();
+
+    match action.as_str() {
+        "import" | "" => Ok(DeepLinkAction::ImportSession {
+            session: required(&params, "session", is_session_char)?,
+        }),
+        "open-token" => Ok(DeepLinkAction::OpenToken {
+            token_id: required(&params, "id", |c| c.is_ascii_alphanumeric() || c == '-' || c == '_')?,
+        }),
+        "sync" => {
+            let direction = match params.get("direction").map(String::as_str) {
+                None | Some("both") => SyncDirection::Both,
+                Some("upload") => SyncDirection::Upload,
+                Some("download") => SyncDirection::Download,
+                Some(other) => return Err(DeepLinkError::InvalidParam("direction", other.to_string())),
+            };
+            Ok(DeepLinkAction::TriggerSync { direction })
+        }
+        "add-bookmark" => {
+            let name = required(&params, "name", |c| !c.is_control())?;
+            let target = required(&params, "url", |c| !c.is_control() && !c.is_whitespace())?;
+            match url::Url::parse(&target) {
+                Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+                _ => return Err(DeepLinkError::InvalidParam("url", target)),
+            }
+            let category = match params.get("category").map(String::as_str) {
+                None => "temp".to_string(),
+                Some(category @ ("temp" | "service")) => category.to_string(),
+                Some(other) => return Err(DeepLinkError::InvalidParam("category", other.to_string())),
+            };
+            Ok(DeepLinkAction::AddBookmark {
+                name,
+                url: target,
+                description: params.get("description").map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+                category,
+            })
+        }
+        other => Err(DeepLinkError::UnknownAction(other.to_string())),
+    }
+}
+
+fn is_session_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_.~+/=%".contains(c)
+}
+
+fn required(
+    params: &HashMap<String, String>,
+    name: &'static str,
+    allowed: impl Fn(char) -> bool,
+) -> Result<String, DeepLinkError> {
+    let value = params.get(name).map(|v| v.trim()).unwrap_or_default();
+    if value.is_empty() {
+        return Err(DeepLinkError::MissingParam(name));
+    }
+    if !value.chars().all(allowed) {
+        return Err(DeepLinkError::InvalidParam(name, "包含非法字符".to_string()));
+    }
+    Ok(value.to_string())
+}
+
+/// 发送给前端的待确认链接
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingDeepLink {
+    pub id: String,
+    pub action: &'static str,
+    pub summary: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// 等待用户确认的链接
+#[derive(Debug, Default)]
+pub struct DeepLinkInbox {
+    pending: Mutex<Vec<(PendingDeepLink, DeepLinkAction)>>,
+}
+
+impl DeepLinkInbox {
+    /// 加入待确认队列，队列已满时丢弃最早的一条
+    pub fn push(&self, action: DeepLinkAction) -> PendingDeepLink {
+        let entry = PendingDeepLink {
+            id: uuid::Uuid::new_v4().to_string(),
+            action: match action {
+                DeepLinkAction::ImportSession { .. } => "import_session",
+                DeepLinkAction::OpenToken { .. } => "open_token",
+                DeepLinkAction::TriggerSync { .. } => "trigger_sync",
+                DeepLinkAction::AddBookmark { .. } => "add_bookmark",
+            },
+            summary: action.summary(),
+            received_at: Utc::now(),
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= MAX_PENDING {
+            let (dropped, _) = pending.remove(0);
+            log::warn!("待确认的 deep-link 过多，丢弃: {}", dropped.summary);
+        }
+        pending.push((entry.clone(), action));
+        entry
+    }
+
+    pub fn list(&self) -> Vec<PendingDeepLink> {
+        self.pending.lock().unwrap().iter().map(|(entry, _)| entry.clone()).collect()
+    }
+
+    /// 取出一条链接（确认或拒绝时调用）
+    pub fn take(&self, id: &str) -> Option<DeepLinkAction> {
+        let mut pending = self.pending.lock().unwrap();
+        let index = pending.iter().position(|(entry, _)| entry.id == id)?;
+        Some(pending.remove(index).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        assert_eq!(
+            parse("zaugment://import?session=abc.DEF-123").unwrap(),
+            DeepLinkAction::ImportSession { session: "abc.DEF-123".to_string() }
+        );
+        assert_eq!(
+            parse("zaugment://open-token?id=4f0c2b1e-9a6d").unwrap(),
+            DeepLinkAction::OpenToken { token_id: "4f0c2b1e-9a6d".to_string() }
+        );
+        assert_eq!(
+            parse("zaugment://sync").unwrap(),
+            DeepLinkAction::TriggerSync { direction: SyncDirection::Both }
+        );
+        assert_eq!(
+            parse("zaugment://sync?direction=download").unwrap(),
+            DeepLinkAction::TriggerSync { direction: SyncDirection::Download }
+        );
+        assert_eq!(
+            parse("zaugment://add-bookmark?name=%E6%96%87%E6%A1%A3&url=https%3A%2F%2Fdocs.augmentcode.com").unwrap(),
+            DeepLinkAction::AddBookmark {
+                name: "文档".to_string(),
+                url: "https://docs.augmentcode.com".to_string(),
+                description: None,
+                category: "temp".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_links() {
+        assert!(matches!(parse("https://import?session=a"), Err(DeepLinkError::InvalidScheme(_))));
+        assert!(matches!(parse("not a url"), Err(DeepLinkError::Malformed(_))));
+        assert!(matches!(parse("zaugment://delete-all"), Err(DeepLinkError::UnknownAction(_))));
+        assert_eq!(parse("zaugment://import"), Err(DeepLinkError::MissingParam("session")));
+        assert!(matches!(parse("zaugment://import?session=a&session=b"), Err(DeepLinkError::Malformed(_))));
+        assert!(matches!(parse("zaugment://import?session=%3Cscript%3E"), Err(DeepLinkError::InvalidParam("session", _))));
+        assert!(matches!(parse("zaugment://open-token?id=../x"), Err(DeepLinkError::InvalidParam("id", _))));
+        assert!(matches!(parse("zaugment://sync?direction=sideways"), Err(DeepLinkError::InvalidParam("direction", _))));
+        assert!(matches!(
+            parse("zaugment://add-bookmark?name=x&url=javascript:alert(1)"),
+            Err(DeepLinkError::InvalidParam("url", _))
+        ));
+
+        let long_session = "a".repeat(MAX_PARAM_LENGTH + 1);
+        assert!(matches!(
+            parse(&format!("zaugment://import?session={}", long_session)),
+            Err(DeepLinkError::TooLong(_))
+        ));
+        assert!(matches!(
+            parse(&format!("zaugment://import?session={}", "a".repeat(MAX_URL_LENGTH))),
+            Err(DeepLinkError::TooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_inbox_requires_explicit_take() {
+        let inbox = DeepLinkInbox::default();
+        let entry = inbox.push(DeepLinkAction::ImportSession { session: "secret-session-value".to_string() });
+        assert_eq!(entry.action, "import_session");
+        assert!(!entry.summary.contains("secret-session-value"));
+        assert_eq!(inbox.list().len(), 1);
+
+        assert!(inbox.take("unknown").is_none());
+        assert!(matches!(inbox.take(&entry.id), Some(DeepLinkAction::ImportSession { .. })));
+        assert!(inbox.take(&entry.id).is_none());
+
+        for _ in 0..MAX_PENDING + 2 {
+            inbox.push(DeepLinkAction::TriggerSync { direction: SyncDirection::Both });
+        }
+        assert_eq!(inbox.list().len(), MAX_PENDING);
+    }
+}
@@ -0,0 +1,155 @@
+//! 系统文件/目录选择对话框
+//!
+//! 基于 tauri-plugin-dialog，各平台使用原生对话框，不再依赖 PowerShell、osascript 或 zenity。
+//! 用户取消与打开失败分别返回 `DialogError::Cancelled` 和 `DialogError::Failed`，
+//! 前端根据 `kind` 字段区分。
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder, FilePath};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileFilter {
+    pub name: String,
+    pub extensions: Vec<String>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum DialogError {
+    /// 用户关闭了对话框
+    #[error("用户取消了选择")]
+    Cancelled,
+
+    /// 对话框无法打开，或选择结果无法转换为本地路径
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<String> for DialogError {
+    fn from(message: String) -> Self {
+        DialogError::Failed(message)
+    }
+}
+
+/// 对话框的通用选项
+#[derive(Debug, Clone, Default)]
+pub struct DialogOptions {
+    pub title: Option<String>,
+    pub filters: Vec<FileFilter>,
+    pub directory: Option<PathBuf>,
+    pub file_name: Option<String>,
+}
+
+fn builder(app: &tauri::AppHandle, options: &DialogOptions) -> FileDialogBuilder<tauri::Wry> {
+    let mut builder = app.dialog().file();
+    if let Some(title) = &options.title {
+        builder = builder.set_title(title);
+    }
+    for filter in &options.filters {
+        let extensions: Vec<&str> = filter
+            .extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches("*.").trim_start_matches('.'))
+            .collect();
+        builder = builder.add_filter(&filter.name, &extensions);
+    }
+    if let Some(directory) = &options.directory {
+        builder = builder.set_directory(directory);
+    }
+    if let Some(file_name) = &options.file_name {
+        builder = builder.set_file_name(file_name);
+    }
+    builder
+}
+
+fn to_path(file_path: FilePath) -> Result<PathBuf, DialogError> {
+    file_path
+        .into_path()
+        .map_err(|e| DialogError::Failed(format!("无法识别选择的路径: {}", e)))
+}
+
+/// 等待回调式对话框的结果，对话框未返回就被销毁时视为失败
+async fn wait_for<T>(receiver: oneshot::Receiver<Option<T>>) -> Result<T, DialogError> {
+    receiver
+        .await
+        .map_err(|_| DialogError::Failed("对话框意外关闭".to_string()))?
+        .ok_or(DialogError::Cancelled)
+}
+
+/// 选择目录
+pub async fn pick_folder(app: &tauri::AppHandle, options: DialogOptions) -> Result<PathBuf, DialogError> {
+    let (sender, receiver) = oneshot::channel();
+    builder(app, &options).pick_folder(move |folder| {
+        let _ = sender.send(folder);
+    });
+    to_path(wait_for(receiver).await?)
+}
+
+/// 选择一个或多个文件
+pub async fn pick_files(
+    app: &tauri::AppHandle,
+    options: DialogOptions,
+    multiple: bool,
+) -> Result<Vec<PathBuf>, DialogError> {
+    let (sender, receiver) = oneshot::channel();
+    let builder = builder(app, &options);
+    if multiple {
+        builder.pick_files(move |files| {
+            let _ = sender.send(files);
+        });
+    } else {
+        builder.pick_file(move |file| {
+            let _ = sender.send(file.map(|file| vec![file]));
+        });
+    }
+
+    let files = wait_for(receiver).await?;
+    if files.is_empty() {
+        return Err(DialogError::Cancelled);
+    }
+    files.into_iter().map(to_path).collect()
+}
+
+/// 选择保存位置
+pub async fn save_file(app: &tauri::AppHandle, options: DialogOptions) -> Result<PathBuf, DialogError> {
+    let (sender, receiver) = oneshot::channel();
+    builder(app, &options).save_file(move |file| {
+        let _ = sender.send(file);
+    });
+    to_path(wait_for(receiver).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialog_error_serialization() {
+        assert_eq!(
+            serde_json::to_value(DialogError::Cancelled).unwrap(),
+            serde_json::json!({"kind": "cancelled"})
+        );
+        assert_eq!(
+            serde_json::to_value(DialogError::Failed("boom".to_string())).unwrap(),
+            serde_json::json!({"kind": "failed", "message": "boom"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_distinguishes_cancel_and_failure() {
+        let (sender, receiver) = oneshot::channel::<Option<u8>>();
+        sender.send(None).unwrap();
+        assert_eq!(wait_for(receiver).await, Err(DialogError::Cancelled));
+
+        let (sender, receiver) = oneshot::channel::<Option<u8>>();
+        drop(sender);
+        assert!(matches!(wait_for(receiver).await, Err(DialogError::Failed(_))));
+
+        let (sender, receiver) = oneshot::channel();
+        sender.send(Some(1u8)).unwrap();
+        assert_eq!(wait_for(receiver).await, Ok(1));
+    }
+}
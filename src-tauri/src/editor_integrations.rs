@@ -0,0 +1,493 @@
+//! 编辑器集成
+//!
+//! JetBrains 系编辑器、Vim 插件和 Auggie CLI 都通过用户目录下的 JSON 文件读取登录信息，
+//! 但格式各不相同。每种编辑器实现 `IntegrationProvider`，统一提供检测、读取当前配置、
+//! 写入和还原。写入前会把原文件备份到 `~/.augment/zaugment_backups/<provider>/`，
+//! 原文件不存在时记录一个 `.absent` 标记，还原时删除写入的文件。
+
+use crate::storage::{write_file_atomic, TokenData};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const BACKUP_DIR: &str = "zaugment_backups";
+/// 每个编辑器最多保留的备份数
+pub const MAX_BACKUPS: usize = 10;
+
+const ABSENT_EXTENSION: &str = "absent";
+const BACKUP_EXTENSION: &str = "json";
+
+pub const JETBRAINS_EDITORS: [(&str, &str, &[&str]); 12] = [
+    ("idea", "IntelliJ IDEA", &["IntelliJIdea", "IdeaIC"]),
+    ("pycharm", "PyCharm", &["PyCharm"]),
+    ("goland", "GoLand", &["GoLand"]),
+    ("rustrover", "RustRover", &["RustRover"]),
+    ("webstorm", "WebStorm", &["WebStorm"]),
+    ("phpstorm", "PhpStorm", &["PhpStorm"]),
+    ("androidstudio", "Android Studio", &["AndroidStudio"]),
+    ("clion", "CLion", &["CLion"]),
+    ("datagrip", "DataGrip", &["DataGrip"]),
+    ("rider", "Rider", &["Rider"]),
+    ("rubymine", "RubyMine", &["RubyMine"]),
+    ("aqua", "Aqua", &["Aqua"]),
+];
+
+/// 写入编辑器的登录信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorCredentials {
+    pub access_token: String,
+    pub tenant_url: String,
+}
+
+pub trait IntegrationProvider: Send + Sync {
+    fn id(&self) -> &str;
+    fn name(&self) -> &str;
+    /// 编辑器读取的配置文件
+    fn config_path(&self, home: &Path) -> PathBuf;
+    /// 编辑器或插件是否已安装
+    fn detect(&self, home: &Path) -> bool;
+    /// 生成配置文件内容
+    fn render(&self, credentials: &EditorCredentials) -> Result<String, String>;
+    /// 从配置文件内容中读取登录信息
+    fn parse(&self, content: &str) -> Option<EditorCredentials>;
+}
+
+/// JetBrains 系编辑器：`~/.augment/<editor>_token.json`
+pub struct JetBrainsProvider {
+    editor_type: &'static str,
+    name: &'static str,
+    config_prefixes: &'static [&'static str],
+}
+
+impl IntegrationProvider for JetBrainsProvider {
+    fn id(&self) -> &str {
+        self.editor_type
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn config_path(&self, home: &Path) -> PathBuf {
+        home.join(".augment").join(format!("{}_token.json", self.editor_type))
+    }
+
+    fn detect(&self, home: &Path) -> bool {
+        // JetBrains 的配置目录形如 PyCharm2024.1，Android Studio 位于 Google 目录下
+        let roots = [
+            home.join(".config"),
+            home.join("Library").join("Application Support"),
+            home.join("AppData").join("Roaming"),
+        ];
+        roots
+            .iter()
+            .flat_map(|root| [root.join("JetBrains"), root.join("Google")])
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                self.config_prefixes.iter().any(|prefix| name.starts_with(prefix))
+            })
+            || self.config_path(home).exists()
+    }
+
+    fn render(&self, credentials: &EditorCredentials) -> Result<String, String> {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "url": credentials.tenant_url,
+            "token": credentials.access_token,
+            "timestamp": Utc::now().timestamp_millis(),
+            "ide": self.editor_type,
+        }))
+        .map_err(|e| format!("Failed to serialize token file: {}", e))
+    }
+
+    fn parse(&self, content: &str) -> Option<EditorCredentials> {
+        let value: serde_json::Value = serde_json::from_str(content).ok()?;
+        Some(EditorCredentials {
+            access_token: value.get("token")?.as_str()?.to_string(),
+            tenant_url: value.get("url")?.as_str()?.to_string(),
+        })
+    }
+}
+
+/// Vim 插件：`~/.local/share/vim-augment/secrets.json`，会话信息以 JSON 字符串嵌套保存
+pub struct VimAugmentProvider;
+
+impl IntegrationProvider for VimAugmentProvider {
+    fn id(&self) -> &str {
+        "vim"
+    }
+
+    fn name(&self) -> &str {
+        "Vim"
+    }
+
+    fn config_path(&self, home: &Path) -> PathBuf {
+        home.join(".local").join("share").join("vim-augment").join("secrets.json")
+    }
+
+    fn detect(&self, home: &Path) -> bool {
+        [
+            home.join(".local").join("share").join("vim-augment"),
+            home.join(".vim"),
+            home.join(".config").join("nvim"),
+            home.join("vimfiles"),
+            home.join("AppData").join("Local").join("nvim"),
+        ]
+        .iter()
+        .any(|dir| dir.exists())
+    }
+
+    fn render(&self, credentials: &EditorCredentials) -> Result<String, String> {
+        let inner = serde_json::to_string(&serde_json::json!({
+            "accessToken": credentials.access_token,
+            "tenantURL": credentials.tenant_url,
+            "scopes": ["email"]
+        }))
+        .map_err(|e| format!("Failed to serialize inner JSON: {}", e))?;
+
+        serde_json::to_string_pretty(&serde_json::json!({ "augment.sessions": inner }))
+            .map_err(|e| format!("Failed to serialize outer JSON: {}", e))
+    }
+
+    fn parse(&self, content: &str) -> Option<EditorCredentials> {
+        let outer: serde_json::Value = serde_json::from_str(content).ok()?;
+        parse_session(outer.get("augment.sessions")?.as_str()?)
+    }
+}
+
+/// Auggie CLI：`~/.augment/session.json`
+pub struct AuggieProvider;
+
+impl IntegrationProvider for AuggieProvider {
+    fn id(&self) -> &str {
+        "auggie"
+    }
+
+    fn name(&self) -> &str {
+        "Auggie"
+    }
+
+    fn config_path(&self, home: &Path) -> PathBuf {
+        home.join(".augment").join("session.json")
+    }
+
+    fn detect(&self, home: &Path) -> bool {
+        home.join(".augment").exists()
+    }
+
+    fn render(&self, credentials: &EditorCredentials) -> Result<String, String> {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "accessToken": credentials.access_token,
+            "tenantURL": credentials.tenant_url,
+            "scopes": ["read", "write"]
+        }))
+        .map_err(|e| format!("Failed to serialize session JSON: {}", e))
+    }
+
+    fn parse(&self, content: &str) -> Option<EditorCredentials> {
+        parse_session(content)
+    }
+}
+
+fn parse_session(content: &str) -> Option<EditorCredentials> {
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    Some(EditorCredentials {
+        access_token: value.get("accessToken")?.as_str()?.to_string(),
+        tenant_url: value.get("tenantURL")?.as_str()?.to_string(),
+    })
+}
+
+pub fn all_providers() -> Vec<Box<dyn IntegrationProvider>> {
+    let mut providers: Vec<Box<dyn IntegrationProvider>> = JETBRAINS_EDITORS
+        .iter()
+        .map(|(editor_type, name, config_prefixes)| {
+            Box::new(JetBrainsProvider { editor_type, name, config_prefixes }) as Box<dyn IntegrationProvider>
+        })
+        .collect();
+    providers.push(Box::new(VimAugmentProvider));
+    providers.push(Box::new(AuggieProvider));
+    providers
+}
+
+pub fn provider(id: &str) -> Result<Box<dyn IntegrationProvider>, String> {
+    all_providers()
+        .into_iter()
+        .find(|provider| provider.id() == id)
+        .ok_or_else(|| format!("不支持的编辑器: {}", id))
+}
+
+/// 当前用户主目录
+pub fn home_dir() -> Result<PathBuf, String> {
+    std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .map(PathBuf::from)
+        .map_err(|_| "Failed to get home directory".to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationBackup {
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    /// 写入前配置文件不存在
+    pub absent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationStatus {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub detected: bool,
+    pub configured: bool,
+    pub tenant_url: Option<String>,
+    /// 当前配置对应的已保存账号（按 access_token 匹配）
+    pub token_id: Option<String>,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub backups: Vec<IntegrationBackup>,
+}
+
+fn backup_dir(provider: &dyn IntegrationProvider, home: &Path) -> PathBuf {
+    home.join(".augment").join(BACKUP_DIR).join(provider.id())
+}
+
+/// 按时间从新到旧列出备份
+pub fn list_backups(provider: &dyn IntegrationProvider, home: &Path) -> Vec<IntegrationBackup> {
+    let mut backups: Vec<IntegrationBackup> = fs::read_dir(backup_dir(provider, home))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let extension = path.extension()?.to_str()?.to_string();
+                    if extension != BACKUP_EXTENSION && extension != ABSENT_EXTENSION {
+                        return None;
+                    }
+                    let stem = path.file_stem()?.to_str()?;
+                    let created_at = DateTime::parse_from_str(&format!("{} +0000", stem), "%Y%m%dT%H%M%S%.f %z").ok()?;
+                    Some(IntegrationBackup {
+                        path: path.to_string_lossy().to_string(),
+                        created_at: created_at.with_timezone(&Utc),
+                        absent: extension == ABSENT_EXTENSION,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    backups
+}
+
+/// 读取编辑器当前的配置
+pub fn read_current(provider: &dyn IntegrationProvider, home: &Path, tokens: &[TokenData]) -> IntegrationStatus {
+    let path = provider.config_path(home);
+    let credentials = fs::read_to_string(&path).ok().and_then(|content| provider.parse(&content));
+    let token_id = credentials.as_ref().and_then(|credentials| {
+        tokens
+            .iter()
+            .find(|token| token.access_token == credentials.access_token)
+            .map(|token| token.id.clone())
+    });
+
+    IntegrationStatus {
+        id: provider.id().to_string(),
+        name: provider.name().to_string(),
+        detected: provider.detect(home),
+        configured: credentials.is_some(),
+        tenant_url: credentials.map(|credentials| credentials.tenant_url),
+        token_id,
+        modified_at: fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(DateTime::<Utc>::from),
+        backups: list_backups(provider, home),
+        path: path.to_string_lossy().to_string(),
+    }
+}
+
+/// 写入新配置，返回配置文件路径和本次创建的备份
+pub fn apply(
+    provider: &dyn IntegrationProvider,
+    home: &Path,
+    credentials: &EditorCredentials,
+) -> Result<(PathBuf, IntegrationBackup), String> {
+    let content = provider.render(credentials)?;
+    let path = provider.config_path(home);
+
+    let backup = create_backup(provider, home, &path)?;
+    write_file_atomic(&path, content.as_bytes())?;
+    restrict_permissions(&path)?;
+    prune_backups(provider, home);
+
+    Ok((path, backup))
+}
+
+/// 用最近一次备份还原配置文件，备份使用后删除；返回使用的备份
+pub fn revert(provider: &dyn IntegrationProvider, home: &Path) -> Result<IntegrationBackup, String> {
+    let backup = list_backups(provider, home)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("{} 没有可还原的备份", provider.name()))?;
+    let path = provider.config_path(home);
+
+    if backup.absent {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除配置文件失败: {}", e))?;
+        }
+    } else {
+        let content = fs::read(&backup.path).map_err(|e| format!("读取备份失败: {}", e))?;
+        write_file_atomic(&path, &content)?;
+        restrict_permissions(&path)?;
+    }
+
+    fs::remove_file(&backup.path).map_err(|e| format!("删除已还原的备份失败: {}", e))?;
+    Ok(backup)
+}
+
+fn create_backup(provider: &dyn IntegrationProvider, home: &Path, path: &Path) -> Result<IntegrationBackup, String> {
+    let dir = backup_dir(provider, home);
+    fs::create_dir_all(&dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+
+    // 时间戳精确到毫秒，同一毫秒内多次写入时顺延
+    let mut created_at = Utc::now();
+    let absent = !path.exists();
+    let extension = if absent { ABSENT_EXTENSION } else { BACKUP_EXTENSION };
+    let mut backup_path;
+    loop {
+        backup_path = dir.join(format!("{}.{}", created_at.format("%Y%m%dT%H%M%S%.3f"), extension));
+        let taken = [BACKUP_EXTENSION, ABSENT_EXTENSION]
+            .iter()
+            .any(|ext| backup_path.with_extension(ext).exists());
+        if !taken {
+            break;
+        }
+        created_at += chrono::Duration::milliseconds(1);
+    }
+
+    if absent {
+        fs::write(&backup_path, b"").map_err(|e| format!("写入备份失败: {}", e))?;
+    } else {
+        fs::copy(path, &backup_path).map_err(|e| format!("备份配置文件失败: {}", e))?;
+        restrict_permissions(&backup_path)?;
+    }
+
+    Ok(IntegrationBackup {
+        path: backup_path.to_string_lossy().to_string(),
+        created_at,
+        absent,
+    })
+}
+
+fn prune_backups(provider: &dyn IntegrationProvider, home: &Path) {
+    for backup in list_backups(provider, home).into_iter().skip(MAX_BACKUPS) {
+        let _ = fs::remove_file(&backup.path);
+    }
+}
+
+/// 配置文件包含 access token，在 Unix 系统上仅所有者可读写
+fn restrict_permissions(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn credentials(token: &str) -> EditorCredentials {
+        EditorCredentials {
+            access_token: token.to_string(),
+            tenant_url: "https://d1.api.augmentcode.com/".to_string(),
+        }
+    }
+
+    fn token(id: &str, access_token: &str) -> TokenData {
+        TokenData::new(
+            id.to_string(),
+            "https://d1.api.augmentcode.com/".to_string(),
+            access_token.to_string(),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_providers_round_trip() {
+        for provider in all_providers() {
+            let content = provider.render(&credentials("tok-1")).unwrap();
+            assert_eq!(provider.parse(&content), Some(credentials("tok-1")), "{}", provider.id());
+        }
+    }
+
+    #[test]
+    fn test_apply_reports_token_and_reverts_to_absent() {
+        let home = tempdir().unwrap();
+        let provider = provider("auggie").unwrap();
+        let tokens = vec![token("id-1", "tok-1"), token("id-2", "tok-2")];
+
+        let status = read_current(provider.as_ref(), home.path(), &tokens);
+        assert!(!status.configured);
+        assert!(!status.detected);
+
+        let (path, backup) = apply(provider.as_ref(), home.path(), &credentials("tok-2")).unwrap();
+        assert!(backup.absent);
+        assert!(path.ends_with(".augment/session.json"));
+
+        let status = read_current(provider.as_ref(), home.path(), &tokens);
+        assert!(status.detected);
+        assert_eq!(status.token_id.as_deref(), Some("id-2"));
+        assert_eq!(status.backups.len(), 1);
+
+        revert(provider.as_ref(), home.path()).unwrap();
+        assert!(!path.exists());
+        assert!(revert(provider.as_ref(), home.path()).is_err());
+    }
+
+    #[test]
+    fn test_apply_backs_up_previous_file() {
+        let home = tempdir().unwrap();
+        let provider = provider("vim").unwrap();
+
+        apply(provider.as_ref(), home.path(), &credentials("tok-1")).unwrap();
+        let (path, backup) = apply(provider.as_ref(), home.path(), &credentials("tok-2")).unwrap();
+        assert!(!backup.absent);
+        assert_eq!(provider.parse(&fs::read_to_string(&backup.path).unwrap()), Some(credentials("tok-1")));
+
+        let backups = list_backups(provider.as_ref(), home.path());
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].path, backup.path);
+
+        // 还原到第一次写入的内容，再还原则删除文件
+        revert(provider.as_ref(), home.path()).unwrap();
+        assert_eq!(provider.parse(&fs::read_to_string(&path).unwrap()), Some(credentials("tok-1")));
+        revert(provider.as_ref(), home.path()).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_jetbrains_detection_and_backup_limit() {
+        let home = tempdir().unwrap();
+        let pycharm = provider("pycharm").unwrap();
+        assert!(!pycharm.detect(home.path()));
+        fs::create_dir_all(home.path().join(".config/JetBrains/PyCharm2024.1")).unwrap();
+        assert!(pycharm.detect(home.path()));
+        assert!(!provider("goland").unwrap().detect(home.path()));
+        assert!(provider("../x").is_err());
+
+        for i in 0..MAX_BACKUPS + 3 {
+            apply(pycharm.as_ref(), home.path(), &credentials(&format!("tok-{}", i))).unwrap();
+        }
+        assert_eq!(list_backups(pycharm.as_ref(), home.path()).len(), MAX_BACKUPS);
+        assert!(home.path().join(".augment/pycharm_token.json").exists());
+    }
+}
@@ -0,0 +1,310 @@
+//! 应用共享的 HTTP 客户端
+//!
+//! 所有网络请求（Augment API、Orb 门户、更新检查、WebDAV 等）都使用同一个按配置构建的
+//! `reqwest::Client`，统一代理、自定义 CA 证书、超时和连接池设置。
+//! 配置保存在 `UnifiedAppConfig.http_client` 中，代理密码保存在系统密钥链中。
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 统一的浏览器 User-Agent，替代各处手写的请求头
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    /// 使用系统环境变量中的代理（HTTP_PROXY / HTTPS_PROXY / ALL_PROXY）
+    #[default]
+    System,
+    /// 不使用任何代理
+    None,
+    /// HTTP / HTTPS 代理
+    Http,
+    /// SOCKS5 代理
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub mode: ProxyMode,
+    /// 代理地址，例如 `http://proxy.corp:8080` 或 `socks5://127.0.0.1:1080`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// 代理认证用户名，密码保存在系统密钥链中
+    #[serde(default)]
+    pub username: Option<String>,
+    /// 不走代理的主机列表，逗号分隔
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpClientSettings {
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// 额外信任的 CA 证书（PEM 文件路径，可包含多个证书）
+    #[serde(default)]
+    pub ca_certificates: Vec<String>,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// 自定义 User-Agent，为空时使用 DEFAULT_USER_AGENT
+    #[serde(default)]
+    pub user_agent: Option<String>,
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    8
+}
+
+impl Default for HttpClientSettings {
+    fn default() -> Self {
+        Self {
+            proxy: ProxySettings::default(),
+            ca_certificates: Vec::new(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            request_timeout_secs: default_request_timeout_secs(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            user_agent: None,
+        }
+    }
+}
+
+impl HttpClientSettings {
+    /// 检查配置是否完整，返回规范化后的代理地址
+    fn proxy_url(&self) -> Result<Option<String>, String> {
+        let (default_scheme, allowed_schemes): (&str, &[&str]) = match self.proxy.mode {
+            ProxyMode::System | ProxyMode::None => return Ok(None),
+            ProxyMode::Http => ("http", &["http", "https"]),
+            ProxyMode::Socks5 => ("socks5", &["socks5", "socks5h"]),
+        };
+
+        let raw = self.proxy.url.as_deref().map(str::trim).unwrap_or("");
+        if raw.is_empty() {
+            return Err("代理地址不能为空".to_string());
+        }
+
+        let url = if raw.contains("://") {
+            raw.to_string()
+        } else {
+            format!("{}://{}", default_scheme, raw)
+        };
+
+        let parsed = url::Url::parse(&url)
+            .map_err(|e| format!("代理地址无效: {}", e))?;
+        if !allowed_schemes.contains(&parsed.scheme()) {
+            return Err(format!("代理地址协议不支持: {}", parsed.scheme()));
+        }
+        if parsed.host_str().is_none() {
+            return Err("代理地址缺少主机名".to_string());
+        }
+
+        Ok(Some(url))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.connect_timeout_secs == 0 || self.request_timeout_secs == 0 {
+            return Err("超时时间必须大于 0 秒".to_string());
+        }
+        self.proxy_url()?;
+        for path in &self.ca_certificates {
+            load_certificates(Path::new(path))?;
+        }
+        Ok(())
+    }
+
+    pub fn user_agent(&self) -> &str {
+        self.user_agent
+            .as_deref()
+            .filter(|ua| !ua.trim().is_empty())
+            .unwrap_or(DEFAULT_USER_AGENT)
+    }
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>, String> {
+    let content = std::fs::read(path)
+        .map_err(|e| format!("读取 CA 证书失败 {}: {}", path.display(), e))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&content)
+        .map_err(|e| format!("解析 CA 证书失败 {}: {}", path.display(), e))?;
+    if certificates.is_empty() {
+        return Err(format!("CA 证书文件中没有证书: {}", path.display()));
+    }
+    Ok(certificates)
+}
+
+/// 按配置构建的共享客户端
+///
+/// `reqwest::Client` 内部是引用计数的，克隆 `HttpClient` 会共享同一个连接池。
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    settings: Arc<HttpClientSettings>,
+    proxy_password: Option<Arc<String>>,
+}
+
+impl std::fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpClient")
+            .field("settings", &self.settings)
+            .finish()
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(HttpClientSettings::default(), None)
+            .expect("default HTTP client settings must be valid")
+    }
+}
+
+impl HttpClient {
+    pub fn new(settings: HttpClientSettings, proxy_password: Option<String>) -> Result<Self, String> {
+        let mut client = Self {
+            client: reqwest::Client::new(),
+            settings: Arc::new(settings),
+            proxy_password: proxy_password.map(Arc::new),
+        };
+        client.client = client.builder()?
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(client)
+    }
+
+    /// 共享的客户端
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn settings(&self) -> &HttpClientSettings {
+        &self.settings
+    }
+
+    /// 返回已应用代理、证书和超时设置的 builder
+    ///
+    /// 用于需要独立 cookie 存储等无法共享客户端的场景。
+    pub fn builder(&self) -> Result<reqwest::ClientBuilder, String> {
+        let settings = &self.settings;
+        settings.validate()?;
+
+        let mut builder = reqwest::Client::builder()
+            .user_agent(settings.user_agent())
+            .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+            .timeout(Duration::from_secs(settings.request_timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(settings.pool_idle_timeout_secs))
+            .pool_max_idle_per_host(settings.pool_max_idle_per_host);
+
+        if settings.proxy.mode == ProxyMode::None {
+            builder = builder.no_proxy();
+        }
+
+        if let Some(proxy_url) = settings.proxy_url()? {
+            let mut proxy = reqwest::Proxy::all(&proxy_url)
+                .map_err(|e| format!("代理地址无效: {}", e))?;
+            if let Some(username) = settings.proxy.username.as_deref().filter(|u| !u.is_empty()) {
+                let password = self.proxy_password.as_deref().map(String::as_str).unwrap_or("");
+                proxy = proxy.basic_auth(username, password);
+            }
+            if let Some(no_proxy) = settings.proxy.no_proxy.as_deref() {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+            }
+            builder = builder.proxy(proxy);
+        }
+
+        for path in &settings.ca_certificates {
+            for certificate in load_certificates(Path::new(path))? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_with_proxy(mode: ProxyMode, url: Option<&str>) -> HttpClientSettings {
+        HttpClientSettings {
+            proxy: ProxySettings {
+                mode,
+                url: url.map(str::to_string),
+                ..ProxySettings::default()
+            },
+            ..HttpClientSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_default_settings_build() {
+        let client = HttpClient::default();
+        assert_eq!(client.settings().user_agent(), DEFAULT_USER_AGENT);
+        assert_eq!(client.settings().request_timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_proxy_url_is_normalized() {
+        let settings = settings_with_proxy(ProxyMode::Http, Some("proxy.corp:8080"));
+        assert_eq!(settings.proxy_url().unwrap().as_deref(), Some("http://proxy.corp:8080"));
+
+        let settings = settings_with_proxy(ProxyMode::Socks5, Some("127.0.0.1:1080"));
+        assert_eq!(settings.proxy_url().unwrap().as_deref(), Some("socks5://127.0.0.1:1080"));
+        assert!(HttpClient::new(settings, None).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_proxy_settings_rejected() {
+        assert!(settings_with_proxy(ProxyMode::Http, None).validate().is_err());
+        assert!(settings_with_proxy(ProxyMode::Http, Some("socks5://127.0.0.1:1080")).validate().is_err());
+        assert!(settings_with_proxy(ProxyMode::Socks5, Some("http://proxy:8080")).validate().is_err());
+        assert!(settings_with_proxy(ProxyMode::None, Some("ignored")).validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_timeouts_and_certificates_rejected() {
+        let settings = HttpClientSettings {
+            request_timeout_secs: 0,
+            ..HttpClientSettings::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cert_path = temp_dir.path().join("ca.pem");
+        std::fs::write(&cert_path, "not a certificate").unwrap();
+        let settings = HttpClientSettings {
+            ca_certificates: vec![cert_path.to_string_lossy().to_string()],
+            ..HttpClientSettings::default()
+        };
+        assert!(settings.validate().is_err());
+        assert!(HttpClient::new(settings, None).is_err());
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let settings: HttpClientSettings = serde_json::from_str(r#"{"proxy": {"mode": "none"}}"#).unwrap();
+        assert_eq!(settings.proxy.mode, ProxyMode::None);
+        assert_eq!(settings.connect_timeout_secs, 10);
+        assert_eq!(settings.pool_max_idle_per_host, 8);
+    }
+}
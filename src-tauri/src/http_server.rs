@@ -95,7 +95,7 @@ impl HttpServer {
                 shutdown_rx.await.ok();
             });
 
-        println!("OAuth callback server started on http://{}", addr);
+        log::info!("OAuth callback server started on http://{}", addr);
 
         // Start the server in a background task
         tokio::spawn(server);
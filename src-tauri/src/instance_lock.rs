@@ -0,0 +1,229 @@
+//! 数据目录锁
+//!
+//! 桌面应用和命令行工具共用同一个数据目录。应用在使用数据目录期间持有
+//! `<数据目录>/.zaugment.lock` 上的系统文件锁，命令行工具在写入数据前也要获取该锁，
+//! 避免两边同时写入互相覆盖。文件锁由操作系统在进程退出时自动释放，不会残留。
+//!
+//! 另有一把短时的写入锁 `<数据目录>/.zaugment.write.lock`：每次"读取-修改-写入"期间持有，
+//! 数据目录被多个应用实例共用（例如放在网盘同步目录中）时保证各实例的写入串行进行。
+//!
+//! 数据目录锁只用来挡住命令行工具：应用获取失败时（例如另一个应用实例已经持有）
+//! 只记录警告并照常启动，不拒绝也不切换为只读。因此多个应用实例之间的数据安全
+//! 只依赖每次写入时的写入锁，加上 `data_watcher` 对外部修改的冲突检测。
+
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub const LOCK_FILE: &str = ".zaugment.lock";
+pub const WRITE_LOCK_FILE: &str = ".zaugment.write.lock";
+
+/// 等待写入锁的最长时间
+const WRITE_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_LOCK_RETRY: Duration = Duration::from_millis(20);
+
+/// 持有锁的进程信息，写在锁文件中便于提示
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    /// "app" 或 "cli"
+    pub kind: String,
+    pub acquired_at: DateTime<Utc>,
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("数据目录正被其他进程使用{}", describe_owner(.0))]
+    Held(Option<LockOwner>),
+
+    #[error("无法创建数据目录锁: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+fn describe_owner(owner: &Option<LockOwner>) -> String {
+    match owner {
+        Some(owner) => format!(
+            "（{}，pid {}，{} 起）",
+            if owner.kind == "app" { "桌面应用" } else { "命令行工具" },
+            owner.pid,
+            owner.acquired_at.format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => String::new(),
+    }
+}
+
+/// 已获取的数据目录锁，释放（drop）时解锁
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// 尝试获取锁，已被其他进程持有时立即返回 `LockError::Held`
+    pub fn acquire(data_dir: &Path, kind: &str) -> Result<Self, LockError> {
+        fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(LOCK_FILE);
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+        if file.try_lock_exclusive().is_err() {
+            return Err(LockError::Held(read_owner(data_dir)));
+        }
+
+        let owner = LockOwner {
+            pid: std::process::id(),
+            kind: kind.to_string(),
+            acquired_at: Utc::now(),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(serde_json::to_string(&owner).unwrap_or_default().as_bytes())?;
+        file.sync_all()?;
+
+        Ok(Self { file, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// 短时写入锁，释放（drop）时解锁
+///
+/// 同一进程内不可重入：持有期间再次获取会等待到超时。
+#[derive(Debug)]
+pub struct WriteLock {
+    file: File,
+}
+
+impl WriteLock {
+    /// 获取写入锁，其他进程持有时等待，超过 10 秒返回 `LockError::Held`
+    pub fn acquire(data_dir: &Path) -> Result<Self, LockError> {
+        Self::acquire_with_timeout(data_dir, WRITE_LOCK_TIMEOUT)
+    }
+
+    /// 同 `acquire`，等待期间让出线程，供异步代码使用（不阻塞 tokio 工作线程）
+    pub async fn acquire_async(data_dir: &Path) -> Result<Self, LockError> {
+        Self::acquire_async_with_timeout(data_dir, WRITE_LOCK_TIMEOUT).await
+    }
+
+    fn acquire_with_timeout(data_dir: &Path, timeout: Duration) -> Result<Self, LockError> {
+        let file = Self::open(data_dir)?;
+        let started = Instant::now();
+        while file.try_lock_exclusive().is_err() {
+            if started.elapsed() >= timeout {
+                return Err(LockError::Held(None));
+            }
+            std::thread::sleep(WRITE_LOCK_RETRY);
+        }
+        Ok(Self { file })
+    }
+
+    async fn acquire_async_with_timeout(data_dir: &Path, timeout: Duration) -> Result<Self, LockError> {
+        let file = Self::open(data_dir)?;
+        let started = Instant::now();
+        while file.try_lock_exclusive().is_err() {
+            if started.elapsed() >= timeout {
+                return Err(LockError::Held(None));
+            }
+            tokio::time::sleep(WRITE_LOCK_RETRY).await;
+        }
+        Ok(Self { file })
+    }
+
+    fn open(data_dir: &Path) -> Result<File, LockError> {
+        fs::create_dir_all(data_dir)?;
+        Ok(OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(data_dir.join(WRITE_LOCK_FILE))?)
+    }
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// 读取当前持有锁的进程（锁空闲或信息不可读时为 None）
+pub fn read_owner(data_dir: &Path) -> Option<LockOwner> {
+    let mut content = String::new();
+    File::open(data_dir.join(LOCK_FILE)).ok()?.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let dir = tempdir().unwrap();
+        let lock = InstanceLock::acquire(dir.path(), "app").unwrap();
+        assert!(lock.path().ends_with(LOCK_FILE));
+
+        let owner = read_owner(dir.path()).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+        assert_eq!(owner.kind, "app");
+
+        match InstanceLock::acquire(dir.path(), "cli") {
+            Err(LockError::Held(Some(owner))) => assert_eq!(owner.kind, "app"),
+            other => panic!("expected held lock, got {:?}", other),
+        }
+
+        drop(lock);
+        assert!(read_owner(dir.path()).is_none());
+        let lock = InstanceLock::acquire(dir.path(), "cli").unwrap();
+        assert_eq!(read_owner(dir.path()).unwrap().kind, "cli");
+        drop(lock);
+    }
+
+    #[test]
+    fn test_write_lock_waits_for_holder() {
+        let dir = tempdir().unwrap();
+        let lock = WriteLock::acquire(dir.path()).unwrap();
+        assert!(matches!(
+            WriteLock::acquire_with_timeout(dir.path(), Duration::from_millis(50)),
+            Err(LockError::Held(None))
+        ));
+
+        let path = dir.path().to_path_buf();
+        let waiter = std::thread::spawn(move || WriteLock::acquire(&path).is_ok());
+        std::thread::sleep(Duration::from_millis(100));
+        drop(lock);
+        assert!(waiter.join().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_async_write_lock_does_not_block_runtime() {
+        let dir = tempdir().unwrap();
+        let lock = WriteLock::acquire(dir.path()).unwrap();
+        assert!(matches!(
+            WriteLock::acquire_async_with_timeout(dir.path(), Duration::from_millis(50)).await,
+            Err(LockError::Held(None))
+        ));
+
+        // 单线程运行时：等待锁的任务必须让出线程，释放锁的任务才能执行
+        let path = dir.path().to_path_buf();
+        let waiter = tokio::spawn(async move { WriteLock::acquire_async(&path).await.is_ok() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(lock);
+        assert!(waiter.await.unwrap());
+    }
+}
@@ -2,28 +2,60 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod api_server;
+mod augment_api;
 mod augment_oauth;
 mod augment_user_info;
+mod backups;
 mod bookmarks;
+mod browser_profiles;
+mod browsers;
+mod data_relocation;
+mod data_watcher;
+mod deep_link;
+mod dialogs;
+mod editor_integrations;
+mod http_client;
 mod http_server;
+mod instance_lock;
+mod logging;
+mod migrations;
 mod outlook_manager;
+mod profiles;
+mod status_scheduler;
 mod storage;
 mod thresholds;
+mod token_export;
+mod token_import;
+mod updater;
 mod webdav;
 
-use augment_oauth::{create_augment_oauth_state, generate_augment_authorize_url, complete_augment_oauth_flow, check_account_ban_status, extract_token_from_session, batch_check_account_status, get_credit_info, get_models, get_batch_credit_consumption_with_app_session, AugmentOAuthState, AugmentTokenResponse, TokenInfo, TokenStatusResult, BatchCreditConsumptionResponse};
-use augment_user_info::exchange_auth_session_for_app_session;
+use augment_oauth::{create_augment_oauth_state, generate_augment_authorize_url, complete_augment_oauth_flow, check_single_token, extract_token_from_session, batch_check_account_status_with, get_credit_info, get_models, get_batch_credit_consumption_with_app_session, AccountStatusCode, AugmentOAuthState, AugmentTokenResponse, BatchCheckOptions, BatchCheckOutcome, CancelHandle, CheckError, CheckLimiter, StatusReason, TokenInfo, TokenStatusResult, BatchCreditConsumptionResponse};
+use augment_api::AugmentApi;
+use augment_user_info::{exchange_auth_session_for_app_session, AppSessionCacheSettings, AppSessionInfo, AppSessionStore, SharedAppSessions};
+use backups::{BackupInfo, BackupInspection, BackupReason, BackupSettings, IntegrityIssue};
 use bookmarks::{BookmarkManager, Bookmark};
+use data_watcher::{DataWatcher, ExternalChange};
+use browser_profiles::BrowserWindowTracker;
+use browsers::{BrowserChoice, BrowserSettings, CustomBrowser, DetectedBrowser, DetectionEnv};
+use deep_link::{DeepLinkAction, DeepLinkInbox, PendingDeepLink};
+use dialogs::{DialogError, DialogOptions, FileFilter};
+use editor_integrations::{EditorCredentials, IntegrationBackup, IntegrationStatus};
+use http_client::{HttpClient, HttpClientSettings};
 use http_server::HttpServer;
+use instance_lock::InstanceLock;
 use outlook_manager::{OutlookManager, OutlookCredentials, EmailListResponse, EmailDetailsResponse, AccountStatus as OutlookAccountStatus};
-use storage::{LocalFileStorage, TokenStorage};
+use storage::journal::{JournalEntry, TrashEntry};
+use status_scheduler::{CheckSummary, StatusCheckSchedule};
+use storage::{convert_to_legacy_format, ChangeSource, HistorySettings, LocalFileStorage, TokenData, TokenStorage};
 use thresholds::StatusThresholds;
-use webdav::{WebDAVConfig, CloudSync, SecureWebDAVConfig, PasswordManager};
+use token_export::{ExportRequest, ExportSummary};
+use token_import::{ImportReport, ImportRequest};
+use updater::{GitHubRelease, UpdateChannel, UpdateSettings};
+use webdav::{WebDAVConfig, CloudSync, SecureWebDAVConfig, PasswordManager, StoredCredential};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use std::time::SystemTime;
 use tauri::{State, Manager, WebviewWindowBuilder, WebviewUrl, Emitter, Listener};
 use tauri_plugin_deep_link::DeepLinkExt;
 use chrono;
@@ -49,16 +81,20 @@ pub struct UserDataPackage {
     pub unified_config: Option<UnifiedAppConfig>,    // 统一配置（包含WebDAV配置和阈值配置）
     pub bookmarks: Option<serde_json::Value>,        // 书签数据
     // 注意：status_thresholds 已经在 unified_config 中，不再单独存储
+    // 旧版本数据包中的明文WebDAV密码，只在恢复时写入本机密钥链
+    #[serde(skip)]
+    pub pending_secrets: migrations::PendingSecrets,
 }
 
 impl Default for UserDataPackage {
     fn default() -> Self {
         Self {
-            version: "1.0.0".to_string(),
+            version: migrations::SYNC_PACKAGE_VERSION.to_string(),
             timestamp: chrono::Utc::now(),
             tokens: None,
             unified_config: None,
             bookmarks: None,
+            pending_secrets: migrations::PendingSecrets::default(),
         }
     }
 }
@@ -73,25 +109,30 @@ impl UserDataPackage {
         
         // 1. 收集 tokens.json
         let tokens_path = data_dir.join("tokens.json");
-        if tokens_path.exists() {
-            let tokens_content = fs::read_to_string(&tokens_path)
-                .map_err(|e| format!("读取tokens.json失败: {}", e))?;
-            package.tokens = serde_json::from_str(&tokens_content)
-                .map_err(|e| format!("解析tokens.json失败: {}", e))?;
-        }
+        package.tokens = migrations::migrate_file(migrations::DataKind::Tokens, &tokens_path, &migrations::MigrationContext::default())
+            .map_err(|e| format!("读取tokens.json失败: {}", e))?;
         
         // 2. 收集统一配置（从用户指定目录的配置文件）
-        let unified_config = load_unified_config_with_state(app, state);
+        let mut unified_config = load_unified_config_with_state(app, state);
+
+        // WebDAV密码默认只以本机密钥链引用的形式上传；
+        // 用户启用加密凭据同步后才携带使用同步口令加密的密码
+        if unified_config.encrypted_credential_sync {
+            if let Some(ref mut webdav_config) = unified_config.webdav_config {
+                let password = webdav_config.to_config(&state.password_manager)?.password;
+                let passphrase = state.password_manager.get_sync_passphrase()
+                    .map_err(|e| format!("已启用加密凭据同步，但无法读取同步口令: {}", e))?;
+                webdav_config.credential = Some(
+                    state.password_manager.encrypt_with_passphrase(&password, &passphrase)?
+                );
+            }
+        }
         package.unified_config = Some(unified_config);
         
         // 4. 收集书签数据
         let bookmarks_path = data_dir.join("bookmarks.json");
-        if bookmarks_path.exists() {
-            let bookmarks_content = fs::read_to_string(&bookmarks_path)
-                .map_err(|e| format!("读取bookmarks.json失败: {}", e))?;
-            package.bookmarks = serde_json::from_str(&bookmarks_content)
-                .map_err(|e| format!("解析bookmarks.json失败: {}", e))?;
-        }
+        package.bookmarks = migrations::migrate_file(migrations::DataKind::Bookmarks, &bookmarks_path, &migrations::MigrationContext::default())
+            .map_err(|e| format!("读取bookmarks.json失败: {}", e))?;
 
         // 注意：不再单独收集 status_thresholds，因为它已经在 unified_config 中了
         // 移除重复的 status_thresholds 字段，避免数据冗余
@@ -110,32 +151,75 @@ impl UserDataPackage {
         fs::create_dir_all(&data_dir)
             .map_err(|e| format!("创建数据目录失败: {}", e))?;
         
-        // 1. 恢复 tokens.json
+        // 1. 恢复 tokens.json（from_bytes 已将内容迁移到当前版本）
         if let Some(ref tokens) = self.tokens {
-            let tokens_path = data_dir.join("tokens.json");
-            let tokens_content = serde_json::to_string_pretty(tokens)
-                .map_err(|e| format!("序列化tokens失败: {}", e))?;
-            fs::write(&tokens_path, tokens_content)
+            let tokens: Vec<TokenData> = migrations::tokens_from_document(tokens.clone())
+                .iter()
+                .filter_map(|item| match storage::convert_legacy_token(item) {
+                    Ok(token) => Some(token),
+                    Err(e) => {
+                        log::warn!("跳过无法解析的云端 token: {}", e);
+                        None
+                    }
+                })
+                .collect();
+            state.token_storage()?
+                .for_source(ChangeSource::Sync)
+                .replace_all_tokens(&tokens)
+                .await
                 .map_err(|e| format!("写入tokens.json失败: {}", e))?;
         }
         
         // 2. 恢复统一配置
-        if let Some(ref unified_config) = self.unified_config {
-            save_unified_config_with_state(app, unified_config, state)
+        if let Some(ref remote_config) = self.unified_config {
+            let local_config = load_unified_config_with_state(app, state);
+            let mut unified_config = remote_config.clone();
+
+            // 同步口令只保存在本机，是否启用加密凭据同步以本机设置为准
+            unified_config.encrypted_credential_sync = local_config.encrypted_credential_sync;
+            // 代理等网络设置与本机网络环境相关，不随云端数据覆盖
+            unified_config.http_client = local_config.http_client.clone();
+
+            // 加密的WebDAV密码解密后写入本机密钥链，配置中只保留引用
+            if let Some(mut webdav_config) = unified_config.webdav_config.take() {
+                unified_config.webdav_config = match webdav_config.credential.clone() {
+                    Some(credential @ StoredCredential::Encrypted { .. }) => {
+                        let decrypted = state.password_manager.get_sync_passphrase()
+                            .and_then(|passphrase| state.password_manager.decrypt_with_passphrase(&credential, &passphrase));
+                        match decrypted {
+                            Ok(password) => {
+                                state.password_manager.store_password(&webdav_config.keyring_account(), &password)?;
+                                webdav_config.credential = Some(webdav_config.keyring_credential());
+                                Some(webdav_config)
+                            }
+                            Err(e) => {
+                                log::warn!("无法解密云端WebDAV凭据，保留本机WebDAV配置: {}", e);
+                                local_config.webdav_config.clone()
+                            }
+                        }
+                    }
+                    // 只接受 WebDAV 服务下的引用，账户名按本机规则重新生成
+                    Some(StoredCredential::Keyring { service, .. }) if service != webdav::secure_config::WEBDAV_KEYRING_SERVICE => {
+                        log::warn!("云端WebDAV凭据引用了不受信任的密钥链服务 {}，保留本机WebDAV配置", service);
+                        local_config.webdav_config.clone()
+                    }
+                    _ => {
+                        if let Some(password) = self.pending_secrets.take(&webdav_config.keyring_account()) {
+                            state.password_manager.store_password(&webdav_config.keyring_account(), &password)?;
+                        }
+                        webdav_config.credential = Some(webdav_config.keyring_credential());
+                        Some(webdav_config)
+                    }
+                };
+            }
+
+            save_unified_config_with_state(app, &unified_config, state)
                 .map_err(|e| format!("保存统一配置失败: {}", e))?;
             
-            // 同时更新内存中的状态（将WebDAVConfig转换为SecureWebDAVConfig用于内存存储）
+            // 同时更新内存中的状态
             if let Some(ref webdav_config) = unified_config.webdav_config {
-                // 先清除旧密码（如果有）
-                let _ = state.password_manager.delete_password(&webdav_config.username);
-                // 存储新密码到keyring
-                let _ = state.password_manager.store_password(&webdav_config.username, &webdav_config.password);
-                
-                // 创建SecureWebDAVConfig用于内存
-                if let Ok(secure_config) = SecureWebDAVConfig::from_config(webdav_config, &state.password_manager) {
-                    let mut config_guard = state.webdav_config.lock().unwrap();
-                    *config_guard = Some(secure_config);
-                }
+                let mut config_guard = state.webdav_config.lock().unwrap();
+                *config_guard = Some(webdav_config.clone());
             }
             
             if let Some(ref custom_dir) = unified_config.custom_data_dir {
@@ -147,9 +231,13 @@ impl UserDataPackage {
         // 4. 恢复书签数据
         if let Some(ref bookmarks) = self.bookmarks {
             let bookmarks_path = data_dir.join("bookmarks.json");
+            migrations::ensure_writable(migrations::DataKind::Bookmarks, &bookmarks_path)
+                .map_err(|e| e.to_string())?;
             let bookmarks_content = serde_json::to_string_pretty(bookmarks)
                 .map_err(|e| format!("序列化bookmarks失败: {}", e))?;
-            fs::write(&bookmarks_path, bookmarks_content)
+            backups::before_write(&bookmarks_path);
+            data_watcher::record_write(&bookmarks_path, bookmarks_content.as_bytes());
+            storage::write_file_atomic(&bookmarks_path, bookmarks_content.as_bytes())
                 .map_err(|e| format!("写入bookmarks.json失败: {}", e))?;
         }
 
@@ -165,21 +253,25 @@ impl UserDataPackage {
             .map_err(|e| format!("序列化用户数据包失败: {}", e))
     }
     
-    /// 从JSON字节数组解析
+    /// 从JSON字节数组解析，并将数据包及其中的各份数据迁移到当前版本
     pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
-        serde_json::from_slice(data)
-            .map_err(|e| format!("解析用户数据包失败: {}", e))
+        let value: serde_json::Value = serde_json::from_slice(data)
+            .map_err(|e| format!("解析用户数据包失败: {}", e))?;
+
+        // 旧版本数据包中的明文WebDAV密码先暂存，恢复时才写入本机密钥链
+        let pending_secrets = migrations::PendingSecrets::default();
+        let ctx = migrations::MigrationContext::with_secrets(&pending_secrets);
+        let value = migrations::migrate_sync_package(value, &ctx)
+            .map_err(|e| e.to_string())?;
+
+        let mut package: Self = serde_json::from_value(value)
+            .map_err(|e| format!("解析用户数据包失败: {}", e))?;
+        package.pending_secrets = pending_secrets;
+        Ok(package)
     }
 }
 use std::env;
 
-// App Session 缓存结构 (公开以便其他模块使用)
-#[derive(Clone)]
-pub struct AppSessionCache {
-    pub app_session: String,
-    pub created_at: SystemTime,
-}
-
 // Global state to store OAuth state and storage managers
 pub struct AppState {
     augment_oauth_state: Mutex<Option<AugmentOAuthState>>,
@@ -191,11 +283,87 @@ pub struct AppState {
     webdav_config: Arc<Mutex<Option<SecureWebDAVConfig>>>,
     cloud_sync: Arc<Mutex<Option<CloudSync>>>,
     password_manager: Arc<PasswordManager>,
-    // App session 缓存: key 为 auth_session, value 为缓存的 app_session
-    app_session_cache: Arc<Mutex<HashMap<String, AppSessionCache>>>,
+    // App session 缓存: 按 auth_session 查找缓存的 app_session，带有效期和数量上限
+    app_session_cache: SharedAppSessions,
+    // 配置档案切换锁，避免并发切换时状态交错
+    profile_switch_lock: Arc<tokio::sync::Mutex<()>>,
+    // 共享 HTTP 客户端，网络设置变更时整体替换
+    http_client: Arc<Mutex<HttpClient>>,
+    // 打开中的内置浏览器窗口
+    browser_windows: Arc<BrowserWindowTracker>,
+    // 等待用户确认的 deep-link
+    deep_links: Arc<DeepLinkInbox>,
+    // 当前数据目录上的锁，防止命令行工具同时写入
+    instance_lock: Arc<Mutex<Option<InstanceLock>>>,
+    // 数据目录外部修改检测
+    data_watcher: Arc<Mutex<Option<DataWatcher>>>,
+    // 进行中的批量状态检测（批次 id -> 取消句柄）
+    batch_checks: Arc<Mutex<HashMap<String, CancelHandle>>>,
+    // 手动与后台批量检测共享的并发上限，批量检测设置变更时整体替换
+    check_limiter: Arc<Mutex<CheckLimiter>>,
     pub app_handle: tauri::AppHandle,
 }
 
+impl AppState {
+    /// 当前的共享 HTTP 客户端（克隆开销很小，共享连接池）
+    pub fn http_client(&self) -> HttpClient {
+        self.http_client.lock().unwrap().clone()
+    }
+
+    pub fn check_limiter(&self) -> CheckLimiter {
+        self.check_limiter.lock().unwrap().clone()
+    }
+
+    /// 基于共享 HTTP 客户端访问 Augment / Orb 线上服务
+    pub fn augment_api(&self) -> AugmentApi {
+        AugmentApi::from_http_client(self.http_client())
+    }
+
+    /// 绑定到当前有效数据目录的 token 存储
+    ///
+    /// 所有 token 写入（前端命令、API 导入、云端恢复）都经过这里；数据目录变更后
+    /// 第一次调用会自动切换到新目录下的 tokens.json。
+    pub fn token_storage(&self) -> Result<Arc<LocalFileStorage>, String> {
+        let tokens_path = get_effective_data_dir(&self.app_handle, self)?.join("tokens.json");
+
+        let mut guard = self.storage_manager.lock().unwrap();
+        if let Some(storage) = guard.as_ref() {
+            if storage.storage_path() == tokens_path {
+                return Ok(storage.clone());
+            }
+        }
+
+        log::info!("Token 存储切换到: {}", tokens_path.display());
+        self.lock_data_dir(tokens_path.parent().unwrap_or(Path::new(".")));
+        let storage = Arc::new(LocalFileStorage::new_with_path(tokens_path));
+        *guard = Some(storage.clone());
+        Ok(storage)
+    }
+
+    /// 检查数据目录中的外部修改；数据目录变更后以新目录的当前内容为基准
+    fn poll_data_files(&self, data_dir: &Path) -> Vec<ExternalChange> {
+        let mut guard = self.data_watcher.lock().unwrap();
+        match guard.as_mut() {
+            Some(watcher) if watcher.data_dir() == data_dir => watcher.poll(),
+            _ => {
+                *guard = Some(DataWatcher::new(data_dir));
+                Vec::new()
+            }
+        }
+    }
+
+    /// 释放旧数据目录的锁并锁定新目录；锁被命令行工具或其他应用实例占用时只记录警告，
+    /// 不影响应用使用。应用实例之间靠每次写入的 `WriteLock` 串行，见 `instance_lock` 模块文档
+    fn lock_data_dir(&self, data_dir: &Path) {
+        let mut lock = self.instance_lock.lock().unwrap();
+        *lock = None;
+        match InstanceLock::acquire(data_dir, "app") {
+            Ok(acquired) => *lock = Some(acquired),
+            Err(e) => log::warn!("无法锁定数据目录 {}: {}", data_dir.display(), e),
+        }
+    }
+}
+
 #[tauri::command]
 async fn generate_auth_url(state: State<'_, AppState>) -> Result<String, String> {
     let augment_oauth_state = create_augment_oauth_state();
@@ -230,7 +398,7 @@ async fn get_token(code: String, state: State<'_, AppState>) -> Result<AugmentTo
             .ok_or("No Augment OAuth state found. Please generate auth URL first.")?
     };
 
-    complete_augment_oauth_flow(&augment_oauth_state, &code)
+    complete_augment_oauth_flow(&state.augment_api(), &augment_oauth_state, &code)
         .await
         .map_err(|e| format!("Failed to complete OAuth flow: {}", e))
 }
@@ -243,7 +411,7 @@ async fn get_augment_token(code: String, state: State<'_, AppState>) -> Result<A
             .ok_or("No Augment OAuth state found. Please generate auth URL first.")?
     };
 
-    complete_augment_oauth_flow(&augment_oauth_state, &code)
+    complete_augment_oauth_flow(&state.augment_api(), &augment_oauth_state, &code)
         .await
         .map_err(|e| format!("Failed to complete Augment OAuth flow: {}", e))
 }
@@ -251,7 +419,9 @@ async fn get_augment_token(code: String, state: State<'_, AppState>) -> Result<A
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckAccountStatusResult {
     pub is_banned: bool,
-    pub status: String,
+    pub status: AccountStatusCode,
+    pub reason: Option<StatusReason>,
+    pub error: Option<CheckError>,
     pub error_message: Option<String>,
     pub response_code: Option<u16>,
     pub access_token: String,
@@ -263,94 +433,170 @@ async fn check_account_status(
     token: String,
     tenant_url: String,
     auth_session: Option<String>,
-    token_id: Option<String>
+    token_id: Option<String>,
+    state: State<'_, AppState>,
 ) -> Result<CheckAccountStatusResult, String> {
-    let mut current_token = token;
-    let mut current_tenant_url = tenant_url;
+    // 与批量检测使用同一套检测和自动刷新流程
+    let stored = match &token_id {
+        Some(id) => state.token_storage()?.get_token(id).await.ok().flatten(),
+        None => None,
+    };
+    let token_info = TokenInfo {
+        access_token: token,
+        tenant_url,
+        id: token_id,
+        portal_url: stored.as_ref().and_then(|t| t.portal_url.clone()),
+        auth_session,
+        email_note: stored.and_then(|t| t.email_note),
+    };
+    let mut refreshed = None;
+    let result = check_single_token(&state.augment_api(), &token_info, state.app_session_cache.clone(), false, &mut refreshed).await;
 
-    // 1. 检测账号状态
-    let mut status_result = check_account_ban_status(&current_token, &current_tenant_url)
-        .await
-        .map_err(|e| format!("Failed to check account status: {}", e))?;
-
-    // 2. 如果检测到 INVALID_TOKEN 且有 auth_session，尝试自动刷新
-    if status_result.status == "INVALID_TOKEN" {
-        if let Some(ref session) = auth_session {
-            println!("Detected INVALID_TOKEN for {:?}, attempting auto-refresh", token_id);
-
-            match extract_token_from_session(session).await {
-                Ok(new_token_response) => {
-                    println!("Successfully refreshed token for {:?}", token_id);
-                    // 更新 token 和 tenant_url
-                    current_token = new_token_response.access_token;
-                    current_tenant_url = new_token_response.tenant_url;
-
-                    // 重新检测状态
-                    match check_account_ban_status(&current_token, &current_tenant_url).await {
-                        Ok(new_status) => {
-                            status_result = new_status;
-                            status_result.error_message = Some(format!(
-                                "Token was invalid but successfully auto-refreshed. New status: {}",
-                                status_result.status
-                            ));
-                        }
-                        Err(e) => {
-                            println!("Failed to check status after refresh: {}", e);
-                        }
-                    }
-                }
-                Err(err) => {
-                    println!("Failed to refresh token for {:?}: {}", token_id, err);
-                    // 如果刷新失败原因是 SESSION_ERROR_OR_ACCOUNT_BANNED，视为账号封禁
-                    if err.contains("SESSION_ERROR_OR_ACCOUNT_BANNED") {
-                        status_result.status = "SUSPENDED".to_string();
-                        status_result.is_banned = true;
-                        status_result.error_message = Some(
-                            "Account is suspended (detected during token refresh)".to_string()
-                        );
-                    } else {
-                        status_result.error_message = Some(format!(
-                            "Token is invalid. Failed to auto-refresh: {}",
-                            err
-                        ));
-                    }
-                }
-            }
-        } else {
-            println!("Token {:?} is invalid but no auth_session available", token_id);
-            status_result.error_message = Some(
-                "Token is invalid. No auth_session available for auto-refresh".to_string()
-            );
+    // 自动刷新的 access_token 立即写入存储并记入变更记录，与批量检测一致
+    if refreshed.is_some() {
+        if let Err(e) = persist_refreshed_tokens(&state, &[&result]).await {
+            log::warn!("保存自动刷新的 token 失败: {}", e);
         }
+    } else if result.status_result.status == AccountStatusCode::Error {
+        return Err(result.status_result.error_message.unwrap_or_else(|| "Failed to check account status".to_string()));
     }
 
-    // 3. 返回结果（包含可能已更新的 token 和 tenant_url）
+    let status_result = result.status_result;
     Ok(CheckAccountStatusResult {
         is_banned: status_result.is_banned,
         status: status_result.status,
+        reason: status_result.reason,
+        error: status_result.error,
         error_message: status_result.error_message,
         response_code: status_result.response_code,
-        access_token: current_token,
-        tenant_url: current_tenant_url,
+        access_token: result.access_token,
+        tenant_url: result.tenant_url,
     })
 }
 
+/// 批量检测账号状态；传入 `batch_id` 时可通过 `cancel_batch_check` 取消，
+/// 每完成一个账号发送 `batch-check-progress` 事件，取消后返回已完成的部分并标记 `cancelled`
 #[tauri::command]
 async fn batch_check_tokens_status(
     tokens: Vec<TokenInfo>,
+    batch_id: Option<String>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Vec<TokenStatusResult>, String> {
-    batch_check_account_status(tokens, state.app_session_cache.clone())
+) -> Result<BatchCheckOutcome, String> {
+    let previous: HashMap<String, String> = tokens.iter()
+        .filter_map(|t| t.id.clone().map(|id| (id, t.access_token.clone())))
+        .collect();
+
+    let options = load_unified_config_with_state(&app, &state).batch_check_options;
+    let batch_id = batch_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let cancel = CancelHandle::default();
+    // 批次 id 由前端传入，不能覆盖进行中的批次（包括后台定时检测），否则原批次无法再取消
+    if batch_id == status_scheduler::BATCH_ID {
+        return Err(format!("批次 id {} 保留给后台定时检测", batch_id));
+    }
+    match state.batch_checks.lock().unwrap().entry(batch_id.clone()) {
+        std::collections::hash_map::Entry::Occupied(_) => return Err(format!("批量检测 {} 正在进行中", batch_id)),
+        std::collections::hash_map::Entry::Vacant(entry) => {
+            entry.insert(cancel.clone());
+        }
+    }
+
+    let outcome = batch_check_account_status_with(
+        &state.augment_api(),
+        tokens,
+        state.app_session_cache.clone(),
+        &options,
+        &state.check_limiter(),
+        &cancel,
+        |progress| {
+            let _ = app.emit("batch-check-progress", serde_json::json!({
+                "batch_id": batch_id,
+                "completed": progress.completed,
+                "total": progress.total,
+                "result": progress.result,
+            }));
+        },
+    )
+    .await;
+    state.batch_checks.lock().unwrap().remove(&batch_id);
+    let outcome = outcome.map_err(|e| format!("Failed to batch check tokens status: {}", e))?;
+
+    // 自动刷新的 access_token 立即写入存储并记入变更记录，不依赖前端随后保存
+    let refreshed: Vec<&TokenStatusResult> = outcome.results.iter()
+        .filter(|r| r.token_id.as_ref()
+            .and_then(|id| previous.get(id))
+            .is_some_and(|old| *old != r.access_token))
+        .collect();
+    if !refreshed.is_empty() {
+        if let Err(e) = persist_refreshed_tokens(&state, &refreshed).await {
+            log::warn!("保存自动刷新的 token 失败: {}", e);
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// 取消进行中的批量检测，返回是否找到该批次
+#[tauri::command]
+async fn cancel_batch_check(batch_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let cancel = state.batch_checks.lock().unwrap().get(&batch_id).cloned();
+    if let Some(cancel) = &cancel {
+        log::info!("取消批量检测: {}", batch_id);
+        cancel.cancel();
+    }
+    Ok(cancel.is_some())
+}
+
+#[tauri::command]
+async fn get_batch_check_options(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<BatchCheckOptions, String> {
+    Ok(load_unified_config_with_state(&app, &state).batch_check_options)
+}
+
+#[tauri::command]
+async fn set_batch_check_options(
+    options: BatchCheckOptions,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    options.validate()?;
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.batch_check_options = options;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+    // 进行中的检测继续使用原来的上限，之后开始的检测使用新上限
+    *state.check_limiter.lock().unwrap() = CheckLimiter::new(options.max_concurrency);
+    Ok(())
+}
+
+async fn persist_refreshed_tokens(state: &State<'_, AppState>, refreshed: &[&TokenStatusResult]) -> Result<(), String> {
+    let storage = state.token_storage()?.for_source(ChangeSource::AutoRefresh);
+    storage
+        .modify_tokens(|tokens| {
+            let mut changed = false;
+            for result in refreshed {
+                let Some(id) = result.token_id.as_deref() else { continue };
+                let Some(token) = tokens.iter_mut().find(|t| t.id == id) else { continue };
+                token.access_token = result.access_token.clone();
+                token.tenant_url = result.tenant_url.clone();
+                if result.portal_url.is_some() {
+                    token.portal_url = result.portal_url.clone();
+                }
+                token.updated_at = chrono::Utc::now();
+                changed = true;
+            }
+            ((), changed)
+        })
         .await
-        .map_err(|e| format!("Failed to batch check tokens status: {}", e))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn get_credit_info_from_token(
     token: String,
     tenant_url: String,
+    state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let credit_info = get_credit_info(&token, &tenant_url)
+    let credit_info = get_credit_info(&state.augment_api(), &token, &tenant_url)
         .await
         .map_err(|e| format!("Failed to get credit info: {}", e))?;
 
@@ -362,8 +608,9 @@ async fn get_credit_info_from_token(
 async fn get_models_from_token(
     token: String,
     tenant_url: String,
+    state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let models_response = get_models(&token, &tenant_url)
+    let models_response = get_models(&state.augment_api(), &token, &tenant_url)
         .await
         .map_err(|e| format!("Failed to get models: {}", e))?;
 
@@ -377,58 +624,133 @@ async fn fetch_batch_credit_consumption(
     auth_session: String,
     state: State<'_, AppState>,
 ) -> Result<BatchCreditConsumptionResponse, String> {
-    println!("fetch_batch_credit_consumption called");
+    log::info!("fetch_batch_credit_consumption called");
+    let api = state.augment_api();
     // 1. 检查缓存中是否有有效的 app_session
-    let cached_app_session = {
-        let cache = state.app_session_cache.lock().unwrap();
-        cache.get(&auth_session).map(|c| c.app_session.clone())
-    };
+    let cached_app_session = state.app_session_cache.lock().unwrap().get(&auth_session);
 
     // 2. 如果有缓存，先尝试使用缓存的 app_session
     if let Some(app_session) = cached_app_session {
-        println!("Using cached app_session for credit consumption");
+        log::info!("Using cached app_session for credit consumption");
 
         // 尝试使用缓存的 app_session 获取数据
-        match get_batch_credit_consumption_with_app_session(&app_session).await {
+        match get_batch_credit_consumption_with_app_session(&api, &app_session).await {
             Ok(result) => {
-                println!("Successfully fetched credit data with cached app_session");
+                log::info!("Successfully fetched credit data with cached app_session");
                 return Ok(result);
             }
             Err(e) => {
-                // 如果失败（可能是 session 过期），记录日志并继续获取新的
-                println!("Cached app_session failed: {}, will refresh", e);
+                // 如果失败（可能是 session 过期），丢弃缓存并继续获取新的
+                log::warn!("Cached app_session failed: {}, will refresh", e);
+                state.app_session_cache.lock().unwrap().remove(&auth_session);
             }
         }
     }
 
     // 3. 没有缓存或缓存失效，获取新的 app_session
-    println!("Exchanging auth_session for new app_session...");
-    let app_session = exchange_auth_session_for_app_session(&auth_session).await?;
-    println!("New app session obtained: {}", &app_session[..20.min(app_session.len())]);
+    log::info!("Exchanging auth_session for new app_session...");
+    let app_session = exchange_auth_session_for_app_session(&api, &auth_session).await?;
+    log::debug!("New app session obtained");
 
     // 4. 更新缓存
-    {
-        let mut cache = state.app_session_cache.lock().unwrap();
-        cache.insert(
-            auth_session.clone(),
-            AppSessionCache {
-                app_session: app_session.clone(),
-                created_at: SystemTime::now(),
-            },
-        );
-        println!("App session cached for future use");
-    }
+    state.app_session_cache.lock().unwrap().insert(&auth_session, app_session.clone());
+    log::info!("App session cached for future use");
 
     // 5. 使用新的 app_session 获取数据
-    let result = get_batch_credit_consumption_with_app_session(&app_session).await?;
+    let result = get_batch_credit_consumption_with_app_session(&api, &app_session).await?;
 
     Ok(result)
 }
 
+/// 按当前数据目录和设置重新绑定 app session 缓存，启用持久化时从密钥链获取加密密钥
+fn configure_app_session_cache(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    settings: &AppSessionCacheSettings,
+) -> Result<(), String> {
+    let data_dir = get_effective_data_dir(app, state)?;
+    let key = if settings.persist {
+        Some(state.password_manager.get_or_create_session_cache_key()?)
+    } else {
+        None
+    };
+    state.app_session_cache.lock().unwrap().attach(&data_dir, settings.clone(), key);
+    Ok(())
+}
+
+async fn token_auth_session(state: &State<'_, AppState>, token_id: &str) -> Result<String, String> {
+    let token = state.token_storage()?
+        .get_token(token_id)
+        .await
+        .map_err(|e| format!("读取账号失败: {}", e))?
+        .ok_or_else(|| format!("账号不存在: {}", token_id))?;
+    token.auth_session.ok_or_else(|| "该账号没有 auth session".to_string())
+}
+
+#[tauri::command]
+async fn get_app_session_cache_settings(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<AppSessionCacheSettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).app_session_cache)
+}
+
+#[tauri::command]
+async fn set_app_session_cache_settings(
+    settings: AppSessionCacheSettings,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    settings.validate()?;
+    configure_app_session_cache(&app, &state, &settings)?;
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.app_session_cache = settings;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
+}
+
+/// 查看账号缓存的 app session 状态，没有缓存时返回 None
+#[tauri::command]
+async fn get_app_session_info(token_id: String, state: State<'_, AppState>) -> Result<Option<AppSessionInfo>, String> {
+    let auth_session = token_auth_session(&state, &token_id).await?;
+    Ok(state.app_session_cache.lock().unwrap().info(&auth_session))
+}
+
+/// 校验账号缓存的 app session，失效或强制刷新时重新交换
+#[tauri::command]
+async fn refresh_app_session(
+    token_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AppSessionInfo, String> {
+    let auth_session = token_auth_session(&state, &token_id).await?;
+    augment_user_info::validate_or_refresh_app_session(
+        &state.augment_api(),
+        &auth_session,
+        &state.app_session_cache,
+        force.unwrap_or(false),
+    )
+    .await
+}
+
+/// 清除账号缓存的 app session，返回是否存在缓存
+#[tauri::command]
+async fn clear_app_session(token_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let auth_session = token_auth_session(&state, &token_id).await?;
+    Ok(state.app_session_cache.lock().unwrap().remove(&auth_session))
+}
+
+/// 清除全部缓存的 app session，返回清除的数量
+#[tauri::command]
+async fn clear_all_app_sessions(state: State<'_, AppState>) -> Result<usize, String> {
+    let mut cache = state.app_session_cache.lock().unwrap();
+    let count = cache.len();
+    cache.clear();
+    Ok(count)
+}
+
 // 内部函数：从 session 导入 token（供 API 服务器使用，不发送进度事件）
-pub async fn add_token_from_session_internal(session: &str, _app: &tauri::AppHandle) -> Result<TokenFromSessionResponse, String> {
+pub async fn add_token_from_session_internal(session: &str, app: &tauri::AppHandle) -> Result<TokenFromSessionResponse, String> {
     // 从 session 提取 token (包含 email)
-    let token_response = extract_token_from_session(session).await?;
+    let api = app.state::<AppState>().augment_api();
+    let token_response = extract_token_from_session(&api, session).await?;
 
     Ok(TokenFromSessionResponse {
         access_token: token_response.access_token,
@@ -439,10 +761,10 @@ pub async fn add_token_from_session_internal(session: &str, _app: &tauri::AppHan
 
 // 内部函数：从 session 导入 token（供 API 服务器批量导入使用，使用 app_session 缓存）
 // 注意：这个函数与 add_token_from_session_internal 功能相同，但为了与 augment-token-mng-1.3.3 保持一致而添加
-pub async fn add_token_from_session_internal_with_cache(session: &str, _state: &AppState) -> Result<TokenFromSessionResponse, String> {
+pub async fn add_token_from_session_internal_with_cache(session: &str, state: &AppState) -> Result<TokenFromSessionResponse, String> {
     // 从 session 提取 token (包含 email)
     // 注意：extract_token_from_session 内部已经使用了 app_session 缓存机制
-    let token_response = extract_token_from_session(session).await?;
+    let token_response = extract_token_from_session(&state.augment_api(), session).await?;
 
     Ok(TokenFromSessionResponse {
         access_token: token_response.access_token,
@@ -452,10 +774,10 @@ pub async fn add_token_from_session_internal_with_cache(session: &str, _state: &
 }
 
 #[tauri::command]
-async fn add_token_from_session(session: String, app: tauri::AppHandle) -> Result<TokenFromSessionResponse, String> {
+async fn add_token_from_session(session: String, app: tauri::AppHandle, state: State<'_, AppState>) -> Result<TokenFromSessionResponse, String> {
     // 从 session 提取 token (包含 email)
     let _ = app.emit("session-import-progress", "sessionImportExtractingToken");
-    let token_response = extract_token_from_session(&session).await?;
+    let token_response = extract_token_from_session(&state.augment_api(), &session).await?;
 
     let _ = app.emit("session-import-progress", "sessionImportComplete");
 
@@ -473,180 +795,459 @@ async fn open_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
         .map_err(|e| format!("Failed to open URL: {}", e))
 }
 
-#[tauri::command]
-async fn save_tokens_json(json_string: String, app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+/// 前端传入的 token：前端的数据由 load_tokens_json 读出，已是当前版本的格式，直接转换，不再迁移
+fn tokens_from_frontend(tokens: Vec<serde_json::Value>) -> Result<Vec<TokenData>, String> {
+    tokens
+        .iter()
+        .map(|item| storage::convert_legacy_token(item).map_err(|e| format!("Invalid token: {}", e)))
+        .collect()
+}
+
+/// 有效数据目录中还没有 tokens.json 时，从默认目录或旧版目录复制过来
+fn migrate_legacy_tokens_file(app: &tauri::AppHandle, state: &AppState) -> Result<(), String> {
     use std::fs;
-    use std::io::Write;
 
     // 获取有效的数据目录（优先使用自定义目录）
-    let data_dir = get_effective_data_dir(&app, &state)?;
+    let effective_data_dir = get_effective_data_dir(app, state)?;
+    let effective_storage_path = effective_data_dir.join("tokens.json");
 
-    // 确保目录存在并且可写
-    fs::create_dir_all(&data_dir)
-        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    // 如果有效目录中存在文件，直接使用
+    if effective_storage_path.exists() {
+        return Ok(());
+    }
+
+    // 如果有效目录中没有文件，尝试从默认目录读取（用于迁移）
+    let default_app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
 
-    let storage_path = data_dir.join("tokens.json");
+    let default_storage_path = default_app_data_dir.join("tokens.json");
 
-    // 使用同一目录下的临时文件，确保在同一文件系统上
-    let temp_path = data_dir.join(format!("tokens.{}.tmp", uuid::Uuid::new_v4()));
+    let source = if default_storage_path.exists() {
+        default_storage_path
+    } else {
+        // 如果默认目录没有文件，尝试从旧目录读取
+        log::info!("默认目录中没有文件，尝试从旧目录读取...");
+        let old_storage_path = get_old_app_data_dir()?.join("tokens.json");
+        log::info!("尝试读取旧文件路径: {:?}", old_storage_path);
+
+        if !old_storage_path.exists() {
+            log::info!("所有目录都没有找到 tokens.json 文件");
+            return Ok(());
+        }
+        old_storage_path
+    };
+
+    // 创建有效目录（如果不存在）
+    fs::create_dir_all(&effective_data_dir)
+        .map_err(|e| format!("Failed to create effective data directory: {}", e))?;
+
+    // 将文件迁移到有效目录，原文件保持不变
+    fs::copy(&source, &effective_storage_path)
+        .map_err(|e| format!("Failed to migrate tokens file: {}", e))?;
+
+    log::info!("文件已迁移到有效目录: {:?}", effective_storage_path);
+    Ok(())
+}
 
-    // 解析 JSON 并清理废弃字段
-    let mut tokens = serde_json::from_str::<serde_json::Value>(&json_string)
-        .map_err(|e| format!("Invalid JSON format: {}", e))?;
+/// 处理数据目录中的外部修改：重新加载并通知前端
+async fn sync_external_changes(app: &tauri::AppHandle, state: &State<'_, AppState>) -> Result<(), String> {
+    let data_dir = get_effective_data_dir(app, state)?;
+    let changes = state.poll_data_files(&data_dir);
+    if changes.is_empty() {
+        return Ok(());
+    }
 
-    // 如果是数组，清理每个 token 对象中的废弃字段
-    if let serde_json::Value::Array(ref mut tokens_array) = tokens {
-        for token in tokens_array.iter_mut() {
-            if let serde_json::Value::Object(ref mut obj) = token {
-                // 移除废弃的 balance_color_mode 字段
-                obj.remove("balance_color_mode");
+    for change in &changes {
+        log::info!("检测到外部修改: {}（涉及账号 {:?}）", change.file, change.token_ids);
+        match change.file.as_str() {
+            "tokens.json" => {
+                let _ = app.emit("tokens-updated", serde_json::json!({ "source": "external", "token_ids": change.token_ids }));
+            }
+            "bookmarks.json" => {
+                let _ = app.emit("bookmarks-updated", ());
             }
+            "config.json" => {
+                // 重新读取配置中缓存在内存里的部分（网络设置、WebDAV 配置、备份策略）
+                initialize_storage_manager(app, state)
+                    .await
+                    .map_err(|e| format!("重新加载配置失败: {}", e))?;
+            }
+            _ => {}
         }
     }
+    let _ = app.emit("data-files-changed", &changes);
+    Ok(())
+}
 
-    // 将清理后的数据序列化为字符串
-    let cleaned_json_string = serde_json::to_string_pretty(&tokens)
-        .map_err(|e| format!("Failed to serialize cleaned tokens: {}", e))?;
+/// 保存前检查冲突：账号在前端重新加载之前被外部修改过时拒绝保存，避免覆盖外部的修改
+async fn ensure_no_external_conflicts(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    token_ids: &[String],
+) -> Result<(), String> {
+    sync_external_changes(app, state).await?;
+    let conflicts = state.data_watcher.lock().unwrap()
+        .as_ref()
+        .map(|watcher| watcher.conflicts(token_ids))
+        .unwrap_or_default();
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("以下账号已被其他程序修改，请重新加载后再保存: {}", conflicts.join(", ")))
+    }
+}
 
-    // 原子性写入：先写临时文件，再重命名
-    let write_result = (|| -> Result<(), String> {
-        let mut temp_file = fs::File::create(&temp_path)
-            .map_err(|e| format!("Failed to create temp file: {}", e))?;
+// 读取 tokens 文件并迁移到当前版本，返回前端使用的 token 数组 JSON
+#[tauri::command]
+async fn load_tokens_json(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    migrate_legacy_tokens_file(&app, &state)?;
 
-        temp_file.write_all(cleaned_json_string.as_bytes())
-            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    // 先处理尚未发现的外部修改，返回的数据即为最新内容
+    sync_external_changes(&app, &state).await?;
+    let tokens = state.token_storage()?
+        .load_tokens()
+        .await
+        .map_err(|e| format!("Failed to load tokens: {}", e))?;
+    if let Some(watcher) = state.data_watcher.lock().unwrap().as_mut() {
+        watcher.acknowledge_tokens();
+    }
 
-        temp_file.sync_all()
-            .map_err(|e| format!("Failed to sync temp file: {}", e))?;
+    let legacy_tokens: Vec<serde_json::Value> = tokens.iter()
+        .map(convert_to_legacy_format)
+        .collect();
+    serde_json::to_string_pretty(&legacy_tokens)
+        .map_err(|e| format!("Failed to serialize tokens: {}", e))
+}
 
-        // 显式关闭文件
-        drop(temp_file);
+/// 新增或更新单个 token（按 id）
+#[tauri::command]
+async fn save_token(token: serde_json::Value, state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
+    let tokens = tokens_from_frontend(vec![token])?;
+    let ids: Vec<String> = tokens.iter().map(|t| t.id.clone()).collect();
+    ensure_no_external_conflicts(&app, &state, &ids).await?;
+    state.token_storage()?
+        .save_tokens(&tokens)
+        .await
+        .map_err(|e| format!("Save failed: {}", e))
+}
 
-        Ok(())
-    })();
+/// 批量新增或更新 token（按 id），只写入传入的 token
+#[tauri::command]
+async fn save_tokens(tokens: Vec<serde_json::Value>, state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
+    let tokens = tokens_from_frontend(tokens)?;
+    let ids: Vec<String> = tokens.iter().map(|t| t.id.clone()).collect();
+    ensure_no_external_conflicts(&app, &state, &ids).await?;
+    state.token_storage()?
+        .save_tokens(&tokens)
+        .await
+        .map_err(|e| format!("Save failed: {}", e))
+}
 
-    // 如果写入失败，清理临时文件
-    if let Err(e) = write_result {
-        let _ = fs::remove_file(&temp_path);
-        return Err(e);
-    }
+#[tauri::command]
+async fn delete_tokens(token_ids: Vec<String>, state: State<'_, AppState>, app: tauri::AppHandle) -> Result<usize, String> {
+    ensure_no_external_conflicts(&app, &state, &token_ids).await?;
+    state.token_storage()?
+        .delete_tokens(&token_ids)
+        .await
+        .map_err(|e| format!("Delete failed: {}", e))
+}
 
-    // 尝试原子性重命名，如果失败则使用复制+删除的方式
-    let rename_result = fs::rename(&temp_path, &storage_path);
+/// 批量导入账号（CSV、JSON Lines、tokens.json 导出、Session 列表）
+///
+/// `dry_run` 为 true 时只返回预览报告；否则将报告中可导入的账号一次性写入，
+/// 写入失败或与现有数据冲突时整体放弃
+#[tauri::command]
+async fn import_tokens(
+    request: ImportRequest,
+    dry_run: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<ImportReport, String> {
+    let (format, rows) = token_import::parse(&request)?;
+    let storage = state.token_storage()?.for_source(ChangeSource::BulkImport);
+    let existing = storage.load_tokens()
+        .await
+        .map_err(|e| format!("Failed to load tokens: {}", e))?;
 
-    if let Err(rename_err) = rename_result {
-        // 如果 rename 失败（可能是跨文件系统），尝试复制+删除
-        println!("Rename failed, trying copy+delete: {}", rename_err);
+    let resolved = token_import::resolve(&state.augment_api(), rows, &existing).await;
+    let plan = token_import::plan(format, resolved, &existing, dry_run);
+    let mut report = plan.report;
 
-        match fs::copy(&temp_path, &storage_path) {
-            Ok(_) => {
-                // 复制成功，删除临时文件
-                let _ = fs::remove_file(&temp_path);
-                Ok(())
-            }
-            Err(copy_err) => {
-                // 复制也失败，清理临时文件并返回错误
-                let _ = fs::remove_file(&temp_path);
-                Err(format!("Failed to save file (rename failed: {}, copy failed: {})", rename_err, copy_err))
-            }
-        }
-    } else {
-        Ok(())
+    if !dry_run && !plan.tokens.is_empty() {
+        storage.insert_tokens(&plan.tokens)
+            .await
+            .map_err(|e| format!("导入失败，未写入任何账号: {}", e))?;
+        report.imported = plan.tokens.len();
+        log::info!("批量导入 {} 个账号（重复 {}，无效 {}）", report.imported, report.duplicates, report.invalid);
+        let _ = app.emit("tokens-updated", ());
     }
+
+    Ok(report)
 }
 
+/// 按条件导出账号到指定文件（JSON、CSV 或加密归档）
+#[tauri::command]
+async fn export_tokens(
+    request: ExportRequest,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<ExportSummary, String> {
+    let tokens = state.token_storage()?
+        .load_tokens()
+        .await
+        .map_err(|e| format!("Failed to load tokens: {}", e))?;
+    let (content, summary) = token_export::export(&tokens, &request)?;
+
+    let path = Path::new(&file_path);
+    storage::write_file_atomic(path, &content)?;
+    // 导出文件可能包含凭据，仅当前用户可读
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("设置文件权限失败: {}", e))?;
+    }
 
+    log::info!("导出 {} 个账号到 {:?}（格式 {:?}）", summary.count, path, summary.format);
+    Ok(summary)
+}
+
+/// 账号的变更记录（按时间顺序）
 #[tauri::command]
-async fn load_tokens_json(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, String> {
-    use std::fs;
+async fn get_token_history(token_id: String, state: State<'_, AppState>) -> Result<Vec<JournalEntry>, String> {
+    state.token_storage()?.history(&token_id).await
+}
 
-    // 获取有效的数据目录（优先使用自定义目录）
-    let effective_data_dir = get_effective_data_dir(&app, &state)?;
-    let effective_storage_path = effective_data_dir.join("tokens.json");
+/// 把账号回退到指定变更记录之后的版本
+#[tauri::command]
+async fn revert_token(
+    token_id: String,
+    entry_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<serde_json::Value, String> {
+    let token = state.token_storage()?.revert_token(&token_id, &entry_id).await?;
+    log::info!("账号 {} 已回退到变更 {} 之后的版本", token_id, entry_id);
+    let _ = app.emit("tokens-updated", ());
+    Ok(convert_to_legacy_format(&token))
+}
 
-    // 如果有效目录中存在文件，直接读取
-    if effective_storage_path.exists() {
-        let content = fs::read_to_string(&effective_storage_path)
-            .map_err(|e| format!("Failed to read tokens file: {}", e))?;
+#[tauri::command]
+async fn list_trash(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<Vec<TrashEntry>, String> {
+    let retention_days = load_unified_config_with_state(&app, &state).history_settings.trash_retention_days;
+    state.token_storage()?.trash(retention_days).await
+}
 
-        // 如果文件为空，返回空数组的 JSON
-        if content.trim().is_empty() {
-            return Ok("[]".to_string());
+#[tauri::command]
+async fn restore_from_trash(
+    trash_ids: Vec<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<usize, String> {
+    let storage = state.token_storage()?;
+    let mut restored = 0;
+    let mut errors = Vec::new();
+    for trash_id in &trash_ids {
+        match storage.restore_from_trash(trash_id).await {
+            Ok(_) => restored += 1,
+            Err(e) => errors.push(e),
         }
-
-        return process_token_content(content);
     }
+    if restored > 0 {
+        let _ = app.emit("tokens-updated", ());
+    }
+    if errors.is_empty() {
+        Ok(restored)
+    } else {
+        Err(format!("已恢复 {} 个账号，失败 {} 个: {}", restored, errors.len(), errors.join("; ")))
+    }
+}
 
-    // 如果有效目录中没有文件，尝试从默认目录读取（用于迁移）
-    let default_app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+#[tauri::command]
+async fn get_history_settings(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<HistorySettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).history_settings)
+}
 
-    let default_storage_path = default_app_data_dir.join("tokens.json");
+// 设置回收站保留天数（0 表示永久保留），保存后立即清理过期条目
+#[tauri::command]
+async fn set_trash_retention_days(
+    days: u32,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.history_settings.trash_retention_days = days;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+    state.token_storage()?.purge_trash(days).await
+}
 
-    // 尝试从默认目录读取（用于数据迁移）
-    if default_storage_path.exists() {
-        let content = fs::read_to_string(&default_storage_path)
-            .map_err(|e| format!("Failed to read tokens file: {}", e))?;
+#[tauri::command]
+async fn list_backups(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<Vec<BackupInfo>, String> {
+    backups::list_backups(&get_effective_data_dir(&app, &state)?)
+}
 
-        // 如果文件为空，返回空数组的 JSON
-        if content.trim().is_empty() {
-            return Ok("[]".to_string());
-        }
+#[tauri::command]
+async fn inspect_backup(
+    file_name: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BackupInspection, String> {
+    backups::inspect_backup(&get_effective_data_dir(&app, &state)?, &file_name)
+}
 
-        // 如果用户设置了自定义目录，迁移数据
-        if effective_data_dir != default_app_data_dir {
-            // 创建自定义目录（如果不存在）
-            fs::create_dir_all(&effective_data_dir)
-                .map_err(|e| format!("Failed to create custom data directory: {}", e))?;
+#[tauri::command]
+async fn create_backup(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<BackupInfo, String> {
+    let max_generations = load_unified_config_with_state(&app, &state).backup_settings.max_generations;
+    backups::create_backup(&get_effective_data_dir(&app, &state)?, BackupReason::Manual, max_generations)
+}
 
-            // 将文件迁移到自定义目录
-            fs::copy(&default_storage_path, &effective_storage_path)
-                .map_err(|e| format!("Failed to migrate tokens file: {}", e))?;
+/// 数据文件的完整性检查结果，启动时前端未及时收到事件也可以主动查询
+#[tauri::command]
+async fn check_data_integrity(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<Vec<IntegrityIssue>, String> {
+    Ok(backups::check_integrity(&get_effective_data_dir(&app, &state)?))
+}
 
-            println!("文件已迁移到自定义目录: {:?}", effective_storage_path);
-        }
+/// 从备份恢复数据文件，`files` 为空时恢复备份中的全部文件
+///
+/// 恢复前先备份当前数据；账号经由 token 存储写入，恢复的变化会记入变更记录
+#[tauri::command]
+async fn restore_backup(
+    file_name: String,
+    files: Vec<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let data_dir = get_effective_data_dir(&app, &state)?;
+    let contents = backups::read_backup(&data_dir, &file_name, &files)?;
 
-        return process_token_content(content);
+    if backups::BACKUP_FILES.iter().any(|name| data_dir.join(name).exists()) {
+        let max_generations = load_unified_config_with_state(&app, &state).backup_settings.max_generations;
+        backups::create_backup(&data_dir, BackupReason::BeforeRestore, max_generations)
+            .map_err(|e| format!("恢复前备份当前数据失败: {}", e))?;
     }
 
-    // 如果默认目录没有文件，尝试从旧目录读取
-    println!("默认目录中没有文件，尝试从旧目录读取...");
+    let mut restored = Vec::new();
+    for (name, content) in contents {
+        if name == "tokens.json" {
+            let document: serde_json::Value = serde_json::from_slice(&content)
+                .map_err(|e| format!("备份中的 tokens.json 无法解析: {}", e))?;
+            let outcome = migrations::migrate_value(migrations::DataKind::Tokens, document, &migrations::MigrationContext::default())
+                .map_err(|e| e.to_string())?;
+            let tokens = migrations::tokens_from_document(outcome.value)
+                .iter()
+                .map(storage::convert_legacy_token)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("备份中的账号无效: {}", e))?;
+            state.token_storage()?
+                .for_source(ChangeSource::Backup)
+                .replace_all_tokens(&tokens)
+                .await
+                .map_err(|e| format!("写入tokens.json失败: {}", e))?;
+        } else {
+            // 书签和配置在读取时按需迁移，原样写回即可
+            let path = data_dir.join(&name);
+            data_watcher::record_write(&path, &content);
+            storage::write_file_atomic(&path, &content)?;
+        }
+        restored.push(name);
+    }
 
-    // 构造旧的应用数据目录路径
-    let old_app_data_dir = get_old_app_data_dir()?;
-    let old_storage_path = old_app_data_dir.join("tokens.json");
+    log::info!("已从备份 {} 恢复: {:?}", file_name, restored);
+    if restored.iter().any(|name| name == "config.json") {
+        initialize_storage_manager(&app, &state)
+            .await
+            .map_err(|e| format!("重新加载配置失败: {}", e))?;
+    }
+    let _ = app.emit("tokens-updated", ());
+    let _ = app.emit("bookmarks-updated", ());
+    Ok(restored)
+}
 
-    println!("尝试读取旧文件路径: {:?}", old_storage_path);
+#[tauri::command]
+async fn get_backup_settings(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<BackupSettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).backup_settings)
+}
 
-    if old_storage_path.exists() {
-        let content = fs::read_to_string(&old_storage_path)
-            .map_err(|e| format!("Failed to read old tokens file: {}", e))?;
+#[tauri::command]
+async fn set_backup_settings(
+    settings: BackupSettings,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.backup_settings = settings.clone();
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+    backups::configure(&get_effective_data_dir(&app, &state)?, settings);
+    Ok(())
+}
 
-        println!("从旧目录读取到的文件内容: {}", content);
+#[tauri::command]
+async fn get_status_check_schedule(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<StatusCheckSchedule, String> {
+    Ok(load_unified_config_with_state(&app, &state).status_check_schedule)
+}
 
-        // 如果文件为空，返回空数组的 JSON
-        if content.trim().is_empty() {
-            return Ok("[]".to_string());
-        }
+#[tauri::command]
+async fn set_status_check_schedule(
+    schedule: StatusCheckSchedule,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    schedule.validate()?;
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.status_check_schedule = schedule;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
+}
 
-        // 创建有效目录（如果不存在）
-        fs::create_dir_all(&effective_data_dir)
-            .map_err(|e| format!("Failed to create effective data directory: {}", e))?;
+#[tauri::command]
+async fn run_status_check_now(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<CheckSummary, String> {
+    run_status_check(&app, &state, "manual").await
+}
 
-        // 将文件迁移到有效目录
-        fs::copy(&old_storage_path, &effective_storage_path)
-            .map_err(|e| format!("Failed to migrate tokens file: {}", e))?;
+#[tauri::command]
+async fn get_last_status_check(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<Option<CheckSummary>, String> {
+    Ok(status_scheduler::load_summary(&get_effective_data_dir(&app, &state)?))
+}
 
-        println!("文件已迁移到有效目录: {:?}", effective_storage_path);
+/// 检测所有账号并写回结果，完成后通知前端重新加载
+async fn run_status_check(app: &tauri::AppHandle, state: &State<'_, AppState>, trigger: &str) -> Result<CheckSummary, String> {
+    let unified_config = load_unified_config_with_state(app, state);
+    let options = BatchCheckOptions {
+        max_concurrency: unified_config.status_check_schedule.max_concurrency,
+        ..unified_config.batch_check_options
+    };
+    let storage = state.token_storage()?;
 
-        return process_token_content(content);
+    let cancel = CancelHandle::default();
+    {
+        let mut batch_checks = state.batch_checks.lock().unwrap();
+        if batch_checks.contains_key(status_scheduler::BATCH_ID) {
+            return Err("已有状态检测正在进行".to_string());
+        }
+        batch_checks.insert(status_scheduler::BATCH_ID.to_string(), cancel.clone());
     }
+    let summary = status_scheduler::run_checks(
+        &state.augment_api(),
+        &storage,
+        state.app_session_cache.clone(),
+        &options,
+        &state.check_limiter(),
+        &cancel,
+        trigger,
+    )
+    .await;
+    state.batch_checks.lock().unwrap().remove(status_scheduler::BATCH_ID);
+    let summary = summary?;
 
-    // 所有目录都没有文件
-    println!("所有目录都没有找到 tokens.json 文件");
-    Ok("[]".to_string())
+    if summary.updated > 0 {
+        let _ = app.emit("tokens-updated", serde_json::json!({ "source": "status-check" }));
+    }
+    let _ = app.emit("status-check-completed", &summary);
+    Ok(summary)
 }
 
 // 获取旧的应用数据目录
@@ -681,64 +1282,29 @@ fn get_old_app_data_dir() -> Result<PathBuf, String> {
     Ok(old_path)
 }
 
-// 处理 token 内容的通用函数
-fn process_token_content(content: String) -> Result<String, String> {
-    // 尝试解析 JSON 内容
-    match serde_json::from_str::<serde_json::Value>(&content) {
-        Ok(mut value) => {
-            // 如果解析成功，检查是否需要转换格式
-            match value {
-                serde_json::Value::Array(ref mut tokens) => {
-                    // 清理每个 token 对象中的废弃字段
-                    for token in tokens.iter_mut() {
-                        if let serde_json::Value::Object(ref mut obj) = token {
-                            // 移除废弃的 balance_color_mode 字段
-                            obj.remove("balance_color_mode");
-                        }
-                    }
-                    // 返回清理后的数组
-                    Ok(serde_json::to_string_pretty(&tokens)
-                        .map_err(|e| format!("Failed to serialize tokens: {}", e))?)
-                }
-                serde_json::Value::Object(ref obj) => {
-                    // 检查是否是旧格式 {tokens: [...]}
-                    if let Some(tokens_array) = obj.get("tokens") {
-                        if let serde_json::Value::Array(tokens) = tokens_array {
-                            // 旧格式，提取 tokens 数组并清理废弃字段
-                            let mut cleaned_tokens = tokens.clone();
-                            for token in cleaned_tokens.iter_mut() {
-                                if let serde_json::Value::Object(ref mut obj) = token {
-                                    obj.remove("balance_color_mode");
-                                }
-                            }
-                            Ok(serde_json::to_string_pretty(&cleaned_tokens)
-                                .map_err(|e| format!("Failed to serialize tokens: {}", e))?)
-                        } else {
-                            Ok("[]".to_string())
-                        }
-                    } else {
-                        // 如果是单个对象格式，包装成数组并清理废弃字段
-                        let mut obj = value.as_object().unwrap().clone();
-                        obj.remove("balance_color_mode");
-                        let array = serde_json::Value::Array(vec![serde_json::Value::Object(obj)]);
-                        Ok(serde_json::to_string_pretty(&array)
-                            .map_err(|e| format!("Failed to serialize tokens: {}", e))?)
-                    }
-                }
-                _ => {
-                    // 其他格式，返回空数组
-                    Ok("[]".to_string())
-                }
-            }
-        }
-        Err(_) => {
-            // 如果 JSON 解析失败，可能是其他格式的旧数据，返回空数组
-            Ok("[]".to_string())
+/// 书签跟随有效数据目录；该目录还没有书签文件时从默认目录复制过来
+fn bookmark_manager(app: &tauri::AppHandle, state: &AppState) -> Result<BookmarkManager, String> {
+    let data_dir = get_effective_data_dir(app, state)?;
+    let storage_path = data_dir.join("bookmarks.json");
+
+    if !storage_path.exists() {
+        let default_path = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data directory: {}", e))?
+            .join("bookmarks.json");
+        if default_path.exists() && default_path != storage_path {
+            fs::create_dir_all(&data_dir)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+            fs::copy(&default_path, &storage_path)
+                .map_err(|e| format!("Failed to migrate bookmarks file: {}", e))?;
+            log::info!("书签文件已迁移到有效目录: {:?}", storage_path);
         }
     }
-}
-
 
+    BookmarkManager::new(&data_dir)
+        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))
+}
 
 // Bookmark management commands
 #[tauri::command]
@@ -748,9 +1314,9 @@ async fn add_bookmark(
     description: Option<String>,
     category: String,
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let bookmark_manager = BookmarkManager::new(&app)
-        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))?;
+    let bookmark_manager = bookmark_manager(&app, &state)?;
 
     bookmark_manager.add_bookmark(name, url, description, category)
         .map_err(|e| format!("Failed to add bookmark: {}", e))
@@ -763,9 +1329,9 @@ async fn update_bookmark(
     url: String,
     description: Option<String>,
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<bool, String> {
-    let bookmark_manager = BookmarkManager::new(&app)
-        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))?;
+    let bookmark_manager = bookmark_manager(&app, &state)?;
 
     bookmark_manager.update_bookmark(&id, name, url, description)
         .map_err(|e| format!("Failed to update bookmark: {}", e))
@@ -775,9 +1341,9 @@ async fn update_bookmark(
 async fn delete_bookmark(
     id: String,
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<bool, String> {
-    let bookmark_manager = BookmarkManager::new(&app)
-        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))?;
+    let bookmark_manager = bookmark_manager(&app, &state)?;
 
     bookmark_manager.remove_bookmark(&id)
         .map_err(|e| format!("Failed to delete bookmark: {}", e))
@@ -787,9 +1353,9 @@ async fn delete_bookmark(
 async fn get_bookmarks(
     category: String,
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<Vec<Bookmark>, String> {
-    let bookmark_manager = BookmarkManager::new(&app)
-        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))?;
+    let bookmark_manager = bookmark_manager(&app, &state)?;
 
     bookmark_manager.get_bookmarks_by_category(&category)
         .map_err(|e| format!("Failed to get bookmarks: {}", e))
@@ -798,9 +1364,9 @@ async fn get_bookmarks(
 #[tauri::command]
 async fn get_all_bookmarks(
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<Vec<Bookmark>, String> {
-    let bookmark_manager = BookmarkManager::new(&app)
-        .map_err(|e| format!("Failed to initialize bookmark manager: {}", e))?;
+    let bookmark_manager = bookmark_manager(&app, &state)?;
 
     bookmark_manager.get_all_bookmarks()
         .map_err(|e| format!("Failed to get all bookmarks: {}", e))
@@ -815,12 +1381,14 @@ async fn get_all_bookmarks(
 #[tauri::command]
 async fn open_internal_browser(
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
     url: String,
-    title: Option<String>
+    title: Option<String>,
+    token_id: Option<String>,
 ) -> Result<String, String> {
-    let window_label = format!("browser_{}", chrono::Utc::now().timestamp());
+    let window_label = format!("browser_{}", uuid::Uuid::new_v4().simple());
 
-    let _window = WebviewWindowBuilder::new(
+    let mut builder = WebviewWindowBuilder::new(
         &app,
         &window_label,
         WebviewUrl::External(url.parse().map_err(|e| format!("Invalid URL: {}", e))?)
@@ -828,81 +1396,275 @@ async fn open_internal_browser(
     .title(&title.unwrap_or_else(|| "内置浏览器".to_string()))
     .inner_size(1000.0, 700.0)
     .center()
-    .resizable(true)
-    .build()
-    .map_err(|e| format!("Failed to create browser window: {}", e))?;
+    .resizable(true);
+
+    // 指定账号时使用该账号独立的数据目录，cookie 不与其他账号共享
+    if let Some(token_id) = token_id.as_deref() {
+        let profile_dir = browser_profiles::profile_dir(&default_data_dir(&app)?, token_id);
+        fs::create_dir_all(&profile_dir)
+            .map_err(|e| format!("Failed to create browser profile: {}", e))?;
+        builder = builder.data_directory(profile_dir);
+
+        #[cfg(target_os = "macos")]
+        {
+            builder = builder.data_store_identifier(browser_profiles::data_store_identifier(token_id));
+        }
+    }
+
+    let window = builder
+        .build()
+        .map_err(|e| format!("Failed to create browser window: {}", e))?;
+
+    state.browser_windows.register(&window_label, token_id.as_deref());
+    let browser_windows = state.browser_windows.clone();
+    let label = window_label.clone();
+    window.on_window_event(move |event| {
+        if let tauri::WindowEvent::Destroyed = event {
+            browser_windows.unregister(&label);
+        }
+    });
 
     Ok(window_label)
 }
 
 #[tauri::command]
-async fn close_window(app: tauri::AppHandle, window_label: String) -> Result<(), String> {
+async fn close_window(app: tauri::AppHandle, state: State<'_, AppState>, window_label: String) -> Result<(), String> {
     if let Some(window) = app.get_webview_window(&window_label) {
         window.close().map_err(|e| format!("Failed to close window: {}", e))?;
     }
+    state.browser_windows.unregister(&window_label);
     Ok(())
 }
 
+// 关闭内置浏览器窗口；指定 token_id 时只关闭使用该账号档案的窗口
+fn close_browser_windows(app: &tauri::AppHandle, tracker: &BrowserWindowTracker, token_id: Option<&str>) {
+    let labels = match token_id {
+        Some(token_id) => tracker.windows_for_profile(&browser_profiles::profile_id(token_id)),
+        None => tracker.labels(),
+    };
+    for label in labels {
+        if let Some(window) = app.get_webview_window(&label) {
+            // 档案目录被 WebView 占用时无法删除，这里直接销毁窗口
+            if let Err(e) = window.destroy() {
+                log::warn!("关闭浏览器窗口 {} 失败: {}", label, e);
+            }
+        }
+        tracker.unregister(&label);
+    }
+}
+
+#[tauri::command]
+async fn list_browser_profiles(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<browser_profiles::BrowserProfileInfo>, String> {
+    browser_profiles::list_profiles(&default_data_dir(&app)?, &state.browser_windows)
+}
+
+// 清除账号浏览器档案中的 cookie 和缓存
+#[tauri::command]
+async fn clear_browser_profile(
+    token_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    close_browser_windows(&app, &state.browser_windows, Some(&token_id));
+    browser_profiles::clear_profile(&default_data_dir(&app)?, &token_id)
+}
+
+#[tauri::command]
+async fn delete_browser_profile(
+    token_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    close_browser_windows(&app, &state.browser_windows, Some(&token_id));
+    browser_profiles::delete_profile(&default_data_dir(&app)?, &token_id)
+}
+
 #[tauri::command]
 async fn open_with_chrome(url: String) -> Result<(), String> {
-    use std::process::Command;
-    use std::env;
-    use std::path::Path;
+    let detected = browsers::detect_browsers(&DetectionEnv::current());
+    let choice = BrowserChoice {
+        browser_id: "chrome".to_string(),
+        profile: None,
+        incognito: false,
+    };
+    let (path, args) = browsers::resolve_launch(&choice, &detected, &[], &url)
+        .map_err(|_| "Chrome not found in default locations".to_string())?;
+    launch_browser(&path, &args)
+}
 
-    // Chrome 的常见安装路径
-    let user_profile_chrome = format!("{}\\AppData\\Local\\Google\\Chrome\\Application\\chrome.exe",
-                                     env::var("USERPROFILE").unwrap_or_default());
-    let chrome_paths = vec![
-        // Windows 路径
-        r"C:\Program Files\Google\Chrome\Application\chrome.exe",
-        r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
-        // 用户目录路径
-        &user_profile_chrome,
-    ];
+#[tauri::command]
+async fn open_with_custom_browser(url: String, browser_path: String) -> Result<(), String> {
+    if !Path::new(&browser_path).exists() {
+        return Err("Browser path does not exist".to_string());
+    }
+    launch_browser(Path::new(&browser_path), &[url])
+}
 
-    // 查找 Chrome
-    let mut chrome_path = None;
-    for path in chrome_paths {
-        if Path::new(path).exists() {
-            chrome_path = Some(path);
-            break;
-        }
+fn launch_browser(path: &Path, args: &[String]) -> Result<(), String> {
+    std::process::Command::new(path)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to launch browser: {}", e))
+}
+
+#[tauri::command]
+async fn detect_browsers() -> Result<Vec<DetectedBrowser>, String> {
+    Ok(browsers::detect_browsers(&DetectionEnv::current()))
+}
+
+#[tauri::command]
+async fn get_browser_settings(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<BrowserSettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).browser_settings)
+}
+
+// 新增或更新自定义浏览器，返回保存后的记录（新增时生成 id）
+#[tauri::command]
+async fn save_custom_browser(
+    browser: CustomBrowser,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CustomBrowser, String> {
+    let browser = browsers::validate_custom_browser(browser)?;
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    let custom_browsers = &mut unified_config.browser_settings.custom_browsers;
+    match custom_browsers.iter_mut().find(|b| b.id == browser.id) {
+        Some(existing) => *existing = browser.clone(),
+        None => custom_browsers.push(browser.clone()),
+    }
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+    Ok(browser)
+}
+
+// 删除自定义浏览器，同时清除引用它的首选设置
+#[tauri::command]
+async fn delete_custom_browser(
+    browser_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    let settings = &mut unified_config.browser_settings;
+    let before = settings.custom_browsers.len();
+    settings.custom_browsers.retain(|b| b.id != browser_id);
+    if settings.custom_browsers.len() == before {
+        return Ok(false);
+    }
+    if settings.default_browser.as_ref().map(|c| c.browser_id.as_str()) == Some(browser_id.as_str()) {
+        settings.default_browser = None;
     }
+    settings.bookmark_browsers.retain(|_, c| c.browser_id != browser_id);
+    settings.portal_browsers.retain(|_, c| c.browser_id != browser_id);
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+    Ok(true)
+}
 
-    match chrome_path {
-        Some(path) => {
-            match Command::new(path)
-                .arg(&url)
-                .spawn() {
-                Ok(_) => Ok(()),
-                Err(e) => Err(format!("Failed to launch Chrome: {}", e))
-            }
+// 设置首选浏览器：target 为 "default"、"bookmark" 或 "token"，choice 为 None 时恢复为上一级设置
+#[tauri::command]
+async fn set_preferred_browser(
+    target: String,
+    target_id: Option<String>,
+    choice: Option<BrowserChoice>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    let settings = &mut unified_config.browser_settings;
+    let map = match target.as_str() {
+        "default" => None,
+        "bookmark" => Some(&mut settings.bookmark_browsers),
+        "token" => Some(&mut settings.portal_browsers),
+        _ => return Err(format!("未知的设置目标: {}", target)),
+    };
+    match map {
+        None => settings.default_browser = choice,
+        Some(map) => {
+            let id = target_id.ok_or("缺少书签或账号 id")?;
+            match choice {
+                Some(choice) => map.insert(id, choice),
+                None => map.remove(&id),
+            };
         }
-        None => Err("Chrome not found in default locations".to_string())
     }
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
 }
 
+// 使用外部浏览器打开链接：显式指定 > 书签首选 > 账号首选 > 默认设置 > 系统默认浏览器
 #[tauri::command]
-async fn open_with_custom_browser(url: String, browser_path: String) -> Result<(), String> {
-    use std::process::Command;
-    use std::path::Path;
+async fn open_with_browser(
+    url: String,
+    choice: Option<BrowserChoice>,
+    bookmark_id: Option<String>,
+    token_id: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let settings = load_unified_config_with_state(&app, &state).browser_settings;
+    let choice = choice.or_else(|| settings.resolve(bookmark_id.as_deref(), token_id.as_deref()).cloned());
+    let Some(choice) = choice else {
+        return open_url(app, url).await;
+    };
 
-    if !Path::new(&browser_path).exists() {
-        return Err("Browser path does not exist".to_string());
+    let detected = browsers::detect_browsers(&DetectionEnv::current());
+    let (path, args) = browsers::resolve_launch(&choice, &detected, &settings.custom_browsers, &url)?;
+    launch_browser(&path, &args)
+}
+
+/// 解析 deep-link 并放入待确认队列，由前端弹出确认框；无效链接只记录原因
+fn route_deep_link(app: &tauri::AppHandle, raw: &str) {
+    let action = match deep_link::parse(raw) {
+        Ok(action) => action,
+        Err(e) => {
+            log::warn!("拒绝 deep-link（{} 字节）: {}", raw.len(), e);
+            return;
+        }
+    };
+
+    let pending = app.state::<AppState>().deep_links.push(action);
+    log::info!("收到 deep-link，等待确认: {}", pending.summary);
+
+    if let Some(main_window) = app.get_webview_window("main") {
+        let _ = main_window.set_focus();
+        let _ = main_window.unminimize();
     }
+    // 前端未就绪时会在启动后通过 list_pending_deep_links 获取
+    let _ = app.emit("deep-link-received", &pending);
+}
 
-    match Command::new(&browser_path)
-        .arg(&url)
-        .spawn() {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Failed to launch browser: {}", e))
+#[tauri::command]
+async fn list_pending_deep_links(state: State<'_, AppState>) -> Result<Vec<PendingDeepLink>, String> {
+    Ok(state.deep_links.list())
+}
+
+// 用户确认 deep-link：添加书签在后端完成，其余操作返回给前端执行
+#[tauri::command]
+async fn confirm_deep_link(
+    id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DeepLinkAction, String> {
+    let action = state.deep_links.take(&id).ok_or("链接已处理或不存在")?;
+
+    if let DeepLinkAction::AddBookmark { name, url, description, category } = &action {
+        bookmark_manager(&app, &state)?
+            .add_bookmark(name.clone(), url.clone(), description.clone(), category.clone())
+            .map_err(|e| format!("Failed to add bookmark: {}", e))?;
+        let _ = app.emit("bookmarks-updated", ());
     }
+
+    log::info!("deep-link 已确认: {}", action.summary());
+    Ok(action)
 }
 
-#[derive(serde::Deserialize)]
-struct FileFilter {
-    name: String,
-    extensions: Vec<String>,
+#[tauri::command]
+async fn reject_deep_link(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.deep_links.take(&id).is_some())
 }
 
 // 窗口控制命令
@@ -1004,96 +1766,25 @@ async fn get_window_size(app: tauri::AppHandle) -> Result<serde_json::Value, Str
 }
 
 #[tauri::command]
-async fn save_file_dialog(default_filename: String) -> Result<Option<String>, String> {
-    use std::process::Command;
-
-    #[cfg(target_os = "windows")]
-    {
-        let output = Command::new("powershell")
-            .args(&[
-                "-Command",
-                &format!(r#"
-                Add-Type -AssemblyName System.Windows.Forms
-                $saveFileDialog = New-Object System.Windows.Forms.SaveFileDialog
-                $saveFileDialog.Filter = "JSON文件 (*.json)|*.json|所有文件 (*.*)|*.*"
-                $saveFileDialog.Title = "导出账号数据"
-                $saveFileDialog.FileName = "{}"
-                $saveFileDialog.DefaultExt = "json"
-                $result = $saveFileDialog.ShowDialog()
-                if ($result -eq [System.Windows.Forms.DialogResult]::OK) {{
-                    Write-Output $saveFileDialog.FileName
-                }}
-                "#, default_filename)
-            ])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if path_str.is_empty() {
-                    Ok(None)
-                } else {
-                    Ok(Some(path_str))
-                }
-            }
-            Err(e) => Err(format!("打开文件保存对话框失败: {}", e))
-        }
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new("osascript")
-            .args(&[
-                "-e",
-                &format!(r#"choose file name with prompt "导出账号数据" default name "{}""#, default_filename)
-            ])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let path = stdout.trim();
-                if path.is_empty() {
-                    Ok(None)
-                } else {
-                    let path = path.replace("alias ", "").replace(":", "/");
-                    let path = if path.starts_with("Macintosh HD") {
-                        path.replace("Macintosh HD", "")
-                    } else {
-                        path
-                    };
-                    Ok(Some(path))
-                }
-            }
-            Err(e) => Err(format!("打开文件保存对话框失败: {}", e))
-        }
-    }
+async fn save_file_dialog(
+    default_filename: String,
+    filters: Option<Vec<FileFilter>>,
+    app: tauri::AppHandle,
+) -> Result<String, DialogError> {
+    let filters = filters.unwrap_or_else(|| vec![
+        FileFilter { name: "JSON文件".to_string(), extensions: vec!["json".to_string()] },
+        FileFilter { name: "所有文件".to_string(), extensions: vec!["*".to_string()] },
+    ]);
+
+    let path = dialogs::save_file(&app, DialogOptions {
+        title: Some("导出账号数据".to_string()),
+        filters,
+        file_name: Some(default_filename),
+        ..Default::default()
+    })
+    .await?;
 
-    #[cfg(target_os = "linux")]
-    {
-        let output = Command::new("zenity")
-            .args(&[
-                "--file-selection",
-                "--save",
-                "--filename",
-                &default_filename,
-                "--title=导出账号数据"
-            ])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let path = stdout.trim();
-                if path.is_empty() {
-                    Ok(None)
-                } else {
-                    Ok(Some(path.to_string()))
-                }
-            }
-            Err(e) => Err(format!("打开文件保存对话框失败: {}", e))
-        }
-    }
+    Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
@@ -1140,69 +1831,32 @@ async fn write_file_content(file_path: String, content: String) -> Result<(), St
     }
 }
 
+// 选择文件，multiple 为 true 时允许多选
 #[tauri::command]
-async fn select_file(_app: tauri::AppHandle, _filters: Vec<FileFilter>) -> Result<Option<String>, String> {
-    use std::process::Command;
-
-    // 使用Windows的文件选择对话框
-    let output = Command::new("powershell")
-        .args(&[
-            "-Command",
-            r#"
-            Add-Type -AssemblyName System.Windows.Forms
-            $openFileDialog = New-Object System.Windows.Forms.OpenFileDialog
-            $openFileDialog.Filter = "可执行文件 (*.exe)|*.exe|所有文件 (*.*)|*.*"
-            $openFileDialog.Title = "选择浏览器可执行文件"
-            $openFileDialog.InitialDirectory = "C:\Program Files"
-            $result = $openFileDialog.ShowDialog()
-            if ($result -eq [System.Windows.Forms.DialogResult]::OK) {
-                Write-Output $openFileDialog.FileName
-            }
-            "#
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let path = stdout.trim();
-            if path.is_empty() {
-                Ok(None)
-            } else {
-                Ok(Some(path.to_string()))
-            }
-        }
-        Err(e) => {
-            // 如果PowerShell失败，返回常见浏览器路径
-            let common_browsers = vec![
-                r"C:\Program Files\Mozilla Firefox\firefox.exe",
-                r"C:\Program Files (x86)\Mozilla Firefox\firefox.exe",
-                r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
-                r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
-            ];
-
-            for browser_path in common_browsers {
-                if std::path::Path::new(browser_path).exists() {
-                    return Ok(Some(browser_path.to_string()));
-                }
-            }
-
-            Err(format!("Failed to open file dialog: {}", e))
-        }
-    }
+async fn select_file(
+    filters: Vec<FileFilter>,
+    title: Option<String>,
+    multiple: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, DialogError> {
+    let paths = dialogs::pick_files(&app, DialogOptions {
+        title,
+        filters,
+        ..Default::default()
+    }, multiple.unwrap_or(false))
+    .await?;
+
+    Ok(paths.iter().map(|path| path.to_string_lossy().to_string()).collect())
 }
 
 #[tauri::command]
-async fn get_customer_info(token: String) -> Result<String, String> {
-    let url = format!("https://portal.withorb.com/api/v1/customer_from_link?token={}", token);
+async fn get_customer_info(token: String, state: State<'_, AppState>) -> Result<String, String> {
+    let api = state.augment_api();
+    let url = api.endpoints().portal_url(&format!("/api/v1/customer_from_link?token={}", token));
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .header("Accept-Charset", "utf-8")
@@ -1243,16 +1897,13 @@ async fn get_customer_info(token: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn get_subscriptions_from_link(token: String) -> Result<String, String> {
-    let url = format!("https://portal.withorb.com/api/v1/subscriptions_from_link?token={}", token);
+async fn get_subscriptions_from_link(token: String, state: State<'_, AppState>) -> Result<String, String> {
+    let api = state.augment_api();
+    let url = api.endpoints().portal_url(&format!("/api/v1/subscriptions_from_link?token={}", token));
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .header("Accept-Charset", "utf-8")
@@ -1295,17 +1946,14 @@ async fn get_subscriptions_from_link(token: String) -> Result<String, String> {
 
 
 #[tauri::command]
-async fn get_ledger_summary(customer_id: String, pricing_unit_id: String, token: String) -> Result<String, String> {
-    let url = format!("https://portal.withorb.com/api/v1/customers/{}/ledger_summary?pricing_unit_id={}&token={}",
-                     customer_id, pricing_unit_id, token);
+async fn get_ledger_summary(customer_id: String, pricing_unit_id: String, token: String, state: State<'_, AppState>) -> Result<String, String> {
+    let api = state.augment_api();
+    let url = api.endpoints().portal_url(&format!("/api/v1/customers/{}/ledger_summary?pricing_unit_id={}&token={}",
+                     customer_id, pricing_unit_id, token));
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .header("Accept-Charset", "utf-8")
@@ -1346,16 +1994,13 @@ async fn get_ledger_summary(customer_id: String, pricing_unit_id: String, token:
 }
 
 #[tauri::command]
-async fn test_api_call() -> Result<String, String> {
-    let url = "https://portal.withorb.com/api/v1/customer_from_link?token=ImRhUHFhU3ZtelpKdEJrUVci.1konHDs_4UqVUJWcxaZpKV4nQik";
+async fn test_api_call(state: State<'_, AppState>) -> Result<String, String> {
+    let api = state.augment_api();
+    let url = api.endpoints().portal_url("/api/v1/customer_from_link?token=ImRhUHFhU3ZtelpKdEJrUVci.1konHDs_4UqVUJWcxaZpKV4nQik");
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = api.client();
     let response = client
-        .get(url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .get(&url)
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "en-US,en;q=0.9")
         .header("Accept-Charset", "utf-8")
@@ -1402,24 +2047,8 @@ async fn test_api_call() -> Result<String, String> {
 // 版本检查相关结构体
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppVersionInfo {
-    pub current_version: String,
-    pub commit_hash: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GitHubRelease {
-    pub tag_name: String,
-    pub name: String,
-    pub body: String,
-    pub published_at: String,
-    pub assets: Vec<GitHubAsset>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GitHubAsset {
-    pub name: String,
-    pub browser_download_url: String,
-    pub size: u64,
+    pub current_version: String,
+    pub commit_hash: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1430,6 +2059,14 @@ pub struct UpdateCheckResult {
     pub release_notes: String,
     pub download_url: String,
     pub asset_name: String,
+    /// 安装包签名文件地址，没有签名的版本只能前往页面手动下载
+    pub signature_url: Option<String>,
+    pub channel: UpdateChannel,
+    pub prerelease: bool,
+    /// 最新版本已被用户跳过
+    pub skipped: bool,
+    /// 是否可以在应用内下载并校验安装包
+    pub can_install: bool,
 }
 
 // 获取当前应用版本信息
@@ -1443,20 +2080,14 @@ async fn get_app_version() -> Result<AppVersionInfo, String> {
 
 // 检查更新
 #[tauri::command]
-async fn check_for_updates() -> Result<UpdateCheckResult, String> {
+async fn check_for_updates(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<UpdateCheckResult, String> {
     let current_version = env!("CARGO_PKG_VERSION");
-    println!("检查更新: 当前版本 {}", current_version);
+    let settings = load_unified_config_with_state(&app, &state).update_settings;
+    log::info!("检查更新: 当前版本 {}，渠道 {:?}", current_version, settings.channel);
 
-    // 获取最新 Release 信息，带重试机制
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .user_agent("ZAugment-UpdateChecker/1.0")
-        .build()
-        .map_err(|e| {
-            let error_msg = format!("Failed to create HTTP client: {}", e);
-            println!("错误: {}", error_msg);
-            error_msg
-        })?;
+    // 获取 Release 列表，带重试机制
+    let http = state.http_client();
+    let client = http.client();
 
     // GitHub token - 从环境变量读取，如果没有则使用空字符串
     let github_token = std::env::var("GITHUB_TOKEN").unwrap_or_default();
@@ -1464,10 +2095,10 @@ async fn check_for_updates() -> Result<UpdateCheckResult, String> {
     // 重试机制：最多重试3次，每次间隔递增
     let mut last_error = String::new();
     for attempt in 1..=3 {
-        println!("正在请求 GitHub API... (尝试 {}/3)", attempt);
+        log::info!("正在请求 GitHub API... (尝试 {}/3)", attempt);
 
         let mut request = client
-            .get("https://api.github.com/repos/Zheng-up/zAugment/releases/latest")
+            .get(updater::RELEASES_URL)
             .header("Accept", "application/vnd.github.v3+json")
             .header("User-Agent", "ZAugment/1.0");
 
@@ -1480,60 +2111,77 @@ async fn check_for_updates() -> Result<UpdateCheckResult, String> {
 
         match response {
             Ok(resp) => {
-                println!("GitHub API 响应状态: {}", resp.status());
+                log::info!("GitHub API 响应状态: {}", resp.status());
 
                 if resp.status().is_success() {
                     // 成功响应，继续处理
-                    let release: GitHubRelease = resp
+                    let releases: Vec<GitHubRelease> = resp
                         .json()
                         .await
                         .map_err(|e| {
                             let error_msg = format!("Failed to parse release JSON: {}", e);
-                            println!("JSON 解析错误: {}", error_msg);
+                            log::warn!("JSON 解析错误: {}", error_msg);
                             error_msg
                         })?;
 
-                    let latest_version = release.tag_name.trim_start_matches('v');
-                    println!("最新版本: {}, 当前版本: {}", latest_version, current_version);
-
-                    let has_update = is_newer_version(latest_version, current_version);
-                    println!("是否有更新: {}", has_update);
-
-                    // 获取适合当前平台的下载链接
-                    let (download_url, asset_name) = get_platform_download_url(&release.assets);
-                    println!("下载链接: {}, 文件名: {}", download_url, asset_name);
+                    let Some((release, latest)) = updater::select_release(&releases, settings.channel) else {
+                        return Err("没有找到可用的发布版本".to_string());
+                    };
+                    let latest_version = latest.to_string();
+                    log::info!("最新版本: {}, 当前版本: {}", latest_version, current_version);
+
+                    let newer = updater::is_newer_version(&latest_version, current_version);
+                    let skipped = newer
+                        && settings.skipped_version.as_deref().and_then(updater::parse_version).as_ref() == Some(&latest);
+                    let has_update = newer && !skipped;
+                    log::info!("是否有更新: {}{}", has_update, if skipped { "（已跳过该版本）" } else { "" });
+
+                    // 获取适合当前平台的下载链接和签名文件
+                    let asset = updater::select_asset(&release.assets);
+                    let signature_url = asset
+                        .and_then(|asset| updater::signature_asset(&release.assets, &asset.name))
+                        .map(|signature| signature.browser_download_url.clone());
+                    let (download_url, asset_name) = asset
+                        .map(|asset| (asset.browser_download_url.clone(), asset.name.clone()))
+                        .unwrap_or_default();
+                    log::info!("下载链接: {}, 文件名: {}", download_url, asset_name);
 
                     let result = UpdateCheckResult {
                         has_update,
                         current_version: current_version.to_string(),
-                        latest_version: latest_version.to_string(),
-                        release_notes: parse_release_notes(&release.body),
+                        latest_version,
+                        release_notes: parse_release_notes(release.body.as_deref().unwrap_or_default()),
+                        can_install: signature_url.is_some() && updater::UPDATER_PUBKEY.is_some(),
                         download_url,
                         asset_name,
+                        signature_url,
+                        channel: settings.channel,
+                        prerelease: release.prerelease || !latest.pre.is_empty(),
+                        skipped,
                     };
 
-                    println!("检查更新完成: {:?}", result);
+                    log::info!("检查更新完成: {:?}", result);
                     return Ok(result);
                 } else if resp.status() == 403 {
                     // API 速率限制
                     last_error = format!("GitHub API 速率限制，请稍后重试 (状态码: {})", resp.status());
-                    println!("API 速率限制: {}", last_error);
+                    log::info!("API 速率限制: {}", last_error);
                 } else {
                     // 其他错误
                     last_error = format!("GitHub API 请求失败，状态码: {}", resp.status());
-                    println!("API 请求失败: {}", last_error);
+                    log::warn!("API 请求失败: {}", last_error);
                 }
             }
             Err(e) => {
                 last_error = format!("网络请求失败: {}", e);
-                println!("网络请求错误: {}", last_error);
+                log::warn!("网络请求错误: {}", last_error);
             }
         }
 
         // 如果不是最后一次尝试，等待后重试
         if attempt < 3 {
             let delay = std::time::Duration::from_secs(attempt * 2); // 2s, 4s
-            println!("等待 {}s 后重试...", delay.as_secs());
+            log::info!("等待 {}s 后重试...", delay.as_secs());
             tokio::time::sleep(delay).await;
         }
     }
@@ -1542,63 +2190,108 @@ async fn check_for_updates() -> Result<UpdateCheckResult, String> {
     Err(format!("检查更新失败: {}", last_error))
 }
 
-// 版本比较函数
-fn is_newer_version(latest: &str, current: &str) -> bool {
-    let latest_parts: Vec<u32> = latest.split('.').filter_map(|s| s.parse().ok()).collect();
-    let current_parts: Vec<u32> = current.split('.').filter_map(|s| s.parse().ok()).collect();
+fn update_download_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get app cache directory: {}", e))?
+        .join("updates");
+    fs::create_dir_all(&dir).map_err(|e| format!("创建更新目录失败: {}", e))?;
+    Ok(dir)
+}
 
-    let max_len = latest_parts.len().max(current_parts.len());
+// 下载安装包并校验签名，校验通过后返回本地路径
+#[tauri::command]
+async fn download_update(
+    version: String,
+    download_url: String,
+    signature_url: String,
+    asset_name: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let public_key = updater::UPDATER_PUBKEY
+        .ok_or_else(|| "当前版本未内置更新签名公钥，请前往发布页面手动下载".to_string())?;
+
+    // 安装包名来自发布页面，只保留文件名部分
+    let file_name = Path::new(&asset_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| "无效的安装包文件名".to_string())?;
+
+    let http = state.http_client();
+    let client = http.client();
+
+    let signature = updater::download_with_progress(client, &signature_url, |_, _| {}).await?;
+    let signature = String::from_utf8(signature).map_err(|e| format!("签名文件无效: {}", e))?;
+
+    let mut last_emitted = 0u64;
+    let data = updater::download_with_progress(client, &download_url, |downloaded, total| {
+        // 每 256KB 或下载完成时通知一次前端
+        if downloaded - last_emitted >= 256 * 1024 || Some(downloaded) == total {
+            last_emitted = downloaded;
+            let _ = app.emit(updater::DOWNLOAD_PROGRESS_EVENT, updater::DownloadProgress {
+                version: version.clone(),
+                downloaded,
+                total,
+            });
+        }
+    })
+    .await?;
 
-    for i in 0..max_len {
-        let latest_part = latest_parts.get(i).unwrap_or(&0);
-        let current_part = current_parts.get(i).unwrap_or(&0);
+    updater::verify_signature(public_key, &data, &signature)?;
 
-        if latest_part > current_part {
-            return true;
-        } else if latest_part < current_part {
-            return false;
-        }
+    let path = update_download_dir(&app)?.join(file_name);
+    storage::write_file_atomic(&path, &data)?;
+    log::info!("更新 {} 已下载并通过签名校验: {}", version, path.display());
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+// 打开已校验的安装包
+#[tauri::command]
+async fn install_update(path: String, app: tauri::AppHandle) -> Result<(), String> {
+    let download_dir = update_download_dir(&app)?;
+    let path = PathBuf::from(path);
+    if path.parent() != Some(download_dir.as_path()) || !path.is_file() {
+        return Err("只能安装已下载并校验的更新包".to_string());
     }
 
-    false
+    use tauri_plugin_opener::OpenerExt;
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("启动安装程序失败: {}", e))
 }
 
-// 获取平台特定的下载链接
-fn get_platform_download_url(assets: &[GitHubAsset]) -> (String, String) {
-    let platform = std::env::consts::OS;
-    let arch = std::env::consts::ARCH;
+#[tauri::command]
+async fn get_update_settings(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<UpdateSettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).update_settings)
+}
 
-    // 根据平台和架构选择合适的资源
-    let preferred_asset = match platform {
-        "windows" => assets.iter().find(|asset|
-            asset.name.contains("windows") && asset.name.ends_with(".exe")
-        ),
-        "macos" => {
-            if arch == "aarch64" {
-                assets.iter().find(|asset|
-                    asset.name.contains("aarch64") && asset.name.ends_with(".dmg")
-                )
-            } else {
-                assets.iter().find(|asset|
-                    asset.name.contains("x64") && asset.name.ends_with(".dmg")
-                )
-            }
-        },
-        "linux" => assets.iter().find(|asset|
-            asset.name.contains("amd64") && asset.name.ends_with(".deb")
-        ).or_else(|| assets.iter().find(|asset|
-            asset.name.contains("x86_64") && asset.name.ends_with(".rpm")
-        )),
-        _ => None,
-    };
+#[tauri::command]
+async fn set_update_channel(
+    channel: UpdateChannel,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.update_settings.channel = channel;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
+}
 
-    if let Some(asset) = preferred_asset {
-        (asset.browser_download_url.clone(), asset.name.clone())
-    } else if let Some(first_asset) = assets.first() {
-        (first_asset.browser_download_url.clone(), first_asset.name.clone())
-    } else {
-        (String::new(), String::new())
-    }
+// 跳过指定版本，传入 None 取消跳过
+#[tauri::command]
+async fn skip_update_version(
+    version: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.update_settings.skipped_version = version;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
 }
 
 // 解析 Release Notes
@@ -1607,7 +2300,7 @@ fn parse_release_notes(body: &str) -> String {
         return "暂无更新说明".to_string();
     }
 
-    println!("原始 release notes: {}", body);
+    log::debug!("原始 release notes: {}", body);
 
     // 只提取更新内容部分，不包含发布标题
     let lines: Vec<&str> = body.lines().collect();
@@ -1641,7 +2334,7 @@ fn parse_release_notes(body: &str) -> String {
     }
 
     let final_result = result.join("\n");
-    println!("解析后的 release notes: {}", final_result);
+    log::debug!("解析后的 release notes: {}", final_result);
 
     if result.is_empty() {
         return "暂无更新说明".to_string();
@@ -1691,35 +2384,34 @@ async fn open_data_folder(
     Ok(())
 }
 
+/// 写入编辑器配置（原文件先备份），返回配置文件路径
+fn apply_editor_credentials(provider_id: &str, credentials: EditorCredentials) -> Result<String, String> {
+    let provider = editor_integrations::provider(provider_id)?;
+    let home = editor_integrations::home_dir()?;
+    let (path, backup) = editor_integrations::apply(provider.as_ref(), &home, &credentials)?;
+    log::info!("已写入 {} 配置: {}（备份: {}）", provider.name(), path.display(), backup.path);
+    Ok(path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 async fn create_jetbrains_token_file(
     editor_type: String,
     token_data: String,
 ) -> Result<String, String> {
-    use std::fs;
-    use std::env;
-    use std::path::PathBuf;
-
-    // 获取用户主目录
-    let home_dir = env::var("USERPROFILE")
-        .or_else(|_| env::var("HOME"))
-        .map_err(|_| "Failed to get home directory".to_string())?;
-
-    let augment_dir = PathBuf::from(&home_dir).join(".augment");
-
-    // 确保 .augment 目录存在
-    fs::create_dir_all(&augment_dir)
-        .map_err(|e| format!("Failed to create .augment directory: {}", e))?;
-
-    // 创建文件路径
-    let file_name = format!("{}_token.json", editor_type);
-    let file_path = augment_dir.join(&file_name);
-
-    // 写入文件
-    fs::write(&file_path, token_data)
-        .map_err(|e| format!("Failed to write token file: {}", e))?;
+    // 前端传入 { url, token, timestamp, ide }，由 provider 重新生成文件内容
+    let value: serde_json::Value = serde_json::from_str(&token_data)
+        .map_err(|e| format!("Invalid token data: {}", e))?;
+    let field = |name: &str| {
+        value.get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or(format!("Invalid token data: missing {}", name))
+    };
 
-    Ok(file_path.to_string_lossy().to_string())
+    apply_editor_credentials(&editor_type, EditorCredentials {
+        access_token: field("token")?,
+        tenant_url: field("url")?,
+    })
 }
 
 #[tauri::command]
@@ -1727,67 +2419,7 @@ async fn configure_vim_augment(
     access_token: String,
     tenant_url: String,
 ) -> Result<String, String> {
-    use std::fs;
-    use std::env;
-    use std::path::PathBuf;
-
-    // 获取用户主目录
-    let home_dir = env::var("USERPROFILE")
-        .or_else(|_| env::var("HOME"))
-        .map_err(|_| "Failed to get home directory".to_string())?;
-
-    // 配置文件路径 (所有系统统一使用 .local/share/vim-augment)
-    let vim_augment_dir = PathBuf::from(&home_dir).join(".local").join("share").join("vim-augment");
-
-    // 确保目录存在
-    fs::create_dir_all(&vim_augment_dir)
-        .map_err(|e| format!("Failed to create vim-augment directory: {}", e))?;
-
-    let secrets_path = vim_augment_dir.join("secrets.json");
-
-    // 如果文件已存在，先删除
-    if secrets_path.exists() {
-        fs::remove_file(&secrets_path)
-            .map_err(|e| format!("Failed to remove existing secrets.json: {}", e))?;
-    }
-
-    // 构建内层 JSON 对象
-    let inner_json = serde_json::json!({
-        "accessToken": access_token,
-        "tenantURL": tenant_url,
-        "scopes": ["email"]
-    });
-
-    // 将内层 JSON 转换为字符串（这会自动转义引号）
-    let inner_json_str = serde_json::to_string(&inner_json)
-        .map_err(|e| format!("Failed to serialize inner JSON: {}", e))?;
-
-    // 构建外层 JSON 对象
-    let outer_json = serde_json::json!({
-        "augment.sessions": inner_json_str
-    });
-
-    // 将外层 JSON 转换为格式化的字符串
-    let json_content = serde_json::to_string_pretty(&outer_json)
-        .map_err(|e| format!("Failed to serialize outer JSON: {}", e))?;
-
-    // 写入文件
-    fs::write(&secrets_path, json_content)
-        .map_err(|e| format!("Failed to write secrets.json: {}", e))?;
-
-    // 在 Unix 系统上设置文件权限
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&secrets_path)
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?
-            .permissions();
-        perms.set_mode(0o600); // 仅所有者可读写
-        fs::set_permissions(&secrets_path, perms)
-            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
-    }
-
-    Ok(secrets_path.to_string_lossy().to_string())
+    apply_editor_credentials("vim", EditorCredentials { access_token, tenant_url })
 }
 
 #[tauri::command]
@@ -1795,58 +2427,29 @@ async fn configure_auggie(
     access_token: String,
     tenant_url: String,
 ) -> Result<String, String> {
-    use std::fs;
-    use std::env;
-    use std::path::PathBuf;
-
-    // 获取用户主目录
-    let home_dir = env::var("USERPROFILE")
-        .or_else(|_| env::var("HOME"))
-        .map_err(|_| "Failed to get home directory".to_string())?;
-
-    // 确定 .augment 目录路径
-    let augment_dir = PathBuf::from(&home_dir).join(".augment");
-
-    // 确保目录存在
-    fs::create_dir_all(&augment_dir)
-        .map_err(|e| format!("Failed to create .augment directory: {}", e))?;
-
-    let session_path = augment_dir.join("session.json");
-
-    // 如果文件已存在，先删除
-    if session_path.exists() {
-        fs::remove_file(&session_path)
-            .map_err(|e| format!("Failed to remove existing session.json: {}", e))?;
-    }
-
-    // 构建 JSON 对象
-    let session_json = serde_json::json!({
-        "accessToken": access_token,
-        "tenantURL": tenant_url,
-        "scopes": ["read", "write"]
-    });
-
-    // 将 JSON 转换为格式化的字符串
-    let json_content = serde_json::to_string_pretty(&session_json)
-        .map_err(|e| format!("Failed to serialize session JSON: {}", e))?;
+    apply_editor_credentials("auggie", EditorCredentials { access_token, tenant_url })
+}
 
-    // 写入文件
-    fs::write(&session_path, json_content)
-        .map_err(|e| format!("Failed to write session.json: {}", e))?;
+// 各编辑器当前的配置，以及对应的已保存账号
+#[tauri::command]
+async fn get_editor_integrations(state: State<'_, AppState>) -> Result<Vec<IntegrationStatus>, String> {
+    let tokens = state.token_storage()?
+        .load_tokens()
+        .await
+        .map_err(|e| format!("Failed to load tokens: {}", e))?;
+    let home = editor_integrations::home_dir()?;
 
-    // 在 Unix 系统上设置文件权限
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&session_path)
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?
-            .permissions();
-        perms.set_mode(0o600); // 仅所有者可读写
-        fs::set_permissions(&session_path, perms)
-            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
-    }
+    Ok(editor_integrations::all_providers()
+        .iter()
+        .map(|provider| editor_integrations::read_current(provider.as_ref(), &home, &tokens))
+        .collect())
+}
 
-    Ok(session_path.to_string_lossy().to_string())
+// 把编辑器配置还原为最近一次写入前的状态
+#[tauri::command]
+async fn revert_editor_integration(provider_id: String) -> Result<IntegrationBackup, String> {
+    let provider = editor_integrations::provider(&provider_id)?;
+    editor_integrations::revert(provider.as_ref(), &editor_integrations::home_dir()?)
 }
 
 #[tauri::command]
@@ -1854,7 +2457,7 @@ async fn open_editor_with_protocol(
     app: tauri::AppHandle,
     protocol_url: String,
 ) -> Result<(), String> {
-    println!("Opening editor with protocol URL: {}", protocol_url);
+    log::info!("Opening editor with protocol URL: {}", protocol_url);
 
     use tauri_plugin_opener::OpenerExt;
     app.opener().open_url(protocol_url, None::<&str>)
@@ -1919,7 +2522,7 @@ async fn outlook_check_account_status(
         let manager = state.outlook_manager.lock().unwrap();
         manager.get_credentials(&email)?
     };
-    let check_manager = OutlookManager::new();
+    let check_manager = OutlookManager::with_http_client(state.http_client());
     check_manager.check_account_status_with_credentials(&credentials).await
 }
 
@@ -1936,7 +2539,7 @@ async fn outlook_get_emails(
         let manager = state.outlook_manager.lock().unwrap();
         manager.get_credentials(&email)?
     };
-    let fetch_manager = OutlookManager::new();
+    let fetch_manager = OutlookManager::with_http_client(state.http_client());
     fetch_manager.get_emails_with_credentials(&credentials, &folder, page, page_size).await
 }
 
@@ -1951,7 +2554,7 @@ async fn outlook_get_email_details(
         let manager = state.outlook_manager.lock().unwrap();
         manager.get_credentials(&email)?
     };
-    let details_manager = OutlookManager::new();
+    let details_manager = OutlookManager::with_http_client(state.http_client());
     details_manager.get_email_details_with_credentials(&credentials, &message_id).await
 }
 
@@ -1970,12 +2573,9 @@ async fn delete_token(
     token_id: String,
     state: State<'_, AppState>,
 ) -> Result<bool, String> {
-    let storage_manager = {
-        let guard = state.storage_manager.lock().unwrap();
-        guard.clone().ok_or("Storage manager not initialized")?
-    };
-
-    storage_manager.delete_token(&token_id).await
+    state.token_storage()?
+        .delete_token(&token_id)
+        .await
         .map_err(|e| format!("Delete failed: {}", e))
 }
 
@@ -2002,7 +2602,7 @@ fn save_custom_data_dir(app: &tauri::AppHandle, path: &str) -> Result<(), String
     Ok(())
 }
 
-fn get_effective_data_dir(app: &tauri::AppHandle, state: &State<'_, AppState>) -> Result<PathBuf, String> {
+fn get_effective_data_dir(app: &tauri::AppHandle, state: &AppState) -> Result<PathBuf, String> {
     // 首先检查内存中的自定义目录
     {
         let custom_dir_guard = state.custom_data_dir.lock().unwrap();
@@ -2031,173 +2631,23 @@ fn get_effective_data_dir(app: &tauri::AppHandle, state: &State<'_, AppState>) -
     fs::create_dir_all(&default_dir)
         .map_err(|e| format!("Failed to create app data directory: {}", e))?;
     
-    println!("使用AppData目录: {}", default_dir.display());
+    log::info!("使用AppData目录: {}", default_dir.display());
     Ok(default_dir)
 }
 
 // 设置页面相关命令
 #[tauri::command]
-async fn select_data_directory(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, String> {
-    use std::process::Command;
-
-    #[cfg(target_os = "windows")]
-    {
-        // 使用改进的 PowerShell 命令，处理中文路径
-        let script = r#"
-        chcp 65001 > $null
-        Add-Type -AssemblyName System.Windows.Forms
-        $folderBrowser = New-Object System.Windows.Forms.FolderBrowserDialog
-        $folderBrowser.Description = "选择数据存储目录"
-        $folderBrowser.ShowNewFolderButton = $true
-        $documentsPath = [Environment]::GetFolderPath([Environment+SpecialFolder]::MyDocuments)
-        $folderBrowser.SelectedPath = $documentsPath
-        $result = $folderBrowser.ShowDialog()
-        if ($result -eq [System.Windows.Forms.DialogResult]::OK) {
-            $utf8 = [System.Text.Encoding]::UTF8
-            $bytes = $utf8.GetBytes($folderBrowser.SelectedPath)
-            [System.Console]::WriteLine([System.Text.Encoding]::UTF8.GetString($bytes))
-        }
-        "#;
-
-        let output = Command::new("powershell")
-            .args(&["-NoProfile", "-ExecutionPolicy", "Bypass", "-Command", script])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                
-                if path_str.is_empty() {
-                    return Err("用户取消了目录选择".to_string());
-                }
-
-                // 验证路径是否包含中文字符
-                if path_str.chars().any(|c| {
-                    // 检查是否包含中文字符（Unicode范围）
-                    ('\u{4e00}'..='\u{9fff}').contains(&c) || // 中日韩统一表意文字
-                    ('\u{3400}'..='\u{4dbf}').contains(&c) || // 中日韩扩展A
-                    ('\u{20000}'..='\u{2a6df}').contains(&c) || // 中日韩扩展B
-                    ('\u{f900}'..='\u{faff}').contains(&c) || // 中日韩兼容表意文字
-                    ('\u{2f800}'..='\u{2fa1f}').contains(&c) // 中日韩兼容表意文字补充
-                }) {
-                    return Err("为避免编码问题，请选择不包含中文字符的文件夹路径。建议使用英文路径，如：D:\\TokenData".to_string());
-                }
-
-                // 验证路径是否存在
-                let path_buf = PathBuf::from(&path_str);
-                if !path_buf.exists() {
-                    return Err(format!("选择的路径不存在: {}", path_str));
-                }
-
-                if !path_buf.is_dir() {
-                    return Err("选择的路径不是一个目录".to_string());
-                }
-
-                // 保存选择的目录到配置文件
-                save_custom_data_dir(&app, &path_str)?;
-                
-                // 更新内存中的配置
-                *state.custom_data_dir.lock().unwrap() = Some(path_buf);
-                
-                Ok(path_str)
-            }
-            Err(e) => {
-                Err(format!("打开文件夹选择对话框失败: {}", e))
-            }
-        }
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new("osascript")
-            .args(&[
-                "-e",
-                r#"choose folder with prompt "选择数据存储目录""#
-            ])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let path = stdout.trim();
-                if path.is_empty() {
-                    Err("用户取消了目录选择".to_string())
-                } else {
-                    let path = path.replace("alias ", "").replace(":", "/");
-                    let path = if path.starts_with("Macintosh HD") {
-                        path.replace("Macintosh HD", "")
-                    } else {
-                        path
-                    };
-                    
-                    // 验证路径是否包含中文字符
-                    if path.chars().any(|c| {
-                        ('\u{4e00}'..='\u{9fff}').contains(&c) || 
-                        ('\u{3400}'..='\u{4dbf}').contains(&c) || 
-                        ('\u{20000}'..='\u{2a6df}').contains(&c) || 
-                        ('\u{f900}'..='\u{faff}').contains(&c) || 
-                        ('\u{2f800}'..='\u{2fa1f}').contains(&c)
-                    }) {
-                        return Err("为避免编码问题，请选择不包含中文字符的文件夹路径。建议使用英文路径。".to_string());
-                    }
-                    
-                    save_custom_data_dir(&app, &path)?;
-                    *state.custom_data_dir.lock().unwrap() = Some(PathBuf::from(&path));
-                    
-                    Ok(path)
-                }
-            }
-            Err(e) => {
-                Err(format!("打开文件夹选择对话框失败: {}", e))
-            }
-        }
-    }
+async fn select_data_directory(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, DialogError> {
+    let path = dialogs::pick_folder(&app, DialogOptions {
+        title: Some("选择数据存储目录".to_string()),
+        directory: dirs::document_dir(),
+        ..Default::default()
+    })
+    .await?;
 
-    #[cfg(target_os = "linux")]
-    {
-        let output = Command::new("zenity")
-            .args(&[
-                "--file-selection",
-                "--directory",
-                "--title=选择数据存储目录"
-            ])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let path = stdout.trim();
-                if path.is_empty() {
-                    Err("用户取消了目录选择".to_string())
-                } else {
-                    // 验证路径是否包含中文字符
-                    if path.chars().any(|c| {
-                        ('\u{4e00}'..='\u{9fff}').contains(&c) || 
-                        ('\u{3400}'..='\u{4dbf}').contains(&c) || 
-                        ('\u{20000}'..='\u{2a6df}').contains(&c) || 
-                        ('\u{f900}'..='\u{faff}').contains(&c) || 
-                        ('\u{2f800}'..='\u{2fa1f}').contains(&c)
-                    }) {
-                        return Err("为避免编码问题，请选择不包含中文字符的文件夹路径。建议使用英文路径。".to_string());
-                    }
-                    
-                    save_custom_data_dir(&app, path)?;
-                    *state.custom_data_dir.lock().unwrap() = Some(PathBuf::from(path));
-                    Ok(path.to_string())
-                }
-            }
-            Err(_) => {
-                let home_dir = env::var("HOME")
-                    .map_err(|_| "无法获取用户主目录".to_string())?;
-                let documents_dir = format!("{}/Documents", home_dir);
-                
-                save_custom_data_dir(&app, &documents_dir)?;
-                *state.custom_data_dir.lock().unwrap() = Some(PathBuf::from(&documents_dir));
-                
-                Ok(documents_dir)
-            }
-        }
-    }
+    // 迁移现有数据到新目录并切换，旧目录中的副本等待确认后删除
+    relocate_data_dir(&app, &state, path.clone()).await?;
+    Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
@@ -2217,8 +2667,38 @@ async fn get_data_directory(app: tauri::AppHandle, state: State<'_, AppState>) -
 
 #[tauri::command]
 async fn reset_data_directory(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
-    // 删除配置文件中的自定义目录设置
-    let config_path = get_config_path(&app)?;
+    let default_app_data_dir = default_data_dir(&app)?;
+    let current_dir = get_effective_data_dir(&app, &state)?;
+
+    if data_relocation::same_dir(&current_dir, &default_app_data_dir) {
+        // 已经在使用默认目录，只需清除残留的自定义目录设置
+        clear_custom_data_dir(&app)?;
+        *state.custom_data_dir.lock().unwrap() = None;
+        return Ok(());
+    }
+
+    // 把数据迁回默认目录
+    relocate_data_dir(&app, &state, default_app_data_dir).await?;
+    Ok(())
+}
+
+// 默认应用数据目录（保存自定义目录设置和迁移记录）
+fn default_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let default_app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get default app data directory: {}", e))?;
+
+    // 确保默认目录存在
+    fs::create_dir_all(&default_app_data_dir)
+        .map_err(|e| format!("Failed to create default app local data directory: {}", e))?;
+
+    Ok(default_app_data_dir)
+}
+
+// 删除默认配置文件中的自定义目录设置
+fn clear_custom_data_dir(app: &tauri::AppHandle) -> Result<(), String> {
+    let config_path = get_config_path(app)?;
     if config_path.exists() {
         // 读取现有配置
         let content = fs::read_to_string(&config_path).unwrap_or_default();
@@ -2233,23 +2713,126 @@ async fn reset_data_directory(app: tauri::AppHandle, state: State<'_, AppState>)
             }
         }
     }
-    
-    // 清除内存中的自定义目录设置
-    *state.custom_data_dir.lock().unwrap() = None;
-    
-    // 获取默认应用数据目录
-    let default_app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get default app data directory: {}", e))?;
-
-    // 确保默认目录存在
-    fs::create_dir_all(&default_app_data_dir)
-        .map_err(|e| format!("Failed to create default app local data directory: {}", e))?;
+    Ok(())
+}
 
+// 切换有效数据目录：默认目录清除自定义设置，其他目录写入默认配置
+fn switch_data_dir(app: &tauri::AppHandle, state: &AppState, dir: &Path) -> Result<(), String> {
+    if data_relocation::same_dir(dir, &default_data_dir(app)?) {
+        clear_custom_data_dir(app)?;
+        *state.custom_data_dir.lock().unwrap() = None;
+    } else {
+        save_custom_data_dir(app, &dir.to_string_lossy())?;
+        *state.custom_data_dir.lock().unwrap() = Some(dir.to_path_buf());
+    }
     Ok(())
 }
 
+fn relocation_record_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(default_data_dir(app)?.join(data_relocation::RELOCATION_FILE))
+}
+
+// 迁移数据到新目录并切换；失败时撤销已复制的文件并保持原目录
+async fn relocate_data_dir(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    target: PathBuf,
+) -> Result<data_relocation::PendingRelocation, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let record_path = relocation_record_path(app)?;
+    if data_relocation::load_pending(&record_path)?.is_some() {
+        return Err("上一次数据目录迁移尚未确认，请先确认或撤销".to_string());
+    }
+
+    let source = get_effective_data_dir(app, state)?;
+    let default_dir = default_data_dir(app)?;
+
+    let mut options = data_relocation::RelocationOptions::default();
+    if data_relocation::same_dir(&source, &default_dir) {
+        // 默认目录的 config.json 记录自定义目录位置，必须保留
+        options.retain_in_source.push("config.json".to_string());
+    }
+    if data_relocation::same_dir(&target, &default_dir) {
+        // 迁回默认目录时覆盖其中残留的旧文件（确认前保留备份）
+        options.replace_in_target = data_relocation::collect_entries(&source)?;
+    }
+
+    let pending = data_relocation::relocate(&source, &target, &options)?;
+
+    let switched = data_relocation::save_pending(&record_path, Some(&pending))
+        .and_then(|_| switch_data_dir(app, state, &target));
+    if let Err(e) = switched {
+        log::error!("切换数据目录失败，撤销迁移: {}", e);
+        if let Err(revert_err) = data_relocation::revert(&pending) {
+            log::error!("撤销迁移失败: {}", revert_err);
+        }
+        let _ = switch_data_dir(app, state, &source);
+        let _ = data_relocation::save_pending(&record_path, None);
+        return Err(format!("切换数据目录失败: {}", e));
+    }
+
+    initialize_storage_manager(app, state)
+        .await
+        .map_err(|e| format!("初始化新数据目录失败: {}", e))?;
+
+    log::info!("数据目录已切换到 {}，等待确认删除旧目录中的副本", target.display());
+    Ok(pending)
+}
+
+#[tauri::command]
+async fn relocate_data_directory(
+    target_path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<data_relocation::PendingRelocation, String> {
+    relocate_data_dir(&app, &state, PathBuf::from(target_path)).await
+}
+
+#[tauri::command]
+async fn get_pending_data_relocation(
+    app: tauri::AppHandle,
+) -> Result<Option<data_relocation::PendingRelocation>, String> {
+    data_relocation::load_pending(&relocation_record_path(&app)?)
+}
+
+// 确认迁移：删除旧目录中的副本，返回删除的文件数
+#[tauri::command]
+async fn confirm_data_relocation(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<usize, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let record_path = relocation_record_path(&app)?;
+    let pending = data_relocation::load_pending(&record_path)?
+        .ok_or_else(|| "没有待确认的数据目录迁移".to_string())?;
+
+    let removed = data_relocation::finalize(&pending)?;
+    data_relocation::save_pending(&record_path, None)?;
+
+    log::info!("数据目录迁移已确认，删除旧目录中的 {} 个文件", removed);
+    Ok(removed)
+}
+
+// 撤销迁移：把新目录中的改动带回原目录并切换回去，返回原目录
+#[tauri::command]
+async fn cancel_data_relocation(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let record_path = relocation_record_path(&app)?;
+    let pending = data_relocation::load_pending(&record_path)?
+        .ok_or_else(|| "没有待确认的数据目录迁移".to_string())?;
+
+    data_relocation::revert(&pending)?;
+    switch_data_dir(&app, &state, &pending.source)?;
+    data_relocation::save_pending(&record_path, None)?;
+
+    initialize_storage_manager(&app, &state)
+        .await
+        .map_err(|e| format!("初始化数据目录失败: {}", e))?;
+
+    log::info!("数据目录迁移已撤销，恢复使用 {}", pending.source.display());
+    Ok(pending.source.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 async fn open_data_directory(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
     // 获取有效的数据目录（优先使用自定义目录）
@@ -2287,6 +2870,70 @@ async fn open_data_directory(app: tauri::AppHandle, state: State<'_, AppState>)
     Ok(())
 }
 
+// 导出诊断包：脱敏后的日志、系统信息和配置摘要，返回生成的 zip 路径
+#[tauri::command]
+async fn export_diagnostic_bundle(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    let data_dir = get_effective_data_dir(&app, &state)?;
+
+    let system_info = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "commit_hash": option_env!("GIT_HASH"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "data_dir": data_dir.display().to_string(),
+        "log_dir": logging::log_dir().map(|dir| dir.display().to_string()),
+        "generated_at": chrono::Utc::now(),
+    });
+
+    // 配置摘要只保留结构性信息，WebDAV 只记录是否已配置
+    let config = load_unified_config_with_state(&app, &state);
+    let config_summary = serde_json::json!({
+        "version": config.version,
+        "last_updated": config.last_updated,
+        "app_settings": config.app_settings,
+        "custom_data_dir": config.custom_data_dir,
+        "webdav_configured": config.webdav_config.is_some(),
+        "encrypted_credential_sync": config.encrypted_credential_sync,
+        "ui_settings": config.ui_settings,
+        "status_thresholds": config.status_thresholds,
+    });
+
+    let token_count = match state.token_storage() {
+        Ok(storage) => storage.load_tokens().await.map(|tokens| tokens.len()).ok(),
+        Err(_) => None,
+    };
+    let storage_summary = serde_json::json!({
+        "token_count": token_count,
+        "files": backups::BACKUP_FILES
+            .iter()
+            .map(|name| {
+                let path = data_dir.join(name);
+                serde_json::json!({
+                    "name": name,
+                    "exists": path.exists(),
+                    "size": fs::metadata(&path).map(|m| m.len()).ok(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+
+    let extra_entries = vec![
+        ("system.json".to_string(), serde_json::to_string_pretty(&system_info).unwrap_or_default()),
+        ("config.json".to_string(), serde_json::to_string_pretty(&config_summary).unwrap_or_default()),
+        ("storage.json".to_string(), serde_json::to_string_pretty(&storage_summary).unwrap_or_default()),
+    ];
+
+    let log_dir = logging::log_dir();
+    let bundle = logging::export_diagnostic_bundle(
+        log_dir.as_deref(),
+        &extra_entries,
+        &data_dir.join("diagnostics"),
+    )?;
+
+    log::info!("诊断包已导出: {}", bundle.display());
+    Ok(bundle.to_string_lossy().to_string())
+}
+
 
 
 
@@ -2298,11 +2945,62 @@ async fn initialize_storage_manager(
     app: &tauri::AppHandle,
     state: &State<'_, AppState>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // 创建本地存储
-    let local_storage = Arc::new(LocalFileStorage::new(app)?);
+    // 创建本地存储（使用有效数据目录，切换配置档案后指向新目录）
+    let data_dir = get_effective_data_dir(app, state)?;
+    fs::create_dir_all(&data_dir)?;
+
+    // 日志文件跟随有效数据目录
+    if let Err(e) = logging::set_log_dir(&data_dir.join(logging::LOG_DIR_NAME)) {
+        log::warn!("无法设置日志目录: {}", e);
+    }
+
+    // 绑定 token 存储到当前有效数据目录，并清理超过保留期的回收站条目
+    let retention_days = load_unified_config_with_state(app, state).history_settings.trash_retention_days;
+    match state.token_storage()?.purge_trash(retention_days).await {
+        Ok(0) => {}
+        Ok(purged) => log::info!("已清理回收站中 {} 个过期账号", purged),
+        Err(e) => log::warn!("清理回收站失败: {}", e),
+    }
+
+    // 从当前配置加载WebDAV配置
+    let unified_config = load_unified_config_with_state(app, state);
+
+    // 自动备份跟随有效数据目录；数据文件损坏时通知前端，由用户选择是否从备份恢复
+    backups::configure(&data_dir, unified_config.backup_settings.clone());
+    let issues = backups::check_integrity(&data_dir);
+    if !issues.is_empty() {
+        log::error!("数据文件校验失败: {:?}", issues);
+        let _ = app.emit("data-integrity-failed", &issues);
+    }
+
+    let http_client = build_http_client(&unified_config.http_client, &state.password_manager, None)
+        .unwrap_or_else(|e| {
+            log::warn!("网络设置无效，使用默认 HTTP 客户端: {}", e);
+            HttpClient::default()
+        });
+    *state.http_client.lock().unwrap() = http_client;
+    *state.check_limiter.lock().unwrap() = CheckLimiter::new(unified_config.batch_check_options.max_concurrency);
+    // 同步目标或数据目录变化时，旧的同步实例属于之前的目标，直接丢弃；
+    // 只是重新读取配置（例如 config.json 被外部修改）时保留同步状态
+    {
+        let mut webdav_config = state.webdav_config.lock().unwrap();
+        let mut cloud_sync = state.cloud_sync.lock().unwrap();
+        let same_target = match (webdav_config.as_ref(), unified_config.webdav_config.as_ref()) {
+            (Some(old), Some(new)) => old.same_target(new),
+            _ => false,
+        };
+        if !same_target || cloud_sync.as_ref().is_some_and(|sync| !sync.local_file_path().starts_with(&data_dir)) {
+            *cloud_sync = None;
+        }
+        *webdav_config = unified_config.webdav_config;
+    }
 
-    // 更新应用状态
-    *state.storage_manager.lock().unwrap() = Some(local_storage);
+    // app session 缓存跟随有效数据目录，切换目录时丢弃旧缓存；密钥链不可用时只缓存在内存中
+    if let Err(e) = configure_app_session_cache(app, state, &unified_config.app_session_cache) {
+        log::warn!("无法启用 app session 缓存持久化: {}", e);
+        // 保持持久化设置不变，只是本次不读写缓存文件，避免删除已保存的缓存
+        state.app_session_cache.lock().unwrap().attach(&data_dir, unified_config.app_session_cache.clone(), None);
+    }
 
     Ok(())
 }
@@ -2320,7 +3018,7 @@ async fn configure_webdav(
     config.remote_path = "ZAugment/tokens.json".to_string();
     
     // 测试连接
-    let client = webdav::WebDAVClient::new(config.clone())
+    let client = webdav::WebDAVClient::new(config.clone(), &state.http_client())
         .map_err(|e| format!("创建WebDAV客户端失败: {}", e))?;
     
     client.test_connection().await
@@ -2333,9 +3031,9 @@ async fn configure_webdav(
     // 保存安全配置到内存
     *state.webdav_config.lock().unwrap() = Some(secure_config.clone());
     
-    // 保存配置到统一配置文件（使用原始WebDAVConfig包含明文密码）
+    // 保存配置到统一配置文件（只保存密钥链引用，不含明文密码）
     let mut unified_config = load_unified_config_with_state(&app, &state);
-    unified_config.webdav_config = Some(config.clone());
+    unified_config.webdav_config = Some(secure_config);
     unified_config.last_updated = chrono::Utc::now();
     save_unified_config_with_state(&app, &unified_config, &state)?;
     
@@ -2352,7 +3050,7 @@ async fn test_webdav_connection(state: State<'_, AppState>) -> Result<bool, Stri
             .map_err(|e| format!("获取配置失败: {}", e))?
     };
     
-    let client = webdav::WebDAVClient::new(config)
+    let client = webdav::WebDAVClient::new(config, &state.http_client())
         .map_err(|e| format!("创建WebDAV客户端失败: {}", e))?;
     
     client.test_connection().await
@@ -2382,17 +3080,10 @@ async fn sync_to_cloud(state: State<'_, AppState>, app: tauri::AppHandle) -> Res
         .map_err(|e| format!("写入临时文件失败: {}", e))?;
     
     // 创建同步实例（使用新的文件名）
-    let mut sync_config = config.clone();
-    // 将远程路径改为用户数据包文件
-    // 确保远程路径正确：将 tokens.json 替换为 user_data.json
-    if sync_config.remote_path.contains("tokens.json") {
-        sync_config.remote_path = sync_config.remote_path.replace("tokens.json", "user_data.json");
-    } else {
-        // 如果路径中没有 tokens.json，使用默认路径
-        sync_config.remote_path = "/ZAugment/user_data.json".to_string();
-    }
+    // 远程文件使用用户数据包 user_data.json
+    let sync_config = config.user_data_config();
     
-    let mut sync = CloudSync::new(sync_config, temp_path.clone())
+    let mut sync = CloudSync::new(sync_config, temp_path.clone(), &state.http_client())
         .map_err(|e| format!("创建同步实例失败: {}", e))?;
     
     // 执行同步
@@ -2434,20 +3125,14 @@ async fn force_upload_to_cloud(state: State<'_, AppState>, app: tauri::AppHandle
         .map_err(|e| format!("写入临时文件失败: {}", e))?;
     
     // 创建同步实例（使用新的文件名）
-    let mut sync_config = config.clone();
-    // 确保远程路径正确：将 tokens.json 替换为 user_data.json
-    if sync_config.remote_path.contains("tokens.json") {
-        sync_config.remote_path = sync_config.remote_path.replace("tokens.json", "user_data.json");
-    } else {
-        // 如果路径中没有 tokens.json，使用默认路径
-        sync_config.remote_path = "/ZAugment/user_data.json".to_string();
-    }
+    // 远程文件使用用户数据包 user_data.json
+    let sync_config = config.user_data_config();
     
     // 调试信息：显示上传路径（在移动之前）
-    println!("上传路径: {}", sync_config.remote_path);
-    println!("上传URL: {}", sync_config.get_remote_file_url().unwrap_or_else(|_| "URL构建失败".to_string()));
+    log::info!("上传路径: {}", sync_config.remote_path);
+    log::debug!("上传URL: {}", logging::redact(&sync_config.get_remote_file_url().unwrap_or_else(|_| "URL构建失败".to_string())));
     
-    let mut sync = CloudSync::new(sync_config, temp_path.clone())
+    let mut sync = CloudSync::new(sync_config, temp_path.clone(), &state.http_client())
         .map_err(|e| format!("创建同步实例失败: {}", e))?;
     
     let result = sync.force_upload().await
@@ -2479,20 +3164,14 @@ async fn force_download_from_cloud(state: State<'_, AppState>, app: tauri::AppHa
     let temp_path = data_dir.join("user_data_temp.json");
     
     // 创建同步实例（使用新的文件名）
-    let mut sync_config = config.clone();
-    // 确保远程路径正确：将 tokens.json 替换为 user_data.json
-    if sync_config.remote_path.contains("tokens.json") {
-        sync_config.remote_path = sync_config.remote_path.replace("tokens.json", "user_data.json");
-    } else {
-        // 如果路径中没有 tokens.json，使用默认路径
-        sync_config.remote_path = "/ZAugment/user_data.json".to_string();
-    }
+    // 远程文件使用用户数据包 user_data.json
+    let sync_config = config.user_data_config();
     
     // 调试信息：显示使用的路径（在移动之前）
-    println!("下载路径: {}", sync_config.remote_path);
-    println!("完整URL: {}", sync_config.get_remote_file_url().unwrap_or_else(|_| "URL构建失败".to_string()));
+    log::info!("下载路径: {}", sync_config.remote_path);
+    log::debug!("完整URL: {}", logging::redact(&sync_config.get_remote_file_url().unwrap_or_else(|_| "URL构建失败".to_string())));
     
-    let mut sync = CloudSync::new(sync_config, temp_path.clone())
+    let mut sync = CloudSync::new(sync_config, temp_path.clone(), &state.http_client())
         .map_err(|e| format!("创建同步实例失败: {}", e))?;
     
     // 强制下载到临时文件
@@ -2531,9 +3210,50 @@ async fn force_download_from_cloud(state: State<'_, AppState>, app: tauri::AppHa
 
 #[tauri::command]
 async fn get_webdav_config(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<Option<WebDAVConfig>, String> {
-    // 从统一配置文件获取WebDAV配置（包含明文密码）
+    // 从统一配置文件获取WebDAV配置，密码从系统密钥链读取
     let unified_config = load_unified_config_with_state(&app, &state);
-    Ok(unified_config.webdav_config)
+    Ok(unified_config.webdav_config.map(|secure_config| {
+        secure_config.to_config(&state.password_manager).unwrap_or_else(|e| {
+            log::warn!("警告：无法从密钥链读取WebDAV密码: {}", e);
+            WebDAVConfig {
+                server_url: secure_config.server_url.clone(),
+                username: secure_config.username.clone(),
+                password: String::new(),
+                enabled: secure_config.enabled,
+                auto_sync: secure_config.auto_sync,
+                sync_interval_minutes: secure_config.sync_interval_minutes,
+                remote_path: secure_config.remote_path.clone(),
+            }
+        })
+    }))
+}
+
+// 启用或关闭加密凭据同步：启用时云端数据包携带使用同步口令加密的WebDAV密码
+#[tauri::command]
+async fn set_encrypted_credential_sync(
+    enabled: bool,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    if enabled {
+        let passphrase = passphrase
+            .filter(|p| !p.is_empty())
+            .ok_or("启用加密凭据同步需要设置同步口令")?;
+        state.password_manager.store_sync_passphrase(&passphrase)?;
+    } else {
+        let _ = state.password_manager.delete_sync_passphrase();
+    }
+
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    unified_config.encrypted_credential_sync = enabled;
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)
+}
+
+#[tauri::command]
+async fn get_encrypted_credential_sync(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<bool, String> {
+    Ok(load_unified_config_with_state(&app, &state).encrypted_credential_sync)
 }
 
 #[tauri::command]
@@ -2549,7 +3269,7 @@ async fn check_sync_conflicts(state: State<'_, AppState>, app: tauri::AppHandle)
     let data_dir = get_effective_data_dir(&app, &state)?;
     let tokens_file = data_dir.join("tokens.json");
     
-    let cloud_sync = CloudSync::new(config, tokens_file)
+    let cloud_sync = CloudSync::new(config, tokens_file, &state.http_client())
         .map_err(|e| format!("创建CloudSync失败: {}", e))?;
     
     cloud_sync.get_conflict_info().await
@@ -2581,7 +3301,7 @@ async fn resolve_sync_conflict(
     let data_dir = get_effective_data_dir(&app, &state)?;
     let tokens_file = data_dir.join("tokens.json");
     
-    let mut cloud_sync = CloudSync::new(config, tokens_file)
+    let mut cloud_sync = CloudSync::new(config, tokens_file, &state.http_client())
         .map_err(|e| format!("创建CloudSync失败: {}", e))?;
     
     let result = cloud_sync.resolve_conflict(conflict_resolution).await
@@ -2590,54 +3310,6 @@ async fn resolve_sync_conflict(
     Ok(format!("冲突解决完成: {} (传输 {} 字节)", result.message, result.bytes_transferred))
 }
 
-// 保存安全WebDAV配置到文件（不包含密码）
-fn save_secure_webdav_config(app: &tauri::AppHandle, config: &SecureWebDAVConfig) -> Result<(), String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-    
-    fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
-    
-    let config_path = app_data_dir.join("webdav_config.json");
-    let config_json = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    
-    fs::write(&config_path, config_json)
-        .map_err(|e| format!("Failed to write config file: {}", e))?;
-    
-    Ok(())
-}
-
-// 从文件加载安全WebDAV配置
-fn load_secure_webdav_config(app: &tauri::AppHandle) -> Option<SecureWebDAVConfig> {
-    let app_data_dir = app.path().app_data_dir().ok()?;
-    let config_path = app_data_dir.join("webdav_config.json");
-    
-    if !config_path.exists() {
-        return None;
-    }
-    
-    let content = fs::read_to_string(&config_path).ok()?;
-    serde_json::from_str(&content).ok()
-}
-
-// 兼容性函数：保存WebDAV配置到文件
-fn save_webdav_config(app: &tauri::AppHandle, config: &WebDAVConfig) -> Result<(), String> {
-    let password_manager = PasswordManager::new();
-    let secure_config = SecureWebDAVConfig::from_config(config, &password_manager)
-        .map_err(|e| format!("Failed to create secure config: {}", e))?;
-    save_secure_webdav_config(app, &secure_config)
-}
-
-// 兼容性函数：从文件加载WebDAV配置
-fn load_webdav_config(app: &tauri::AppHandle) -> Option<WebDAVConfig> {
-    let secure_config = load_secure_webdav_config(app)?;
-    let password_manager = PasswordManager::new();
-    secure_config.to_config(&password_manager).ok()
-}
-
 // 统一的应用配置结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnifiedAppConfig {
@@ -2650,14 +3322,50 @@ pub struct UnifiedAppConfig {
     // 数据目录设置
     pub custom_data_dir: Option<String>,
 
-    // WebDAV配置（直接存储明文密码）
-    pub webdav_config: Option<WebDAVConfig>,
+    // WebDAV配置（密码保存在系统密钥链中，这里只记录凭据引用）
+    pub webdav_config: Option<SecureWebDAVConfig>,
+
+    // 是否在云端数据包中携带使用同步口令加密的WebDAV密码
+    #[serde(default)]
+    pub encrypted_credential_sync: bool,
 
     // UI设置
     pub ui_settings: UiSettings,
 
     // 账号状态阈值配置
     pub status_thresholds: Option<StatusThresholds>,
+
+    // 网络设置（代理、CA 证书、超时），代理密码保存在系统密钥链中
+    #[serde(default)]
+    pub http_client: HttpClientSettings,
+
+    // 更新设置（发布渠道、跳过的版本）
+    #[serde(default)]
+    pub update_settings: UpdateSettings,
+
+    // 外部浏览器设置（自定义浏览器、书签/账号的首选浏览器）
+    #[serde(default)]
+    pub browser_settings: BrowserSettings,
+
+    // 账号变更记录与回收站设置
+    #[serde(default)]
+    pub history_settings: HistorySettings,
+
+    // 本地自动备份设置
+    #[serde(default)]
+    pub backup_settings: BackupSettings,
+
+    // 后台定时检测账号状态
+    #[serde(default)]
+    pub status_check_schedule: StatusCheckSchedule,
+
+    // 批量检测账号状态的并发数与超时
+    #[serde(default)]
+    pub batch_check_options: BatchCheckOptions,
+
+    // App session 缓存的有效期、数量上限与持久化
+    #[serde(default)]
+    pub app_session_cache: AppSessionCacheSettings,
 }
 
 // 应用基础设置
@@ -2701,13 +3409,22 @@ impl Default for UiSettings {
 impl Default for UnifiedAppConfig {
     fn default() -> Self {
         Self {
-            version: "1.0.0".to_string(),
+            version: migrations::CONFIG_VERSION.to_string(),
             last_updated: chrono::Utc::now(),
             app_settings: AppSettings::default(),
             custom_data_dir: None,
             webdav_config: None,
+            encrypted_credential_sync: false,
             ui_settings: UiSettings::default(),
             status_thresholds: Some(StatusThresholds::default()),
+            http_client: HttpClientSettings::default(),
+            update_settings: UpdateSettings::default(),
+            browser_settings: BrowserSettings::default(),
+            history_settings: HistorySettings::default(),
+            backup_settings: BackupSettings::default(),
+            status_check_schedule: StatusCheckSchedule::default(),
+            batch_check_options: BatchCheckOptions::default(),
+            app_session_cache: AppSessionCacheSettings::default(),
         }
     }
 }
@@ -2715,38 +3432,7 @@ impl Default for UnifiedAppConfig {
 // 保存统一配置到文件（注意：需要AppState来获取有效数据目录）
 fn save_unified_config_with_state(app: &tauri::AppHandle, config: &UnifiedAppConfig, state: &State<'_, AppState>) -> Result<(), String> {
     let data_dir = get_effective_data_dir(app, state)?;
-
-    fs::create_dir_all(&data_dir)
-        .map_err(|e| format!("Failed to create data directory: {}", e))?;
-
-    let config_path = data_dir.join("config.json");
-    let config_json = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize unified config: {}", e))?;
-
-    // 使用同一目录下的唯一临时文件名，确保在同一文件系统上
-    let temp_path = data_dir.join(format!("config.{}.tmp", uuid::Uuid::new_v4()));
-
-    // 写入临时文件
-    fs::write(&temp_path, &config_json)
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
-
-    // 尝试原子性重命名，如果失败则使用复制+删除的方式
-    match fs::rename(&temp_path, &config_path) {
-        Ok(_) => Ok(()),
-        Err(rename_err) => {
-            // 如果 rename 失败（可能是跨文件系统），尝试复制+删除
-            match fs::copy(&temp_path, &config_path) {
-                Ok(_) => {
-                    let _ = fs::remove_file(&temp_path);
-                    Ok(())
-                }
-                Err(copy_err) => {
-                    let _ = fs::remove_file(&temp_path);
-                    Err(format!("Failed to save config (rename: {}, copy: {})", rename_err, copy_err))
-                }
-            }
-        }
-    }
+    write_unified_config(&data_dir, config)
 }
 
 // 保存统一配置到默认应用目录（用于初始化或没有state时）
@@ -2755,37 +3441,41 @@ fn save_unified_config(app: &tauri::AppHandle, config: &UnifiedAppConfig) -> Res
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    write_unified_config(&data_dir, config)
+}
 
-    fs::create_dir_all(&data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+// 将统一配置原子性写入指定目录的 config.json
+fn write_unified_config(data_dir: &std::path::Path, config: &UnifiedAppConfig) -> Result<(), String> {
+    fs::create_dir_all(data_dir)
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
 
     let config_path = data_dir.join("config.json");
+
+    // 不覆盖由更新版本应用写入的配置
+    migrations::ensure_writable(migrations::DataKind::Config, &config_path)
+        .map_err(|e| e.to_string())?;
+
     let config_json = serde_json::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize unified config: {}", e))?;
 
-    // 使用同一目录下的唯一临时文件名，确保在同一文件系统上
-    let temp_path = data_dir.join(format!("config.{}.tmp", uuid::Uuid::new_v4()));
-
-    // 写入临时文件
-    fs::write(&temp_path, &config_json)
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    backups::before_write(&config_path);
+    data_watcher::record_write(&config_path, config_json.as_bytes());
+    storage::write_file_atomic(&config_path, config_json.as_bytes())
+        .map_err(|e| format!("Failed to save config: {}", e))
+}
 
-    // 尝试原子性重命名，如果失败则使用复制+删除的方式
-    match fs::rename(&temp_path, &config_path) {
-        Ok(_) => Ok(()),
-        Err(rename_err) => {
-            // 如果 rename 失败（可能是跨文件系统），尝试复制+删除
-            match fs::copy(&temp_path, &config_path) {
-                Ok(_) => {
-                    let _ = fs::remove_file(&temp_path);
-                    Ok(())
-                }
-                Err(copy_err) => {
-                    let _ = fs::remove_file(&temp_path);
-                    Err(format!("Failed to save config (rename: {}, copy: {})", rename_err, copy_err))
-                }
-            }
-        }
+// 读取 config.json 并迁移到当前版本；文件不存在时返回 None
+fn read_unified_config(config_path: &std::path::Path) -> Result<Option<UnifiedAppConfig>, String> {
+    // 旧配置中的明文WebDAV密码在迁移时移入系统密钥链
+    let password_manager = PasswordManager::new();
+    let ctx = migrations::MigrationContext::with_secrets(&password_manager);
+
+    match migrations::migrate_file(migrations::DataKind::Config, config_path, &ctx) {
+        Ok(Some(value)) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| format!("配置文件格式错误: {}", e)),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e.to_string()),
     }
 }
 
@@ -2796,40 +3486,34 @@ fn load_unified_config(app: &tauri::AppHandle) -> UnifiedAppConfig {
         Ok(dir) => {
             // 确保目录存在
             if let Err(e) = fs::create_dir_all(&dir) {
-                println!("警告：无法创建应用数据目录 {}: {}", dir.display(), e);
+                log::warn!("警告：无法创建应用数据目录 {}: {}", dir.display(), e);
                 return UnifiedAppConfig::default();
             }
             dir
         },
         Err(e) => {
-            println!("警告：无法获取应用数据目录: {}", e);
+            log::warn!("警告：无法获取应用数据目录: {}", e);
             return UnifiedAppConfig::default();
         },
     };
     
     let config_path = app_data_dir.join("config.json");
     
-    // 如果统一配置文件存在，直接加载
+    // 如果统一配置文件存在，加载并按需迁移
     if config_path.exists() {
-        match fs::read_to_string(&config_path) {
-            Ok(content) => {
-                if let Ok(config) = serde_json::from_str::<UnifiedAppConfig>(&content) {
-                    return config;
-                }
-                // 如果解析失败，尝试从旧格式迁移
-                if let Ok(old_config) = serde_json::from_str::<serde_json::Value>(&content) {
-                    return migrate_from_old_config(app, old_config);
-                }
-                println!("警告：配置文件格式错误，使用默认配置");
-            },
+        match read_unified_config(&config_path) {
+            Ok(Some(config)) => return config,
+            Ok(None) => {},
             Err(e) => {
-                println!("警告：无法读取配置文件 {}: {}", config_path.display(), e);
+                // 版本过新或格式错误时使用默认配置，保存时会拒绝覆盖更新版本的文件
+                log::warn!("警告：无法加载配置文件 {}: {}，使用默认配置", config_path.display(), e);
+                return UnifiedAppConfig::default();
             }
         }
     }
     
     // 如果配置文件不存在，尝试从旧文件迁移
-    migrate_from_old_files(app)
+    migrate_from_old_files(app, &app_data_dir)
 }
 
 // 从有效数据目录加载统一配置
@@ -2838,7 +3522,7 @@ fn load_unified_config_with_state(app: &tauri::AppHandle, state: &State<'_, AppS
         Ok(dir) => {
             // 确保目录存在
             if let Err(e) = fs::create_dir_all(&dir) {
-                println!("警告：无法创建有效数据目录 {}: {}", dir.display(), e);
+                log::warn!("警告：无法创建有效数据目录 {}: {}", dir.display(), e);
                 return load_unified_config(app); // 回退到默认加载方式
             }
             dir
@@ -2848,92 +3532,48 @@ fn load_unified_config_with_state(app: &tauri::AppHandle, state: &State<'_, AppS
     
     let config_path = data_dir.join("config.json");
     
-    // 如果统一配置文件存在，直接加载
-    if config_path.exists() {
-        match fs::read_to_string(&config_path) {
-            Ok(content) => {
-                if let Ok(config) = serde_json::from_str::<UnifiedAppConfig>(&content) {
-                    return config;
-                }
-                println!("警告：自定义目录配置文件格式错误");
-            },
-            Err(e) => {
-                println!("警告：无法读取自定义目录配置文件 {}: {}", config_path.display(), e);
-            }
+    // 如果统一配置文件存在，加载并按需迁移
+    match read_unified_config(&config_path) {
+        Ok(Some(config)) => return config,
+        Ok(None) => {},
+        Err(e) => {
+            log::warn!("警告：无法加载自定义目录配置文件 {}: {}", config_path.display(), e);
         }
     }
     
     // 避免递归调用，直接返回默认配置
-    println!("自定义目录中没有配置文件，使用默认配置");
+    log::info!("自定义目录中没有配置文件，使用默认配置");
     UnifiedAppConfig::default()
 }
 
-// 从旧文件迁移配置
-fn migrate_from_old_files(app: &tauri::AppHandle) -> UnifiedAppConfig {
-    let mut config = UnifiedAppConfig::default();
-    let data_dir = match app.path().app_data_dir() {
-        Ok(dir) => dir,
-        Err(_) => return config,
-    };
-    
-    // 注释掉递归调用，避免栈溢出
-    // if let Some(custom_dir) = load_custom_data_dir(app) {
-    //     config.custom_data_dir = Some(custom_dir.to_string_lossy().to_string());
-    // }
-    
-    // 迁移WebDAV配置（从SecureWebDAVConfig转换为WebDAV Config）
-    if let Some(secure_webdav_config) = load_secure_webdav_config(app) {
-        // 尝试从keyring获取密码
-        let password_manager = PasswordManager::new();
-        if let Ok(webdav_config) = secure_webdav_config.to_config(&password_manager) {
-            config.webdav_config = Some(webdav_config);
+// 从统一配置出现之前的旧文件（app_settings.json / webdav_config.json）迁移配置
+fn migrate_from_old_files(app: &tauri::AppHandle, data_dir: &std::path::Path) -> UnifiedAppConfig {
+    let password_manager = PasswordManager::new();
+    let ctx = migrations::MigrationContext::with_secrets(&password_manager);
+
+    let (value, consumed) = match migrations::import_legacy_config_files(data_dir, &ctx) {
+        Ok(result) => result,
+        Err(e) => {
+            log::warn!("警告：迁移旧配置文件失败: {}", e);
+            return UnifiedAppConfig::default();
         }
-    }
-    
-    // 迁移旧的应用设置（如果存在）
-    let old_app_settings_path = data_dir.join("app_settings.json");
-    if old_app_settings_path.exists() {
-        if let Ok(content) = fs::read_to_string(&old_app_settings_path) {
-            if let Ok(old_settings) = serde_json::from_str::<AppSettings>(&content) {
-                config.app_settings = old_settings;
-            }
+    };
+
+    let config = match serde_json::from_value::<UnifiedAppConfig>(value) {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("警告：旧配置文件格式错误: {}", e);
+            return UnifiedAppConfig::default();
         }
-    }
-    
-    // 保存迁移后的配置（如果有自定义数据目录，也要保存到那里）
-    let _ = save_unified_config(app, &config);
-    
-    // 如果存在自定义数据目录，也要保存到那里
-    if let Some(ref custom_dir_str) = config.custom_data_dir {
-        let custom_dir = PathBuf::from(custom_dir_str);
-        if custom_dir.exists() {
-            let custom_config_path = custom_dir.join("config.json");
-            if let Ok(config_json) = serde_json::to_string_pretty(&config) {
-                let _ = fs::write(custom_config_path, config_json);
-                println!("已将迁移的配置保存到自定义目录: {}", custom_dir_str);
-            }
+    };
+
+    // 新配置保存成功后再清理旧文件
+    if save_unified_config(app, &config).is_ok() {
+        for path in consumed {
+            let _ = fs::remove_file(path);
         }
     }
-    
-    // 清理旧文件
-    let _ = fs::remove_file(old_app_settings_path);
-    let _ = fs::remove_file(data_dir.join("webdav_config.json"));
-    
-    config
-}
 
-// 从旧配置格式迁移
-fn migrate_from_old_config(app: &tauri::AppHandle, old_config: serde_json::Value) -> UnifiedAppConfig {
-    let mut config = UnifiedAppConfig::default();
-    
-    // 迁移自定义数据目录
-    if let Some(custom_dir) = old_config.get("custom_data_dir").and_then(|v| v.as_str()) {
-        config.custom_data_dir = Some(custom_dir.to_string());
-    }
-    
-    // 保存新的配置格式
-    let _ = save_unified_config(app, &config);
-    
     config
 }
 
@@ -3031,6 +3671,244 @@ async fn get_default_status_thresholds() -> Result<StatusThresholds, String> {
     Ok(StatusThresholds::default())
 }
 
+// ================================
+// 网络设置命令
+// ================================
+
+// 按网络设置构建共享 HTTP 客户端，未提供代理密码时从密钥链读取
+fn build_http_client(
+    settings: &HttpClientSettings,
+    password_manager: &PasswordManager,
+    proxy_password: Option<String>,
+) -> Result<HttpClient, String> {
+    let proxy_password = proxy_password.or_else(|| {
+        settings.proxy.username.as_deref()
+            .filter(|username| !username.is_empty())
+            .and_then(|username| password_manager.get_proxy_password(username).ok())
+    });
+    HttpClient::new(settings.clone(), proxy_password)
+}
+
+#[tauri::command]
+async fn get_http_client_settings(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<HttpClientSettings, String> {
+    Ok(load_unified_config_with_state(&app, &state).http_client)
+}
+
+#[tauri::command]
+async fn save_http_client_settings(
+    settings: HttpClientSettings,
+    proxy_password: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    // 先构建客户端，配置无效时不保存
+    let client = build_http_client(&settings, &state.password_manager, proxy_password.clone())?;
+
+    let mut unified_config = load_unified_config_with_state(&app, &state);
+    let previous_username = unified_config.http_client.proxy.username.clone();
+    unified_config.http_client = settings.clone();
+    unified_config.last_updated = chrono::Utc::now();
+    save_unified_config_with_state(&app, &unified_config, &state)?;
+
+    // 代理密码只保存在密钥链中，用户名变更时清理旧密码
+    let username = settings.proxy.username.filter(|u| !u.is_empty());
+    if let Some(previous) = previous_username.filter(|u| !u.is_empty()) {
+        if username.as_deref() != Some(previous.as_str()) {
+            let _ = state.password_manager.delete_proxy_password(&previous);
+        }
+    }
+    if let (Some(username), Some(password)) = (username, proxy_password) {
+        state.password_manager.store_proxy_password(&username, &password)?;
+    }
+
+    *state.http_client.lock().unwrap() = client;
+    log::info!("网络设置已更新");
+    Ok(())
+}
+
+// ================================
+// 配置档案管理命令
+// ================================
+
+fn get_profiles_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    Ok(app_data_dir.join(profiles::PROFILES_FILE))
+}
+
+// 加载档案列表，并用当前生效的设置刷新当前档案
+fn load_profile_store(app: &tauri::AppHandle, state: &State<'_, AppState>) -> Result<profiles::ProfileStore, String> {
+    let bootstrap_config = load_unified_config(app);
+    let effective_config = load_unified_config_with_state(app, state);
+
+    let mut store = match profiles::ProfileStore::load(&get_profiles_path(app)?)? {
+        Some(store) => store,
+        // 首次使用时，把现有设置作为默认档案
+        None => profiles::ProfileStore::new(profiles::ConfigProfile::new(
+            profiles::DEFAULT_PROFILE_NAME.to_string(),
+            None,
+            None,
+            None,
+        )),
+    };
+
+    store.update_active(
+        bootstrap_config.custom_data_dir,
+        effective_config.webdav_config,
+        effective_config.status_thresholds,
+    );
+    Ok(store)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileListResponse {
+    pub active_profile: String,
+    pub profiles: Vec<profiles::ConfigProfile>,
+}
+
+#[tauri::command]
+async fn list_profiles(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<ProfileListResponse, String> {
+    let store = load_profile_store(&app, &state)?;
+    Ok(ProfileListResponse {
+        active_profile: store.active_profile,
+        profiles: store.profiles,
+    })
+}
+
+#[tauri::command]
+async fn create_profile(
+    name: String,
+    custom_data_dir: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<profiles::ConfigProfile, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let mut store = load_profile_store(&app, &state)?;
+    let profile = store.create(&name, custom_data_dir.filter(|dir| !dir.trim().is_empty()))?.clone();
+    store.save(&get_profiles_path(&app)?)?;
+
+    Ok(profile)
+}
+
+#[tauri::command]
+async fn clone_profile(
+    source: String,
+    name: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<profiles::ConfigProfile, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let mut store = load_profile_store(&app, &state)?;
+    let profile = store.clone_profile(&source, &name)?.clone();
+    store.save(&get_profiles_path(&app)?)?;
+
+    Ok(profile)
+}
+
+#[tauri::command]
+async fn delete_profile(name: String, app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let mut store = load_profile_store(&app, &state)?;
+    store.delete(&name)?;
+    store.save(&get_profiles_path(&app)?)
+}
+
+#[tauri::command]
+async fn switch_profile(name: String, app: tauri::AppHandle, state: State<'_, AppState>) -> Result<profiles::ConfigProfile, String> {
+    let _switch_guard = state.profile_switch_lock.lock().await;
+
+    let mut store = load_profile_store(&app, &state)?;
+    if store.active_profile == name {
+        return Ok(store.active().clone());
+    }
+
+    let target = store.get(&name)
+        .cloned()
+        .ok_or_else(|| format!("配置档案不存在: {}", name))?;
+
+    // 先确认目标数据目录可用，再修改任何状态
+    if let Some(ref dir) = target.custom_data_dir {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("无法访问配置档案的数据目录 {}: {}", dir, e))?;
+    }
+
+    // 保存当前档案（已包含最新设置），切换失败时可用于回滚
+    let profiles_path = get_profiles_path(&app)?;
+    store.save(&profiles_path)?;
+
+    let mut bootstrap_config = load_unified_config(&app);
+    let previous_data_dir = bootstrap_config.custom_data_dir.clone();
+    let previous_memory_dir = state.custom_data_dir.lock().unwrap().clone();
+
+    // 1. 切换数据目录
+    bootstrap_config.custom_data_dir = target.custom_data_dir.clone();
+    bootstrap_config.last_updated = chrono::Utc::now();
+    save_unified_config(&app, &bootstrap_config)?;
+    *state.custom_data_dir.lock().unwrap() = target.custom_data_dir.as_ref().map(PathBuf::from);
+
+    // 2-4 任一步失败都回滚到原档案：恢复新数据目录中原有的配置、数据目录设置，并按原目录重新初始化
+    let mut target_config_backup: Option<(PathBuf, Option<Vec<u8>>)> = None;
+    let switch_result = async {
+        // 2. 在新数据目录的配置中写入档案的WebDAV和阈值设置
+        let target_config_path = get_effective_data_dir(&app, &state)?.join("config.json");
+        target_config_backup = Some((target_config_path.clone(), fs::read(&target_config_path).ok()));
+        let mut effective_config = load_unified_config_with_state(&app, &state);
+        effective_config.custom_data_dir = target.custom_data_dir.clone();
+        effective_config.webdav_config = target.webdav_config.clone();
+        effective_config.status_thresholds = target.status_thresholds.clone();
+        effective_config.last_updated = chrono::Utc::now();
+        save_unified_config_with_state(&app, &effective_config, &state)?;
+
+        // 3. 重新初始化存储管理器、WebDAV配置和同步实例
+        initialize_storage_manager(&app, &state).await
+            .map_err(|e| format!("重新初始化存储失败: {}", e))?;
+
+        // 4. 记录当前档案
+        let profile = store.set_active(&name)?.clone();
+        store.save(&profiles_path)?;
+        Ok::<_, String>(profile)
+    }
+    .await;
+
+    let profile = match switch_result {
+        Ok(profile) => profile,
+        Err(e) => {
+            if let Some((path, content)) = target_config_backup {
+                let restored = match content {
+                    Some(content) => {
+                        data_watcher::record_write(&path, &content);
+                        storage::write_file_atomic(&path, &content)
+                    }
+                    None => fs::remove_file(&path).map_err(|e| e.to_string()),
+                };
+                if let Err(e) = restored {
+                    log::warn!("回滚时恢复配置 {:?} 失败: {}", path, e);
+                }
+            }
+            bootstrap_config.custom_data_dir = previous_data_dir;
+            if let Err(e) = save_unified_config(&app, &bootstrap_config) {
+                log::error!("回滚数据目录设置失败: {}", e);
+            }
+            *state.custom_data_dir.lock().unwrap() = previous_memory_dir;
+            if let Err(e) = initialize_storage_manager(&app, &state).await {
+                log::error!("回滚后重新初始化存储失败: {}", e);
+            }
+            return Err(format!("切换配置档案失败: {}", e));
+        }
+    };
+
+    log::info!("已切换到配置档案: {}", name);
+    let _ = app.emit("profile-switched", &profile.name);
+    let _ = app.emit("tokens-updated", ());
+
+    Ok(profile)
+}
+
 // ================================
 // 编辑器重置功能相关命令
 // ================================
@@ -3383,7 +4261,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
         use std::process::Command;
 
         for (pid, process_name) in &process_pids {
-            println!("尝试关闭进程: {} (PID: {})", process_name, pid);
+            log::info!("尝试关闭进程: {} (PID: {})", process_name, pid);
 
             // 第一步：使用温和的方式关闭进程
             let output = Command::new("taskkill")
@@ -3397,13 +4275,13 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
                     if result.status.success() {
                         closed_count += 1;
                         process_closed = true;
-                        println!("温和关闭成功: {} (PID: {})", process_name, pid);
+                        log::info!("温和关闭成功: {} (PID: {})", process_name, pid);
                     } else {
-                        println!("温和关闭失败，尝试强制关闭: {} (PID: {})", process_name, pid);
+                        log::warn!("温和关闭失败，尝试强制关闭: {} (PID: {})", process_name, pid);
                     }
                 }
                 Err(e) => {
-                    println!("温和关闭命令执行失败: {}, 尝试强制关闭", e);
+                    log::warn!("温和关闭命令执行失败: {}, 尝试强制关闭", e);
                 }
             }
 
@@ -3418,14 +4296,14 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
                         if force_result.status.success() {
                             closed_count += 1;
                             process_closed = true;
-                            println!("强制关闭成功: {} (PID: {})", process_name, pid);
+                            log::info!("强制关闭成功: {} (PID: {})", process_name, pid);
                         } else {
                             let stderr = String::from_utf8_lossy(&force_result.stderr);
-                            println!("强制关闭失败: {} (PID: {}), 错误: {}", process_name, pid, stderr);
+                            log::warn!("强制关闭失败: {} (PID: {}), 错误: {}", process_name, pid, stderr);
                         }
                     }
                     Err(e) => {
-                        println!("强制关闭命令执行失败: {} (PID: {}), 错误: {}", process_name, pid, e);
+                        log::warn!("强制关闭命令执行失败: {} (PID: {}), 错误: {}", process_name, pid, e);
                     }
                 }
             }
@@ -3443,7 +4321,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
         use std::process::Command;
 
         for (pid, process_name) in &process_pids {
-            println!("尝试关闭进程: {} (PID: {})", process_name, pid);
+            log::info!("尝试关闭进程: {} (PID: {})", process_name, pid);
             let mut process_closed = false;
 
             // 第一步：使用SIGTERM温和关闭
@@ -3454,7 +4332,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
             match term_output {
                 Ok(result) => {
                     if result.status.success() {
-                        println!("发送SIGTERM信号成功: {} (PID: {})", process_name, pid);
+                        log::info!("发送SIGTERM信号成功: {} (PID: {})", process_name, pid);
                         // 等待进程响应SIGTERM
                         tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
 
@@ -3468,19 +4346,19 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
                                 // 进程已经不存在了
                                 closed_count += 1;
                                 process_closed = true;
-                                println!("SIGTERM关闭成功: {} (PID: {})", process_name, pid);
+                                log::info!("SIGTERM关闭成功: {} (PID: {})", process_name, pid);
                             }
                         }
                     }
                 }
                 Err(e) => {
-                    println!("发送SIGTERM失败: {} (PID: {}), 错误: {}", process_name, pid, e);
+                    log::warn!("发送SIGTERM失败: {} (PID: {}), 错误: {}", process_name, pid, e);
                 }
             }
 
             // 如果SIGTERM失败，使用SIGKILL强制关闭
             if !process_closed {
-                println!("SIGTERM无效，尝试SIGKILL强制关闭: {} (PID: {})", process_name, pid);
+                log::info!("SIGTERM无效，尝试SIGKILL强制关闭: {} (PID: {})", process_name, pid);
                 let kill_output = Command::new("kill")
                     .args(&["-KILL", &pid.to_string()])
                     .output();
@@ -3490,14 +4368,14 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
                         if result.status.success() {
                             closed_count += 1;
                             process_closed = true;
-                            println!("SIGKILL关闭成功: {} (PID: {})", process_name, pid);
+                            log::info!("SIGKILL关闭成功: {} (PID: {})", process_name, pid);
                         } else {
                             let stderr = String::from_utf8_lossy(&result.stderr);
-                            println!("SIGKILL失败: {} (PID: {}), 错误: {}", process_name, pid, stderr);
+                            log::warn!("SIGKILL失败: {} (PID: {}), 错误: {}", process_name, pid, stderr);
                         }
                     }
                     Err(e) => {
-                        println!("SIGKILL命令执行失败: {} (PID: {}), 错误: {}", process_name, pid, e);
+                        log::warn!("SIGKILL命令执行失败: {} (PID: {}), 错误: {}", process_name, pid, e);
                     }
                 }
             }
@@ -3511,7 +4389,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
 
     // 等待所有进程完全关闭
     if closed_count > 0 {
-        println!("等待进程完全关闭...");
+        log::info!("等待进程完全关闭...");
         tokio::time::sleep(tokio::time::Duration::from_millis(3000)).await;
 
         // 再次检查是否还有残留进程
@@ -3525,7 +4403,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
         }
 
         if !remaining_processes.is_empty() {
-            println!("警告: 发现残留进程: {:?}", remaining_processes);
+            log::warn!("警告: 发现残留进程: {:?}", remaining_processes);
             // 将残留进程添加到失败列表，但不阻止继续执行
             for remaining in remaining_processes {
                 if !failed_processes.contains(&remaining) {
@@ -3545,7 +4423,7 @@ async fn close_editor_processes(editor_type: String) -> Result<String, String> {
     // 如果有失败的进程，添加警告信息但不返回错误
     if !failed_processes.is_empty() {
         result_message.push_str(&format!(" (警告: {} 个进程可能未完全关闭)", failed_processes.len()));
-        println!("部分进程关闭失败: {:?}", failed_processes);
+        log::warn!("部分进程关闭失败: {:?}", failed_processes);
     }
 
     Ok(result_message)
@@ -3595,10 +4473,10 @@ async fn clean_editor_database(editor_type: String, keyword: String) -> Result<S
     // 创建备份，如果失败则跳过
     let backup_path = db_path.with_extension("vscdb.backup");
     if let Err(e) = std::fs::copy(&db_path, &backup_path) {
-        println!("警告: 创建备份失败: {}, 继续执行...", e);
+        log::warn!("警告: 创建备份失败: {}, 继续执行...", e);
     }
 
-    println!("开始连接数据库: {}", db_path.display());
+    log::info!("开始连接数据库: {}", db_path.display());
 
     // 连接数据库并清理，添加重试机制
     let mut conn_result = Connection::open(&db_path);
@@ -3606,50 +4484,50 @@ async fn clean_editor_database(editor_type: String, keyword: String) -> Result<S
 
     while conn_result.is_err() && retry_count < 3 {
         retry_count += 1;
-        println!("数据库连接失败，重试第 {} 次...", retry_count);
+        log::warn!("数据库连接失败，重试第 {} 次...", retry_count);
         std::thread::sleep(std::time::Duration::from_millis(1000));
         conn_result = Connection::open(&db_path);
     }
 
     let conn = conn_result.map_err(|e| format!("打开数据库失败 (重试{}次后): {}", retry_count, e))?;
-    println!("数据库连接成功");
+    log::info!("数据库连接成功");
 
-    println!("开始查询包含关键字 '{}' 的条目", keyword);
+    log::info!("开始查询包含关键字 '{}' 的条目", keyword);
 
     // 查询匹配的条目
     let mut stmt = conn.prepare("SELECT key FROM ItemTable WHERE LOWER(key) LIKE LOWER(?)")
         .map_err(|e| format!("准备查询失败: {}", e))?;
 
     let keyword_pattern = format!("%{}%", keyword);
-    println!("查询模式: {}", keyword_pattern);
+    log::info!("查询模式: {}", keyword_pattern);
 
     let rows = stmt.query_map([&keyword_pattern], |row| {
         Ok(row.get::<_, String>(0)?)
     }).map_err(|e| format!("查询失败: {}", e))?;
 
-    println!("查询执行成功，开始收集结果");
+    log::info!("查询执行成功，开始收集结果");
 
     let mut matching_keys = Vec::new();
     for row in rows {
         let key = row.map_err(|e| format!("读取行失败: {}", e))?;
-        println!("找到匹配的键: {}", key);
+        log::info!("找到匹配的键: {}", key);
         matching_keys.push(key);
     }
 
-    println!("总共找到 {} 个匹配的键", matching_keys.len());
+    log::info!("总共找到 {} 个匹配的键", matching_keys.len());
 
     if matching_keys.is_empty() {
         return Ok(format!("清理数据: {} 数据库中未找到需要清理的条目", editor_display_name));
     }
 
-    println!("开始删除匹配的条目");
+    log::info!("开始删除匹配的条目");
     // 删除匹配的条目
     let deleted_count = conn.execute(
         "DELETE FROM ItemTable WHERE LOWER(key) LIKE LOWER(?)",
         [&keyword_pattern]
     ).map_err(|e| format!("删除失败: {}", e))?;
 
-    println!("删除操作完成，删除了 {} 个条目", deleted_count);
+    log::info!("删除操作完成，删除了 {} 个条目", deleted_count);
 
     Ok(format!("清理数据: {} 成功删除 {} 个包含关键字的条目", editor_display_name, deleted_count))
 }
@@ -3699,29 +4577,29 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
     let metadata = std::fs::metadata(&storage_path)
         .map_err(|e| format!("无法读取文件元数据: {}", e))?;
 
-    println!("文件权限检查:");
-    println!("  - 文件大小: {} 字节", metadata.len());
-    println!("  - 只读状态: {}", metadata.permissions().readonly());
+    log::info!("文件权限检查:");
+    log::info!("  - 文件大小: {} 字节", metadata.len());
+    log::info!("  - 只读状态: {}", metadata.permissions().readonly());
 
     // 如果文件是只读的，尝试修改权限
     if metadata.permissions().readonly() {
-        println!("文件是只读的，尝试修改权限...");
+        log::info!("文件是只读的，尝试修改权限...");
         let mut perms = metadata.permissions();
         perms.set_readonly(false);
         if let Err(e) = std::fs::set_permissions(&storage_path, perms) {
-            println!("警告: 无法修改文件权限: {}, 继续尝试...", e);
+            log::warn!("警告: 无法修改文件权限: {}, 继续尝试...", e);
         } else {
-            println!("文件权限修改成功");
+            log::info!("文件权限修改成功");
         }
     }
 
     // 创建备份，如果失败则跳过
     let backup_path = storage_path.with_extension("json.backup");
     if let Err(e) = std::fs::copy(&storage_path, &backup_path) {
-        println!("警告: 创建备份失败: {}, 继续执行...", e);
+        log::warn!("警告: 创建备份失败: {}, 继续执行...", e);
     }
 
-    println!("开始读取存储文件: {}", storage_path.display());
+    log::info!("开始读取存储文件: {}", storage_path.display());
 
     // 读取并修改JSON文件，添加重试机制
     let mut content_result = std::fs::read_to_string(&storage_path);
@@ -3729,84 +4607,84 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
 
     while content_result.is_err() && retry_count < 3 {
         retry_count += 1;
-        println!("读取存储文件失败，重试第 {} 次...", retry_count);
+        log::warn!("读取存储文件失败，重试第 {} 次...", retry_count);
         std::thread::sleep(std::time::Duration::from_millis(1000));
         content_result = std::fs::read_to_string(&storage_path);
     }
 
     let content = content_result.map_err(|e| format!("读取存储文件失败 (重试{}次后): {}", retry_count, e))?;
-    println!("存储文件读取成功，文件大小: {} 字节", content.len());
+    log::info!("存储文件读取成功，文件大小: {} 字节", content.len());
 
-    println!("开始解析JSON数据");
+    log::info!("开始解析JSON数据");
     let mut data: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("解析JSON失败: {}", e))?;
-    println!("JSON解析成功");
+    log::info!("JSON解析成功");
 
-    println!("开始生成新的UUID");
+    log::info!("开始生成新的UUID");
     // 生成新的ID
     let new_machine_id = Uuid::new_v4().to_string();
     let new_device_id = Uuid::new_v4().to_string();
-    println!("新的machine_id: {}", new_machine_id);
-    println!("新的device_id: {}", new_device_id);
+    log::info!("新的machine_id: {}", new_machine_id);
+    log::info!("新的device_id: {}", new_device_id);
 
     let mut modified = false;
-    println!("开始修改JSON数据");
+    log::info!("开始修改JSON数据");
 
-    println!("开始检查根级对象");
+    log::info!("开始检查根级对象");
     // 修改根级 machineId
     if let Some(obj) = data.as_object_mut() {
-        println!("根级对象存在，包含 {} 个键", obj.len());
+        log::info!("根级对象存在，包含 {} 个键", obj.len());
 
         if obj.contains_key("machineId") {
-            println!("找到根级 machineId，正在修改...");
+            log::info!("找到根级 machineId，正在修改...");
             obj.insert("machineId".to_string(), serde_json::Value::String(new_machine_id.clone()));
             modified = true;
-            println!("根级 machineId 修改完成");
+            log::info!("根级 machineId 修改完成");
         } else {
-            println!("根级不包含 machineId");
+            log::info!("根级不包含 machineId");
         }
 
-        println!("开始检查遥测对象");
+        log::info!("开始检查遥测对象");
         // 修改遥测相关ID
         if let Some(telemetry) = obj.get_mut("telemetry") {
-            println!("找到遥测对象");
+            log::info!("找到遥测对象");
             if let Some(telemetry_obj) = telemetry.as_object_mut() {
-                println!("遥测对象包含 {} 个键", telemetry_obj.len());
+                log::info!("遥测对象包含 {} 个键", telemetry_obj.len());
 
                 if telemetry_obj.contains_key("machineId") {
-                    println!("找到遥测 machineId，正在修改...");
+                    log::info!("找到遥测 machineId，正在修改...");
                     telemetry_obj.insert("machineId".to_string(), serde_json::Value::String(new_machine_id.clone()));
                     modified = true;
-                    println!("遥测 machineId 修改完成");
+                    log::info!("遥测 machineId 修改完成");
                 }
 
                 if telemetry_obj.contains_key("devDeviceId") {
-                    println!("找到 devDeviceId，正在修改...");
+                    log::info!("找到 devDeviceId，正在修改...");
                     telemetry_obj.insert("devDeviceId".to_string(), serde_json::Value::String(new_device_id.clone()));
                     modified = true;
-                    println!("devDeviceId 修改完成");
+                    log::info!("devDeviceId 修改完成");
                 }
             } else {
-                println!("遥测对象不是JSON对象类型");
+                log::info!("遥测对象不是JSON对象类型");
             }
         } else {
-            println!("未找到遥测对象");
+            log::info!("未找到遥测对象");
         }
     } else {
-        println!("根级不是JSON对象类型");
+        log::info!("根级不是JSON对象类型");
     }
 
-    println!("JSON数据修改完成，modified = {}", modified);
+    log::info!("JSON数据修改完成，modified = {}", modified);
 
     // 如果没有找到标准字段，尝试查找其他可能的遥测相关字段
     if !modified {
-        println!("未找到标准字段，开始搜索其他可能的遥测字段...");
+        log::info!("未找到标准字段，开始搜索其他可能的遥测字段...");
 
         if let Some(obj) = data.as_object_mut() {
             // 打印所有根级键，帮助调试
-            println!("根级键列表:");
+            log::info!("根级键列表:");
             for key in obj.keys() {
-                println!("  - {}", key);
+                log::info!("  - {}", key);
             }
 
             // 搜索包含machine、device、telemetry等关键字的字段
@@ -3822,29 +4700,29 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
                 }
             }
 
-            println!("找到可能的遥测相关字段: {:?}", keys_to_modify);
+            log::info!("找到可能的遥测相关字段: {:?}", keys_to_modify);
 
             // 修改找到的字段
             let mut modified_fields = Vec::new();
             for key in keys_to_modify {
-                println!("正在修改字段: {}", key);
+                log::info!("正在修改字段: {}", key);
                 obj.insert(key.clone(), serde_json::Value::String(new_machine_id.clone()));
                 modified_fields.push(key);
                 modified = true;
             }
 
             if !modified_fields.is_empty() {
-                println!("成功修改了 {} 个遥测字段: {:?}", modified_fields.len(), modified_fields);
+                log::info!("成功修改了 {} 个遥测字段: {:?}", modified_fields.len(), modified_fields);
             }
         }
 
         if !modified {
-            println!("仍未找到可修改的字段，将添加新的machineId字段");
+            log::info!("仍未找到可修改的字段，将添加新的machineId字段");
             if let Some(obj) = data.as_object_mut() {
                 obj.insert("machineId".to_string(), serde_json::Value::String(new_machine_id.clone()));
                 obj.insert("deviceId".to_string(), serde_json::Value::String(new_device_id.clone()));
                 modified = true;
-                println!("已添加新的遥测字段");
+                log::info!("已添加新的遥测字段");
             }
         }
     }
@@ -3853,14 +4731,14 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
         return Ok("无法修改遥测ID字段".to_string());
     }
 
-    println!("开始序列化修改后的JSON数据");
+    log::info!("开始序列化修改后的JSON数据");
     // 写入修改后的内容
     let new_content = serde_json::to_string_pretty(&data)
         .map_err(|e| format!("序列化JSON失败: {}", e))?;
-    println!("JSON序列化成功，准备写入文件");
+    log::info!("JSON序列化成功，准备写入文件");
 
     // 使用临时文件 + 原子性重命名的方式写入，避免权限问题
-    println!("开始写入文件，内容大小: {} 字节", new_content.len());
+    log::info!("开始写入文件，内容大小: {} 字节", new_content.len());
 
     // 获取原文件的所有权信息（仅在 Unix 系统上）
     #[cfg(unix)]
@@ -3890,7 +4768,7 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
 
         match write_result {
             Ok(_) => {
-                println!("临时文件写入成功");
+                log::info!("临时文件写入成功");
 
                 // 在 Unix 系统上，尝试恢复原文件的所有权
                 #[cfg(unix)]
@@ -3905,9 +4783,9 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
 
                     if let Ok(output) = chown_result {
                         if output.status.success() {
-                            println!("成功恢复临时文件的所有权: uid={}, gid={}", uid, gid);
+                            log::info!("成功恢复临时文件的所有权: uid={}, gid={}", uid, gid);
                         } else {
-                            println!("警告: 无法恢复临时文件所有权，但继续执行...");
+                            log::warn!("警告: 无法恢复临时文件所有权，但继续执行...");
                         }
                     }
 
@@ -3924,21 +4802,21 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
 
                 match rename_result {
                     Ok(_) => {
-                        println!("文件写入成功，遥测ID重置完成");
+                        log::info!("文件写入成功，遥测ID重置完成");
                         break;
                     }
                     Err(rename_err) => {
-                        println!("重命名文件失败 (第{}次): {} (错误代码: {:?})", retry_count, rename_err, rename_err.kind());
+                        log::warn!("重命名文件失败 (第{}次): {} (错误代码: {:?})", retry_count, rename_err, rename_err.kind());
 
                         // 如果 rename 失败，尝试复制+删除
                         match std::fs::copy(&temp_path, &storage_path) {
                             Ok(_) => {
-                                println!("使用复制方式写入成功");
+                                log::info!("使用复制方式写入成功");
                                 let _ = std::fs::remove_file(&temp_path);
                                 break;
                             }
                             Err(copy_err) => {
-                                println!("复制文件也失败: {} (错误代码: {:?})", copy_err, copy_err.kind());
+                                log::warn!("复制文件也失败: {} (错误代码: {:?})", copy_err, copy_err.kind());
                                 last_error = Some(rename_err);
 
                                 // 清理临时文件
@@ -3953,7 +4831,7 @@ async fn reset_editor_telemetry(editor_type: String) -> Result<String, String> {
                 }
             }
             Err(e) => {
-                println!("写入临时文件失败 (第{}次): {} (错误代码: {:?})", retry_count, e, e.kind());
+                log::warn!("写入临时文件失败 (第{}次): {} (错误代码: {:?})", retry_count, e, e.kind());
                 last_error = Some(e);
 
                 if retry_count < max_retries {
@@ -4135,7 +5013,7 @@ async fn clear_augment_chat_history(editor_type: String) -> Result<String, Strin
         return Ok(format!("清除聊天记录: {} 的 workspaceStorage 目录不存在，跳过清除", editor_display_name));
     }
 
-    println!("开始清除 {} 的聊天记录，路径: {:?}", editor_display_name, workspace_storage_path);
+    log::info!("开始清除 {} 的聊天记录，路径: {:?}", editor_display_name, workspace_storage_path);
 
     let mut deleted_count = 0;
     let mut error_count = 0;
@@ -4157,15 +5035,15 @@ async fn clear_augment_chat_history(editor_type: String) -> Result<String, Strin
             file_name.contains("conversation") ||
             file_name.contains("history")) {
 
-            println!("找到 Augment 聊天记录文件: {:?}", path);
+            log::info!("找到 Augment 聊天记录文件: {:?}", path);
 
             match std::fs::remove_file(path) {
                 Ok(_) => {
-                    println!("成功删除: {:?}", path);
+                    log::info!("成功删除: {:?}", path);
                     deleted_count += 1;
                 },
                 Err(e) => {
-                    println!("删除失败: {:?}, 错误: {}", path, e);
+                    log::warn!("删除失败: {:?}, 错误: {}", path, e);
                     error_count += 1;
                 }
             }
@@ -4173,15 +5051,15 @@ async fn clear_augment_chat_history(editor_type: String) -> Result<String, Strin
 
         // 同时检查目录名，如果是 augmentcode.augment-* 开头的目录，删除整个目录
         if path.is_dir() && file_name.starts_with("augmentcode.augment") {
-            println!("找到 Augment 扩展目录: {:?}", path);
+            log::info!("找到 Augment 扩展目录: {:?}", path);
 
             match std::fs::remove_dir_all(path) {
                 Ok(_) => {
-                    println!("成功删除目录: {:?}", path);
+                    log::info!("成功删除目录: {:?}", path);
                     deleted_count += 1;
                 },
                 Err(e) => {
-                    println!("删除目录失败: {:?}, 错误: {}", path, e);
+                    log::warn!("删除目录失败: {:?}, 错误: {}", path, e);
                     error_count += 1;
                 }
             }
@@ -4236,7 +5114,7 @@ fn clear_jetbrains_chat_history(editor_type: &str, editor_display_name: &str) ->
         return Ok(format!("清除聊天记录: {} 配置目录不存在，跳过清除", editor_display_name));
     }
 
-    println!("开始清除 {} 的聊天记录，配置目录: {:?}", editor_display_name, config_base);
+    log::info!("开始清除 {} 的聊天记录，配置目录: {:?}", editor_display_name, config_base);
 
     let mut deleted_count = 0;
     let mut error_count = 0;
@@ -4257,7 +5135,7 @@ fn clear_jetbrains_chat_history(editor_type: &str, editor_display_name: &str) ->
             continue;
         }
 
-        println!("找到 {} 配置目录: {:?}", editor_display_name, path);
+        log::info!("找到 {} 配置目录: {:?}", editor_display_name, path);
 
         // 在配置目录中查找 Augment 相关文件
         // JetBrains 插件数据通常存储在：
@@ -4277,15 +5155,15 @@ fn clear_jetbrains_chat_history(editor_type: &str, editor_display_name: &str) ->
                 let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
                 if file_name.to_lowercase().contains("augment") {
-                    println!("找到 Augment 配置文件: {:?}", file_path);
+                    log::info!("找到 Augment 配置文件: {:?}", file_path);
 
                     match std::fs::remove_file(file_path) {
                         Ok(_) => {
-                            println!("成功删除: {:?}", file_path);
+                            log::info!("成功删除: {:?}", file_path);
                             deleted_count += 1;
                         },
                         Err(e) => {
-                            println!("删除失败: {:?}, 错误: {}", file_path, e);
+                            log::warn!("删除失败: {:?}, 错误: {}", file_path, e);
                             error_count += 1;
                         }
                     }
@@ -4304,27 +5182,27 @@ fn clear_jetbrains_chat_history(editor_type: &str, editor_display_name: &str) ->
                 let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
                 if file_name.to_lowercase().contains("augment") {
-                    println!("找到 Augment 工作区文件: {:?}", file_path);
+                    log::info!("找到 Augment 工作区文件: {:?}", file_path);
 
                     if file_path.is_dir() {
                         match std::fs::remove_dir_all(file_path) {
                             Ok(_) => {
-                                println!("成功删除目录: {:?}", file_path);
+                                log::info!("成功删除目录: {:?}", file_path);
                                 deleted_count += 1;
                             },
                             Err(e) => {
-                                println!("删除目录失败: {:?}, 错误: {}", file_path, e);
+                                log::warn!("删除目录失败: {:?}, 错误: {}", file_path, e);
                                 error_count += 1;
                             }
                         }
                     } else {
                         match std::fs::remove_file(file_path) {
                             Ok(_) => {
-                                println!("成功删除: {:?}", file_path);
+                                log::info!("成功删除: {:?}", file_path);
                                 deleted_count += 1;
                             },
                             Err(e) => {
-                                println!("删除失败: {:?}, 错误: {}", file_path, e);
+                                log::warn!("删除失败: {:?}, 错误: {}", file_path, e);
                                 error_count += 1;
                             }
                         }
@@ -4452,6 +5330,14 @@ async fn start_api_server(state: State<'_, AppState>) -> Result<String, String>
         cloud_sync: state.cloud_sync.clone(),
         password_manager: state.password_manager.clone(),
         app_session_cache: state.app_session_cache.clone(),
+        profile_switch_lock: state.profile_switch_lock.clone(),
+        http_client: state.http_client.clone(),
+        browser_windows: state.browser_windows.clone(),
+        deep_links: state.deep_links.clone(),
+        instance_lock: state.instance_lock.clone(),
+        data_watcher: state.data_watcher.clone(),
+        batch_checks: state.batch_checks.clone(),
+        check_limiter: state.check_limiter.clone(),
         app_handle: state.app_handle.clone(),
     });
 
@@ -4503,6 +5389,8 @@ async fn get_api_server_status(state: State<'_, AppState>) -> Result<api_server:
 }
 
 fn main() {
+    logging::init(logging::LogSettings::default());
+
     let mut builder = tauri::Builder::default();
 
     // 在桌面平台上添加 single-instance 插件
@@ -4517,31 +5405,8 @@ fn main() {
             }
 
             // 处理 deep-link URL（从 argv 中查找）
-            for arg in argv {
-                if arg.starts_with("zaugment://") {
-                    // 解析 URL 获取 session 参数
-                    if let Ok(parsed_url) = url::Url::parse(&arg) {
-                        if let Some(session) = parsed_url.query_pairs().find(|(k, _)| k == "session").map(|(_, v)| v.to_string()) {
-                            // 发送 session 到前端
-                            let app_handle = app.app_handle().clone();
-                            let session_clone = session.clone();
-
-                            // 等待一下确保前端已经准备好
-                            std::thread::spawn(move || {
-                                std::thread::sleep(std::time::Duration::from_millis(1000));
-
-                                let _ = app_handle.emit(
-                                    "deep-link-session-received",
-                                    serde_json::json!({
-                                        "session": session_clone
-                                    })
-                                );
-                            });
-                        }
-                    }
-
-                    break;
-                }
+            for arg in argv.iter().filter(|arg| arg.starts_with(&format!("{}:", deep_link::SCHEME))) {
+                route_deep_link(app, arg);
             }
         }));
     }
@@ -4549,9 +5414,10 @@ fn main() {
     builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
-            println!("应用启动中...");
+            log::info!("应用启动中...");
 
             // 最小化状态管理
             let app_state = AppState {
@@ -4564,13 +5430,21 @@ fn main() {
                 webdav_config: Arc::new(Mutex::new(None)),
                 cloud_sync: Arc::new(Mutex::new(None)),
                 password_manager: Arc::new(PasswordManager::new()),
-                app_session_cache: Arc::new(Mutex::new(HashMap::new())),
+                app_session_cache: Arc::new(Mutex::new(AppSessionStore::default())),
+                profile_switch_lock: Arc::new(tokio::sync::Mutex::new(())),
+                http_client: Arc::new(Mutex::new(HttpClient::default())),
+                browser_windows: Arc::new(BrowserWindowTracker::default()),
+                deep_links: Arc::new(DeepLinkInbox::default()),
+                instance_lock: Arc::new(Mutex::new(None)),
+                data_watcher: Arc::new(Mutex::new(None)),
+                batch_checks: Arc::new(Mutex::new(HashMap::new())),
+                check_limiter: Arc::new(Mutex::new(CheckLimiter::default())),
                 app_handle: app.app_handle().clone(),
             };
 
             app.manage(app_state);
 
-            println!("状态管理器初始化完成");
+            log::info!("状态管理器初始化完成");
 
             // 异步初始化存储管理器
             let app_handle_for_storage = app.handle().clone();
@@ -4579,9 +5453,9 @@ fn main() {
 
                 // 初始化存储管理器
                 if let Err(e) = initialize_storage_manager(&app_handle_for_storage, &state).await {
-                    eprintln!("❌ Failed to initialize storage manager: {}", e);
+                    log::error!("❌ Failed to initialize storage manager: {}", e);
                 } else {
-                    println!("✅ Storage manager initialized successfully");
+                    log::info!("✅ Storage manager initialized successfully");
                 }
             });
 
@@ -4595,52 +5469,63 @@ fn main() {
             // 处理 Deep-Link 事件（仅在首次启动时触发）
             let app_handle = app.app_handle().clone();
             app.listen("deep-link://new-deep-link", move |event| {
-                // 从 URL 中提取 session 参数
-                let payload = event.payload();
-
-                if let Ok(url_str) = serde_json::from_str::<Vec<String>>(payload) {
-                    if let Some(url) = url_str.first() {
-                        // 解析 URL 获取 session 参数
-                        if let Ok(parsed_url) = url::Url::parse(url) {
-                            if let Some(session) = parsed_url.query_pairs().find(|(k, _)| k == "session").map(|(_, v)| v.to_string()) {
-                                // 等待主窗口加载完成（最多等待 10 秒）
-                                let app_handle_clone = app_handle.clone();
-                                let session_clone = session.clone();
-
-                                tokio::spawn(async move {
-                                    let mut attempts = 0;
-                                    let max_attempts = 100; // 100 * 100ms = 10 秒
-
-                                    while attempts < max_attempts {
-                                        if let Some(main_window) = app_handle_clone.get_webview_window("main") {
-                                            // 窗口存在，再等待一小段时间确保前端事件监听器已注册
-                                            tokio::time::sleep(tokio::time::Duration::from_millis(800)).await;
-
-                                            // 聚焦主窗口
-                                            let _ = main_window.set_focus();
-                                            let _ = main_window.unminimize();
-
-                                            // 发送 session 到前端，由前端调用导入方法
-                                            let _ = app_handle_clone.emit(
-                                                "deep-link-session-received",
-                                                serde_json::json!({
-                                                    "session": session_clone
-                                                })
-                                            );
-                                            break;
-                                        }
-
-                                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                                        attempts += 1;
-                                    }
-
-                                    if attempts >= max_attempts {
-                                        eprintln!("⚠️ Timeout waiting for main window to be ready");
-                                    }
-                                });
-                            }
+                match serde_json::from_str::<Vec<String>>(event.payload()) {
+                    Ok(urls) => urls.iter().for_each(|url| route_deep_link(&app_handle, url)),
+                    Err(e) => log::warn!("拒绝 deep-link: 事件数据无法解析: {}", e),
+                }
+            });
+
+            // 轮询数据目录，发现外部修改时重新加载
+            let app_handle_for_watcher = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(data_watcher::POLL_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let state = app_handle_for_watcher.state::<AppState>();
+                    if let Err(e) = sync_external_changes(&app_handle_for_watcher, &state).await {
+                        log::warn!("检查数据目录修改失败: {}", e);
+                    }
+                }
+            });
+
+            // 按设置定时检测账号状态；每分钟重新读取设置，设置变化时重新计算下一轮时间
+            let app_handle_for_scheduler = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(status_scheduler::TICK_INTERVAL);
+                let mut planned: Option<(StatusCheckSchedule, chrono::DateTime<chrono::Utc>)> = None;
+                loop {
+                    interval.tick().await;
+                    let state = app_handle_for_scheduler.state::<AppState>();
+                    let schedule = load_unified_config_with_state(&app_handle_for_scheduler, &state).status_check_schedule;
+                    if !schedule.enabled {
+                        planned = None;
+                        continue;
+                    }
+
+                    let next = match &planned {
+                        Some((planned_schedule, next)) if *planned_schedule == schedule => *next,
+                        _ => {
+                            let last_run = get_effective_data_dir(&app_handle_for_scheduler, &state)
+                                .ok()
+                                .and_then(|dir| status_scheduler::load_summary(&dir))
+                                .map(|summary| summary.finished_at);
+                            let next = status_scheduler::next_run(&schedule, last_run, chrono::Utc::now(), rand::random());
+                            log::info!("下一次定时检测账号状态: {}", next.with_timezone(&chrono::Local));
+                            next
                         }
+                    };
+                    if chrono::Utc::now() < next {
+                        planned = Some((schedule, next));
+                        continue;
                     }
+
+                    if let Err(e) = run_status_check(&app_handle_for_scheduler, &state, "scheduled").await {
+                        log::warn!("定时检测账号状态失败: {}", e);
+                    }
+                    // 失败时也等到下一轮，避免每分钟重试
+                    let next = status_scheduler::next_run_after(&schedule, &chrono::Local::now(), rand::random());
+                    log::info!("下一次定时检测账号状态: {}", next.with_timezone(&chrono::Local));
+                    planned = Some((schedule, next));
                 }
             });
 
@@ -4662,7 +5547,7 @@ fn main() {
                 }
 
                 // 等待 storage_manager 初始化完成
-                println!("⏳ Waiting for storage manager to initialize...");
+                log::info!("⏳ Waiting for storage manager to initialize...");
                 let mut storage_attempts = 0;
                 let max_storage_attempts = 50; // 50 * 100ms = 5 秒
 
@@ -4673,7 +5558,7 @@ fn main() {
                     };
 
                     if storage_ready {
-                        println!("✅ Storage manager is ready");
+                        log::info!("✅ Storage manager is ready");
                         break;
                     }
 
@@ -4682,7 +5567,7 @@ fn main() {
                 }
 
                 if storage_attempts >= max_storage_attempts {
-                    eprintln!("⚠️ Storage manager initialization timeout, API server may not work properly");
+                    log::warn!("⚠️ Storage manager initialization timeout, API server may not work properly");
                 }
 
                 // 创建 API 服务器实例
@@ -4700,17 +5585,25 @@ fn main() {
                     cloud_sync: state.cloud_sync.clone(),
                     password_manager: state.password_manager.clone(),
                     app_session_cache: state.app_session_cache.clone(),
+                    profile_switch_lock: state.profile_switch_lock.clone(),
+                    http_client: state.http_client.clone(),
+                    browser_windows: state.browser_windows.clone(),
+                    deep_links: state.deep_links.clone(),
+                    instance_lock: state.instance_lock.clone(),
+                    data_watcher: state.data_watcher.clone(),
+                    batch_checks: state.batch_checks.clone(),
+                    check_limiter: state.check_limiter.clone(),
                     app_handle: app_handle_for_api.clone(),
                 });
 
                 // 启动 API 服务器
                 match server.start(api_state).await {
                     Ok(_) => {
-                        println!("✅ API Server initialized successfully on port 8766");
+                        log::info!("✅ API Server initialized successfully on port 8766");
                         *state.api_server.lock().unwrap() = Some(server);
                     }
                     Err(e) => {
-                        eprintln!("❌ Failed to start API server: {}", e);
+                        log::error!("❌ Failed to start API server: {}", e);
                     }
                 }
             });
@@ -4724,14 +5617,44 @@ fn main() {
             get_augment_token,
             check_account_status,
             batch_check_tokens_status,
+            cancel_batch_check,
+            get_batch_check_options,
+            set_batch_check_options,
+            get_app_session_cache_settings,
+            set_app_session_cache_settings,
+            get_app_session_info,
+            refresh_app_session,
+            clear_app_session,
+            clear_all_app_sessions,
             get_credit_info_from_token,
             get_models_from_token,
             fetch_batch_credit_consumption,
             add_token_from_session,
             open_url,
             // 新的简化命令
-            save_tokens_json,
             load_tokens_json,
+            save_token,
+            save_tokens,
+            delete_tokens,
+            import_tokens,
+            export_tokens,
+            get_token_history,
+            revert_token,
+            list_trash,
+            restore_from_trash,
+            get_history_settings,
+            set_trash_retention_days,
+            list_backups,
+            inspect_backup,
+            create_backup,
+            check_data_integrity,
+            restore_backup,
+            get_backup_settings,
+            set_backup_settings,
+            get_status_check_schedule,
+            set_status_check_schedule,
+            run_status_check_now,
+            get_last_status_check,
             // 文件操作命令
             save_file_dialog,
             write_file_content,
@@ -4751,6 +5674,8 @@ fn main() {
             create_jetbrains_token_file,
             configure_vim_augment,
             configure_auggie,
+            get_editor_integrations,
+            revert_editor_integration,
             // Outlook 邮箱管理命令
             outlook_save_credentials,
             outlook_get_all_accounts,
@@ -4762,10 +5687,15 @@ fn main() {
             delete_token,
             // 设置页面命令
             select_data_directory,
+            relocate_data_directory,
+            get_pending_data_relocation,
+            confirm_data_relocation,
+            cancel_data_relocation,
             get_current_data_path,
             get_data_directory,
             reset_data_directory,
             open_data_directory,
+            export_diagnostic_bundle,
             
             // WebDAV云同步命令
             configure_webdav,
@@ -4774,6 +5704,8 @@ fn main() {
             force_upload_to_cloud,
             force_download_from_cloud,
             get_webdav_config,
+            set_encrypted_credential_sync,
+            get_encrypted_credential_sync,
             
             // 冲突检测和解决命令
             check_sync_conflicts,
@@ -4789,15 +5721,41 @@ fn main() {
             save_status_thresholds,
             load_status_thresholds,
             get_default_status_thresholds,
+            // 网络设置
+            get_http_client_settings,
+            save_http_client_settings,
+            // 配置档案
+            list_profiles,
+            create_profile,
+            clone_profile,
+            switch_profile,
+            delete_profile,
 
             // 版本检查命令
             get_app_version,
             check_for_updates,
+            download_update,
+            install_update,
+            get_update_settings,
+            set_update_channel,
+            skip_update_version,
 
             open_internal_browser,
             close_window,
+            list_browser_profiles,
+            clear_browser_profile,
+            delete_browser_profile,
             open_with_chrome,
             open_with_custom_browser,
+            detect_browsers,
+            get_browser_settings,
+            save_custom_browser,
+            delete_custom_browser,
+            set_preferred_browser,
+            open_with_browser,
+            list_pending_deep_links,
+            confirm_deep_link,
+            reject_deep_link,
             select_file,
 
             // 窗口控制命令
@@ -4824,6 +5782,14 @@ fn main() {
             stop_api_server,
             get_api_server_status
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // 退出前关闭仍然打开的内置浏览器窗口
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                if let Some(state) = app.try_state::<AppState>() {
+                    close_browser_windows(app, &state.browser_windows, None);
+                }
+            }
+        });
 }
@@ -0,0 +1,970 @@
+//! 持久化数据的版本化迁移
+//!
+//! 每种数据文件（tokens.json、config.json、bookmarks.json、profiles.json、云端同步数据包）都有自己的
+//! 迁移注册表。文件中的 `version` 字段记录其架构版本，加载时按顺序执行从该版本到
+//! 当前版本的所有迁移步骤。升级磁盘文件前会先在同目录下备份原文件；由更新版本应用
+//! 写入的文件会被拒绝，避免旧版本应用覆盖无法理解的数据。
+
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::data_watcher;
+use crate::instance_lock::WriteLock;
+use crate::storage::write_file_atomic;
+
+/// 未带版本号的历史文件统一视为该版本
+pub const LEGACY_VERSION: &str = "1.0.0";
+
+pub const TOKENS_VERSION: &str = "1.1.0";
+pub const CONFIG_VERSION: &str = "1.2.0";
+pub const BOOKMARKS_VERSION: &str = "1.1.0";
+pub const SYNC_PACKAGE_VERSION: &str = "1.2.0";
+pub const PROFILES_VERSION: &str = "1.0.0";
+
+/// 受迁移框架管理的数据种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    Tokens,
+    Config,
+    Bookmarks,
+    SyncPackage,
+    Profiles,
+}
+
+impl DataKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataKind::Tokens => "tokens",
+            DataKind::Config => "config",
+            DataKind::Bookmarks => "bookmarks",
+            DataKind::SyncPackage => "sync package",
+            DataKind::Profiles => "profiles",
+        }
+    }
+
+    pub fn current_version(&self) -> &'static str {
+        match self {
+            DataKind::Tokens => TOKENS_VERSION,
+            DataKind::Config => CONFIG_VERSION,
+            DataKind::Bookmarks => BOOKMARKS_VERSION,
+            DataKind::SyncPackage => SYNC_PACKAGE_VERSION,
+            DataKind::Profiles => PROFILES_VERSION,
+        }
+    }
+
+    /// 该数据种类的迁移步骤，按版本顺序排列
+    pub fn migrations(&self) -> &'static [Migration] {
+        match self {
+            DataKind::Tokens => TOKENS_MIGRATIONS,
+            DataKind::Config => CONFIG_MIGRATIONS,
+            DataKind::Bookmarks => BOOKMARKS_MIGRATIONS,
+            DataKind::SyncPackage => SYNC_PACKAGE_MIGRATIONS,
+            DataKind::Profiles => PROFILES_MIGRATIONS,
+        }
+    }
+}
+
+/// 单个迁移步骤：把 `from` 版本的文档原地改写为 `to` 版本
+///
+/// 迁移函数只负责数据形状的变换，`version` 字段由迁移引擎在步骤完成后写入。
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub description: &'static str,
+    pub apply: fn(&mut Value, &MigrationContext) -> Result<(), String>,
+}
+
+/// 迁移过程中需要把敏感信息移出数据文件时使用的密钥存储
+pub trait SecretStore {
+    fn store_secret(&self, account: &str, secret: &str) -> Result<(), String>;
+}
+
+impl SecretStore for crate::webdav::PasswordManager {
+    fn store_secret(&self, account: &str, secret: &str) -> Result<(), String> {
+        self.store_password(account, secret)
+    }
+}
+
+/// 暂存迁移过程中移出的敏感信息，由调用方确认后再写入密钥链
+///
+/// 云端数据包在下载解析时迁移，只有用户明确恢复时才应写入本机密钥链。
+#[derive(Default)]
+pub struct PendingSecrets {
+    secrets: std::sync::Mutex<Vec<(String, String)>>,
+}
+
+impl PendingSecrets {
+    /// 取出指定账户的敏感信息
+    pub fn take(&self, account: &str) -> Option<String> {
+        let mut secrets = self.secrets.lock().unwrap();
+        let index = secrets.iter().position(|(a, _)| a == account)?;
+        Some(secrets.remove(index).1)
+    }
+}
+
+impl Clone for PendingSecrets {
+    fn clone(&self) -> Self {
+        Self {
+            secrets: std::sync::Mutex::new(self.secrets.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl std::fmt::Debug for PendingSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 不输出敏感内容
+        f.debug_struct("PendingSecrets").field("count", &self.secrets.lock().unwrap().len()).finish()
+    }
+}
+
+impl SecretStore for PendingSecrets {
+    fn store_secret(&self, account: &str, secret: &str) -> Result<(), String> {
+        self.secrets.lock().unwrap().push((account.to_string(), secret.to_string()));
+        Ok(())
+    }
+}
+
+/// 迁移步骤可访问的外部资源
+#[derive(Default, Clone, Copy)]
+pub struct MigrationContext<'a> {
+    secrets: Option<&'a dyn SecretStore>,
+}
+
+impl<'a> MigrationContext<'a> {
+    pub fn with_secrets(secrets: &'a dyn SecretStore) -> Self {
+        Self { secrets: Some(secrets) }
+    }
+
+    fn secrets(&self) -> Result<&'a dyn SecretStore, String> {
+        self.secrets.ok_or_else(|| "该迁移需要访问系统密钥链".to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("{kind} 数据版本 {found} 高于当前应用支持的版本 {supported}，请升级应用后再试")]
+    NewerVersion {
+        kind: &'static str,
+        found: String,
+        supported: &'static str,
+    },
+
+    #[error("{kind} 数据版本号无效: {version}")]
+    InvalidVersion { kind: &'static str, version: String },
+
+    #[error("{kind} 缺少从版本 {from} 开始的迁移步骤")]
+    MissingStep { kind: &'static str, from: String },
+
+    #[error("{kind} 迁移 {from} -> {to} 失败: {message}")]
+    StepFailed {
+        kind: &'static str,
+        from: &'static str,
+        to: &'static str,
+        message: String,
+    },
+
+    #[error("解析 {kind} 数据失败: {message}")]
+    Parse { kind: &'static str, message: String },
+
+    #[error("文件操作失败: {0}")]
+    Io(String),
+}
+
+/// 迁移结果
+#[derive(Debug)]
+pub struct MigrationOutcome {
+    pub value: Value,
+    pub from_version: String,
+    /// 已执行的步骤（目标版本号）
+    pub applied: Vec<&'static str>,
+}
+
+impl MigrationOutcome {
+    pub fn migrated(&self) -> bool {
+        !self.applied.is_empty()
+    }
+}
+
+// ==================== 迁移注册表 ====================
+
+static TOKENS_MIGRATIONS: &[Migration] = &[Migration {
+    from: "1.0.0",
+    to: "1.1.0",
+    description: "包装为带版本的 {version, tokens} 结构，tag_text 重命名为 tag_name，移除 balance_color_mode",
+    apply: tokens_1_0_0_to_1_1_0,
+}];
+
+static CONFIG_MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0.0",
+        to: "1.1.0",
+        description: "为旧格式配置补全缺失的设置段",
+        apply: config_1_0_0_to_1_1_0,
+    },
+    Migration {
+        from: "1.1.0",
+        to: "1.2.0",
+        description: "WebDAV 明文密码移入系统密钥链，配置中只保留凭据引用",
+        apply: config_1_1_0_to_1_2_0,
+    },
+];
+
+static BOOKMARKS_MIGRATIONS: &[Migration] = &[Migration {
+    from: "1.0.0",
+    to: "1.1.0",
+    description: "为书签文件加入版本号",
+    apply: bookmarks_1_0_0_to_1_1_0,
+}];
+
+static SYNC_PACKAGE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0.0",
+        to: "1.1.0",
+        description: "将顶层 status_thresholds 合并到 unified_config 中",
+        apply: sync_package_1_0_0_to_1_1_0,
+    },
+    Migration {
+        from: "1.1.0",
+        to: "1.2.0",
+        description: "unified_config 中的 WebDAV 凭据改为引用或加密形式（由配置迁移处理）",
+        apply: sync_package_1_1_0_to_1_2_0,
+    },
+];
+
+static PROFILES_MIGRATIONS: &[Migration] = &[];
+
+fn tokens_1_0_0_to_1_1_0(value: &mut Value, _ctx: &MigrationContext) -> Result<(), String> {
+    // 旧文件可能是数组、{tokens: [...]} 或单个 token 对象
+    let tokens = match value.take() {
+        Value::Array(array) => array,
+        Value::Object(mut obj) => match obj.remove("tokens") {
+            Some(Value::Array(array)) => array,
+            Some(_) => return Err("tokens 字段不是数组".to_string()),
+            None if obj.is_empty() => Vec::new(),
+            None => vec![Value::Object(obj)],
+        },
+        Value::Null => Vec::new(),
+        other => return Err(format!("不支持的 tokens 数据格式: {}", type_name(&other))),
+    };
+
+    let tokens = tokens
+        .into_iter()
+        .map(|mut token| {
+            if let Value::Object(ref mut obj) = token {
+                // tag_text 是 tag_name 的旧名称，两者同时存在时以 tag_name 为准
+                if let Some(tag_text) = obj.remove("tag_text") {
+                    let has_tag_name = obj.get("tag_name").is_some_and(|v| !v.is_null());
+                    if !has_tag_name {
+                        obj.insert("tag_name".to_string(), tag_text);
+                    }
+                }
+                // 废弃的 balance_color_mode 字段
+                obj.remove("balance_color_mode");
+            }
+            token
+        })
+        .collect::<Vec<_>>();
+
+    *value = json!({ "tokens": tokens });
+    Ok(())
+}
+
+fn config_1_0_0_to_1_1_0(value: &mut Value, _ctx: &MigrationContext) -> Result<(), String> {
+    let obj = value.as_object_mut().ok_or("配置不是 JSON 对象")?;
+
+    // 旧格式配置可能只有 custom_data_dir 等少量字段，补全其余设置段的默认值
+    obj.entry("last_updated")
+        .or_insert_with(|| json!(chrono::Utc::now()));
+    obj.entry("app_settings").or_insert_with(|| {
+        json!({
+            "current_view": "token-generator",
+            "auto_sync_enabled": false,
+            "last_sync_time": null
+        })
+    });
+    obj.entry("custom_data_dir").or_insert(Value::Null);
+    obj.entry("webdav_config").or_insert(Value::Null);
+    obj.entry("ui_settings").or_insert_with(|| {
+        json!({
+            "theme": null,
+            "window_size": null,
+            "window_position": null,
+            "language": "zh-CN"
+        })
+    });
+    obj.entry("status_thresholds").or_insert_with(|| {
+        json!({
+            "time": { "warning": 10, "safe": 20 },
+            "balance": { "warning": 10000, "safe": 20000 },
+            "timeMax": 365,
+            "balanceMax": 1000000
+        })
+    });
+
+    Ok(())
+}
+
+fn bookmarks_1_0_0_to_1_1_0(value: &mut Value, _ctx: &MigrationContext) -> Result<(), String> {
+    match value {
+        Value::Object(obj) => {
+            obj.entry("bookmarks").or_insert_with(|| json!([]));
+            Ok(())
+        }
+        Value::Array(array) => {
+            let bookmarks = std::mem::take(array);
+            *value = json!({ "bookmarks": bookmarks });
+            Ok(())
+        }
+        other => Err(format!("不支持的书签数据格式: {}", type_name(other))),
+    }
+}
+
+fn sync_package_1_0_0_to_1_1_0(value: &mut Value, _ctx: &MigrationContext) -> Result<(), String> {
+    let obj = value.as_object_mut().ok_or("同步数据包不是 JSON 对象")?;
+
+    // 早期数据包把阈值配置放在顶层，现在只保存在 unified_config 中
+    if let Some(thresholds) = obj.remove("status_thresholds") {
+        if let Some(Value::Object(config)) = obj.get_mut("unified_config") {
+            let has_thresholds = config.get("status_thresholds").is_some_and(|v| !v.is_null());
+            if !has_thresholds {
+                config.insert("status_thresholds".to_string(), thresholds);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn config_1_1_0_to_1_2_0(value: &mut Value, ctx: &MigrationContext) -> Result<(), String> {
+    let obj = value.as_object_mut().ok_or("配置不是 JSON 对象")?;
+
+    obj.entry("encrypted_credential_sync").or_insert(Value::Bool(false));
+
+    let webdav = match obj.get_mut("webdav_config") {
+        Some(Value::Object(webdav)) => webdav,
+        _ => return Ok(()),
+    };
+
+    let field = |key: &str| webdav.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let account = crate::webdav::secure_config::webdav_keyring_account(&field("server_url"), &field("username"));
+
+    // 明文密码写入密钥链后从配置中删除
+    if let Some(password) = webdav.remove("password") {
+        if let Some(password) = password.as_str().filter(|p| !p.is_empty()) {
+            ctx.secrets()?.store_secret(&account, password)?;
+        }
+    }
+
+    // 密码保存在 ZAugment_WebDAV 服务下，账户名包含服务器地址
+    webdav.entry("credential").or_insert_with(|| {
+        json!({
+            "type": "keyring",
+            "service": "ZAugment_WebDAV",
+            "account": account
+        })
+    });
+
+    Ok(())
+}
+
+fn sync_package_1_1_0_to_1_2_0(_value: &mut Value, _ctx: &MigrationContext) -> Result<(), String> {
+    // 数据包自身结构未变化，嵌套的 unified_config 在恢复时按配置迁移链升级
+    Ok(())
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// ==================== 迁移引擎 ====================
+
+fn parse_version(kind: DataKind, version: &str) -> Result<(u64, u64, u64), MigrationError> {
+    let invalid = || MigrationError::InvalidVersion {
+        kind: kind.name(),
+        version: version.to_string(),
+    };
+
+    let mut parts = version.trim().split('.');
+    let mut next = || -> Result<u64, MigrationError> {
+        parts.next().ok_or_else(invalid)?.parse::<u64>().map_err(|_| invalid())
+    };
+    let parsed = (next()?, next()?, next()?);
+
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(parsed)
+}
+
+fn compare_versions(kind: DataKind, a: &str, b: &str) -> Result<Ordering, MigrationError> {
+    Ok(parse_version(kind, a)?.cmp(&parse_version(kind, b)?))
+}
+
+/// 读取文档中记录的版本号，没有版本号的历史文档视为 [`LEGACY_VERSION`]
+pub fn detect_version(kind: DataKind, value: &Value) -> Result<String, MigrationError> {
+    let version = match (kind, value) {
+        // tokens 只有带 tokens 数组的对象才可能携带版本号
+        (DataKind::Tokens, Value::Object(obj)) if !obj.contains_key("tokens") => None,
+        (_, Value::Object(obj)) => obj.get("version"),
+        _ => None,
+    };
+
+    match version {
+        None | Some(Value::Null) => Ok(LEGACY_VERSION.to_string()),
+        Some(Value::String(v)) => {
+            parse_version(kind, v)?;
+            Ok(v.clone())
+        }
+        Some(other) => Err(MigrationError::InvalidVersion {
+            kind: kind.name(),
+            version: other.to_string(),
+        }),
+    }
+}
+
+fn stamp_version(value: &mut Value, version: &str) {
+    if let Value::Object(obj) = value {
+        obj.insert("version".to_string(), Value::String(version.to_string()));
+    }
+}
+
+/// 在内存中把文档迁移到当前版本
+pub fn migrate_value(
+    kind: DataKind,
+    mut value: Value,
+    ctx: &MigrationContext,
+) -> Result<MigrationOutcome, MigrationError> {
+    let from_version = detect_version(kind, &value)?;
+    let current = kind.current_version();
+
+    if compare_versions(kind, &from_version, current)? == Ordering::Greater {
+        return Err(MigrationError::NewerVersion {
+            kind: kind.name(),
+            found: from_version,
+            supported: current,
+        });
+    }
+
+    let mut version = from_version.clone();
+    let mut applied = Vec::new();
+
+    while compare_versions(kind, &version, current)? == Ordering::Less {
+        let step = kind
+            .migrations()
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| MigrationError::MissingStep {
+                kind: kind.name(),
+                from: version.clone(),
+            })?;
+
+        (step.apply)(&mut value, ctx).map_err(|message| MigrationError::StepFailed {
+            kind: kind.name(),
+            from: step.from,
+            to: step.to,
+            message,
+        })?;
+        stamp_version(&mut value, step.to);
+
+        log::info!("{} 数据已迁移: {} -> {} ({})", kind.name(), step.from, step.to, step.description);
+        applied.push(step.to);
+        version = step.to.to_string();
+    }
+
+    Ok(MigrationOutcome {
+        value,
+        from_version,
+        applied,
+    })
+}
+
+/// 迁移用户数据包，以及其中的 tokens、统一配置和书签
+pub fn migrate_sync_package(value: Value, ctx: &MigrationContext) -> Result<Value, MigrationError> {
+    let mut value = migrate_value(DataKind::SyncPackage, value, ctx)?.value;
+
+    if let Some(obj) = value.as_object_mut() {
+        for (key, kind) in [
+            ("tokens", DataKind::Tokens),
+            ("unified_config", DataKind::Config),
+            ("bookmarks", DataKind::Bookmarks),
+        ] {
+            if let Some(nested) = obj.get_mut(key) {
+                if nested.is_null() {
+                    continue;
+                }
+                *nested = migrate_value(kind, nested.take(), ctx)?.value;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// 备份文件名：`tokens.json.v1.0.0-20250101T120000.bak`
+pub fn backup_path(path: &Path, version: &str) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "data".to_string());
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S");
+    path.with_file_name(format!("{}.v{}-{}.bak", file_name, version, timestamp))
+}
+
+/// 从备份内容中清除敏感字段，避免迁移备份保留已移出配置文件的明文密码
+fn scrub_backup(kind: DataKind, value: &mut Value) -> bool {
+    if kind != DataKind::Config {
+        return false;
+    }
+    match value.pointer_mut("/webdav_config/password") {
+        Some(password) if password.as_str().is_some_and(|p| !p.is_empty()) => {
+            *password = Value::String(String::new());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 读取并迁移磁盘上的数据文件
+///
+/// 文件不存在或为空时返回 `None`。需要升级时获取数据目录的写入锁，在锁内重新读取、
+/// 先备份原文件，再原子性写回迁移后的内容。调用方已持有写入锁时使用 `migrate_file_locked`。
+pub fn migrate_file(
+    kind: DataKind,
+    path: &Path,
+    ctx: &MigrationContext,
+) -> Result<Option<Value>, MigrationError> {
+    let Some((_, value)) = read_document(kind, path)? else {
+        return Ok(None);
+    };
+    if detect_version(kind, &value)? == kind.current_version() {
+        return Ok(Some(value));
+    }
+
+    let _write_lock = WriteLock::acquire(path.parent().unwrap_or(Path::new(".")))
+        .map_err(|e| MigrationError::Io(e.to_string()))?;
+    migrate_file_locked(kind, path, ctx)
+}
+
+/// 同 `migrate_file`，调用方已持有数据目录的写入锁
+pub fn migrate_file_locked(
+    kind: DataKind,
+    path: &Path,
+    ctx: &MigrationContext,
+) -> Result<Option<Value>, MigrationError> {
+    let Some((content, value)) = read_document(kind, path)? else {
+        return Ok(None);
+    };
+
+    let mut original = value.clone();
+    let outcome = migrate_value(kind, value, ctx)?;
+    if outcome.migrated() {
+        let backup = backup_path(path, &outcome.from_version);
+        let backup_content = if scrub_backup(kind, &mut original) {
+            serde_json::to_string_pretty(&original).unwrap_or_default()
+        } else {
+            content
+        };
+        fs::write(&backup, backup_content)
+            .map_err(|e| MigrationError::Io(format!("备份 {} 失败: {}", path.display(), e)))?;
+
+        let migrated = serde_json::to_string_pretty(&outcome.value).map_err(|e| MigrationError::Parse {
+            kind: kind.name(),
+            message: e.to_string(),
+        })?;
+        // 本进程的写入，数据目录检测不应视为外部修改
+        data_watcher::record_write(path, migrated.as_bytes());
+        write_file_atomic(path, migrated.as_bytes()).map_err(MigrationError::Io)?;
+
+        log::info!("已升级 {}，原文件备份为 {}", path.display(), backup.display());
+    }
+
+    Ok(Some(outcome.value))
+}
+
+/// 读取数据文件的原始内容和 JSON；文件不存在或为空时返回 None
+fn read_document(kind: DataKind, path: &Path) -> Result<Option<(String, Value)>, MigrationError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| MigrationError::Io(format!("读取 {} 失败: {}", path.display(), e)))?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let value: Value = serde_json::from_str(&content).map_err(|e| MigrationError::Parse {
+        kind: kind.name(),
+        message: e.to_string(),
+    })?;
+    Ok(Some((content, value)))
+}
+
+/// 覆盖写入前检查：拒绝覆盖由更新版本应用写入的文件
+pub fn ensure_writable(kind: DataKind, path: &Path) -> Result<(), MigrationError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Ok(()),
+    };
+
+    // 无法解析的文件不含版本信息，交由调用方覆盖
+    let value: Value = match serde_json::from_str(&content) {
+        Ok(value) => value,
+        Err(_) => return Ok(()),
+    };
+
+    let version = detect_version(kind, &value)?;
+    if compare_versions(kind, &version, kind.current_version())? == Ordering::Greater {
+        return Err(MigrationError::NewerVersion {
+            kind: kind.name(),
+            found: version,
+            supported: kind.current_version(),
+        });
+    }
+    Ok(())
+}
+
+// ==================== tokens 文档辅助函数 ====================
+
+/// 用当前版本的结构包装 token 列表
+pub fn tokens_document(tokens: Vec<Value>) -> Value {
+    json!({
+        "version": TOKENS_VERSION,
+        "tokens": tokens,
+    })
+}
+
+/// 从已迁移的 tokens 文档中取出 token 列表
+pub fn tokens_from_document(document: Value) -> Vec<Value> {
+    match document {
+        Value::Object(mut obj) => match obj.remove("tokens") {
+            Some(Value::Array(array)) => array,
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+// ==================== 旧版独立配置文件 ====================
+
+/// 统一配置出现之前的旧配置文件
+pub const LEGACY_APP_SETTINGS_FILE: &str = "app_settings.json";
+pub const LEGACY_WEBDAV_CONFIG_FILE: &str = "webdav_config.json";
+
+/// 将旧的 app_settings.json / webdav_config.json 合并为一份当前版本的统一配置
+///
+/// 旧的 webdav_config.json 本身不含密码（密码一直保存在系统密钥链中），迁移后的配置
+/// 直接引用该密钥链条目。返回迁移后的配置文档和被读取的旧文件列表，调用方在新配置
+/// 保存成功后再删除这些旧文件。
+pub fn import_legacy_config_files(
+    data_dir: &Path,
+    ctx: &MigrationContext,
+) -> Result<(Value, Vec<PathBuf>), MigrationError> {
+    let mut config = Map::new();
+    let mut consumed = Vec::new();
+
+    let settings_path = data_dir.join(LEGACY_APP_SETTINGS_FILE);
+    if let Some(settings) = read_json_object(&settings_path) {
+        config.insert("app_settings".to_string(), Value::Object(settings));
+        consumed.push(settings_path);
+    }
+
+    let webdav_path = data_dir.join(LEGACY_WEBDAV_CONFIG_FILE);
+    if let Some(webdav) = read_json_object(&webdav_path) {
+        config.insert("webdav_config".to_string(), Value::Object(webdav));
+        consumed.push(webdav_path);
+    }
+
+    let outcome = migrate_value(DataKind::Config, Value::Object(config), ctx)?;
+    Ok((outcome.value, consumed))
+}
+
+fn read_json_object(path: &Path) -> Option<Map<String, Value>> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(obj)) => Some(obj),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    struct DiscardSecretStore;
+
+    impl SecretStore for DiscardSecretStore {
+        fn store_secret(&self, _account: &str, _secret: &str) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    fn ctx() -> MigrationContext<'static> {
+        MigrationContext::with_secrets(&DiscardSecretStore)
+    }
+
+    const ALL_KINDS: [DataKind; 5] = [
+        DataKind::Tokens,
+        DataKind::Config,
+        DataKind::Bookmarks,
+        DataKind::SyncPackage,
+        DataKind::Profiles,
+    ];
+
+    #[test]
+    fn test_registries_form_ordered_chains() {
+        for kind in ALL_KINDS {
+            let mut version = LEGACY_VERSION.to_string();
+            for step in kind.migrations() {
+                assert_eq!(step.from, version, "{} 迁移链不连续", kind.name());
+                assert_eq!(
+                    compare_versions(kind, step.from, step.to).unwrap(),
+                    Ordering::Less
+                );
+                version = step.to.to_string();
+            }
+            assert_eq!(version, kind.current_version(), "{} 迁移链未到达当前版本", kind.name());
+        }
+    }
+
+    #[test]
+    fn test_legacy_token_array_migration() {
+        let legacy = json!([
+            { "id": "a", "tag_text": "old", "balance_color_mode": "auto" },
+            { "id": "b", "tag_text": "ignored", "tag_name": "kept" }
+        ]);
+
+        let outcome = migrate_value(DataKind::Tokens, legacy, &ctx()).unwrap();
+        assert_eq!(outcome.from_version, LEGACY_VERSION);
+        assert_eq!(outcome.applied, vec![TOKENS_VERSION]);
+        assert_eq!(outcome.value["version"], TOKENS_VERSION);
+
+        let tokens = tokens_from_document(outcome.value);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0]["tag_name"], "old");
+        assert!(tokens[0].get("tag_text").is_none());
+        assert!(tokens[0].get("balance_color_mode").is_none());
+        assert_eq!(tokens[1]["tag_name"], "kept");
+    }
+
+    #[test]
+    fn test_single_token_object_is_wrapped() {
+        let legacy = json!({ "id": "single", "access_token": "t" });
+        let outcome = migrate_value(DataKind::Tokens, legacy, &ctx()).unwrap();
+        let tokens = tokens_from_document(outcome.value);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0]["id"], "single");
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let document = tokens_document(vec![json!({ "id": "a" })]);
+        let outcome = migrate_value(DataKind::Tokens, document.clone(), &ctx()).unwrap();
+        assert!(!outcome.migrated());
+        assert_eq!(outcome.value, document);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let document = json!({ "version": "99.0.0", "tokens": [] });
+        let result = migrate_value(DataKind::Tokens, document, &ctx());
+        assert!(matches!(result, Err(MigrationError::NewerVersion { .. })));
+
+        let config = json!({ "version": "2.0.0" });
+        assert!(matches!(
+            migrate_value(DataKind::Config, config, &ctx()),
+            Err(MigrationError::NewerVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_version_is_rejected() {
+        let config = json!({ "version": "latest" });
+        assert!(matches!(
+            migrate_value(DataKind::Config, config, &ctx()),
+            Err(MigrationError::InvalidVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_old_config_gets_defaults() {
+        let old = json!({ "custom_data_dir": "/data/zaugment" });
+        let outcome = migrate_value(DataKind::Config, old, &ctx()).unwrap();
+        let config = outcome.value;
+
+        assert_eq!(config["version"], CONFIG_VERSION);
+        assert_eq!(config["custom_data_dir"], "/data/zaugment");
+        assert_eq!(config["app_settings"]["current_view"], "token-generator");
+        assert_eq!(config["ui_settings"]["language"], "zh-CN");
+        assert_eq!(config["status_thresholds"]["timeMax"], 365);
+    }
+
+    #[test]
+    fn test_sync_package_moves_thresholds() {
+        let package = json!({
+            "version": "1.0.0",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "unified_config": { "version": "1.0.0" },
+            "status_thresholds": { "timeMax": 100 }
+        });
+        let outcome = migrate_value(DataKind::SyncPackage, package, &ctx()).unwrap();
+        assert!(outcome.value.get("status_thresholds").is_none());
+        assert_eq!(outcome.value["unified_config"]["status_thresholds"]["timeMax"], 100);
+    }
+
+    #[test]
+    fn test_migrate_file_creates_backup() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("tokens.json");
+        fs::write(&path, r#"[{"id": "a", "tag_text": "x"}]"#).unwrap();
+
+        let value = migrate_file(DataKind::Tokens, &path, &ctx()).unwrap().unwrap();
+        assert_eq!(value["version"], TOKENS_VERSION);
+
+        // 磁盘文件已升级
+        let on_disk: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk["version"], TOKENS_VERSION);
+
+        // 原文件已备份
+        let backups: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".bak"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let backup_content = fs::read_to_string(backups[0].path()).unwrap();
+        assert!(backup_content.contains("tag_text"));
+
+        // 再次迁移不会产生新的备份
+        migrate_file(DataKind::Tokens, &path, &ctx()).unwrap();
+        let backup_count = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".bak"))
+            .count();
+        assert_eq!(backup_count, 1);
+    }
+
+    #[test]
+    fn test_migration_write_is_not_an_external_change() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("tokens.json");
+        fs::write(&path, r#"[{"id": "a", "tag_text": "x"}]"#).unwrap();
+        let mut watcher = crate::data_watcher::DataWatcher::new(temp_dir.path());
+
+        migrate_file(DataKind::Tokens, &path, &ctx()).unwrap();
+        assert!(watcher.poll().is_empty());
+        assert!(watcher.conflicts(&["a".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_newer_file_is_not_overwritten() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("bookmarks.json");
+        let content = r#"{"version": "9.0.0", "bookmarks": []}"#;
+        fs::write(&path, content).unwrap();
+
+        assert!(migrate_file(DataKind::Bookmarks, &path, &ctx()).is_err());
+        assert!(ensure_writable(DataKind::Bookmarks, &path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_missing_file_returns_none() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        assert!(migrate_file(DataKind::Config, &path, &ctx()).unwrap().is_none());
+        assert!(ensure_writable(DataKind::Config, &path).is_ok());
+    }
+
+    #[test]
+    fn test_import_legacy_config_files() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(LEGACY_APP_SETTINGS_FILE),
+            r#"{"current_view": "token-list", "auto_sync_enabled": true, "last_sync_time": null}"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join(LEGACY_WEBDAV_CONFIG_FILE),
+            r#"{"server_url": "https://dav.example.com", "username": "alice", "enabled": true,
+                "auto_sync": false, "sync_interval_minutes": 30, "remote_path": "/ZAugment/tokens.json"}"#,
+        )
+        .unwrap();
+
+        let (config, consumed) = import_legacy_config_files(temp_dir.path(), &ctx()).unwrap();
+
+        assert_eq!(consumed.len(), 2);
+        assert_eq!(config["version"], CONFIG_VERSION);
+        assert_eq!(config["app_settings"]["current_view"], "token-list");
+        assert_eq!(config["webdav_config"]["credential"]["type"], "keyring");
+        assert_eq!(config["webdav_config"]["credential"]["account"], "alice@https://dav.example.com");
+    }
+
+    #[test]
+    fn test_plaintext_webdav_password_moves_to_secret_store() {
+        let store = PendingSecrets::default();
+        let config = json!({
+            "version": "1.1.0",
+            "webdav_config": {
+                "server_url": "https://dav.example.com",
+                "username": "alice",
+                "password": "secret",
+                "enabled": true
+            }
+        });
+
+        let outcome =
+            migrate_value(DataKind::Config, config, &MigrationContext::with_secrets(&store)).unwrap();
+        let webdav = &outcome.value["webdav_config"];
+
+        assert!(webdav.get("password").is_none());
+        assert_eq!(webdav["credential"]["type"], "keyring");
+        assert_eq!(webdav["credential"]["account"], "alice@https://dav.example.com");
+        assert_eq!(outcome.value["encrypted_credential_sync"], false);
+        assert!(!format!("{:?}", store).contains("secret"));
+        assert_eq!(store.take("alice@https://dav.example.com").as_deref(), Some("secret"));
+        assert_eq!(store.take("alice@https://dav.example.com"), None);
+    }
+
+    #[test]
+    fn test_plaintext_password_requires_secret_store() {
+        let config = json!({
+            "version": "1.1.0",
+            "webdav_config": { "username": "alice", "password": "secret" }
+        });
+        assert!(matches!(
+            migrate_value(DataKind::Config, config, &MigrationContext::default()),
+            Err(MigrationError::StepFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_config_backup_does_not_keep_password() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{"version": "1.1.0", "webdav_config": {"username": "alice", "password": "secret"}}"#,
+        )
+        .unwrap();
+
+        migrate_file(DataKind::Config, &path, &ctx()).unwrap();
+
+        for entry in fs::read_dir(temp_dir.path()).unwrap().filter_map(|e| e.ok()) {
+            let content = fs::read_to_string(entry.path()).unwrap();
+            assert!(!content.contains("secret"), "{:?} 中仍有明文密码", entry.path());
+        }
+    }
+}
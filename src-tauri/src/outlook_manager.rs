@@ -6,6 +6,8 @@ use native_tls::TlsStream;
 use std::net::TcpStream;
 use chrono;
 
+use crate::http_client::HttpClient;
+
 // XOAUTH2 认证器
 struct XOAuth2 {
     user: String,
@@ -85,13 +87,20 @@ struct TokenResponse {
 pub struct OutlookManager {
     credentials: HashMap<String, OutlookCredentials>,
     storage_path: Option<PathBuf>,
+    http_client: HttpClient,
 }
 
 impl OutlookManager {
     pub fn new() -> Self {
+        Self::with_http_client(HttpClient::default())
+    }
+
+    // 使用应用共享的 HTTP 客户端（代理等设置）获取访问令牌
+    pub fn with_http_client(http_client: HttpClient) -> Self {
         Self {
             credentials: HashMap::new(),
             storage_path: None,
+            http_client,
         }
     }
 
@@ -163,7 +172,7 @@ impl OutlookManager {
 
     // 获取访问令牌（每次重新获取）
     pub async fn get_access_token(&self, credentials: &OutlookCredentials) -> Result<String, String> {
-        println!("🔑 获取短期 Access Token for {}", credentials.email);
+        log::info!("🔑 获取短期 Access Token for {}", credentials.email);
         
         let token_url = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
         let params = [
@@ -173,8 +182,7 @@ impl OutlookManager {
             ("scope", "https://outlook.office.com/IMAP.AccessAsUser.All offline_access"),
         ];
 
-        let client = reqwest::Client::new();
-        let response = client
+        let response = self.http_client.client()
             .post(token_url)
             .form(&params)
             .send()
@@ -192,7 +200,7 @@ impl OutlookManager {
             .await
             .map_err(|e| format!("Failed to parse token response: {}", e))?;
 
-        println!("✅ 成功获取 Access Token for {} (有效期: {}秒)", credentials.email, token_response.expires_in);
+        log::info!("✅ 成功获取 Access Token for {} (有效期: {}秒)", credentials.email, token_response.expires_in);
         Ok(token_response.access_token)
     }
 
@@ -218,7 +226,7 @@ impl OutlookManager {
 
     // 创建 IMAP 连接（每次新建）
     async fn create_imap_connection(&self, credentials: &OutlookCredentials) -> Result<Session<TlsStream<TcpStream>>, String> {
-        println!("🔌 开始创建 IMAP 连接 for {}", credentials.email);
+        log::info!("🔌 开始创建 IMAP 连接 for {}", credentials.email);
         
         // 每次都重新获取 Access Token
         let access_token = self.get_access_token(credentials).await?;
@@ -226,7 +234,7 @@ impl OutlookManager {
         // 在异步上下文中运行同步IMAP代码
         let email = credentials.email.clone();
         tokio::task::spawn_blocking(move || {
-            println!("🌐 连接到 outlook.office365.com:993...");
+            log::info!("🌐 连接到 outlook.office365.com:993...");
             
             let tls = native_tls::TlsConnector::builder().build()
                 .map_err(|e| format!("TLS connector failed: {}", e))?;
@@ -234,7 +242,7 @@ impl OutlookManager {
             let client = imap::connect(("outlook.office365.com", 993), "outlook.office365.com", &tls)
                 .map_err(|e| format!("IMAP connect failed: {}", e))?;
 
-            println!("🔐 开始 XOAUTH2 认证...");
+            log::info!("🔐 开始 XOAUTH2 认证...");
             // XOAUTH2 认证
             let auth = XOAuth2 {
                 user: email,
@@ -244,7 +252,7 @@ impl OutlookManager {
                 .authenticate("XOAUTH2", &auth)
                 .map_err(|e| format!("IMAP authentication failed: {:?}", e))?;
 
-            println!("✅ IMAP 连接和认证成功");
+            log::info!("✅ IMAP 连接和认证成功");
             Ok(session)
         })
         .await
@@ -340,7 +348,7 @@ impl OutlookManager {
 
     // 使用凭证获取邮件列表（避免跨 await 持有锁）
     pub async fn get_emails_with_credentials(&self, credentials: &OutlookCredentials, folder: &str, page: i32, page_size: i32) -> Result<EmailListResponse, String> {
-        println!("📧 准备获取邮件 for {} - 文件夹: {}", credentials.email, folder);
+        log::info!("📧 准备获取邮件 for {} - 文件夹: {}", credentials.email, folder);
         
         // 每次都重新创建连接（包含获取新的 Access Token）
         let mut session = self.create_imap_connection(credentials).await?;
@@ -356,11 +364,11 @@ impl OutlookManager {
         let folder_clone = folder.to_string();
 
         tokio::task::spawn_blocking(move || {
-            println!("📂 选择文件夹: {}", folder_name);
+            log::info!("📂 选择文件夹: {}", folder_name);
             session.select(folder_name)
                 .map_err(|e| format!("Failed to select folder: {:?}", e))?;
 
-            println!("🔍 搜索邮件...");
+            log::info!("🔍 搜索邮件...");
             let messages = session.search("ALL")
                 .map_err(|e| format!("Failed to search messages: {:?}", e))?;
 
@@ -372,7 +380,7 @@ impl OutlookManager {
             let start_idx = ((page - 1) * page_size) as usize;
             let end_idx = std::cmp::min(start_idx + page_size as usize, message_vec.len());
 
-            println!("📊 找到 {} 封邮件，显示第 {} 页 ({}-{})", total_emails, page, start_idx + 1, end_idx);
+            log::info!("📊 找到 {} 封邮件，显示第 {} 页 ({}-{})", total_emails, page, start_idx + 1, end_idx);
 
             let mut emails = Vec::new();
 
@@ -440,7 +448,7 @@ impl OutlookManager {
             }
 
             session.logout().ok();
-            println!("✅ 成功获取 {} 封邮件", emails.len());
+            log::info!("✅ 成功获取 {} 封邮件", emails.len());
 
             Ok(EmailListResponse {
                 email_id: email_clone,
@@ -711,7 +719,7 @@ impl OutlookManager {
             .replace("\t", " ");
         
         let combined_text = format!("{} {}", subject, cleaned_content);
-        println!("🔍 验证码提取 - 清理后的文本: {}", combined_text);
+        log::debug!("🔍 验证码提取 - 文本长度: {}", combined_text.len());
         
         // 针对Augment Code和常见验证码格式的优化正则
         let patterns = [
@@ -745,7 +753,6 @@ impl OutlookManager {
                             // 排除明显不是验证码的数字（如年份、过小的数）
                             if code_num >= 1000 && !(1900..=2100).contains(&code_num) {
                                 candidates.push(code.to_string());
-                                println!("🔢 找到验证码候选: {}", code);
                             }
                         }
                     }
@@ -756,18 +763,18 @@ impl OutlookManager {
         // 优先返回6位数字（最常见的验证码长度）
         for candidate in &candidates {
             if candidate.len() == 6 {
-                println!("✅ 选择6位验证码: {}", candidate);
+                log::debug!("✅ 已找到6位验证码");
                 return Some(candidate.clone());
             }
         }
 
         // 其次返回第一个找到的4-8位验证码
         if let Some(first) = candidates.first() {
-            println!("✅ 选择验证码: {}", first);
+            log::debug!("✅ 已找到验证码 ({}位)", first.len());
             return Some(first.clone());
         }
 
-        println!("❌ 未找到验证码");
+        log::warn!("❌ 未找到验证码");
         None
     }
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+//! 命名配置档案
+//!
+//! 每个档案包含一组独立的数据目录、WebDAV 目标和状态阈值，例如个人数据和团队共享数据。
+//! 档案列表保存在默认应用数据目录的 profiles.json 中；当前生效的设置仍然写在
+//! `UnifiedAppConfig` 里，切换档案时由调用方把档案中的设置应用到配置和运行状态。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::migrations::{self, DataKind, MigrationContext};
+use crate::storage::write_file_atomic;
+use crate::thresholds::StatusThresholds;
+use crate::webdav::SecureWebDAVConfig;
+
+pub const PROFILES_FILE: &str = "profiles.json";
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+const MAX_PROFILE_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub custom_data_dir: Option<String>,
+    pub webdav_config: Option<SecureWebDAVConfig>,
+    pub status_thresholds: Option<StatusThresholds>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ConfigProfile {
+    pub fn new(
+        name: String,
+        custom_data_dir: Option<String>,
+        webdav_config: Option<SecureWebDAVConfig>,
+        status_thresholds: Option<StatusThresholds>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            name,
+            custom_data_dir,
+            webdav_config,
+            status_thresholds,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileStore {
+    pub version: String,
+    pub active_profile: String,
+    pub profiles: Vec<ConfigProfile>,
+}
+
+impl ProfileStore {
+    /// 以一个初始档案创建档案列表，该档案即为当前档案
+    pub fn new(initial: ConfigProfile) -> Self {
+        Self {
+            version: migrations::PROFILES_VERSION.to_string(),
+            active_profile: initial.name.clone(),
+            profiles: vec![initial],
+        }
+    }
+
+    /// 从文件加载档案列表，文件不存在时返回 None
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        let document = migrations::migrate_file(DataKind::Profiles, path, &MigrationContext::default())
+            .map_err(|e| format!("加载配置档案失败: {}", e))?;
+
+        match document {
+            Some(document) => {
+                let store: ProfileStore = serde_json::from_value(document)
+                    .map_err(|e| format!("解析配置档案失败: {}", e))?;
+                if store.get(&store.active_profile).is_none() {
+                    return Err(format!("当前配置档案不存在: {}", store.active_profile));
+                }
+                Ok(Some(store))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        migrations::ensure_writable(DataKind::Profiles, path).map_err(|e| e.to_string())?;
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("序列化配置档案失败: {}", e))?;
+        write_file_atomic(path, content.as_bytes())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConfigProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn active(&self) -> &ConfigProfile {
+        self.get(&self.active_profile)
+            .expect("active profile must exist")
+    }
+
+    /// 用当前生效的设置刷新当前档案，在切换或复制前调用
+    pub fn update_active(
+        &mut self,
+        custom_data_dir: Option<String>,
+        webdav_config: Option<SecureWebDAVConfig>,
+        status_thresholds: Option<StatusThresholds>,
+    ) {
+        let active_name = self.active_profile.clone();
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.name == active_name) {
+            profile.custom_data_dir = custom_data_dir;
+            profile.webdav_config = webdav_config;
+            profile.status_thresholds = status_thresholds;
+            profile.updated_at = Utc::now();
+        }
+    }
+
+    /// 新建档案：使用指定数据目录、不配置 WebDAV、使用默认阈值
+    pub fn create(&mut self, name: &str, custom_data_dir: Option<String>) -> Result<&ConfigProfile, String> {
+        let name = self.validate_new_name(name)?;
+        self.profiles.push(ConfigProfile::new(
+            name,
+            custom_data_dir,
+            None,
+            Some(StatusThresholds::default()),
+        ));
+        Ok(self.profiles.last().unwrap())
+    }
+
+    /// 以现有档案为模板创建新档案
+    pub fn clone_profile(&mut self, source: &str, name: &str) -> Result<&ConfigProfile, String> {
+        let name = self.validate_new_name(name)?;
+        let source = self.get(source)
+            .ok_or_else(|| format!("配置档案不存在: {}", source))?;
+
+        let profile = ConfigProfile::new(
+            name,
+            source.custom_data_dir.clone(),
+            source.webdav_config.clone(),
+            source.status_thresholds.clone(),
+        );
+        self.profiles.push(profile);
+        Ok(self.profiles.last().unwrap())
+    }
+
+    /// 删除档案，不能删除当前档案
+    pub fn delete(&mut self, name: &str) -> Result<ConfigProfile, String> {
+        if name == self.active_profile {
+            return Err("不能删除当前正在使用的配置档案，请先切换到其他档案".to_string());
+        }
+        let index = self.profiles.iter().position(|p| p.name == name)
+            .ok_or_else(|| format!("配置档案不存在: {}", name))?;
+        Ok(self.profiles.remove(index))
+    }
+
+    /// 将指定档案标记为当前档案
+    pub fn set_active(&mut self, name: &str) -> Result<&ConfigProfile, String> {
+        if self.get(name).is_none() {
+            return Err(format!("配置档案不存在: {}", name));
+        }
+        self.active_profile = name.to_string();
+        Ok(self.active())
+    }
+
+    fn validate_new_name(&self, name: &str) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("配置档案名称不能为空".to_string());
+        }
+        if name.chars().count() > MAX_PROFILE_NAME_LEN {
+            return Err(format!("配置档案名称不能超过 {} 个字符", MAX_PROFILE_NAME_LEN));
+        }
+        if self.profiles.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+            return Err(format!("配置档案已存在: {}", name));
+        }
+        Ok(name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn store() -> ProfileStore {
+        ProfileStore::new(ConfigProfile::new(
+            DEFAULT_PROFILE_NAME.to_string(),
+            None,
+            None,
+            Some(StatusThresholds::default()),
+        ))
+    }
+
+    #[test]
+    fn test_create_and_switch_profile() {
+        let mut store = store();
+        store.create("team", Some("/data/team".to_string())).unwrap();
+
+        let active = store.set_active("team").unwrap();
+        assert_eq!(active.custom_data_dir.as_deref(), Some("/data/team"));
+        assert_eq!(store.active_profile, "team");
+
+        assert!(store.set_active("missing").is_err());
+        assert_eq!(store.active_profile, "team");
+    }
+
+    #[test]
+    fn test_profile_names_are_validated() {
+        let mut store = store();
+        assert!(store.create("  ", None).is_err());
+        assert!(store.create("DEFAULT", None).is_err());
+        assert!(store.create(&"x".repeat(MAX_PROFILE_NAME_LEN + 1), None).is_err());
+
+        let created = store.create("  团队 ", None).unwrap();
+        assert_eq!(created.name, "团队");
+    }
+
+    #[test]
+    fn test_clone_copies_settings() {
+        let mut store = store();
+        store.update_active(Some("/data/personal".to_string()), None, None);
+
+        let cloned = store.clone_profile(DEFAULT_PROFILE_NAME, "copy").unwrap();
+        assert_eq!(cloned.custom_data_dir.as_deref(), Some("/data/personal"));
+        assert!(cloned.status_thresholds.is_none());
+
+        assert!(store.clone_profile("missing", "other").is_err());
+    }
+
+    #[test]
+    fn test_cannot_delete_active_profile() {
+        let mut store = store();
+        store.create("team", None).unwrap();
+
+        assert!(store.delete(DEFAULT_PROFILE_NAME).is_err());
+        assert!(store.delete("missing").is_err());
+
+        let removed = store.delete("team").unwrap();
+        assert_eq!(removed.name, "team");
+        assert_eq!(store.profiles.len(), 1);
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(PROFILES_FILE);
+
+        assert!(ProfileStore::load(&path).unwrap().is_none());
+
+        let mut store = store();
+        store.create("team", Some("/data/team".to_string())).unwrap();
+        store.set_active("team").unwrap();
+        store.save(&path).unwrap();
+
+        let loaded = ProfileStore::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.active_profile, "team");
+        assert_eq!(loaded.profiles.len(), 2);
+        assert_eq!(loaded.version, migrations::PROFILES_VERSION);
+    }
+}
@@ -0,0 +1,496 @@
+//! 后台定时检测账号状态
+//!
+//! 按设置的间隔（或每天固定时间）检测所有未设置跳过检测的账号，检测时间加入随机抖动，
+//! 并发数受上限控制。检测结果（封禁状态、门户信息、封禁详情、自动刷新的 token）写回存储，
+//! 每轮的汇总保存在数据目录中，应用重启后据此计算下一轮时间。
+
+use crate::augment_api::AugmentApi;
+use crate::augment_oauth::{
+    batch_check_account_status_with, AccountStatusCode, BatchCheckOptions, CancelHandle, CheckLimiter, TokenInfo,
+    TokenStatusResult,
+};
+use crate::augment_user_info::SharedAppSessions;
+use crate::storage::token_fields::Suspension;
+use crate::storage::{write_file_atomic, BanStatus, ChangeSource, LocalFileStorage, TokenData, TokenStorage};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// 后台检查是否到达检测时间的间隔
+pub const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// 最近一次检测的汇总
+pub const SUMMARY_FILE: &str = "status_check_summary.json";
+
+/// 定时检测设置，保存在 UnifiedAppConfig 中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusCheckSchedule {
+    pub enabled: bool,
+    /// 每天检测的本地时间（HH:MM），设置后忽略 `interval_hours`
+    pub daily_at: Option<String>,
+    /// 两轮检测的间隔小时数
+    pub interval_hours: u32,
+    /// 每轮在计划时间后随机推迟的最长分钟数
+    pub jitter_minutes: u32,
+    /// 本轮同时检测的账号数上限；与手动批量检测合计的并发仍受批量检测设置限制
+    pub max_concurrency: usize,
+}
+
+impl Default for StatusCheckSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            daily_at: None,
+            interval_hours: 24,
+            jitter_minutes: 30,
+            max_concurrency: 4,
+        }
+    }
+}
+
+impl StatusCheckSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(time) = &self.daily_at {
+            parse_time(time)?;
+        } else if self.interval_hours == 0 {
+            return Err("检测间隔必须大于 0 小时".to_string());
+        }
+        if self.max_concurrency == 0 {
+            return Err("并发数必须大于 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("无效的时间: {}（格式为 HH:MM）", time))
+}
+
+/// 上一轮检测之后的下一次检测时间；`jitter` 取值 [0, 1)，决定在计划时间后推迟多久
+pub fn next_run_after<Tz: TimeZone>(schedule: &StatusCheckSchedule, last_run: &DateTime<Tz>, jitter: f64) -> DateTime<Utc> {
+    let jitter = Duration::seconds((f64::from(schedule.jitter_minutes) * 60.0 * jitter.clamp(0.0, 1.0)) as i64);
+
+    let planned = match schedule.daily_at.as_deref().map(parse_time) {
+        Some(Ok(time)) => {
+            let timezone = last_run.timezone();
+            let same_day = last_run
+                .date_naive()
+                .and_time(time)
+                .and_local_timezone(timezone.clone())
+                .earliest();
+            match same_day {
+                Some(planned) if planned > *last_run => planned.with_timezone(&Utc),
+                _ => {
+                    let next_day = last_run.date_naive().succ_opt().unwrap_or(last_run.date_naive()).and_time(time);
+                    // 夏令时切换导致时间不存在时按 UTC 计算
+                    next_day
+                        .and_local_timezone(timezone)
+                        .earliest()
+                        .map(|planned| planned.with_timezone(&Utc))
+                        .unwrap_or_else(|| Utc.from_utc_datetime(&next_day))
+                }
+            }
+        }
+        _ => last_run.with_timezone(&Utc) + Duration::hours(i64::from(schedule.interval_hours.max(1))),
+    };
+    planned + jitter
+}
+
+/// 从未检测过时，以启动时间往前推一个周期作为上一轮，启动后（加上抖动）即检测
+pub fn next_run(schedule: &StatusCheckSchedule, last_run: Option<DateTime<Utc>>, now: DateTime<Utc>, jitter: f64) -> DateTime<Utc> {
+    match last_run {
+        Some(last_run) => next_run_after(schedule, &last_run.with_timezone(&Local), jitter),
+        None => now + Duration::seconds((f64::from(schedule.jitter_minutes) * 60.0 * jitter.clamp(0.0, 1.0)) as i64),
+    }
+}
+
+/// 一轮检测的汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckSummary {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// "scheduled" 或 "manual"
+    pub trigger: String,
+    pub checked: usize,
+    /// 跳过检测的账号数
+    pub skipped: usize,
+    /// 结果有变化并已保存的账号数
+    pub updated: usize,
+    /// 自动刷新了 access_token 的账号数
+    pub refreshed: usize,
+    pub errors: usize,
+    /// 检测被取消，只保存了已完成的部分
+    #[serde(default)]
+    pub cancelled: bool,
+    /// 各状态的账号数
+    pub statuses: BTreeMap<AccountStatusCode, usize>,
+}
+
+pub fn load_summary(data_dir: &Path) -> Option<CheckSummary> {
+    let content = fs::read_to_string(data_dir.join(SUMMARY_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_summary(data_dir: &Path, summary: &CheckSummary) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(summary).map_err(|e| format!("序列化检测汇总失败: {}", e))?;
+    write_file_atomic(&data_dir.join(SUMMARY_FILE), content.as_bytes())
+}
+
+/// 只写回检测过程中刷新得到的 token（检测结果不确定时也要保存，旧 token 已经失效）
+fn apply_refreshed_token(token: &mut TokenData, result: &TokenStatusResult) -> bool {
+    let mut changed = false;
+
+    if token.access_token != result.access_token {
+        token.access_token = result.access_token.clone();
+        changed = true;
+    }
+    if token.tenant_url != result.tenant_url {
+        token.tenant_url = result.tenant_url.clone();
+        changed = true;
+    }
+    if result.portal_url.is_some() && token.portal_url != result.portal_url {
+        token.portal_url = result.portal_url.clone();
+        changed = true;
+    }
+
+    if changed {
+        token.updated_at = Utc::now();
+    }
+    changed
+}
+
+/// 将检测结果写回账号，与前端批量检测后的处理一致；返回是否有变化
+pub fn apply_status_result(token: &mut TokenData, result: &TokenStatusResult) -> bool {
+    let mut changed = apply_refreshed_token(token, result);
+
+    let status = BanStatus::parse(result.status_result.status.as_str());
+    if token.ban_status.as_ref() != Some(&status) {
+        token.ban_status = Some(status);
+        changed = true;
+    }
+
+    if let Some(suspensions) = result.suspensions.as_ref().and_then(Suspension::list_from_value) {
+        if token.suspensions.as_ref() != Some(&suspensions) {
+            token.suspensions = Some(suspensions);
+            changed = true;
+        }
+    }
+
+    if let Some(portal_info) = &result.portal_info {
+        // 在原有门户信息上更新，保留未识别的字段
+        let mut new_info = token.portal_info.clone().unwrap_or_default();
+        new_info.credits_balance = Some(portal_info.credits_balance);
+        new_info.expiry_date = portal_info.expiry_date.clone();
+        if token.portal_info.as_ref() != Some(&new_info) {
+            token.portal_info = Some(new_info);
+            changed = true;
+        }
+    }
+
+    if result.email_note.is_some() && token.email_note != result.email_note {
+        token.email_note = result.email_note.clone();
+        changed = true;
+    }
+
+    if changed {
+        token.updated_at = Utc::now();
+    }
+    changed
+}
+
+/// 后台检测在取消命令中使用的批次 id
+pub const BATCH_ID: &str = "status-check";
+
+/// 检测所有未跳过检测的账号并写回结果，汇总保存到数据目录
+///
+/// 检测期间账号可能被修改，写回在存储锁内重新读取后进行，只更新检测涉及的字段所在的账号。
+/// 同一时间只运行一轮检测由调用方保证（应用中以 `BATCH_ID` 登记在 `AppState::batch_checks` 中）。
+pub async fn run_checks(
+    api: &AugmentApi,
+    storage: &LocalFileStorage,
+    app_session_cache: SharedAppSessions,
+    options: &BatchCheckOptions,
+    limiter: &CheckLimiter,
+    cancel: &CancelHandle,
+    trigger: &str,
+) -> Result<CheckSummary, String> {
+    let started_at = Utc::now();
+    let storage = storage.for_source(ChangeSource::Scheduled);
+
+    let tokens = storage.load_tokens().await.map_err(|e| format!("读取账号失败: {}", e))?;
+    let (targets, skipped): (Vec<&TokenData>, Vec<&TokenData>) =
+        tokens.iter().partition(|t| !t.skip_check.unwrap_or(false));
+    let previous_tokens: HashMap<&str, &str> = targets.iter().map(|t| (t.id.as_str(), t.access_token.as_str())).collect();
+
+    let infos: Vec<TokenInfo> = targets
+        .iter()
+        .map(|t| TokenInfo {
+            access_token: t.access_token.clone(),
+            tenant_url: t.tenant_url.clone(),
+            id: Some(t.id.clone()),
+            portal_url: t.portal_url.clone(),
+            auth_session: t.auth_session.clone(),
+            email_note: t.email_note.clone(),
+        })
+        .collect();
+    // 取消时仍保存已完成的部分
+    let outcome = batch_check_account_status_with(api, infos, app_session_cache, options, limiter, cancel, |_| {}).await?;
+    let results = outcome.results;
+
+    let mut summary = CheckSummary {
+        started_at,
+        trigger: trigger.to_string(),
+        checked: results.len(),
+        skipped: skipped.len(),
+        cancelled: outcome.cancelled,
+        ..CheckSummary::default()
+    };
+
+    // 先统计，再在存储锁内读取最新数据并写回，避免覆盖检测期间用户的修改
+    let mut writes = Vec::new();
+    for result in &results {
+        *summary.statuses.entry(result.status_result.status).or_default() += 1;
+        let conclusive = result.status_result.status.is_conclusive();
+        if !conclusive {
+            summary.errors += 1;
+        }
+        let Some(id) = result.token_id.as_deref() else { continue };
+        let refreshed = previous_tokens.get(id).is_some_and(|old| *old != result.access_token);
+        if refreshed {
+            summary.refreshed += 1;
+        }
+        // 请求失败、限流或服务端错误时不覆盖已有状态，但刷新得到的新 token 仍要保存
+        if conclusive || refreshed {
+            writes.push((id, conclusive, result));
+        }
+    }
+
+    let updated = storage
+        .modify_tokens(|tokens| {
+            let mut updated = 0;
+            for (id, conclusive, result) in &writes {
+                // 检测期间被删除的账号不再写回
+                let Some(token) = tokens.iter_mut().find(|t| t.id == *id) else { continue };
+                let changed = if *conclusive {
+                    apply_status_result(token, result)
+                } else {
+                    apply_refreshed_token(token, result)
+                };
+                if changed {
+                    updated += 1;
+                }
+            }
+            (updated, updated > 0)
+        })
+        .await
+        .map_err(|e| format!("保存检测结果失败: {}", e))?;
+    summary.updated = updated;
+    summary.finished_at = Utc::now();
+
+    if let Some(data_dir) = storage.storage_path().parent() {
+        if let Err(e) = save_summary(data_dir, &summary) {
+            log::warn!("保存检测汇总失败: {}", e);
+        }
+    }
+    log::info!(
+        "账号状态检测完成（{}）: 检测 {}，更新 {}，刷新 {}，失败 {}",
+        trigger,
+        summary.checked,
+        summary.updated,
+        summary.refreshed,
+        summary.errors
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment_api::mock_server::{MockAccount, MockAugmentServer, MockTokenStatus};
+    use crate::augment_oauth::{AccountStatus, PortalInfo, StatusReason};
+    use crate::augment_user_info::AppSessionStore;
+    use crate::storage::token_fields::TokenPortalInfo;
+    use chrono::FixedOffset;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    fn token(id: &str, ban_status: Option<BanStatus>, tag: Option<&str>) -> TokenData {
+        let mut token = TokenData::new(
+            id.to_string(),
+            "https://d1.api.augmentcode.com/".to_string(),
+            format!("token_{}", id),
+            None,
+            Some(format!("{}@example.com", id)),
+        );
+        token.ban_status = ban_status;
+        token.tag_name = tag.map(str::to_string);
+        token
+    }
+
+    #[test]
+    fn test_apply_status_result_updates_changed_fields() {
+        let mut existing = token("a", Some(BanStatus::Active), Some("work"));
+        let result = TokenStatusResult {
+            token_id: Some("a".to_string()),
+            access_token: "refreshed".to_string(),
+            tenant_url: existing.tenant_url.clone(),
+            portal_url: None,
+            status_result: AccountStatus {
+                is_banned: true,
+                status: AccountStatusCode::Suspended,
+                reason: Some(StatusReason::ResponseKeyword { keyword: "suspended".to_string() }),
+                error: None,
+                error_message: None,
+                response_code: Some(200),
+                debug_info: None,
+            },
+            portal_info: Some(PortalInfo { credits_balance: 42, expiry_date: None }),
+            portal_error: None,
+            suspensions: Some(serde_json::json!([{ "suspensionType": "ABUSE" }])),
+            email_note: None,
+        };
+
+        assert!(apply_status_result(&mut existing, &result));
+        assert_eq!(existing.access_token, "refreshed");
+        assert_eq!(existing.ban_status, Some(BanStatus::Suspended));
+        assert_eq!(existing.credits_balance(), Some(42));
+        assert_eq!(existing.suspensions.as_ref().unwrap()[0].suspension_type.as_deref(), Some("ABUSE"));
+        assert_eq!(existing.tag_name.as_deref(), Some("work"));
+        assert_eq!(existing.email_note.as_deref(), Some("a@example.com"));
+
+        // 再次应用相同结果不产生变化
+        assert!(!apply_status_result(&mut existing, &result));
+    }
+
+    #[test]
+    fn test_apply_status_result_keeps_unknown_portal_fields() {
+        let mut existing = token("a", Some(BanStatus::Active), None);
+        existing.portal_info = TokenPortalInfo::from_value(&serde_json::json!({
+            "credits_balance": 10,
+            "plan": "pro"
+        }));
+        let result = TokenStatusResult {
+            token_id: Some("a".to_string()),
+            access_token: existing.access_token.clone(),
+            tenant_url: existing.tenant_url.clone(),
+            portal_url: None,
+            status_result: AccountStatus {
+                is_banned: false,
+                status: AccountStatusCode::Active,
+                reason: None,
+                error: None,
+                error_message: None,
+                response_code: Some(200),
+                debug_info: None,
+            },
+            portal_info: Some(PortalInfo { credits_balance: 20, expiry_date: Some("2025-01-01T00:00:00Z".to_string()) }),
+            portal_error: None,
+            suspensions: None,
+            email_note: None,
+        };
+
+        assert!(apply_status_result(&mut existing, &result));
+        let info = existing.portal_info.as_ref().unwrap();
+        assert_eq!(info.credits_balance, Some(20));
+        assert_eq!(info.expiry_date.as_deref(), Some("2025-01-01T00:00:00Z"));
+        assert_eq!(info.extra["plan"], "pro");
+    }
+
+    #[test]
+    fn test_next_run_interval_and_daily() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let last_run = offset.with_ymd_and_hms(2026, 3, 1, 9, 15, 0).unwrap();
+
+        let interval = StatusCheckSchedule {
+            interval_hours: 6,
+            jitter_minutes: 20,
+            ..StatusCheckSchedule::default()
+        };
+        assert_eq!(next_run_after(&interval, &last_run, 0.0), last_run + Duration::hours(6));
+        assert_eq!(next_run_after(&interval, &last_run, 0.5), last_run + Duration::hours(6) + Duration::minutes(10));
+
+        // 当天的计划时间已过，顺延到第二天
+        let daily = StatusCheckSchedule {
+            daily_at: Some("07:30".to_string()),
+            jitter_minutes: 0,
+            ..StatusCheckSchedule::default()
+        };
+        assert_eq!(next_run_after(&daily, &last_run, 0.3), offset.with_ymd_and_hms(2026, 3, 2, 7, 30, 0).unwrap());
+        let early = offset.with_ymd_and_hms(2026, 3, 1, 6, 0, 0).unwrap();
+        assert_eq!(next_run_after(&daily, &early, 0.0), offset.with_ymd_and_hms(2026, 3, 1, 7, 30, 0).unwrap());
+
+        assert!(StatusCheckSchedule { daily_at: Some("7点".to_string()), ..daily }.validate().is_err());
+        assert!(StatusCheckSchedule { max_concurrency: 0, ..interval }.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_checks_persists_results_and_skips_flagged_tokens() {
+        let active = MockAccount::new("active@example.com", "token-active", MockTokenStatus::Active).with_portal("portal-a", 77.0);
+        let invalid = MockAccount::new("invalid@example.com", "token-invalid", MockTokenStatus::Invalid)
+            .with_session("session-b", "token-b-new");
+        let server = MockAugmentServer::start(vec![active.clone(), invalid.clone()]).await;
+
+        let dir = tempdir().unwrap();
+        let storage = LocalFileStorage::new_with_path(dir.path().join("tokens.json"));
+        let tenant_url = format!("{}/", server.base_url());
+        let mut a = TokenData::new("a".into(), tenant_url.clone(), active.access_token.clone(), Some(server.portal_url("portal-a")), None);
+        a.tag_name = Some("keep".to_string());
+        let mut b = TokenData::new("b".into(), tenant_url.clone(), invalid.access_token.clone(), Some(server.portal_url("portal-a")), None);
+        b.auth_session = Some("session-b".to_string());
+        let mut c = TokenData::new("c".into(), tenant_url, "token-unknown".into(), None, None);
+        c.skip_check = Some(true);
+        storage.save_tokens(&[a, b, c]).await.unwrap();
+
+        let options = BatchCheckOptions { max_concurrency: 2, ..BatchCheckOptions::default() };
+        let cache = Arc::new(Mutex::new(AppSessionStore::default()));
+        let summary = run_checks(&server.api(), &storage, cache, &options, &CheckLimiter::default(), &CancelHandle::default(), "manual")
+            .await
+            .unwrap();
+        assert_eq!(summary.checked, 2);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.refreshed, 1);
+        assert!(!summary.cancelled);
+        assert_eq!(summary.statuses.get(&AccountStatusCode::Active), Some(&2));
+
+        let a = storage.get_token("a").await.unwrap().unwrap();
+        assert_eq!(a.ban_status, Some(BanStatus::Active));
+        assert_eq!(a.credits_balance(), Some(77));
+        assert_eq!(a.tag_name.as_deref(), Some("keep"));
+        assert_eq!(storage.get_token("b").await.unwrap().unwrap().access_token, "token-b-new");
+        assert_eq!(storage.get_token("c").await.unwrap().unwrap().ban_status, None);
+
+        let saved = load_summary(dir.path()).unwrap();
+        assert_eq!(saved.checked, 2);
+        assert_eq!(saved.trigger, "manual");
+    }
+
+    #[tokio::test]
+    async fn test_run_checks_keeps_refreshed_token_when_recheck_is_rate_limited() {
+        let account = MockAccount::new("limited@example.com", "token-old", MockTokenStatus::Invalid)
+            .with_session("session-l", "token-new")
+            .with_refreshed_status(MockTokenStatus::RateLimited);
+        let server = MockAugmentServer::start(vec![account.clone()]).await;
+
+        let dir = tempdir().unwrap();
+        let storage = LocalFileStorage::new_with_path(dir.path().join("tokens.json"));
+        let mut token = TokenData::new("l".into(), format!("{}/", server.base_url()), account.access_token.clone(), None, None);
+        token.auth_session = Some("session-l".to_string());
+        token.ban_status = Some(BanStatus::Active);
+        storage.save_tokens(&[token]).await.unwrap();
+
+        let cache = Arc::new(Mutex::new(AppSessionStore::default()));
+        let summary = run_checks(&server.api(), &storage, cache, &BatchCheckOptions::default(), &CheckLimiter::default(), &CancelHandle::default(), "manual")
+            .await
+            .unwrap();
+        assert_eq!(summary.refreshed, 1);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.statuses.get(&AccountStatusCode::RateLimited), Some(&1));
+
+        let saved = storage.get_token("l").await.unwrap().unwrap();
+        assert_eq!(saved.access_token, "token-new");
+        assert_eq!(saved.ban_status, Some(BanStatus::Active));
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// 原子性写入文件：先写入同目录下的临时文件，再重命名覆盖目标文件
+///
+/// 如果 rename 失败（例如跨文件系统），回退为复制+删除的方式。
+pub fn write_file_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let parent_dir = path.parent()
+        .ok_or_else(|| format!("Failed to get parent directory of {}", path.display()))?;
+
+    // 确保父目录存在
+    fs::create_dir_all(parent_dir)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let stem = path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("data");
+
+    // 使用同一目录下的唯一临时文件名，确保在同一文件系统上
+    let temp_path = parent_dir.join(format!("{}.{}.tmp", stem, uuid::Uuid::new_v4()));
+
+    let write_result = (|| -> Result<(), String> {
+        let mut temp_file = fs::File::create(&temp_path)
+            .map_err(|e| format!("Failed to create temp file: {}", e))?;
+
+        temp_file.write_all(content)
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+        temp_file.sync_all()
+            .map_err(|e| format!("Failed to sync temp file: {}", e))?;
+
+        Ok(())
+    })();
+
+    // 如果写入失败，清理临时文件
+    if let Err(e) = write_result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    // 尝试原子性重命名，如果失败则使用复制+删除的方式
+    match fs::rename(&temp_path, path) {
+        Ok(_) => Ok(()),
+        Err(rename_err) => {
+            log::error!("Rename failed, trying copy+delete: {}", rename_err);
+
+            match fs::copy(&temp_path, path) {
+                Ok(_) => {
+                    let _ = fs::remove_file(&temp_path);
+                    Ok(())
+                }
+                Err(copy_err) => {
+                    let _ = fs::remove_file(&temp_path);
+                    Err(format!("Failed to save file (rename: {}, copy: {})", rename_err, copy_err))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! 账号变更记录与回收站
+//!
+//! 每次写入 tokens.json 后，按账号把字段差异追加到 `history/journal.jsonl`（只追加，不改写），
+//! 被删除的账号移入 `history/trash.json`，在保留期内可以恢复。变更记录中保存了字段的旧值和新值，
+//! 因此可以从当前数据逐条回退，得到任意一次变更之后的版本。
+
+use super::atomic::write_file_atomic;
+use super::traits::{convert_legacy_token, convert_to_legacy_format, TokenData};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 数据目录下存放变更记录和回收站的子目录
+pub const HISTORY_DIR: &str = "history";
+const JOURNAL_FILE: &str = "journal.jsonl";
+const TRASH_FILE: &str = "trash.json";
+
+/// 回收站默认保留天数
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// 不参与差异比较的字段（每次保存都会变化）
+const IGNORED_FIELDS: &[&str] = &["updated_at"];
+
+/// 变更来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSource {
+    /// 前端界面操作
+    #[default]
+    Ui,
+    /// 本地 API 服务器导入
+    ApiImport,
+    /// 批量导入
+    BulkImport,
+    /// 状态检测时自动刷新 access_token
+    AutoRefresh,
+    /// 从云端同步恢复
+    Sync,
+    /// 命令行工具
+    Cli,
+    /// 从本地备份恢复
+    Backup,
+    /// 后台定时检测账号状态
+    Scheduled,
+}
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+    /// 从回收站恢复
+    Restored,
+    /// 回退到历史版本
+    Reverted,
+}
+
+/// 单个字段的变化，`None` 表示字段不存在
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// 一条变更记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub token_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: ChangeSource,
+    pub kind: ChangeKind,
+    pub changes: Vec<FieldChange>,
+}
+
+/// 回收站中的账号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub deleted_at: DateTime<Utc>,
+    pub source: ChangeSource,
+    /// tokens.json 格式的账号数据
+    pub token: Value,
+}
+
+impl TrashEntry {
+    pub fn token_id(&self) -> &str {
+        self.token.get("id").and_then(Value::as_str).unwrap_or_default()
+    }
+}
+
+/// 回收站设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistorySettings {
+    /// 回收站保留天数，0 表示永久保留
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+}
+
+fn default_trash_retention_days() -> u32 {
+    DEFAULT_TRASH_RETENTION_DAYS
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+        }
+    }
+}
+
+fn token_fields(token: Option<&TokenData>) -> Map<String, Value> {
+    match token.map(convert_to_legacy_format) {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// 比较同一账号的两个版本，返回有变化的字段（按字段名排序）
+pub fn diff_tokens(before: Option<&TokenData>, after: Option<&TokenData>) -> Vec<FieldChange> {
+    let before = token_fields(before);
+    let after = token_fields(after);
+
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field);
+            let new = after.get(field);
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                old: old.cloned(),
+                new: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// 比较写入前后的账号列表，生成变更记录并返回被删除的账号
+///
+/// `kind` 用于恢复、回退等操作指定变更类型；为 `None` 时按新增、修改、删除自动判断。
+pub fn collect_changes(
+    before: &[TokenData],
+    after: &[TokenData],
+    source: ChangeSource,
+    kind: Option<ChangeKind>,
+    now: DateTime<Utc>,
+) -> (Vec<JournalEntry>, Vec<TokenData>) {
+    let previous: HashMap<&str, &TokenData> = before.iter().map(|t| (t.id.as_str(), t)).collect();
+    let current: HashMap<&str, &TokenData> = after.iter().map(|t| (t.id.as_str(), t)).collect();
+
+    let entry = |token_id: &str, kind: ChangeKind, changes: Vec<FieldChange>| JournalEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        token_id: token_id.to_string(),
+        timestamp: now,
+        source,
+        kind,
+        changes,
+    };
+
+    let mut entries = Vec::new();
+    for token in after {
+        let old = previous.get(token.id.as_str()).copied();
+        let changes = diff_tokens(old, Some(token));
+        if !changes.is_empty() {
+            let default_kind = if old.is_some() { ChangeKind::Updated } else { ChangeKind::Created };
+            entries.push(entry(&token.id, kind.unwrap_or(default_kind), changes));
+        }
+    }
+
+    let removed: Vec<TokenData> = before
+        .iter()
+        .filter(|t| !current.contains_key(t.id.as_str()))
+        .cloned()
+        .collect();
+    for token in &removed {
+        entries.push(entry(&token.id, ChangeKind::Deleted, diff_tokens(Some(token), None)));
+    }
+
+    (entries, removed)
+}
+
+/// 从当前版本依次撤销 `later` 中的变更（按记录顺序传入），得到这些变更之前的版本
+pub fn reconstruct(current: &TokenData, later: &[JournalEntry]) -> Result<TokenData, String> {
+    let mut fields = token_fields(Some(current));
+    for entry in later.iter().rev() {
+        for change in &entry.changes {
+            match &change.old {
+                Some(value) => fields.insert(change.field.clone(), value.clone()),
+                None => fields.remove(&change.field),
+            };
+        }
+    }
+
+    let mut token = convert_legacy_token(&Value::Object(fields))
+        .map_err(|e| format!("无法还原历史版本: {}", e))?;
+    token.updated_at = Utc::now();
+    Ok(token)
+}
+
+/// 数据目录下的变更记录和回收站文件
+///
+/// 本身不加锁，由 `LocalFileStorage` 在 tokens.json 的文件锁内调用。
+#[derive(Debug, Clone)]
+pub struct TokenJournal {
+    dir: PathBuf,
+}
+
+impl TokenJournal {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join(HISTORY_DIR),
+        }
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.dir.join(JOURNAL_FILE)
+    }
+
+    fn trash_path(&self) -> PathBuf {
+        self.dir.join(TRASH_FILE)
+    }
+
+    /// 追加变更记录，每条一行
+    pub fn append(&self, entries: &[JournalEntry]) -> Result<(), String> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| format!("创建历史目录失败: {}", e))?;
+
+        let mut content = String::new();
+        for entry in entries {
+            let line = serde_json::to_string(entry).map_err(|e| format!("序列化变更记录失败: {}", e))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        // 记录中包含旧的 access_token，仅当前用户可读
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(self.journal_path())
+            .map_err(|e| format!("打开变更记录失败: {}", e))?;
+        file.write_all(content.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| format!("写入变更记录失败: {}", e))
+    }
+
+    /// 读取某个账号的全部变更记录（按写入顺序），跳过无法解析的行
+    pub fn history(&self, token_id: &str) -> Result<Vec<JournalEntry>, String> {
+        let content = match fs::read_to_string(self.journal_path()) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("读取变更记录失败: {}", e)),
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("跳过无法解析的变更记录: {}", e);
+                    None
+                }
+            })
+            .filter(|entry| entry.token_id == token_id)
+            .collect())
+    }
+
+    pub fn load_trash(&self) -> Result<Vec<TrashEntry>, String> {
+        match fs::read_to_string(self.trash_path()) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| format!("解析回收站失败: {}", e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("读取回收站失败: {}", e)),
+        }
+    }
+
+    pub fn save_trash(&self, entries: &[TrashEntry]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(entries).map_err(|e| format!("序列化回收站失败: {}", e))?;
+        write_file_atomic(&self.trash_path(), content.as_bytes())
+    }
+
+    /// 把被删除的账号放入回收站
+    pub fn move_to_trash(&self, tokens: &[TokenData], source: ChangeSource, now: DateTime<Utc>) -> Result<(), String> {
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        let mut trash = self.load_trash()?;
+        trash.extend(tokens.iter().map(|token| TrashEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            deleted_at: now,
+            source,
+            token: convert_to_legacy_format(token),
+        }));
+        self.save_trash(&trash)
+    }
+
+    /// 清理超过保留期的回收站条目，返回清理数量
+    pub fn purge_trash(&self, retention_days: u32, now: DateTime<Utc>) -> Result<usize, String> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let mut trash = self.load_trash()?;
+        let cutoff = now - Duration::days(i64::from(retention_days));
+        let initial_len = trash.len();
+        trash.retain(|entry| entry.deleted_at > cutoff);
+
+        let purged = initial_len - trash.len();
+        if purged > 0 {
+            self.save_trash(&trash)?;
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn token(id: &str, access_token: &str) -> TokenData {
+        TokenData::new(id.to_string(), "https://example.com".to_string(), access_token.to_string(), None, None)
+    }
+
+    #[test]
+    fn test_diff_ignores_updated_at() {
+        let before = token("a", "old");
+        let mut after = before.clone();
+        after.updated_at = before.updated_at + Duration::seconds(5);
+        assert!(diff_tokens(Some(&before), Some(&after)).is_empty());
+
+        after.access_token = "new".to_string();
+        after.tag_name = Some("work".to_string());
+        let changes = diff_tokens(Some(&before), Some(&after));
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["access_token", "tag_name"]);
+        assert_eq!(changes[0].old, Some(Value::from("old")));
+        assert_eq!(changes[0].new, Some(Value::from("new")));
+    }
+
+    #[test]
+    fn test_collect_changes_classifies_entries() {
+        let a = token("a", "token_a");
+        let b = token("b", "token_b");
+        let mut a_updated = a.clone();
+        a_updated.email_note = Some("a@example.com".to_string());
+        let c = token("c", "token_c");
+
+        let (entries, removed) = collect_changes(
+            &[a, b.clone()],
+            &[a_updated, c],
+            ChangeSource::Sync,
+            None,
+            Utc::now(),
+        );
+        let kinds: Vec<(&str, ChangeKind)> = entries.iter().map(|e| (e.token_id.as_str(), e.kind)).collect();
+        assert_eq!(
+            kinds,
+            [("a", ChangeKind::Updated), ("c", ChangeKind::Created), ("b", ChangeKind::Deleted)]
+        );
+        assert!(entries.iter().all(|e| e.source == ChangeSource::Sync));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, b.id);
+    }
+
+    #[test]
+    fn test_reconstruct_undoes_later_changes() {
+        let v1 = token("a", "token_v1");
+        let mut v2 = v1.clone();
+        v2.access_token = "token_v2".to_string();
+        v2.tag_name = Some("tag".to_string());
+        let mut v3 = v2.clone();
+        v3.access_token = "token_v3".to_string();
+
+        let now = Utc::now();
+        let (first, _) = collect_changes(std::slice::from_ref(&v1), std::slice::from_ref(&v2), ChangeSource::Ui, None, now);
+        let (second, _) = collect_changes(&[v2], &[v3.clone()], ChangeSource::AutoRefresh, None, now);
+
+        let restored = reconstruct(&v3, &[first[0].clone(), second[0].clone()]).unwrap();
+        assert_eq!(restored.access_token, "token_v1");
+        assert_eq!(restored.tag_name, None);
+
+        let restored = reconstruct(&v3, &second).unwrap();
+        assert_eq!(restored.access_token, "token_v2");
+        assert_eq!(restored.tag_name.as_deref(), Some("tag"));
+    }
+
+    #[test]
+    fn test_journal_and_trash_files() {
+        let temp_dir = tempdir().unwrap();
+        let journal = TokenJournal::new(temp_dir.path());
+        let now = Utc::now();
+
+        let (entries, removed) = collect_changes(&[token("a", "x"), token("b", "y")], &[], ChangeSource::Ui, None, now);
+        journal.append(&entries).unwrap();
+        journal.append(&entries[..1]).unwrap();
+        assert_eq!(journal.history("a").unwrap().len(), 2);
+        assert_eq!(journal.history("b").unwrap().len(), 1);
+        assert!(journal.history("missing").unwrap().is_empty());
+
+        journal.move_to_trash(&removed[..1], ChangeSource::Ui, now - Duration::days(40)).unwrap();
+        journal.move_to_trash(&removed[1..], ChangeSource::Ui, now).unwrap();
+        assert_eq!(journal.purge_trash(0, now).unwrap(), 0);
+        assert_eq!(journal.purge_trash(30, now).unwrap(), 1);
+
+        let trash = journal.load_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].token_id(), "b");
+    }
+}
@@ -1,199 +1,338 @@
 use super::traits::{TokenStorage, TokenData, convert_legacy_token, convert_to_legacy_format};
-use std::path::PathBuf;
+use super::atomic::write_file_atomic;
+use super::journal::{self, ChangeKind, ChangeSource, JournalEntry, TokenJournal, TrashEntry};
+use crate::backups;
+use crate::data_watcher;
+use crate::instance_lock::WriteLock;
+use crate::migrations::{self, DataKind, MigrationContext};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Mutex as AsyncMutex;
 use tauri::Manager;
 
+type StorageResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// 按文件路径共享的锁：同一个 tokens.json 的所有存储实例（例如切换数据目录前后、
+/// API 服务器和前端命令）串行执行"读取-修改-写入"，避免互相覆盖。
+/// 等待跨进程写入锁期间一直持有，因此使用异步锁，不阻塞 tokio 工作线程
+fn file_lock(path: &Path) -> Arc<AsyncMutex<()>> {
+    static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<AsyncMutex<()>>>>> = OnceLock::new();
+    // 文件可能尚不存在，按所在目录规范化
+    let key = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    };
+    LOCKS.get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_default()
+        .clone()
+}
+
 pub struct LocalFileStorage {
     storage_path: PathBuf,
-    // 使用Mutex来确保文件操作的线程安全
-    _lock: Mutex<()>,
+    // 使用Mutex来确保文件操作的线程安全（同一路径共享）
+    lock: Arc<AsyncMutex<()>>,
+    // 写入时记录到变更日志的来源
+    source: ChangeSource,
+    journal: TokenJournal,
 }
 
 impl LocalFileStorage {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    pub fn new(app_handle: &tauri::AppHandle) -> StorageResult<Self> {
         let app_data_dir = app_handle.path().app_data_dir()?;
         fs::create_dir_all(&app_data_dir)?;
         
-        let storage_path = app_data_dir.join("tokens.json");
-        
-        Ok(Self {
-            storage_path,
-            _lock: Mutex::new(()),
-        })
+        Ok(Self::new_with_path(app_data_dir.join("tokens.json")))
     }
 
     pub fn new_with_path(storage_path: PathBuf) -> Self {
+        let lock = file_lock(&storage_path);
+        let journal = TokenJournal::new(storage_path.parent().unwrap_or(Path::new(".")));
         Self {
             storage_path,
-            _lock: Mutex::new(()),
+            lock,
+            source: ChangeSource::default(),
+            journal,
         }
     }
 
-    async fn read_file_content(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let _guard = self._lock.lock().unwrap();
-        
-        if !self.storage_path.exists() {
-            return Ok("[]".to_string());
+    /// 共享同一文件和锁、但以指定来源记录变更的存储实例
+    pub fn for_source(&self, source: ChangeSource) -> Self {
+        Self {
+            storage_path: self.storage_path.clone(),
+            lock: self.lock.clone(),
+            source,
+            journal: self.journal.clone(),
         }
+    }
+
+    /// tokens.json 的路径
+    pub fn storage_path(&self) -> &Path {
+        &self.storage_path
+    }
+
+    /// 跨进程的写入锁，数据目录被多个应用实例共用时串行化"读取-修改-写入"
+    async fn write_lock(&self) -> StorageResult<WriteLock> {
+        Ok(WriteLock::acquire_async(self.storage_path.parent().unwrap_or(Path::new("."))).await?)
+    }
 
-        let content = fs::read_to_string(&self.storage_path)?;
-        if content.trim().is_empty() {
-            return Ok("[]".to_string());
+    fn read_tokens_locked(&self) -> StorageResult<Vec<TokenData>> {
+        // 读取时按需升级文件版本（升级前会备份原文件）
+        match migrations::migrate_file_locked(DataKind::Tokens, &self.storage_path, &MigrationContext::default())? {
+            Some(document) => Ok(Self::parse_tokens_from_document(document)),
+            None => Ok(Vec::new()),
         }
+    }
+
+    fn write_tokens_locked(&self, tokens: &[TokenData]) -> StorageResult<()> {
+        // 不覆盖由更新版本应用写入的文件
+        migrations::ensure_writable(DataKind::Tokens, &self.storage_path)?;
+
+        // 转换为旧格式并包装为带版本的文档
+        let legacy_tokens: Vec<serde_json::Value> = tokens.iter()
+            .map(convert_to_legacy_format)
+            .collect();
+        let json_content = serde_json::to_string_pretty(&migrations::tokens_document(legacy_tokens))?;
 
-        Ok(content)
+        backups::before_write(&self.storage_path);
+        data_watcher::record_write(&self.storage_path, json_content.as_bytes());
+        write_file_atomic(&self.storage_path, json_content.as_bytes())?;
+        Ok(())
     }
 
-    async fn write_file_content(&self, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let _guard = self._lock.lock().unwrap();
+    /// 在文件锁内读取、修改并写回；`modify` 返回的 bool 为 false 时表示没有变化，不写文件
+    pub async fn modify_tokens<R>(&self, modify: impl FnOnce(&mut Vec<TokenData>) -> (R, bool)) -> StorageResult<R> {
+        self.modify_tokens_as(None, modify).await
+    }
 
-        // 确保父目录存在
-        if let Some(parent) = self.storage_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// 同 `modify_tokens`，`kind` 指定写入的变更类型（恢复、回退）
+    async fn modify_tokens_as<R>(
+        &self,
+        kind: Option<ChangeKind>,
+        modify: impl FnOnce(&mut Vec<TokenData>) -> (R, bool),
+    ) -> StorageResult<R> {
+        let _guard = self.lock.lock().await;
+        let _write_lock = self.write_lock().await?;
+        let before = self.read_tokens_locked()?;
+        let mut tokens = before.clone();
+        let (result, changed) = modify(&mut tokens);
+        if changed {
+            self.write_tokens_locked(&tokens)?;
+            self.record_changes_locked(&before, &tokens, kind);
         }
+        Ok(result)
+    }
 
-        // 使用同一目录下的唯一临时文件名，确保在同一文件系统上
-        let parent_dir = self.storage_path.parent()
-            .ok_or("Failed to get parent directory")?;
-        let temp_path = parent_dir.join(format!("tokens.{}.tmp", uuid::Uuid::new_v4()));
-
-        // 验证JSON格式
-        serde_json::from_str::<serde_json::Value>(content)?;
-
-        // 原子性写入
-        fs::write(&temp_path, content)?;
-
-        // 尝试原子性重命名，如果失败则使用复制+删除的方式
-        match fs::rename(&temp_path, &self.storage_path) {
-            Ok(_) => Ok(()),
-            Err(rename_err) => {
-                // 如果 rename 失败（可能是跨文件系统），尝试复制+删除
-                eprintln!("Rename failed, trying copy+delete: {}", rename_err);
-
-                match fs::copy(&temp_path, &self.storage_path) {
-                    Ok(_) => {
-                        // 复制成功，删除临时文件
-                        let _ = fs::remove_file(&temp_path);
-                        Ok(())
-                    }
-                    Err(copy_err) => {
-                        // 复制也失败，清理临时文件并返回错误
-                        let _ = fs::remove_file(&temp_path);
-                        Err(format!("Failed to save file (rename: {}, copy: {})", rename_err, copy_err).into())
-                    }
-                }
-            }
+    /// 记录变更并把删除的账号放入回收站；tokens.json 已经写入，这里失败只记录日志
+    fn record_changes_locked(&self, before: &[TokenData], after: &[TokenData], kind: Option<ChangeKind>) {
+        let now = chrono::Utc::now();
+        let (entries, removed) = journal::collect_changes(before, after, self.source, kind, now);
+        if let Err(e) = self.journal.move_to_trash(&removed, self.source, now) {
+            log::error!("写入回收站失败: {}", e);
+        }
+        if let Err(e) = self.journal.append(&entries) {
+            log::error!("写入变更记录失败: {}", e);
         }
     }
 
-    async fn parse_tokens_from_content(&self, content: &str) -> Result<Vec<TokenData>, Box<dyn std::error::Error + Send + Sync>> {
-        let json_value: serde_json::Value = serde_json::from_str(content)?;
-        let mut tokens = Vec::new();
+    /// 账号的变更记录（按时间顺序）
+    pub async fn history(&self, token_id: &str) -> Result<Vec<JournalEntry>, String> {
+        let _guard = self.lock.lock().await;
+        self.journal.history(token_id)
+    }
 
-        match json_value {
-            serde_json::Value::Array(array) => {
-                for item in array {
-                    match convert_legacy_token(&item) {
-                        Ok(token) => tokens.push(token),
-                        Err(e) => {
-                            eprintln!("Failed to convert token: {}", e);
-                            continue;
-                        }
-                    }
+    /// 把账号回退到指定变更记录之后的版本，回退本身也记录为一次变更
+    pub async fn revert_token(&self, token_id: &str, entry_id: &str) -> Result<TokenData, String> {
+        self.modify_tokens_as(Some(ChangeKind::Reverted), |tokens| {
+            let reverted = self.journal.history(token_id).and_then(|history| {
+                let position = history.iter()
+                    .position(|entry| entry.id == entry_id)
+                    .ok_or_else(|| format!("找不到变更记录: {}", entry_id))?;
+                if history[position].kind == ChangeKind::Deleted {
+                    return Err("不能回退到删除之后的版本".to_string());
+                }
+                let current = tokens.iter()
+                    .find(|t| t.id == token_id)
+                    .ok_or("账号不存在，请先从回收站恢复")?;
+                journal::reconstruct(current, &history[position + 1..])
+            });
+            match reverted {
+                Ok(token) => {
+                    upsert(tokens, &token);
+                    (Ok(token), true)
                 }
+                Err(e) => (Err(e), false),
             }
-            serde_json::Value::Object(ref obj) => {
-                // 检查是否是旧格式 {tokens: [...]}
-                if let Some(tokens_array) = obj.get("tokens") {
-                    if let serde_json::Value::Array(array) = tokens_array {
-                        for item in array {
-                            match convert_legacy_token(item) {
-                                Ok(token) => tokens.push(token),
-                                Err(e) => {
-                                    eprintln!("Failed to convert token: {}", e);
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    // 单个对象格式
-                    match convert_legacy_token(&json_value) {
-                        Ok(token) => tokens.push(token),
-                        Err(e) => eprintln!("Failed to convert single token: {}", e),
-                    }
+        })
+        .await
+        .map_err(|e| format!("回退失败: {}", e))?
+    }
+
+    /// 回收站中的账号（先清理超过保留期的条目）
+    pub async fn trash(&self, retention_days: u32) -> Result<Vec<TrashEntry>, String> {
+        let _guard = self.lock.lock().await;
+        self.journal.purge_trash(retention_days, chrono::Utc::now())?;
+        self.journal.load_trash()
+    }
+
+    /// 清理超过保留期的回收站条目，返回清理数量
+    pub async fn purge_trash(&self, retention_days: u32) -> Result<usize, String> {
+        let _guard = self.lock.lock().await;
+        self.journal.purge_trash(retention_days, chrono::Utc::now())
+    }
+
+    /// 从回收站恢复账号；id 或 access_token 与现有账号冲突时拒绝恢复
+    pub async fn restore_from_trash(&self, trash_id: &str) -> Result<TokenData, String> {
+        let token = self.modify_tokens_as(Some(ChangeKind::Restored), |tokens| {
+            let restored = self.journal.load_trash().and_then(|trash| {
+                let entry = trash.iter()
+                    .find(|entry| entry.id == trash_id)
+                    .ok_or_else(|| format!("回收站中找不到: {}", trash_id))?;
+                let token = convert_legacy_token(&entry.token).map_err(|e| e.to_string())?;
+                if tokens.iter().any(|t| t.id == token.id || t.access_token == token.access_token) {
+                    return Err(format!("账号 {} 与现有数据冲突", token.id));
+                }
+                Ok(token)
+            });
+            match restored {
+                Ok(token) => {
+                    tokens.push(token.clone());
+                    (Ok(token), true)
                 }
+                Err(e) => (Err(e), false),
             }
-            _ => {
-                // 其他格式，返回空数组
+        })
+        .await
+        .map_err(|e| format!("恢复失败: {}", e))??;
+
+        // 账号写入成功后再移出回收站，写入失败时回收站保持不变
+        let _guard = self.lock.lock().await;
+        let mut trash = self.journal.load_trash()?;
+        trash.retain(|entry| entry.id != trash_id);
+        self.journal.save_trash(&trash)?;
+        Ok(token)
+    }
+
+    fn parse_tokens_from_document(document: serde_json::Value) -> Vec<TokenData> {
+        let mut tokens = Vec::new();
+
+        for item in migrations::tokens_from_document(document) {
+            match convert_legacy_token(&item) {
+                Ok(token) => tokens.push(token),
+                Err(e) => {
+                    log::error!("Failed to convert token: {}", e);
+                    continue;
+                }
             }
         }
 
-        Ok(tokens)
+        tokens
+    }
+}
+
+fn upsert(tokens: &mut Vec<TokenData>, token: &TokenData) {
+    // 检查是否已存在相同ID的token
+    if let Some(existing_index) = tokens.iter().position(|t| t.id == token.id) {
+        tokens[existing_index] = token.clone();
+    } else {
+        tokens.push(token.clone());
     }
 }
 
 #[async_trait::async_trait]
 impl TokenStorage for LocalFileStorage {
-    async fn save_token(&self, token: &TokenData) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut tokens = self.load_tokens().await?;
-        
-        // 检查是否已存在相同ID的token
-        if let Some(existing_index) = tokens.iter().position(|t| t.id == token.id) {
-            tokens[existing_index] = token.clone();
-        } else {
-            tokens.push(token.clone());
-        }
-
-        // 转换为旧格式并保存
-        let legacy_tokens: Vec<serde_json::Value> = tokens.iter()
-            .map(convert_to_legacy_format)
-            .collect();
+    async fn save_token(&self, token: &TokenData) -> StorageResult<()> {
+        self.modify_tokens(|tokens| {
+            upsert(tokens, token);
+            ((), true)
+        })
+        .await
+    }
 
-        let json_content = serde_json::to_string_pretty(&legacy_tokens)?;
-        self.write_file_content(&json_content).await?;
+    async fn save_tokens(&self, new_tokens: &[TokenData]) -> StorageResult<()> {
+        if new_tokens.is_empty() {
+            return Ok(());
+        }
+        self.modify_tokens(|tokens| {
+            for token in new_tokens {
+                upsert(tokens, token);
+            }
+            ((), true)
+        })
+        .await
+    }
 
-        Ok(())
+    async fn insert_tokens(&self, new_tokens: &[TokenData]) -> StorageResult<()> {
+        if new_tokens.is_empty() {
+            return Ok(());
+        }
+        // 检查和写入都在同一把锁内完成；写入是原子替换，失败时原文件保持不变
+        self.modify_tokens(|tokens| {
+            let conflict = new_tokens.iter().find(|new| {
+                tokens.iter().any(|t| t.id == new.id || t.access_token == new.access_token)
+            });
+            if let Some(conflict) = conflict {
+                return (Err(format!("账号 {} 与现有数据冲突，已取消导入", conflict.id)), false);
+            }
+            tokens.extend_from_slice(new_tokens);
+            (Ok(()), true)
+        })
+        .await?
+        .map_err(Into::into)
     }
 
-    async fn load_tokens(&self) -> Result<Vec<TokenData>, Box<dyn std::error::Error + Send + Sync>> {
-        let content = self.read_file_content().await?;
-        self.parse_tokens_from_content(&content).await
+    async fn load_tokens(&self) -> StorageResult<Vec<TokenData>> {
+        let _guard = self.lock.lock().await;
+        // 读取时可能升级并写回文件，同样需要写入锁
+        let _write_lock = self.write_lock().await?;
+        self.read_tokens_locked()
     }
 
-    async fn update_token(&self, token: &TokenData) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn update_token(&self, token: &TokenData) -> StorageResult<()> {
         // 直接保存token，无需更新时间戳
         self.save_token(token).await
     }
 
-    async fn delete_token(&self, token_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let mut tokens = self.load_tokens().await?;
-        let initial_len = tokens.len();
-        
-        tokens.retain(|t| t.id != token_id);
-        
-        if tokens.len() < initial_len {
-            // 转换为旧格式并保存
-            let legacy_tokens: Vec<serde_json::Value> = tokens.iter()
-                .map(convert_to_legacy_format)
-                .collect();
-
-            let json_content = serde_json::to_string_pretty(&legacy_tokens)?;
-            self.write_file_content(&json_content).await?;
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    async fn delete_token(&self, token_id: &str) -> StorageResult<bool> {
+        Ok(self.delete_tokens(&[token_id.to_string()]).await? > 0)
+    }
+
+    async fn delete_tokens(&self, token_ids: &[String]) -> StorageResult<usize> {
+        self.modify_tokens(|tokens| {
+            let initial_len = tokens.len();
+            tokens.retain(|t| !token_ids.contains(&t.id));
+            let removed = initial_len - tokens.len();
+            (removed, removed > 0)
+        })
+        .await
     }
 
-    async fn get_token(&self, token_id: &str) -> Result<Option<TokenData>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn get_token(&self, token_id: &str) -> StorageResult<Option<TokenData>> {
         let tokens = self.load_tokens().await?;
         Ok(tokens.into_iter().find(|t| t.id == token_id))
     }
 
-    async fn clear_all_tokens(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.write_file_content("[]").await
+    async fn replace_all_tokens(&self, tokens: &[TokenData]) -> StorageResult<()> {
+        let _guard = self.lock.lock().await;
+        let _write_lock = self.write_lock().await?;
+        // 旧文件无法读取时照常覆盖，只是不记录变更
+        let before = self.read_tokens_locked();
+        self.write_tokens_locked(tokens)?;
+        match before {
+            Ok(before) => self.record_changes_locked(&before, tokens, None),
+            Err(e) => log::warn!("无法读取旧数据，未记录本次变更: {}", e),
+        }
+        Ok(())
+    }
+
+    async fn clear_all_tokens(&self) -> StorageResult<()> {
+        self.replace_all_tokens(&[]).await
     }
 
     fn storage_type(&self) -> &'static str {
@@ -259,4 +398,94 @@ mod tests {
         assert!(storage.is_available().await);
         assert_eq!(storage.storage_type(), "local_file");
     }
+
+    #[tokio::test]
+    async fn test_concurrent_writers_share_file_lock() {
+        let temp_dir = tempdir().unwrap();
+        let storage_path = temp_dir.path().join("tokens.json");
+
+        // 两个实例指向同一文件（例如 API 服务器和前端命令），并发写入不应丢失数据
+        let mut handles = Vec::new();
+        for i in 0..20 {
+            let storage = LocalFileStorage::new_with_path(storage_path.clone());
+            handles.push(tokio::spawn(async move {
+                let token = TokenData::new(
+                    format!("id_{}", i),
+                    "https://example.com".to_string(),
+                    format!("token_{}", i),
+                    None,
+                    None,
+                );
+                storage.save_token(&token).await.unwrap();
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let storage = LocalFileStorage::new_with_path(storage_path);
+        assert_eq!(storage.load_tokens().await.unwrap().len(), 20);
+
+        let removed = storage
+            .delete_tokens(&["id_1".to_string(), "id_2".to_string(), "missing".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(storage.load_tokens().await.unwrap().len(), 18);
+    }
+
+    #[tokio::test]
+    async fn test_insert_tokens_is_all_or_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let storage_path = temp_dir.path().join("tokens.json");
+        let storage = LocalFileStorage::new_with_path(storage_path.clone());
+        let token = |id: &str, access_token: &str| {
+            TokenData::new(id.to_string(), "https://example.com".to_string(), access_token.to_string(), None, None)
+        };
+
+        storage.save_token(&token("a", "token_a")).await.unwrap();
+        let before = fs::read_to_string(&storage_path).unwrap();
+
+        // 第二条与现有账号的 access_token 冲突，整批都不写入
+        let result = storage.insert_tokens(&[token("b", "token_b"), token("c", "token_a")]).await;
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&storage_path).unwrap(), before);
+
+        storage.insert_tokens(&[token("b", "token_b"), token("c", "token_c")]).await.unwrap();
+        assert_eq!(storage.load_tokens().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_history_revert_and_trash_restore() {
+        let temp_dir = tempdir().unwrap();
+        let storage = LocalFileStorage::new_with_path(temp_dir.path().join("tokens.json"));
+        let mut token = TokenData::new("a".to_string(), "https://example.com".to_string(), "token_v1".to_string(), None, None);
+        storage.save_token(&token).await.unwrap();
+
+        // 自动刷新覆盖 access_token，之后回退到创建时的版本
+        token.access_token = "token_v2".to_string();
+        storage.for_source(ChangeSource::AutoRefresh).save_token(&token).await.unwrap();
+        let history = storage.history("a").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].source, ChangeSource::AutoRefresh);
+        assert_eq!(history[1].kind, ChangeKind::Updated);
+
+        let reverted = storage.revert_token("a", &history[0].id).await.unwrap();
+        assert_eq!(reverted.access_token, "token_v1");
+        assert_eq!(storage.get_token("a").await.unwrap().unwrap().access_token, "token_v1");
+        assert_eq!(storage.history("a").await.unwrap().last().unwrap().kind, ChangeKind::Reverted);
+
+        // 删除进入回收站，恢复后从回收站移除
+        storage.delete_token("a").await.unwrap();
+        let trash = storage.trash(30).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].token_id(), "a");
+        assert!(storage.revert_token("a", &history[0].id).await.is_err());
+
+        let restored = storage.restore_from_trash(&trash[0].id).await.unwrap();
+        assert_eq!(restored.access_token, "token_v1");
+        assert!(storage.trash(30).await.unwrap().is_empty());
+        assert_eq!(storage.load_tokens().await.unwrap().len(), 1);
+        assert!(storage.restore_from_trash(&trash[0].id).await.is_err());
+    }
 }
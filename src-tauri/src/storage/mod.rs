@@ -1,5 +1,11 @@
 pub mod traits;
+pub mod token_fields;
 pub mod local_storage;
+pub mod atomic;
+pub mod journal;
 
 pub use traits::*;
+pub use token_fields::BanStatus;
 pub use local_storage::*;
+pub use atomic::write_file_atomic;
+pub use journal::{ChangeSource, HistorySettings};
@@ -0,0 +1,329 @@
+//! TokenData 中账号状态、门户信息和封禁详情的类型定义
+//!
+//! 这些字段以前是任意 JSON，文件中可能存在各种历史格式，因此反序列化都是宽松的：
+//! 能识别的部分转换为强类型，未知字段原样保存在 `extra` 中，写回时不会丢失。
+//! 已知字段的原始值无法无损转换时（例如 `"N/A"`、小数余额、带附加字段的状态对象），
+//! 原始值也保存在 `extra` 中，只要强类型的值没有被修改，写回时使用原始值。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// 账号状态（对应前端的 ban_status 字符串）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BanStatus {
+    Active,
+    Suspended,
+    Expired,
+    /// 前端手动标记的无效账号
+    Invalid,
+    /// find-missing 返回 401，token 已失效
+    InvalidToken,
+    Error,
+    /// 其他状态（例如 `BANNED-<类型>`），原样保存
+    Other(String),
+}
+
+impl BanStatus {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "ACTIVE" => BanStatus::Active,
+            "SUSPENDED" => BanStatus::Suspended,
+            "EXPIRED" => BanStatus::Expired,
+            "INVALID" => BanStatus::Invalid,
+            "INVALID_TOKEN" => BanStatus::InvalidToken,
+            "ERROR" => BanStatus::Error,
+            _ => BanStatus::Other(value.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            BanStatus::Active => "ACTIVE",
+            BanStatus::Suspended => "SUSPENDED",
+            BanStatus::Expired => "EXPIRED",
+            BanStatus::Invalid => "INVALID",
+            BanStatus::InvalidToken => "INVALID_TOKEN",
+            BanStatus::Error => "ERROR",
+            BanStatus::Other(value) => value,
+        }
+    }
+
+    /// 账号是否已被封禁
+    pub fn is_banned(&self) -> bool {
+        match self {
+            BanStatus::Suspended => true,
+            BanStatus::Other(value) => value.to_ascii_uppercase().starts_with("BANNED"),
+            _ => false,
+        }
+    }
+
+    /// 账号是否不可用（封禁、过期或 token 失效）
+    pub fn is_unusable(&self) -> bool {
+        self.is_banned()
+            || matches!(self, BanStatus::Expired | BanStatus::Invalid | BanStatus::InvalidToken)
+    }
+
+    /// 从旧文件中的值解析：字符串，或带 `status` 字段的对象
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) if !s.trim().is_empty() => Some(Self::parse(s)),
+            Value::Object(map) => map.get("status").and_then(Self::from_value),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BanStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for BanStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for BanStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(&value)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid ban_status: {}", value)))
+    }
+}
+
+/// 门户信息：余额和到期时间
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenPortalInfo {
+    pub credits_balance: Option<i64>,
+    pub expiry_date: Option<String>,
+    /// 未识别的字段，以及无法无损解析的已知字段的原始值
+    pub extra: Map<String, Value>,
+}
+
+impl TokenPortalInfo {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Object(_) => serde_json::from_value(value.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    /// 解析后的到期时间
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        self.expiry_date
+            .as_deref()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+impl Serialize for TokenPortalInfo {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = self.extra.clone();
+        write_typed(&mut map, "credits_balance", self.credits_balance.as_ref(), parse_i64);
+        write_typed(&mut map, "expiry_date", self.expiry_date.as_ref(), parse_string);
+        map.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenPortalInfo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut extra = Map::deserialize(deserializer)?;
+        let credits_balance = read_typed(&mut extra, "credits_balance", parse_i64);
+        let expiry_date = read_typed(&mut extra, "expiry_date", parse_string);
+        Ok(Self { credits_balance, expiry_date, extra })
+    }
+}
+
+/// 单条封禁记录（来自 App `/api/user` 的 suspensions）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Suspension {
+    pub suspension_type: Option<String>,
+    /// 未识别的字段，以及无法无损解析的已知字段的原始值
+    pub extra: Map<String, Value>,
+}
+
+impl Suspension {
+    /// 解析封禁列表：数组，或单个对象
+    pub fn list_from_value(value: &Value) -> Option<Vec<Self>> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::Object(_) => serde_json::from_value(item.clone()).ok(),
+                    _ => None,
+                })
+                .collect(),
+            Value::Object(_) => serde_json::from_value(value.clone()).ok().map(|s| vec![s]),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Suspension {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = self.extra.clone();
+        write_typed(&mut map, "suspensionType", self.suspension_type.as_ref(), parse_string);
+        map.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Suspension {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut extra = Map::deserialize(deserializer)?;
+        let suspension_type = read_typed(&mut extra, "suspensionType", parse_string);
+        Ok(Self { suspension_type, extra })
+    }
+}
+
+/// 从 `map` 中取出已知字段并解析；原始值不能由解析结果无损还原时留在 `map` 中
+pub(crate) fn read_typed<T: Serialize>(
+    map: &mut Map<String, Value>,
+    key: &str,
+    parse: impl Fn(&Value) -> Option<T>,
+) -> Option<T> {
+    let raw = map.remove(key)?;
+    let typed = parse(&raw);
+    let lossless = match &typed {
+        Some(value) => serde_json::to_value(value).ok().as_ref() == Some(&raw),
+        None => raw.is_null(),
+    };
+    if !lossless {
+        log::warn!("Field '{}' has unrecognized format, keeping raw value", key);
+        map.insert(key.to_string(), raw);
+    }
+    typed
+}
+
+/// 把已知字段写入 `map`：保留的原始值仍解析为当前值时原样写回，否则写入当前值
+pub(crate) fn write_typed<T: Serialize + PartialEq>(
+    map: &mut Map<String, Value>,
+    key: &str,
+    typed: Option<&T>,
+    parse: impl Fn(&Value) -> Option<T>,
+) {
+    if map.get(key).is_some_and(|raw| parse(raw).as_ref() == typed) {
+        return;
+    }
+    match typed.and_then(|value| serde_json::to_value(value).ok()) {
+        Some(value) => {
+            map.insert(key.to_string(), value);
+        }
+        None => {
+            map.remove(key);
+        }
+    }
+}
+
+/// 数字或数字字符串，小数四舍五入
+pub(crate) fn parse_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(round_credits)),
+        Value::String(s) => s.trim().parse::<f64>().ok().map(round_credits),
+        _ => None,
+    }
+}
+
+/// 余额取整方式，读取文件和写入检测结果时保持一致
+pub fn round_credits(value: f64) -> i64 {
+    value.round() as i64
+}
+
+/// 非空字符串
+pub(crate) fn parse_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ban_status_parse_and_serialize() {
+        assert_eq!(BanStatus::parse("ACTIVE"), BanStatus::Active);
+        assert_eq!(BanStatus::parse("invalid_token"), BanStatus::InvalidToken);
+        assert_eq!(BanStatus::parse("BANNED-ABUSE"), BanStatus::Other("BANNED-ABUSE".to_string()));
+        assert!(BanStatus::parse("BANNED-ABUSE").is_banned());
+        assert!(BanStatus::Expired.is_unusable());
+        assert!(!BanStatus::Active.is_unusable());
+
+        let json = serde_json::to_value(BanStatus::Other("BANNED-ABUSE".to_string())).unwrap();
+        assert_eq!(json, "BANNED-ABUSE");
+        let parsed: BanStatus = serde_json::from_value(serde_json::json!({"status": "SUSPENDED"})).unwrap();
+        assert_eq!(parsed, BanStatus::Suspended);
+        assert_eq!(BanStatus::from_value(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_portal_info_lenient() {
+        let info = TokenPortalInfo::from_value(&serde_json::json!({
+            "credits_balance": "120.6",
+            "expiry_date": "2024-02-15T00:00:00+00:00",
+            "is_active": true
+        }))
+        .unwrap();
+        assert_eq!(info.credits_balance, Some(121));
+        assert!(info.expiry().is_some());
+        assert_eq!(info.extra.get("is_active"), Some(&Value::Bool(true)));
+
+        let info = TokenPortalInfo::from_value(&serde_json::json!({"credits_balance": null})).unwrap();
+        assert_eq!(info, TokenPortalInfo::default());
+        assert!(TokenPortalInfo::from_value(&serde_json::json!("broken")).is_none());
+
+        let json = serde_json::to_value(TokenPortalInfo {
+            credits_balance: Some(5),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(json, serde_json::json!({"credits_balance": 5}));
+    }
+
+    #[test]
+    fn test_unparseable_values_are_preserved() {
+        let raw = serde_json::json!({
+            "credits_balance": "N/A",
+            "expiry_date": true,
+            "plan": "pro"
+        });
+        let info = TokenPortalInfo::from_value(&raw).unwrap();
+        assert_eq!(info.credits_balance, None);
+        assert_eq!(info.expiry_date, None);
+        assert_eq!(info.extra["credits_balance"], "N/A");
+        assert_eq!(serde_json::to_value(&info).unwrap(), raw);
+
+        // 小数余额解析为整数，未修改时写回原始值，修改后写入新值
+        let raw = serde_json::json!({"credits_balance": "120.6", "expiry_date": ""});
+        let mut info = TokenPortalInfo::from_value(&raw).unwrap();
+        assert_eq!(info.credits_balance, Some(121));
+        assert_eq!(serde_json::to_value(&info).unwrap(), raw);
+        info.credits_balance = Some(80);
+        assert_eq!(serde_json::to_value(&info).unwrap(), serde_json::json!({"credits_balance": 80, "expiry_date": ""}));
+
+        let raw = serde_json::json!([{"suspensionType": 7, "evidence": "x"}]);
+        let list = Suspension::list_from_value(&raw).unwrap();
+        assert_eq!(list[0].suspension_type, None);
+        assert_eq!(serde_json::to_value(&list).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_suspension_list_lenient() {
+        let list = Suspension::list_from_value(&serde_json::json!([
+            {"suspensionType": "ABUSE", "evidence": "x"}
+        ]))
+        .unwrap();
+        assert_eq!(list[0].suspension_type.as_deref(), Some("ABUSE"));
+        assert_eq!(list[0].extra.get("evidence"), Some(&Value::String("x".to_string())));
+        assert_eq!(serde_json::to_value(&list).unwrap()[0]["evidence"], "x");
+
+        let single = Suspension::list_from_value(&serde_json::json!({"suspensionType": "TRIAL"})).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(Suspension::list_from_value(&serde_json::json!([])).unwrap().len(), 0);
+        assert!(Suspension::list_from_value(&serde_json::json!(["oops"])).is_none());
+    }
+}
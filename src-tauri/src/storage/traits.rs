@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use super::token_fields::{read_typed, write_typed, BanStatus, Suspension, TokenPortalInfo};
+
+/// tokens.json 中由 TokenData 字段表示的键，其余键保存在 `extra` 中
+const KNOWN_TOKEN_FIELDS: &[&str] = &[
+    "id", "tenant_url", "access_token", "created_at", "updated_at", "portal_url", "email_note",
+    "tag_name", "tag_color", "ban_status", "portal_info", "auth_session", "suspensions", "skip_check",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TokenDataRepr", into = "TokenDataRepr")]
 pub struct TokenData {
     pub id: String,
     pub tenant_url: String,
@@ -12,11 +21,99 @@ pub struct TokenData {
     pub email_note: Option<String>,
     pub tag_name: Option<String>,
     pub tag_color: Option<String>,
-    pub ban_status: Option<serde_json::Value>,
-    pub portal_info: Option<serde_json::Value>,
+    pub ban_status: Option<BanStatus>,
+    pub portal_info: Option<TokenPortalInfo>,
     pub auth_session: Option<String>,
-    pub suspensions: Option<serde_json::Value>,
+    pub suspensions: Option<Vec<Suspension>>,
     pub skip_check: Option<bool>,
+    /// 未识别的字段（例如更新版本前端写入的字段），以及无法无损解析的已知字段的原始值
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// TokenData 的序列化形式：强类型字段先按原始 JSON 读写，再通过 read_typed/write_typed 转换，
+/// 与 convert_legacy_token/convert_to_legacy_format 的处理保持一致
+#[derive(Clone, Serialize, Deserialize)]
+struct TokenDataRepr {
+    id: String,
+    tenant_url: String,
+    access_token: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    portal_url: Option<String>,
+    email_note: Option<String>,
+    tag_name: Option<String>,
+    tag_color: Option<String>,
+    ban_status: Option<serde_json::Value>,
+    portal_info: Option<serde_json::Value>,
+    auth_session: Option<String>,
+    suspensions: Option<serde_json::Value>,
+    skip_check: Option<bool>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<TokenDataRepr> for TokenData {
+    fn from(repr: TokenDataRepr) -> Self {
+        let mut extra = repr.extra;
+        let raw_fields = [
+            ("ban_status", repr.ban_status),
+            ("portal_info", repr.portal_info),
+            ("suspensions", repr.suspensions),
+        ];
+        for (key, raw) in raw_fields {
+            if let Some(raw) = raw {
+                extra.insert(key.to_string(), raw);
+            }
+        }
+        let ban_status = read_typed(&mut extra, "ban_status", BanStatus::from_value);
+        let portal_info = read_typed(&mut extra, "portal_info", TokenPortalInfo::from_value);
+        let suspensions = read_typed(&mut extra, "suspensions", Suspension::list_from_value);
+
+        Self {
+            id: repr.id,
+            tenant_url: repr.tenant_url,
+            access_token: repr.access_token,
+            created_at: repr.created_at,
+            updated_at: repr.updated_at,
+            portal_url: repr.portal_url,
+            email_note: repr.email_note,
+            tag_name: repr.tag_name,
+            tag_color: repr.tag_color,
+            ban_status,
+            portal_info,
+            auth_session: repr.auth_session,
+            suspensions,
+            skip_check: repr.skip_check,
+            extra,
+        }
+    }
+}
+
+impl From<TokenData> for TokenDataRepr {
+    fn from(token: TokenData) -> Self {
+        let mut extra = token.extra;
+        write_typed(&mut extra, "ban_status", token.ban_status.as_ref(), BanStatus::from_value);
+        write_typed(&mut extra, "portal_info", token.portal_info.as_ref(), TokenPortalInfo::from_value);
+        write_typed(&mut extra, "suspensions", token.suspensions.as_ref(), Suspension::list_from_value);
+
+        Self {
+            id: token.id,
+            tenant_url: token.tenant_url,
+            access_token: token.access_token,
+            created_at: token.created_at,
+            updated_at: token.updated_at,
+            portal_url: token.portal_url,
+            email_note: token.email_note,
+            tag_name: token.tag_name,
+            tag_color: token.tag_color,
+            ban_status: extra.remove("ban_status"),
+            portal_info: extra.remove("portal_info"),
+            auth_session: token.auth_session,
+            suspensions: extra.remove("suspensions"),
+            skip_check: token.skip_check,
+            extra,
+        }
+    }
 }
 
 impl TokenData {
@@ -43,8 +140,24 @@ impl TokenData {
             auth_session: None,
             suspensions: None,
             skip_check: None,
+            extra: serde_json::Map::new(),
         }
     }
+
+    /// 是否已被封禁
+    pub fn is_banned(&self) -> bool {
+        self.ban_status.as_ref().is_some_and(BanStatus::is_banned)
+    }
+
+    /// 门户余额
+    pub fn credits_balance(&self) -> Option<i64> {
+        self.portal_info.as_ref().and_then(|info| info.credits_balance)
+    }
+
+    /// 门户到期时间
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        self.portal_info.as_ref().and_then(TokenPortalInfo::expiry)
+    }
 }
 
 
@@ -52,15 +165,27 @@ impl TokenData {
 #[async_trait::async_trait]
 pub trait TokenStorage: Send + Sync {
     async fn save_token(&self, token: &TokenData) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 批量新增或更新（按 id），一次写入
+    async fn save_tokens(&self, tokens: &[TokenData]) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     
+    /// 事务式批量新增：任何一条与现有账号的 id 或 access_token 冲突时整体放弃，不写入任何数据
+    async fn insert_tokens(&self, tokens: &[TokenData]) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
     async fn load_tokens(&self) -> Result<Vec<TokenData>, Box<dyn std::error::Error + Send + Sync>>;
     
     async fn update_token(&self, token: &TokenData) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     
     async fn delete_token(&self, token_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 批量删除，返回实际删除的数量
+    async fn delete_tokens(&self, token_ids: &[String]) -> Result<usize, Box<dyn std::error::Error + Send + Sync>>;
     
     async fn get_token(&self, token_id: &str) -> Result<Option<TokenData>, Box<dyn std::error::Error + Send + Sync>>;
     
+    /// 整体替换（仅用于云端恢复等需要覆盖全部数据的场景）
+    async fn replace_all_tokens(&self, tokens: &[TokenData]) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
     async fn clear_all_tokens(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     
     fn storage_type(&self) -> &'static str;
@@ -92,6 +217,30 @@ pub fn convert_legacy_token(legacy: &serde_json::Value) -> Result<TokenData, Box
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
         .map(|dt| dt.with_timezone(&Utc))
         .unwrap_or_else(Utc::now);
+
+    let updated_at = legacy.get("updated_at")
+        .and_then(|v| v.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(created_at);
+
+    // 未识别的字段原样保留
+    let mut extra: serde_json::Map<String, serde_json::Value> = legacy.as_object()
+        .map(|map| map.iter()
+            .filter(|(key, _)| !KNOWN_TOKEN_FIELDS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+        .unwrap_or_default();
+
+    // 已知字段无法无损解析时（历史格式），原始值保留在 extra 中，写回时不丢失
+    for key in ["ban_status", "portal_info", "suspensions"] {
+        if let Some(raw) = legacy.get(key) {
+            extra.insert(key.to_string(), raw.clone());
+        }
+    }
+    let ban_status = read_typed(&mut extra, "ban_status", BanStatus::from_value);
+    let portal_info = read_typed(&mut extra, "portal_info", TokenPortalInfo::from_value);
+    let suspensions = read_typed(&mut extra, "suspensions", Suspension::list_from_value);
     
     let portal_url = legacy.get("portal_url")
         .and_then(|v| v.as_str())
@@ -101,23 +250,14 @@ pub fn convert_legacy_token(legacy: &serde_json::Value) -> Result<TokenData, Box
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
     
-    let ban_status = legacy.get("ban_status").cloned();
-    let portal_info = legacy.get("portal_info").cloned();
     let auth_session = legacy.get("auth_session")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    let suspensions = legacy.get("suspensions").cloned();
 
-    // 支持旧字段 tag_text 迁移到 tag_name
+    // 旧字段 tag_text 已由 migrations 模块在加载时重命名为 tag_name
     let tag_name = legacy.get("tag_name")
         .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .or_else(|| {
-            // 如果没有 tag_name，尝试从 tag_text 读取
-            legacy.get("tag_text")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        });
+        .map(|s| s.to_string());
 
     // 颜色名称到十六进制的映射
     let color_name_to_hex = |name: &str| -> String {
@@ -146,7 +286,7 @@ pub fn convert_legacy_token(legacy: &serde_json::Value) -> Result<TokenData, Box
         tenant_url,
         access_token,
         created_at,
-        updated_at: created_at, // 使用 created_at 作为默认值
+        updated_at,
         portal_url,
         email_note,
         tag_name,
@@ -156,13 +296,15 @@ pub fn convert_legacy_token(legacy: &serde_json::Value) -> Result<TokenData, Box
         auth_session,
         suspensions,
         skip_check,
+        extra,
     })
 }
 
 // 辅助函数：将新格式的token转换为旧格式（用于向后兼容）
 pub fn convert_to_legacy_format(token: &TokenData) -> serde_json::Value {
-    let mut map = serde_json::Map::new();
-    
+    // 先写入未识别的字段，已知字段随后覆盖
+    let mut map = token.extra.clone();
+
     map.insert("id".to_string(), serde_json::Value::String(token.id.clone()));
     map.insert("tenant_url".to_string(), serde_json::Value::String(token.tenant_url.clone()));
     map.insert("access_token".to_string(), serde_json::Value::String(token.access_token.clone()));
@@ -185,21 +327,15 @@ pub fn convert_to_legacy_format(token: &TokenData) -> serde_json::Value {
         map.insert("tag_color".to_string(), serde_json::Value::String(tag_color.clone()));
     }
 
-    if let Some(ban_status) = &token.ban_status {
-        map.insert("ban_status".to_string(), ban_status.clone());
-    }
-
-    if let Some(portal_info) = &token.portal_info {
-        map.insert("portal_info".to_string(), portal_info.clone());
-    }
+    // 保留的原始值仍对应当前值时原样写回
+    write_typed(&mut map, "ban_status", token.ban_status.as_ref(), BanStatus::from_value);
+    write_typed(&mut map, "portal_info", token.portal_info.as_ref(), TokenPortalInfo::from_value);
 
     if let Some(auth_session) = &token.auth_session {
         map.insert("auth_session".to_string(), serde_json::Value::String(auth_session.clone()));
     }
 
-    if let Some(suspensions) = &token.suspensions {
-        map.insert("suspensions".to_string(), suspensions.clone());
-    }
+    write_typed(&mut map, "suspensions", token.suspensions.as_ref(), Suspension::list_from_value);
 
     if let Some(skip_check) = token.skip_check {
         map.insert("skip_check".to_string(), serde_json::Value::Bool(skip_check));
@@ -255,4 +391,95 @@ mod tests {
         assert_eq!(converted_back["access_token"], "test_token");
         assert_eq!(converted_back["auth_session"], "test_session");
     }
+
+    #[test]
+    fn test_typed_fields_round_trip() {
+        let legacy_json = serde_json::json!({
+            "id": "typed_id",
+            "tenant_url": "https://example.com",
+            "access_token": "test_token",
+            "created_at": "2023-01-01T00:00:00+00:00",
+            "updated_at": "2023-02-01T00:00:00+00:00",
+            "ban_status": "SUSPENDED",
+            "portal_info": {"credits_balance": 42, "expiry_date": "2024-02-15T00:00:00+00:00", "plan": "pro"},
+            "suspensions": [{"suspensionType": "ABUSE", "evidence": "x"}],
+            "skip_check": true,
+            "pinned": true
+        });
+
+        let token = convert_legacy_token(&legacy_json).unwrap();
+        assert_eq!(token.ban_status, Some(BanStatus::Suspended));
+        assert!(token.is_banned());
+        assert_eq!(token.credits_balance(), Some(42));
+        assert!(token.expiry().is_some());
+        assert_eq!(token.suspensions.as_ref().unwrap()[0].suspension_type.as_deref(), Some("ABUSE"));
+        assert_eq!(token.extra.get("pinned"), Some(&serde_json::Value::Bool(true)));
+
+        assert_eq!(convert_to_legacy_format(&token), legacy_json);
+    }
+
+    #[test]
+    fn test_unrecognized_typed_fields_are_kept() {
+        let legacy_json = serde_json::json!({
+            "id": "old_id",
+            "tenant_url": "https://example.com",
+            "access_token": "test_token",
+            "created_at": "2023-01-01T00:00:00+00:00",
+            "updated_at": "2023-01-01T00:00:00+00:00",
+            "ban_status": 3,
+            "portal_info": "broken",
+            "suspensions": null
+        });
+
+        let token = convert_legacy_token(&legacy_json).unwrap();
+        assert!(token.ban_status.is_none());
+        assert!(token.portal_info.is_none());
+        assert!(token.suspensions.is_none());
+
+        let converted_back = convert_to_legacy_format(&token);
+        assert_eq!(converted_back["ban_status"], 3);
+        assert_eq!(converted_back["portal_info"], "broken");
+        assert!(converted_back.get("suspensions").is_none());
+
+        // 前端传入的 JSON 走同样的解析，原始值保存在 extra 中
+        let token: TokenData = serde_json::from_value(legacy_json.clone()).unwrap();
+        assert!(token.ban_status.is_none());
+        assert!(token.portal_info.is_none());
+        assert_eq!(token.extra["ban_status"], 3);
+        assert_eq!(token.extra["portal_info"], "broken");
+        let serialized = serde_json::to_value(&token).unwrap();
+        assert_eq!(serialized["ban_status"], 3);
+        assert_eq!(serialized["portal_info"], "broken");
+    }
+
+    #[test]
+    fn test_lossy_typed_fields_keep_raw_values() {
+        let legacy_json = serde_json::json!({
+            "id": "lossy_id",
+            "tenant_url": "https://example.com",
+            "access_token": "test_token",
+            "created_at": "2023-01-01T00:00:00+00:00",
+            "updated_at": "2023-01-01T00:00:00+00:00",
+            "ban_status": {"status": "suspended", "reason": "abuse"},
+            "portal_info": {"credits_balance": "N/A", "expiry_date": "2024-02-15T00:00:00+00:00"},
+            "suspensions": {"suspensionType": "ABUSE"}
+        });
+
+        for token in [
+            convert_legacy_token(&legacy_json).unwrap(),
+            serde_json::from_value::<TokenData>(legacy_json.clone()).unwrap(),
+        ] {
+            assert_eq!(token.ban_status, Some(BanStatus::Suspended));
+            assert_eq!(token.extra["ban_status"]["reason"], "abuse");
+            assert_eq!(token.credits_balance(), None);
+            assert_eq!(token.portal_info.as_ref().unwrap().extra["credits_balance"], "N/A");
+            assert_eq!(token.suspensions.as_ref().unwrap().len(), 1);
+            assert_eq!(convert_to_legacy_format(&token), legacy_json);
+
+            // 修改后写入新值
+            let mut token = token;
+            token.ban_status = Some(BanStatus::Active);
+            assert_eq!(convert_to_legacy_format(&token)["ban_status"], "ACTIVE");
+        }
+    }
 }
@@ -0,0 +1,432 @@
+//! 按条件导出账号
+//!
+//! 支持按标签、状态、邮箱域名和创建时间筛选，输出 JSON、CSV 或加密归档。
+//! 可以选择导出的字段；`access_token` 和 `auth_session` 属于敏感字段，默认不导出，
+//! 需要在字段列表中显式选择。
+//!
+//! 加密归档是一个 JSON 信封：内容为 zip（manifest.json + tokens.json），使用口令
+//! 经 PBKDF2 派生的密钥以 AES-256-GCM 加密，与加密凭据同步使用同一套算法。
+//! 接收方可以通过批量导入（`token_import`）输入口令后导入。
+
+use crate::storage::{convert_to_legacy_format, BanStatus, TokenData};
+use crate::webdav::{PasswordManager, StoredCredential};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::io::{Cursor, Read, Write};
+
+/// 加密归档信封中的格式标识
+pub const ARCHIVE_FORMAT: &str = "zaugment-export";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// 可以导出的字段（与 tokens.json 中的键一致）
+pub const EXPORTABLE_FIELDS: &[&str] = &[
+    "id", "email_note", "tenant_url", "access_token", "auth_session", "portal_url",
+    "tag_name", "tag_color", "ban_status", "portal_info", "suspensions", "skip_check",
+    "created_at", "updated_at",
+];
+
+/// 默认不导出的敏感字段
+pub const SENSITIVE_FIELDS: &[&str] = &["access_token", "auth_session"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    EncryptedArchive,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::EncryptedArchive => "zaugment",
+        }
+    }
+}
+
+/// 按账号状态筛选
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFilter {
+    Active,
+    Banned,
+    Expired,
+    Invalid,
+    /// 尚未检测过
+    Unknown,
+}
+
+impl StatusFilter {
+    pub fn matches(&self, token: &TokenData) -> bool {
+        match (self, &token.ban_status) {
+            (StatusFilter::Unknown, status) => status.is_none(),
+            (_, None) => false,
+            (StatusFilter::Active, Some(status)) => *status == BanStatus::Active,
+            (StatusFilter::Banned, Some(status)) => status.is_banned(),
+            (StatusFilter::Expired, Some(status)) => *status == BanStatus::Expired,
+            (StatusFilter::Invalid, Some(status)) => {
+                matches!(status, BanStatus::Invalid | BanStatus::InvalidToken)
+            }
+        }
+    }
+}
+
+/// 筛选条件；同一条件中的多个值为“或”，不同条件之间为“且”，空条件不筛选
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportFilter {
+    pub tags: Vec<String>,
+    pub statuses: Vec<StatusFilter>,
+    /// 邮箱域名，例如 `example.com`
+    pub email_domains: Vec<String>,
+    /// 创建时间范围（含两端）
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl ExportFilter {
+    pub fn matches(&self, token: &TokenData) -> bool {
+        let tag_matches = self.tags.is_empty()
+            || token.tag_name.as_ref().is_some_and(|tag| self.tags.iter().any(|t| t == tag));
+        let status_matches = self.statuses.is_empty() || self.statuses.iter().any(|s| s.matches(token));
+        let domain_matches = self.email_domains.is_empty()
+            || token
+                .email_note
+                .as_deref()
+                .and_then(|email| email.rsplit_once('@'))
+                .is_some_and(|(_, domain)| {
+                    self.email_domains
+                        .iter()
+                        .any(|d| d.trim_start_matches('@').eq_ignore_ascii_case(domain))
+                });
+        let date_matches = self.created_after.is_none_or(|after| token.created_at >= after)
+            && self.created_before.is_none_or(|before| token.created_at <= before);
+
+        tag_matches && status_matches && domain_matches && date_matches
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    #[serde(default)]
+    pub filter: ExportFilter,
+    pub format: ExportFormat,
+    /// 导出的字段；缺省时为除敏感字段之外的全部字段
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    /// 加密归档的口令
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub count: usize,
+    pub fields: Vec<String>,
+    pub format: ExportFormat,
+    /// 是否包含敏感字段
+    pub includes_sensitive: bool,
+}
+
+/// 归档中的说明文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub count: usize,
+    pub fields: Vec<String>,
+}
+
+/// 加密归档信封
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedArchive {
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub credential: StoredCredential,
+}
+
+/// 校验并确定导出字段，保持 `EXPORTABLE_FIELDS` 中的顺序
+pub fn resolve_fields(fields: Option<&[String]>) -> Result<Vec<String>, String> {
+    match fields {
+        None => Ok(EXPORTABLE_FIELDS
+            .iter()
+            .filter(|f| !SENSITIVE_FIELDS.contains(f))
+            .map(|f| f.to_string())
+            .collect()),
+        Some(fields) => {
+            if let Some(unknown) = fields.iter().find(|f| !EXPORTABLE_FIELDS.contains(&f.as_str())) {
+                return Err(format!("未知的导出字段: {}", unknown));
+            }
+            if fields.is_empty() {
+                return Err("至少选择一个导出字段".to_string());
+            }
+            Ok(EXPORTABLE_FIELDS
+                .iter()
+                .filter(|f| fields.iter().any(|selected| selected == *f))
+                .map(|f| f.to_string())
+                .collect())
+        }
+    }
+}
+
+/// 只保留选中的字段
+fn project(token: &TokenData, fields: &[String]) -> Map<String, Value> {
+    let legacy = convert_to_legacy_format(token);
+    fields
+        .iter()
+        .filter_map(|field| legacy.get(field).map(|value| (field.clone(), value.clone())))
+        .collect()
+}
+
+fn csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        // 门户信息、封禁记录等嵌套字段以 JSON 文本写入单元格
+        Some(other) => other.to_string(),
+    }
+}
+
+fn render_csv(rows: &[Map<String, Value>], fields: &[String]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields).map_err(|e| format!("写入CSV失败: {}", e))?;
+    for row in rows {
+        writer
+            .write_record(fields.iter().map(|field| csv_cell(row.get(field))))
+            .map_err(|e| format!("写入CSV失败: {}", e))?;
+    }
+    writer.into_inner().map_err(|e| format!("写入CSV失败: {}", e))
+}
+
+fn build_archive(rows: &[Map<String, Value>], fields: &[String], passphrase: &str) -> Result<Vec<u8>, String> {
+    use zip::write::FileOptions;
+
+    let created_at = Utc::now();
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        created_at,
+        count: rows.len(),
+        fields: fields.to_vec(),
+    };
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, value) in [
+        ("manifest.json", serde_json::to_vec_pretty(&manifest)),
+        ("tokens.json", serde_json::to_vec_pretty(rows)),
+    ] {
+        let content = value.map_err(|e| format!("序列化{}失败: {}", name, e))?;
+        zip.start_file(name, options).map_err(|e| format!("创建归档失败: {}", e))?;
+        zip.write_all(&content).map_err(|e| format!("创建归档失败: {}", e))?;
+    }
+    let zip_bytes = zip.finish().map_err(|e| format!("创建归档失败: {}", e))?.into_inner();
+
+    let credential = PasswordManager::new().encrypt_with_passphrase(&BASE64.encode(zip_bytes), passphrase)?;
+    let envelope = EncryptedArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at,
+        credential,
+    };
+    serde_json::to_vec_pretty(&envelope).map_err(|e| format!("序列化归档失败: {}", e))
+}
+
+/// 是否为加密归档
+pub fn is_archive(value: &Value) -> bool {
+    value.get("format").and_then(Value::as_str) == Some(ARCHIVE_FORMAT)
+}
+
+/// 解密归档，返回说明和账号列表
+pub fn open_archive(envelope: &Value, passphrase: &str) -> Result<(ArchiveManifest, Vec<Value>), String> {
+    let envelope: EncryptedArchive =
+        serde_json::from_value(envelope.clone()).map_err(|e| format!("归档格式错误: {}", e))?;
+    if envelope.version > ARCHIVE_VERSION {
+        return Err(format!("归档版本 {} 高于当前支持的版本，请升级应用", envelope.version));
+    }
+
+    let encoded = PasswordManager::new()
+        .decrypt_with_passphrase(&envelope.credential, passphrase)
+        .map_err(|_| "解密失败，口令不正确".to_string())?;
+    let zip_bytes = BASE64.decode(encoded).map_err(|e| format!("归档内容损坏: {}", e))?;
+    let mut archive = zip::ZipArchive::new(Cursor::new(zip_bytes)).map_err(|e| format!("归档内容损坏: {}", e))?;
+
+    let mut read_entry = |name: &str| -> Result<Vec<u8>, String> {
+        let mut content = Vec::new();
+        archive
+            .by_name(name)
+            .map_err(|e| format!("归档缺少 {}: {}", name, e))?
+            .read_to_end(&mut content)
+            .map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+        Ok(content)
+    };
+    let manifest = serde_json::from_slice(&read_entry("manifest.json")?)
+        .map_err(|e| format!("解析 manifest.json 失败: {}", e))?;
+    let tokens = serde_json::from_slice(&read_entry("tokens.json")?)
+        .map_err(|e| format!("解析 tokens.json 失败: {}", e))?;
+    Ok((manifest, tokens))
+}
+
+/// 筛选并渲染导出内容
+pub fn export(tokens: &[TokenData], request: &ExportRequest) -> Result<(Vec<u8>, ExportSummary), String> {
+    let fields = resolve_fields(request.fields.as_deref())?;
+    let rows: Vec<Map<String, Value>> = tokens
+        .iter()
+        .filter(|token| request.filter.matches(token))
+        .map(|token| project(token, &fields))
+        .collect();
+
+    let content = match request.format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&rows).map_err(|e| format!("序列化账号失败: {}", e))?,
+        ExportFormat::Csv => render_csv(&rows, &fields)?,
+        ExportFormat::EncryptedArchive => {
+            let passphrase = request
+                .passphrase
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or("加密归档需要设置口令")?;
+            build_archive(&rows, &fields, passphrase)?
+        }
+    };
+
+    let summary = ExportSummary {
+        count: rows.len(),
+        includes_sensitive: fields.iter().any(|f| SENSITIVE_FIELDS.contains(&f.as_str())),
+        fields,
+        format: request.format,
+    };
+    Ok((content, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::token_fields::TokenPortalInfo;
+
+    fn tokens() -> Vec<TokenData> {
+        let make = |id: &str, email: &str, tag: Option<&str>, status: Option<BanStatus>, created: &str| {
+            let mut token = TokenData::new(
+                id.to_string(),
+                "https://d1.api.augmentcode.com/".to_string(),
+                format!("secret-{}", id),
+                None,
+                Some(email.to_string()),
+            );
+            token.tag_name = tag.map(str::to_string);
+            token.ban_status = status;
+            token.auth_session = Some(format!("session-{}", id));
+            token.created_at = DateTime::parse_from_rfc3339(created).unwrap().with_timezone(&Utc);
+            token
+        };
+        let mut with_portal = make("b", "b@Team.io", Some("team"), Some(BanStatus::Suspended), "2025-03-01T00:00:00Z");
+        with_portal.portal_info = Some(TokenPortalInfo {
+            credits_balance: Some(120),
+            expiry_date: None,
+            extra: Map::new(),
+        });
+        vec![
+            make("a", "a@example.com", Some("team"), Some(BanStatus::Active), "2025-01-01T00:00:00Z"),
+            with_portal,
+            make("c", "c@example.com", None, None, "2025-06-01T00:00:00Z"),
+        ]
+    }
+
+    fn request(format: ExportFormat) -> ExportRequest {
+        ExportRequest {
+            filter: ExportFilter::default(),
+            format,
+            fields: None,
+            passphrase: None,
+        }
+    }
+
+    fn ids(content: &[u8]) -> Vec<String> {
+        let rows: Vec<Value> = serde_json::from_slice(content).unwrap();
+        rows.iter().map(|r| r["id"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_filters_combine() {
+        let tokens = tokens();
+        let mut req = request(ExportFormat::Json);
+
+        req.filter.tags = vec!["team".to_string()];
+        assert_eq!(ids(&export(&tokens, &req).unwrap().0), ["a", "b"]);
+
+        req.filter.statuses = vec![StatusFilter::Banned, StatusFilter::Unknown];
+        assert_eq!(ids(&export(&tokens, &req).unwrap().0), ["b"]);
+
+        req.filter = ExportFilter {
+            email_domains: vec!["@team.io".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&export(&tokens, &req).unwrap().0), ["b"]);
+
+        req.filter = ExportFilter {
+            created_after: Some("2025-02-01T00:00:00Z".parse().unwrap()),
+            created_before: Some("2025-06-01T00:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&export(&tokens, &req).unwrap().0), ["b", "c"]);
+    }
+
+    #[test]
+    fn test_sensitive_fields_excluded_by_default() {
+        let tokens = tokens();
+        let (content, summary) = export(&tokens, &request(ExportFormat::Json)).unwrap();
+        let text = String::from_utf8(content).unwrap();
+        assert!(!text.contains("secret-a") && !text.contains("session-a"));
+        assert!(!summary.includes_sensitive);
+        assert_eq!(summary.count, 3);
+
+        let mut req = request(ExportFormat::Json);
+        req.fields = Some(vec!["access_token".to_string(), "id".to_string()]);
+        let (content, summary) = export(&tokens, &req).unwrap();
+        let rows: Vec<Map<String, Value>> = serde_json::from_slice(&content).unwrap();
+        assert_eq!(rows[0].keys().collect::<Vec<_>>(), ["access_token", "id"]);
+        assert_eq!(summary.fields, ["id", "access_token"]);
+        assert!(summary.includes_sensitive);
+
+        req.fields = Some(vec!["password".to_string()]);
+        assert!(export(&tokens, &req).is_err());
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let mut req = request(ExportFormat::Csv);
+        req.fields = Some(vec!["id".to_string(), "email_note".to_string(), "portal_info".to_string()]);
+        let (content, _) = export(&tokens(), &req).unwrap();
+
+        let mut reader = csv::Reader::from_reader(content.as_slice());
+        assert_eq!(reader.headers().unwrap(), vec!["id", "email_note", "portal_info"]);
+        let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(&records[1][1], "b@Team.io");
+        assert_eq!(serde_json::from_str::<Value>(&records[1][2]).unwrap()["credits_balance"], 120);
+        assert_eq!(&records[0][2], "");
+    }
+
+    #[test]
+    fn test_encrypted_archive_round_trip() {
+        let mut req = request(ExportFormat::EncryptedArchive);
+        assert!(export(&tokens(), &req).is_err());
+
+        req.passphrase = Some("correct horse".to_string());
+        req.fields = Some(EXPORTABLE_FIELDS.iter().map(|f| f.to_string()).collect());
+        let (content, summary) = export(&tokens(), &req).unwrap();
+        assert_eq!(summary.count, 3);
+        assert!(!String::from_utf8_lossy(&content).contains("secret-a"));
+
+        let envelope: Value = serde_json::from_slice(&content).unwrap();
+        assert!(is_archive(&envelope));
+        assert!(open_archive(&envelope, "wrong").is_err());
+
+        let (manifest, rows) = open_archive(&envelope, "correct horse").unwrap();
+        assert_eq!(manifest.count, 3);
+        assert_eq!(rows[0]["access_token"], "secret-a");
+    }
+}
@@ -0,0 +1,284 @@
+//! 应用更新：发布渠道、版本比较、安装包下载和签名校验
+//!
+//! 版本信息来自 GitHub Releases。稳定渠道只接受正式版本，测试渠道同时接受预发布版本，
+//! 版本号按 semver 规则比较（`0.6.0-beta.2` < `0.6.0`）。安装包必须通过构建时嵌入的
+//! minisign 公钥（与 Tauri updater 的 `.sig` 格式相同）校验后才会保存到本地。
+
+use base64::{engine::general_purpose, Engine as _};
+use futures::StreamExt;
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// GitHub Releases 列表接口（同时包含预发布版本）
+pub const RELEASES_URL: &str = "https://api.github.com/repos/Zheng-up/zAugment/releases?per_page=30";
+
+/// 构建时通过 `ZAUGMENT_UPDATER_PUBKEY` 环境变量嵌入的签名公钥
+pub const UPDATER_PUBKEY: Option<&str> = option_env!("ZAUGMENT_UPDATER_PUBKEY");
+
+/// 下载进度事件
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "update-download-progress";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+/// 更新设置，保存在 UnifiedAppConfig 中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateSettings {
+    #[serde(default)]
+    pub channel: UpdateChannel,
+    /// 用户选择跳过的版本，只跳过这一个版本
+    #[serde(default)]
+    pub skipped_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub version: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// 解析版本号，允许 `v` 前缀和缺省的补丁号（`v1.2` 视为 `1.2.0`）
+pub fn parse_version(tag: &str) -> Option<Version> {
+    let version = tag.trim().trim_start_matches(['v', 'V']);
+    Version::parse(version).ok().or_else(|| {
+        let (core, suffix) = match version.find(['-', '+']) {
+            Some(index) => version.split_at(index),
+            None => (version, ""),
+        };
+        match core.split('.').count() {
+            1 => Version::parse(&format!("{}.0.0{}", core, suffix)).ok(),
+            2 => Version::parse(&format!("{}.0{}", core, suffix)).ok(),
+            _ => None,
+        }
+    })
+}
+
+/// latest 是否比 current 新（按 semver 规则，预发布版本低于对应的正式版本）
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+fn is_prerelease(release: &GitHubRelease, version: &Version) -> bool {
+    release.prerelease || !version.pre.is_empty()
+}
+
+/// 从 Releases 列表中选出当前渠道可用的最新版本
+pub fn select_release(releases: &[GitHubRelease], channel: UpdateChannel) -> Option<(&GitHubRelease, Version)> {
+    releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| parse_version(&release.tag_name).map(|version| (release, version)))
+        .filter(|(release, version)| channel == UpdateChannel::Beta || !is_prerelease(release, version))
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+/// 获取当前平台的安装包
+pub fn select_asset(assets: &[GitHubAsset]) -> Option<&GitHubAsset> {
+    let platform = std::env::consts::OS;
+    let arch = std::env::consts::ARCH;
+    let installers: Vec<&GitHubAsset> = assets.iter().filter(|asset| !asset.name.ends_with(".sig")).collect();
+
+    // 根据平台和架构选择合适的资源
+    let preferred_asset = match platform {
+        "windows" => installers.iter().find(|asset|
+            asset.name.contains("windows") && asset.name.ends_with(".exe")
+        ),
+        "macos" => {
+            if arch == "aarch64" {
+                installers.iter().find(|asset|
+                    asset.name.contains("aarch64") && asset.name.ends_with(".dmg")
+                )
+            } else {
+                installers.iter().find(|asset|
+                    asset.name.contains("x64") && asset.name.ends_with(".dmg")
+                )
+            }
+        },
+        "linux" => installers.iter().find(|asset|
+            asset.name.contains("amd64") && asset.name.ends_with(".deb")
+        ).or_else(|| installers.iter().find(|asset|
+            asset.name.contains("x86_64") && asset.name.ends_with(".rpm")
+        )),
+        _ => None,
+    };
+
+    preferred_asset.or_else(|| installers.first()).copied()
+}
+
+/// 安装包对应的签名文件（`<安装包>.sig`）
+pub fn signature_asset<'a>(assets: &'a [GitHubAsset], asset_name: &str) -> Option<&'a GitHubAsset> {
+    let signature_name = format!("{}.sig", asset_name);
+    assets.iter().find(|asset| asset.name == signature_name)
+}
+
+/// Tauri 生成的公钥和 `.sig` 文件是对 minisign 文本整体做了 base64，这里两种形式都接受
+fn decode_minisign_text(text: &str) -> Result<String, String> {
+    let trimmed = text.trim();
+    if trimmed.starts_with("untrusted comment:") {
+        return Ok(trimmed.to_string());
+    }
+    let decoded = general_purpose::STANDARD
+        .decode(trimmed)
+        .map_err(|e| format!("签名数据不是有效的 base64: {}", e))?;
+    String::from_utf8(decoded).map_err(|e| format!("签名数据不是有效的文本: {}", e))
+}
+
+/// 使用 minisign 公钥校验数据签名
+pub fn verify_signature(public_key: &str, data: &[u8], signature: &str) -> Result<(), String> {
+    let public_key_text = decode_minisign_text(public_key)
+        .or_else(|_| Ok::<_, String>(public_key.trim().to_string()))?;
+    let public_key = if public_key_text.starts_with("untrusted comment:") {
+        PublicKey::decode(&public_key_text)
+    } else {
+        PublicKey::from_base64(&public_key_text)
+    }
+    .map_err(|e| format!("更新公钥无效: {}", e))?;
+
+    let signature = Signature::decode(&decode_minisign_text(signature)?)
+        .map_err(|e| format!("签名文件无效: {}", e))?;
+
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| format!("安装包签名校验失败: {}", e))
+}
+
+/// 下载文件，每收到一块数据回调一次进度（已下载字节数、总字节数）
+pub async fn download_with_progress<F>(client: &reqwest::Client, url: &str, mut on_progress: F) -> Result<Vec<u8>, String>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let response = client
+        .get(url)
+        .header("User-Agent", "ZAugment/1.0")
+        .send()
+        .await
+        .map_err(|e| format!("下载失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("下载失败，状态码: {}", response.status()));
+    }
+
+    let total = response.content_length();
+    let mut data = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("下载中断: {}", e))?;
+        data.extend_from_slice(&chunk);
+        on_progress(data.len() as u64, total);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试用密钥对签名的数据 "zaugment update test"，格式与 Tauri 生成的 .sig 相同
+    const TEST_PUBKEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXkKUldTYTRaV2l5UFFGY1Z3WXFZOHV4TUtodjNiYlFianJORG53a0xqbHovaUowRVlPaG5WbXhDT3YK";
+    const TEST_SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVTYTRaV2l5UFFGY2Y2WERSVk9vZkJXNHgzRjRmK0J2c3pzNDJSdW5BMUF2NkhiNmJYOFpqUXE4TUNCY0xqeE5qYnlONWU1V29naGtwZisxR0tMY0tlekw0WGVjakthL2dZPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzAwMDAwMDAwCWZpbGU6WkF1Z21lbnRfMC42LjBfYW1kNjQuZGViCnQ1TEhZdTBQYnpxUllqeHAxU01ldnI1cWJHRGEwNTF3dzA0ekh2YURwYUl6ak5ieGxHK1UwckRpSHlmSzRTc0JPWFByazBIcjE2R2Fzd1lHbGpMb0N3PT0K";
+
+    fn release(tag: &str, prerelease: bool) -> GitHubRelease {
+        GitHubRelease {
+            tag_name: tag.to_string(),
+            name: None,
+            body: None,
+            published_at: None,
+            prerelease,
+            draft: false,
+            assets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_semver_comparison() {
+        assert!(is_newer_version("v0.6.0", "0.5.3"));
+        assert!(is_newer_version("0.5.10", "0.5.9"));
+        assert!(is_newer_version("0.6.0", "0.6.0-beta.2"));
+        assert!(is_newer_version("0.6.0-beta.10", "0.6.0-beta.2"));
+        assert!(!is_newer_version("0.6.0-beta.1", "0.6.0"));
+        assert!(!is_newer_version("0.5.3", "0.5.3"));
+        assert!(is_newer_version("v1.2", "1.1.9"));
+        assert!(!is_newer_version("nightly", "0.5.3"));
+    }
+
+    #[test]
+    fn test_select_release_by_channel() {
+        let mut draft = release("v0.9.0", false);
+        draft.draft = true;
+        let releases = vec![
+            release("v0.6.0", false),
+            release("v0.7.0-beta.1", true),
+            release("v0.6.1-rc.1", false),
+            draft,
+        ];
+
+        let (stable, version) = select_release(&releases, UpdateChannel::Stable).unwrap();
+        assert_eq!(stable.tag_name, "v0.6.0");
+        assert_eq!(version, Version::new(0, 6, 0));
+
+        let (beta, _) = select_release(&releases, UpdateChannel::Beta).unwrap();
+        assert_eq!(beta.tag_name, "v0.7.0-beta.1");
+    }
+
+    #[test]
+    fn test_signature_asset_lookup() {
+        let assets = vec![
+            GitHubAsset { name: "ZAugment_0.6.0_amd64.deb.sig".to_string(), browser_download_url: "sig".to_string(), size: 1 },
+            GitHubAsset { name: "ZAugment_0.6.0_amd64.deb".to_string(), browser_download_url: "deb".to_string(), size: 2 },
+        ];
+        assert!(!select_asset(&assets).unwrap().name.ends_with(".sig"));
+        assert_eq!(signature_asset(&assets, "ZAugment_0.6.0_amd64.deb").unwrap().browser_download_url, "sig");
+        assert!(signature_asset(&assets, "other.exe").is_none());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        assert!(verify_signature(TEST_PUBKEY, b"zaugment update test", TEST_SIGNATURE).is_ok());
+        assert!(verify_signature(TEST_PUBKEY, b"tampered", TEST_SIGNATURE).is_err());
+        assert!(verify_signature(TEST_PUBKEY, b"zaugment update test", "not a signature").is_err());
+    }
+
+    #[test]
+    fn test_update_settings_defaults() {
+        let settings: UpdateSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, UpdateSettings::default());
+        let settings: UpdateSettings = serde_json::from_str(r#"{"channel": "beta", "skipped_version": "0.6.0"}"#).unwrap();
+        assert_eq!(settings.channel, UpdateChannel::Beta);
+        assert_eq!(settings.skipped_version.as_deref(), Some("0.6.0"));
+    }
+}
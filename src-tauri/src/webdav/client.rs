@@ -5,6 +5,7 @@ use super::config::WebDAVConfig;
 use super::error::WebDAVError;
 use super::retry::{ImprovedRetryExecutor, RetryProgress};
 use super::error::RetryConfig;
+use crate::http_client::HttpClient;
 
 #[derive(Debug, Clone)]
 pub struct WebDAVClient {
@@ -27,22 +28,19 @@ pub struct FileInfo {
 
 impl WebDAVClient {
     /// 创建新的WebDAV客户端
-    pub fn new(config: WebDAVConfig) -> Result<Self, WebDAVError> {
-        Self::new_with_retry_config(config, RetryConfig::network())
+    pub fn new(config: WebDAVConfig, http: &HttpClient) -> Result<Self, WebDAVError> {
+        Self::new_with_retry_config(config, http, RetryConfig::network())
     }
     
     /// 创建新的WebDAV客户端，使用自定义重试配置
-    pub fn new_with_retry_config(config: WebDAVConfig, retry_config: RetryConfig) -> Result<Self, WebDAVError> {
+    ///
+    /// 请求通过应用共享的 HTTP 客户端发送，沿用其代理、证书和超时设置。
+    pub fn new_with_retry_config(config: WebDAVConfig, http: &HttpClient, retry_config: RetryConfig) -> Result<Self, WebDAVError> {
         if !config.is_valid() {
             return Err(WebDAVError::InvalidConfig("配置信息不完整".to_string()));
         }
 
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .user_agent("ZAugment-WebDAV-Client/1.0")
-            .build()
-            .map_err(|e| WebDAVError::from_reqwest_error(e))?;
+        let client = http.client().clone();
 
         let retry_executor = ImprovedRetryExecutor::new(retry_config);
 
@@ -54,13 +52,13 @@ impl WebDAVClient {
     }
     
     /// 创建快速重试的WebDAV客户端
-    pub fn new_fast_retry(config: WebDAVConfig) -> Result<Self, WebDAVError> {
-        Self::new_with_retry_config(config, RetryConfig::fast())
+    pub fn new_fast_retry(config: WebDAVConfig, http: &HttpClient) -> Result<Self, WebDAVError> {
+        Self::new_with_retry_config(config, http, RetryConfig::fast())
     }
     
     /// 创建耐心重试的WebDAV客户端
-    pub fn new_patient_retry(config: WebDAVConfig) -> Result<Self, WebDAVError> {
-        Self::new_with_retry_config(config, RetryConfig::patient())
+    pub fn new_patient_retry(config: WebDAVConfig, http: &HttpClient) -> Result<Self, WebDAVError> {
+        Self::new_with_retry_config(config, http, RetryConfig::patient())
     }
 
     /// 测试连接
@@ -163,25 +161,25 @@ impl WebDAVClient {
             .await
             .map_err(WebDAVError::from_reqwest_error)?;
 
-        println!("PROPFIND请求URL: {}", url);
-        println!("PROPFIND响应状态码: {}", response.status());
+        log::info!("PROPFIND请求URL: {}", url);
+        log::info!("PROPFIND响应状态码: {}", response.status());
         
         match response.status() {
             StatusCode::MULTI_STATUS => {
                 let text = response.text().await.map_err(WebDAVError::from_reqwest_error)?;
-                println!("PROPFIND响应内容: {}", text);
+                log::debug!("PROPFIND响应长度: {} 字节", text.len());
                 self.parse_propfind_response(&text, remote_path)
             }
             StatusCode::NOT_FOUND => {
-                println!("文件未找到 (404)");
+                log::info!("文件未找到 (404)");
                 Ok(None)
             },
             StatusCode::UNAUTHORIZED => {
-                println!("认证失败 (401)");
+                log::warn!("认证失败 (401)");
                 Err(WebDAVError::AuthenticationError)
             },
             code => {
-                println!("其他错误状态码: {}", code);
+                log::warn!("其他错误状态码: {}", code);
                 Err(WebDAVError::from_status_code(code.as_u16(), None))
             },
         }
@@ -417,7 +415,7 @@ impl WebDAVClient {
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_else(|| "unknown".to_string());
             
-            println!("解析到文件信息: 名称={}, 大小={}, 是否目录={}", name, size, is_directory);
+            log::info!("解析到文件信息: 名称={}, 大小={}, 是否目录={}", name, size, is_directory);
             
             Ok(Some(FileInfo {
                 name,
@@ -428,7 +426,7 @@ impl WebDAVClient {
                 etag,
             }))
         } else {
-            println!("XML中未找到getcontentlength标签");
+            log::info!("XML中未找到getcontentlength标签");
             Ok(None)
         }
     }
@@ -475,7 +473,7 @@ mod tests {
             "password123".to_string(),
         );
         
-        let client = WebDAVClient::new(config).unwrap();
+        let client = WebDAVClient::new(config, &HttpClient::default()).unwrap();
         let url = client.build_url("ZAugment/tokens.json").unwrap();
         assert_eq!(url, "https://dav.jianguoyun.com/dav/ZAugment/tokens.json");
     }
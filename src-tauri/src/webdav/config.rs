@@ -59,6 +59,18 @@ impl WebDAVConfig {
         Ok(format!("{}{}", url, remote_path))
     }
 
+    /// 用户数据包（tokens、配置、书签）使用的配置：远程文件由 tokens.json 换成 user_data.json
+    pub fn user_data_config(&self) -> Self {
+        let mut config = self.clone();
+        if config.remote_path.contains("tokens.json") {
+            config.remote_path = config.remote_path.replace("tokens.json", "user_data.json");
+        } else {
+            // 如果路径中没有 tokens.json，使用默认路径
+            config.remote_path = "/ZAugment/user_data.json".to_string();
+        }
+        config
+    }
+
     /// 安全地显示配置信息（隐藏密码）
     pub fn display_safe(&self) -> String {
         format!(
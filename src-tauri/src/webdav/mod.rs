@@ -8,6 +8,6 @@ pub mod retry;
 pub use client::WebDAVClient;
 pub use sync::{CloudSync, ConflictResolution, ConflictInfo};
 pub use config::WebDAVConfig;
-pub use secure_config::{SecureWebDAVConfig, PasswordManager};
+pub use secure_config::{SecureWebDAVConfig, PasswordManager, StoredCredential};
 // pub use error::WebDAVError;
 // pub use retry::{ImprovedRetryExecutor, RetryProgress}; // 暂时注释掉，等需要时再启用
@@ -310,7 +310,7 @@ mod tests {
         
         let executor = ImprovedRetryExecutor::fast();
         
-        let result = executor.execute(|| {
+        let result: Result<(), WebDAVError> = executor.execute(|| {
             let counter = counter_clone.clone();
             async move {
                 counter.fetch_add(1, Ordering::SeqCst);
@@ -335,7 +335,7 @@ mod tests {
             ..RetryConfig::default()
         });
         
-        let _result = executor.execute_with_progress(
+        let _result: Result<(), WebDAVError> = executor.execute_with_progress(
             || {
                 let counter = counter_clone.clone();
                 async move {
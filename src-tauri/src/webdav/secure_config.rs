@@ -1,8 +1,48 @@
 use super::config::WebDAVConfig;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use ring::aead::{Aad, BoundKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
 use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
 
+/// WebDAV 密码在系统密钥链中的服务名
+pub const WEBDAV_KEYRING_SERVICE: &str = "ZAugment_WebDAV";
+/// 加密凭据同步口令在系统密钥链中的服务名和账户名
+const SYNC_PASSPHRASE_SERVICE: &str = "ZAugment_CredentialSync";
+const SYNC_PASSPHRASE_ACCOUNT: &str = "sync_passphrase";
+/// HTTP 代理密码在系统密钥链中的服务名（账户名为代理用户名）
+const PROXY_KEYRING_SERVICE: &str = "ZAugment_Proxy";
+/// app session 缓存文件加密密钥在系统密钥链中的服务名和账户名
+const SESSION_CACHE_KEY_SERVICE: &str = "ZAugment_SessionCache";
+const SESSION_CACHE_KEY_ACCOUNT: &str = "cache_key";
+/// 从同步口令派生密钥时的 PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+
+/// 配置文件中保存的 WebDAV 凭据，永远不包含明文密码
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoredCredential {
+    /// 密码保存在系统密钥链中
+    Keyring { service: String, account: String },
+    /// 使用同步口令加密的密码（仅出现在启用了加密凭据同步的云端数据包中）
+    Encrypted { salt: String, nonce: String, ciphertext: String },
+}
+
+/// WebDAV 密码在密钥链中的账户名：同一用户名在不同服务器上（例如不同配置档案）分别保存
+pub fn webdav_keyring_account(server_url: &str, username: &str) -> String {
+    format!("{}@{}", username, server_url.trim_end_matches('/'))
+}
+
+impl StoredCredential {
+    pub fn keyring(account: &str) -> Self {
+        StoredCredential::Keyring {
+            service: WEBDAV_KEYRING_SERVICE.to_string(),
+            account: account.to_string(),
+        }
+    }
+}
 
 /// 安全存储的WebDAV配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,11 +53,9 @@ pub struct SecureWebDAVConfig {
     pub auto_sync: bool,
     pub sync_interval_minutes: u32,
     pub remote_path: String,
-    // 密码将被加密存储，不在这里明文保存
-    #[serde(skip)]
-    password_encrypted: Option<Vec<u8>>,
-    #[serde(skip)]
-    salt: Option<Vec<u8>>,
+    // 密码不在这里明文保存，只记录其所在位置；缺省时按用户名从密钥链读取
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<StoredCredential>,
 }
 
 /// 简单的随机数生成器
@@ -47,20 +85,77 @@ impl PasswordManager {
         }
     }
 
-    /// 使用keyring安全存储密码
-    pub fn store_password(&self, username: &str, password: &str) -> Result<(), String> {
-        match keyring::Entry::new("ZAugment_WebDAV", username) {
+    /// 使用keyring安全存储密码（账户名见 `webdav_keyring_account`）
+    pub fn store_password(&self, account: &str, password: &str) -> Result<(), String> {
+        self.store_secret_in(WEBDAV_KEYRING_SERVICE, account, password)
+    }
+
+    /// 从keyring获取密码
+    pub fn get_password(&self, account: &str) -> Result<String, String> {
+        self.get_secret_from(WEBDAV_KEYRING_SERVICE, account)
+    }
+
+    /// 删除存储的密码
+    pub fn delete_password(&self, account: &str) -> Result<(), String> {
+        self.delete_secret_from(WEBDAV_KEYRING_SERVICE, account)
+    }
+
+    /// 保存加密凭据同步口令
+    pub fn store_sync_passphrase(&self, passphrase: &str) -> Result<(), String> {
+        self.store_secret_in(SYNC_PASSPHRASE_SERVICE, SYNC_PASSPHRASE_ACCOUNT, passphrase)
+    }
+
+    /// 获取加密凭据同步口令
+    pub fn get_sync_passphrase(&self) -> Result<String, String> {
+        self.get_secret_from(SYNC_PASSPHRASE_SERVICE, SYNC_PASSPHRASE_ACCOUNT)
+    }
+
+    /// 删除加密凭据同步口令
+    pub fn delete_sync_passphrase(&self) -> Result<(), String> {
+        self.delete_secret_from(SYNC_PASSPHRASE_SERVICE, SYNC_PASSPHRASE_ACCOUNT)
+    }
+
+    /// 保存 HTTP 代理密码
+    pub fn store_proxy_password(&self, username: &str, password: &str) -> Result<(), String> {
+        self.store_secret_in(PROXY_KEYRING_SERVICE, username, password)
+    }
+
+    /// 获取 HTTP 代理密码
+    pub fn get_proxy_password(&self, username: &str) -> Result<String, String> {
+        self.get_secret_from(PROXY_KEYRING_SERVICE, username)
+    }
+
+    /// 删除 HTTP 代理密码
+    pub fn delete_proxy_password(&self, username: &str) -> Result<(), String> {
+        self.delete_secret_from(PROXY_KEYRING_SERVICE, username)
+    }
+
+    /// 获取 app session 缓存的加密密钥，不存在时生成并保存到密钥链
+    pub fn get_or_create_session_cache_key(&self) -> Result<[u8; 32], String> {
+        if let Ok(stored) = self.get_secret_from(SESSION_CACHE_KEY_SERVICE, SESSION_CACHE_KEY_ACCOUNT) {
+            if let Some(key) = BASE64.decode(stored).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+                return Ok(key);
+            }
+        }
+        let mut key = [0u8; 32];
+        self.rng.fill(&mut key)
+            .map_err(|_| "生成缓存密钥失败".to_string())?;
+        self.store_secret_in(SESSION_CACHE_KEY_SERVICE, SESSION_CACHE_KEY_ACCOUNT, &BASE64.encode(key))?;
+        Ok(key)
+    }
+
+    fn store_secret_in(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        match keyring::Entry::new(service, account) {
             Ok(entry) => {
-                entry.set_password(password)
+                entry.set_password(secret)
                     .map_err(|e| format!("密码存储失败: {}", e))
             }
             Err(e) => Err(format!("创建密码条目失败: {}", e))
         }
     }
 
-    /// 从keyring获取密码
-    pub fn get_password(&self, username: &str) -> Result<String, String> {
-        match keyring::Entry::new("ZAugment_WebDAV", username) {
+    fn get_secret_from(&self, service: &str, account: &str) -> Result<String, String> {
+        match keyring::Entry::new(service, account) {
             Ok(entry) => {
                 entry.get_password()
                     .map_err(|e| format!("密码获取失败: {}", e))
@@ -69,9 +164,8 @@ impl PasswordManager {
         }
     }
 
-    /// 删除存储的密码
-    pub fn delete_password(&self, username: &str) -> Result<(), String> {
-        match keyring::Entry::new("ZAugment_WebDAV", username) {
+    fn delete_secret_from(&self, service: &str, account: &str) -> Result<(), String> {
+        match keyring::Entry::new(service, account) {
             Ok(entry) => {
                 entry.delete_password()
                     .map_err(|e| format!("密码删除失败: {}", e))
@@ -80,6 +174,53 @@ impl PasswordManager {
         }
     }
 
+    /// 从同步口令和盐派生 AES-256 密钥
+    fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("迭代次数必须大于0");
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+        key
+    }
+
+    /// 使用同步口令加密密码，生成可放入云端数据包的凭据
+    pub fn encrypt_with_passphrase(&self, secret: &str, passphrase: &str) -> Result<StoredCredential, String> {
+        if passphrase.is_empty() {
+            return Err("同步口令不能为空".to_string());
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        self.rng.fill(&mut salt)
+            .map_err(|_| "生成随机盐失败")?;
+
+        let key = Self::derive_key(passphrase, &salt);
+        let (ciphertext, nonce) = self.encrypt_data(secret, &key)?;
+
+        Ok(StoredCredential::Encrypted {
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// 使用同步口令解密云端数据包中的凭据
+    pub fn decrypt_with_passphrase(&self, credential: &StoredCredential, passphrase: &str) -> Result<String, String> {
+        let (salt, nonce, ciphertext) = match credential {
+            StoredCredential::Encrypted { salt, nonce, ciphertext } => (salt, nonce, ciphertext),
+            StoredCredential::Keyring { .. } => return Err("凭据未加密".to_string()),
+        };
+
+        let decode = |value: &str| BASE64.decode(value).map_err(|e| format!("凭据格式错误: {}", e));
+        let salt = decode(salt)?;
+        let nonce: [u8; NONCE_LEN] = decode(nonce)?
+            .try_into()
+            .map_err(|_| "凭据格式错误: 随机数长度不正确".to_string())?;
+        let ciphertext = decode(ciphertext)?;
+
+        let key = Self::derive_key(passphrase, &salt);
+        self.decrypt_data(&ciphertext, &key, &nonce)
+            .map_err(|_| "解密失败，同步口令不正确".to_string())
+    }
+
     /// 生成密钥和随机数
     fn generate_key_and_nonce(&self) -> Result<([u8; 32], [u8; NONCE_LEN]), String> {
         let mut key = [0u8; 32];
@@ -128,10 +269,18 @@ impl PasswordManager {
 }
 
 impl SecureWebDAVConfig {
+    /// 是否指向同一个远程位置（服务器、用户和路径都相同）
+    pub fn same_target(&self, other: &SecureWebDAVConfig) -> bool {
+        self.server_url == other.server_url
+            && self.username == other.username
+            && self.remote_path == other.remote_path
+    }
+
     /// 从普通配置创建安全配置
     pub fn from_config(config: &WebDAVConfig, password_manager: &PasswordManager) -> Result<Self, String> {
         // 使用keyring存储密码
-        password_manager.store_password(&config.username, &config.password)?;
+        let account = webdav_keyring_account(&config.server_url, &config.username);
+        password_manager.store_password(&account, &config.password)?;
         
         Ok(Self {
             server_url: config.server_url.clone(),
@@ -140,14 +289,26 @@ impl SecureWebDAVConfig {
             auto_sync: config.auto_sync,
             sync_interval_minutes: config.sync_interval_minutes,
             remote_path: config.remote_path.clone(),
-            password_encrypted: None,
-            salt: None,
+            credential: Some(StoredCredential::keyring(&account)),
         })
     }
 
     /// 转换为普通配置（包含密码）
     pub fn to_config(&self, password_manager: &PasswordManager) -> Result<WebDAVConfig, String> {
-        let password = password_manager.get_password(&self.username)?;
+        // 只信任 WebDAV 服务下的引用，账户名总是按本机配置重新生成，
+        // 避免云端数据包把引用指向同步口令等其他密钥链条目
+        let password = match &self.credential {
+            Some(StoredCredential::Keyring { service, .. }) if service == WEBDAV_KEYRING_SERVICE => {
+                self.read_password(password_manager)?
+            }
+            Some(StoredCredential::Keyring { service, .. }) => {
+                return Err(format!("WebDAV凭据引用了不受信任的密钥链服务: {}", service));
+            }
+            Some(StoredCredential::Encrypted { .. }) => {
+                return Err("WebDAV密码已加密，需要同步口令解密".to_string());
+            }
+            None => self.read_password(password_manager)?,
+        };
         
         Ok(WebDAVConfig {
             server_url: self.server_url.clone(),
@@ -160,6 +321,31 @@ impl SecureWebDAVConfig {
         })
     }
 
+    /// 本机密钥链中保存该配置密码的账户名
+    pub fn keyring_account(&self) -> String {
+        webdav_keyring_account(&self.server_url, &self.username)
+    }
+
+    /// 本机密钥链中保存该配置密码的引用
+    pub fn keyring_credential(&self) -> StoredCredential {
+        StoredCredential::keyring(&self.keyring_account())
+    }
+
+    /// 从密钥链读取密码；旧版本以用户名为账户名保存，读到后迁移到新的账户名
+    fn read_password(&self, password_manager: &PasswordManager) -> Result<String, String> {
+        let account = self.keyring_account();
+        match password_manager.get_password(&account) {
+            Ok(password) => Ok(password),
+            Err(e) => {
+                let password = password_manager.get_password(&self.username).map_err(|_| e)?;
+                if let Err(e) = password_manager.store_password(&account, &password) {
+                    log::warn!("迁移WebDAV密码到新的密钥链账户失败: {}", e);
+                }
+                Ok(password)
+            }
+        }
+    }
+
     /// 验证配置是否完整（不包括密码检查）
     pub fn is_valid_structure(&self) -> bool {
         !self.server_url.is_empty() && !self.username.is_empty()
@@ -215,8 +401,7 @@ impl Default for SecureWebDAVConfig {
             auto_sync: false,
             sync_interval_minutes: 30,
             remote_path: "/ZAugment/tokens.json".to_string(),
-            password_encrypted: None,
-            salt: None,
+            credential: None,
         }
     }
 }
@@ -225,6 +410,22 @@ impl Default for SecureWebDAVConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_keyring_account_includes_server() {
+        let config = |server_url: &str| SecureWebDAVConfig {
+            server_url: server_url.to_string(),
+            username: "alice".to_string(),
+            ..SecureWebDAVConfig::default()
+        };
+        assert_eq!(config("https://dav.example.com/").keyring_account(), "alice@https://dav.example.com");
+        assert_eq!(config("https://dav.example.com").keyring_account(), "alice@https://dav.example.com");
+        assert_ne!(config("https://dav.example.com").keyring_account(), config("https://dav.other.com").keyring_account());
+        assert_eq!(
+            config("https://dav.example.com").keyring_credential(),
+            StoredCredential::keyring("alice@https://dav.example.com")
+        );
+    }
+
     #[test]
     fn test_password_encryption() {
         let pm = PasswordManager::new();
@@ -237,6 +438,52 @@ mod tests {
         assert_eq!(test_data, decrypted);
     }
 
+    #[test]
+    fn test_passphrase_encryption_roundtrip() {
+        let pm = PasswordManager::new();
+
+        let credential = pm.encrypt_with_passphrase("webdav_secret", "correct horse").unwrap();
+        assert!(matches!(credential, StoredCredential::Encrypted { .. }));
+
+        let decrypted = pm.decrypt_with_passphrase(&credential, "correct horse").unwrap();
+        assert_eq!(decrypted, "webdav_secret");
+
+        assert!(pm.decrypt_with_passphrase(&credential, "wrong passphrase").is_err());
+        assert!(pm.encrypt_with_passphrase("webdav_secret", "").is_err());
+    }
+
+    #[test]
+    fn test_serialized_config_has_no_password() {
+        let secure_config = SecureWebDAVConfig {
+            username: "test@example.com".to_string(),
+            credential: Some(StoredCredential::keyring("test@example.com")),
+            ..SecureWebDAVConfig::default()
+        };
+
+        let json = serde_json::to_value(&secure_config).unwrap();
+        assert!(json.get("password").is_none());
+        assert_eq!(json["credential"]["type"], "keyring");
+        assert_eq!(json["credential"]["service"], WEBDAV_KEYRING_SERVICE);
+
+        let restored: SecureWebDAVConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.credential, secure_config.credential);
+    }
+
+    #[test]
+    fn test_foreign_keyring_reference_is_rejected() {
+        let secure_config = SecureWebDAVConfig {
+            username: "test@example.com".to_string(),
+            credential: Some(StoredCredential::Keyring {
+                service: SYNC_PASSPHRASE_SERVICE.to_string(),
+                account: SYNC_PASSPHRASE_ACCOUNT.to_string(),
+            }),
+            ..SecureWebDAVConfig::default()
+        };
+
+        let err = secure_config.to_config(&PasswordManager::new()).unwrap_err();
+        assert!(err.contains(SYNC_PASSPHRASE_SERVICE));
+    }
+
     #[test]
     fn test_secure_config_creation() {
         let config = WebDAVConfig::new(
@@ -1,7 +1,8 @@
 use super::client::WebDAVClient;
 use super::config::WebDAVConfig;
 use super::error::WebDAVError;
-use std::path::PathBuf;
+use crate::http_client::HttpClient;
+use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 use tokio::fs;
 use serde::{Deserialize, Serialize};
@@ -67,8 +68,8 @@ pub struct CloudSync {
 
 impl CloudSync {
     /// 创建新的云同步实例
-    pub fn new(config: WebDAVConfig, local_file_path: PathBuf) -> Result<Self, WebDAVError> {
-        let client = WebDAVClient::new(config.clone())?;
+    pub fn new(config: WebDAVConfig, local_file_path: PathBuf, http: &HttpClient) -> Result<Self, WebDAVError> {
+        let client = WebDAVClient::new(config.clone(), http)?;
         let remote_file_path = config.remote_path.clone();
         
         Ok(Self {
@@ -160,11 +161,11 @@ impl CloudSync {
     /// 强制从远程下载
     pub async fn force_download(&mut self) -> Result<SyncResult, WebDAVError> {
         // 调试信息
-        println!("正在检查远程文件: {}", self.remote_file_path);
+        log::info!("正在检查远程文件: {}", self.remote_file_path);
         let remote_info = self.client.get_file_info(&self.remote_file_path).await?;
         
         if remote_info.is_none() {
-            println!("远程文件不存在: {}", self.remote_file_path);
+            log::info!("远程文件不存在: {}", self.remote_file_path);
             return Ok(SyncResult {
                 action: SyncAction::NoActionNeeded,
                 success: false,
@@ -176,7 +177,7 @@ impl CloudSync {
             });
         }
         
-        println!("远程文件存在，开始下载: {}", self.remote_file_path);
+        log::info!("远程文件存在，开始下载: {}", self.remote_file_path);
 
         let content = self.client.download_file(&self.remote_file_path).await?;
         
@@ -390,6 +391,11 @@ impl CloudSync {
         &self.status
     }
 
+    /// 本地同步文件的路径
+    pub fn local_file_path(&self) -> &Path {
+        &self.local_file_path
+    }
+
     /// 计算文件校验和（SHA256）
     async fn calculate_file_checksum(&self, file_path: &PathBuf) -> Result<String, WebDAVError> {
         let content = fs::read(file_path).await.map_err(|e| {
@@ -623,7 +629,7 @@ mod tests {
         
         // 测试不存在的文件
         let config = WebDAVConfig::default();
-        let sync = CloudSync::new(config, file_path.clone()).unwrap();
+        let sync = CloudSync::new(config, file_path.clone(), &HttpClient::default()).unwrap();
         let info = sync.get_local_file_info().await.unwrap();
         assert!(info.is_none());
         